@@ -7,6 +7,10 @@ use std::path::PathBuf;
 
 pub static DENO_VERSION: &str = "v2.1.1";
 pub static UV_VERSION: &str = "0.5.7";
+pub static RUFF_VERSION: &str = "0.8.2";
+pub static PYRIGHT_VERSION: &str = "1.1.390";
+pub static NODEJS_WHEEL_VERSION: &str = "22.12.0";
+pub static PYRIGHT_FOLDER_NAME: &str = "pyright";
 static PROFILE: Lazy<String> =
     Lazy::new(|| std::env::var("PROFILE").unwrap_or_else(|_| "debug".to_string()));
 
@@ -110,6 +114,13 @@ pub fn copy_assets(
 ) -> Result<(), std::io::Error> {
     copy_deno(DENO_VERSION, source_path.clone(), target_path.clone())?;
     copy_uv(UV_VERSION, source_path.clone(), target_path.clone())?;
+    copy_ruff(RUFF_VERSION, source_path.clone(), target_path.clone())?;
+    copy_pyright(
+        PYRIGHT_VERSION,
+        NODEJS_WHEEL_VERSION,
+        source_path.clone(),
+        target_path.clone(),
+    )?;
     Ok(())
 }
 
@@ -354,6 +365,297 @@ pub fn copy_uv(
     Ok(())
 }
 
+pub fn ruff_binary_name() -> String {
+    if cfg!(target_os = "windows") {
+        "ruff.exe".to_string()
+    } else {
+        "ruff".to_string()
+    }
+}
+
+fn download_file(url: &str, target_path: &PathBuf) -> Result<(), std::io::Error> {
+    println!("downloading {} to {}", url, target_path.display());
+    let mut response = reqwest::blocking::Client::new()
+        .get(url)
+        .send()
+        .unwrap_or_else(|err| panic!("failed to download {}: {}", url, err));
+    if !response.status().is_success() {
+        panic!("failed to download {}: {}", url, response.status());
+    }
+    let mut file = fs::File::create(target_path)
+        .unwrap_or_else(|err| panic!("failed to create {}: {}", target_path.display(), err));
+    std::io::copy(&mut response, &mut file)
+        .unwrap_or_else(|err| panic!("failed to write {}: {}", target_path.display(), err));
+    file.flush()?;
+    Ok(())
+}
+
+pub fn copy_ruff(
+    version: &str,
+    source_path: Option<PathBuf>,
+    target_path: Option<PathBuf>,
+) -> Result<(), std::io::Error> {
+    let source_path = source_path
+        .unwrap_or_else(get_source_path)
+        .join(RESOURCES_FOLDER_NAME);
+    let target_path = target_path
+        .unwrap_or_else(get_target_path)
+        .join(RESOURCES_FOLDER_NAME);
+    fs::create_dir_all(&source_path).unwrap_or_else(|err| {
+        panic!("failed to create resources directory: {}", err);
+    });
+    fs::create_dir_all(&target_path).unwrap_or_else(|err| {
+        panic!("failed to create target directory: {}", err);
+    });
+
+    let arch = if cfg!(target_os = "linux") {
+        if cfg!(target_arch = "x86_64") {
+            "x86_64-unknown-linux-gnu"
+        } else if cfg!(target_arch = "aarch64") {
+            "aarch64-unknown-linux-gnu"
+        } else {
+            panic!("unsupported Linux architecture");
+        }
+    } else if cfg!(target_os = "macos") {
+        if cfg!(target_arch = "x86_64") {
+            "x86_64-apple-darwin"
+        } else if cfg!(target_arch = "aarch64") {
+            "aarch64-apple-darwin"
+        } else {
+            panic!("unsupported macOS architecture");
+        }
+    } else if cfg!(target_os = "windows") {
+        if cfg!(target_arch = "x86_64") {
+            "x86_64-pc-windows-msvc"
+        } else if cfg!(target_arch = "aarch64") {
+            "aarch64-pc-windows-msvc"
+        } else {
+            panic!("unsupported Windows architecture");
+        }
+    } else {
+        panic!("unsupported target OS");
+    };
+    let extension = if cfg!(windows) { "zip" } else { "tar.gz" };
+
+    // The archive name includes the version so changing RUFF_VERSION downloads it again
+    let archive_path = source_path.join(format!("ruff-{}.{}", version, extension));
+    if !archive_path.exists() {
+        println!("ruff archive does not exist, downloading...");
+        download_file(
+            &format!(
+                "https://github.com/astral-sh/ruff/releases/download/{}/ruff-{}.{}",
+                version, arch, extension
+            ),
+            &archive_path,
+        )?;
+    } else {
+        println!("ruff archive already exists, skipping download");
+    }
+
+    let ruff_binary_source_path = source_path.join(ruff_binary_name());
+    let archive_file = fs::File::open(&archive_path).expect("failed to read ruff archive");
+    if cfg!(windows) {
+        let mut archive = zip::ZipArchive::new(archive_file).expect("failed to open zip archive");
+        archive
+            .extract(&source_path)
+            .expect("failed to extract zip archive");
+    } else {
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive_file));
+        archive
+            .unpack(&source_path)
+            .expect("failed to extract tar.gz archive");
+        let extracted_folder = source_path.join(format!("ruff-{}", arch));
+        fs::rename(extracted_folder.join("ruff"), &ruff_binary_source_path)
+            .expect("failed to move ruff binary from extracted folder");
+        fs::remove_dir_all(extracted_folder).expect("failed to remove extracted folder");
+    }
+
+    let ruff_binary_target_path = target_path.join(ruff_binary_name());
+    println!(
+        "copying ruff binary from {} to {}",
+        ruff_binary_source_path.display(),
+        ruff_binary_target_path.display()
+    );
+    fs::copy(&ruff_binary_source_path, &ruff_binary_target_path).unwrap_or_else(|err| {
+        panic!(
+            "failed to copy downloaded ruff binary to target path: {}",
+            err
+        );
+    });
+    add_exec_permissions(&ruff_binary_target_path)?;
+    Ok(())
+}
+
+/// Fragments of the wheel platform tags of nodejs-wheel-binaries for the host and for the linux
+/// containers of the docker runner
+fn nodejs_wheel_platforms() -> Vec<[&'static str; 2]> {
+    let linux_arch = if cfg!(target_arch = "aarch64") {
+        "aarch64"
+    } else {
+        "x86_64"
+    };
+    let host = if cfg!(target_os = "linux") {
+        ["manylinux", linux_arch]
+    } else if cfg!(target_os = "macos") {
+        if cfg!(target_arch = "aarch64") {
+            ["macosx", "arm64"]
+        } else {
+            ["macosx", "x86_64"]
+        }
+    } else if cfg!(target_os = "windows") {
+        if cfg!(target_arch = "aarch64") {
+            ["win", "arm64"]
+        } else {
+            ["win", "amd64"]
+        }
+    } else {
+        panic!("unsupported target OS");
+    };
+    let docker = ["manylinux", linux_arch];
+    if host == docker {
+        vec![host]
+    } else {
+        vec![host, docker]
+    }
+}
+
+/// Url of the wheel of `project` whose file name contains every fragment of `platform`
+fn pypi_wheel_url(project: &str, version: &str, platform: &[&str]) -> (String, String) {
+    let client = reqwest::blocking::Client::new();
+    let release: serde_json::Value = client
+        .get(format!(
+            "https://pypi.org/pypi/{}/{}/json",
+            project, version
+        ))
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map(|text| serde_json::from_str(&text).unwrap_or_default())
+        .unwrap_or_else(|err| panic!("failed to get {} {} release: {}", project, version, err));
+    release["urls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find_map(|file| {
+            let file_name = file["filename"].as_str()?;
+            let url = file["url"].as_str()?;
+            (file_name.ends_with(".whl")
+                && platform.iter().all(|fragment| file_name.contains(fragment)))
+            .then(|| (file_name.to_string(), url.to_string()))
+        })
+        .unwrap_or_else(|| {
+            panic!(
+                "no {} {} wheel found for {}",
+                project,
+                version,
+                platform.join(" ")
+            )
+        })
+}
+
+/// Folder the pyright npm package of `version` is extracted to, its `package/index.js` is the cli
+pub fn pyright_package_folder_name(version: &str) -> String {
+    format!("pyright-{}", version)
+}
+
+/// Downloads the pyright npm package and the nodejs-wheel-binaries wheels it runs with, for the host
+/// and for the docker runner, so the check venvs install node from them and run pyright without
+/// reaching the network. The npm package bundles all its javascript, unlike the pyright wheel that
+/// downloads it with npm when it first runs.
+pub fn copy_pyright(
+    version: &str,
+    nodejs_version: &str,
+    source_path: Option<PathBuf>,
+    target_path: Option<PathBuf>,
+) -> Result<(), std::io::Error> {
+    let source_path = source_path
+        .unwrap_or_else(get_source_path)
+        .join(RESOURCES_FOLDER_NAME)
+        .join(PYRIGHT_FOLDER_NAME);
+    let target_path = target_path
+        .unwrap_or_else(get_target_path)
+        .join(RESOURCES_FOLDER_NAME)
+        .join(PYRIGHT_FOLDER_NAME);
+    fs::create_dir_all(&source_path).unwrap_or_else(|err| {
+        panic!("failed to create resources directory: {}", err);
+    });
+    fs::create_dir_all(&target_path).unwrap_or_else(|err| {
+        panic!("failed to create target directory: {}", err);
+    });
+
+    // Archive and wheel file names include the version so changing the versions downloads them again
+    let pyright_archive_path = source_path.join(format!("pyright-{}.tgz", version));
+    if !pyright_archive_path.exists() {
+        println!("pyright archive does not exist, downloading...");
+        download_file(
+            &format!(
+                "https://registry.npmjs.org/pyright/-/pyright-{}.tgz",
+                version
+            ),
+            &pyright_archive_path,
+        )?;
+    } else {
+        println!("pyright archive already exists, skipping download");
+    }
+    let pyright_package_path = target_path.join(pyright_package_folder_name(version));
+    if pyright_package_path.exists() {
+        fs::remove_dir_all(&pyright_package_path)?;
+    }
+    println!(
+        "extracting {} to {}",
+        pyright_archive_path.display(),
+        pyright_package_path.display()
+    );
+    let archive_file =
+        fs::File::open(&pyright_archive_path).expect("failed to read pyright archive");
+    tar::Archive::new(flate2::read::GzDecoder::new(archive_file))
+        .unpack(&pyright_package_path)
+        .expect("failed to extract pyright archive");
+
+    let mut wheel_names = Vec::new();
+    for platform in nodejs_wheel_platforms() {
+        let downloaded = fs::read_dir(&source_path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .find(|file_name| {
+                file_name.starts_with(&format!("nodejs_wheel_binaries-{}-", nodejs_version))
+                    && platform.iter().all(|fragment| file_name.contains(fragment))
+            });
+        let wheel_name = match downloaded {
+            Some(wheel_name) => {
+                println!("{} already exists, skipping download", wheel_name);
+                wheel_name
+            }
+            None => {
+                let (wheel_name, url) =
+                    pypi_wheel_url("nodejs-wheel-binaries", nodejs_version, &platform);
+                println!("{} does not exist, downloading...", wheel_name);
+                download_file(&url, &source_path.join(&wheel_name))?;
+                wheel_name
+            }
+        };
+        wheel_names.push(wheel_name);
+    }
+
+    for wheel_name in wheel_names {
+        println!(
+            "copying {} from {} to {}",
+            wheel_name,
+            source_path.display(),
+            target_path.display()
+        );
+        fs::copy(source_path.join(&wheel_name), target_path.join(&wheel_name)).unwrap_or_else(
+            |err| {
+                panic!(
+                    "failed to copy downloaded {} to target path: {}",
+                    wheel_name, err
+                );
+            },
+        );
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,690 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use super::{
+    code_files::CodeFiles,
+    diagnostic::{Diagnostic, DiagnosticPosition, DiagnosticSeverity},
+};
+
+/// How `audit` reports each rule
+#[derive(Clone, Debug, Default)]
+pub struct AuditPolicy {
+    /// Severity per rule id, rules that aren't listed use their default severity
+    pub severities: HashMap<String, DiagnosticSeverity>,
+    /// Rules that aren't reported
+    pub ignore: Vec<String>,
+}
+
+struct AuditRule {
+    id: &'static str,
+    severity: DiagnosticSeverity,
+    message: &'static str,
+}
+
+const TYPESCRIPT_RULES: &[AuditRule] = &[
+    AuditRule {
+        id: "deno-command",
+        severity: DiagnosticSeverity::Warning,
+        message: "spawns a subprocess",
+    },
+    AuditRule {
+        id: "deno-dlopen",
+        severity: DiagnosticSeverity::Error,
+        message: "loads a native library",
+    },
+    AuditRule {
+        id: "deno-dynamic-access",
+        severity: DiagnosticSeverity::Warning,
+        message: "accesses a Deno API chosen at runtime",
+    },
+    AuditRule {
+        id: "eval",
+        severity: DiagnosticSeverity::Error,
+        message: "evaluates dynamic code",
+    },
+    AuditRule {
+        id: "remote-dynamic-import",
+        severity: DiagnosticSeverity::Error,
+        message: "imports code from a remote URL at runtime",
+    },
+    AuditRule {
+        id: "dynamic-import",
+        severity: DiagnosticSeverity::Warning,
+        message: "imports a module chosen at runtime",
+    },
+];
+
+const PYTHON_RULES: &[AuditRule] = &[
+    AuditRule {
+        id: "subprocess",
+        severity: DiagnosticSeverity::Warning,
+        message: "spawns a subprocess",
+    },
+    AuditRule {
+        id: "os-system",
+        severity: DiagnosticSeverity::Warning,
+        message: "runs a shell command",
+    },
+    AuditRule {
+        id: "ctypes",
+        severity: DiagnosticSeverity::Error,
+        message: "loads native code",
+    },
+    AuditRule {
+        id: "pickle-loads",
+        severity: DiagnosticSeverity::Error,
+        message: "deserializes pickled data, which can execute arbitrary code",
+    },
+    AuditRule {
+        id: "eval",
+        severity: DiagnosticSeverity::Error,
+        message: "evaluates dynamic code",
+    },
+    AuditRule {
+        id: "dynamic-import",
+        severity: DiagnosticSeverity::Warning,
+        message: "imports a module chosen at runtime",
+    },
+];
+
+/// Script that audits the Python files it gets as arguments with the `ast` module and prints the
+/// findings as JSON
+pub const PYTHON_AUDIT_SCRIPT: &str = include_str!("python_audit.py");
+
+pub const PYTHON_AUDIT_SCRIPT_FILE_NAME: &str = "__hanzo_audit__.py";
+
+/// A risky API use found in a file, positions are 1-based and the end is exclusive
+#[derive(Deserialize)]
+struct AuditFinding {
+    file: String,
+    line: u32,
+    column: u32,
+    end_line: u32,
+    end_column: u32,
+    rule: String,
+    text: String,
+}
+
+/// Diagnostics of the findings whose rules `policy` doesn't ignore, sorted by position
+fn audit_diagnostics(
+    findings: Vec<AuditFinding>,
+    rules: &[AuditRule],
+    policy: &AuditPolicy,
+) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = findings
+        .into_iter()
+        .filter(|finding| !policy.ignore.contains(&finding.rule))
+        .filter_map(|finding| {
+            let rule = rules.iter().find(|rule| rule.id == finding.rule)?;
+            Some(Diagnostic {
+                file: finding.file,
+                line: finding.line,
+                column: finding.column,
+                end: Some(DiagnosticPosition {
+                    line: finding.end_line,
+                    column: finding.end_column,
+                }),
+                severity: policy
+                    .severities
+                    .get(rule.id)
+                    .cloned()
+                    .unwrap_or(rule.severity.clone()),
+                code: Some(rule.id.to_string()),
+                source_tool: String::from("audit"),
+                message: format!("`{}` {}", finding.text, rule.message),
+            })
+        })
+        .collect();
+    diagnostics.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    diagnostics
+}
+
+fn code_paths<'a>(code: &'a CodeFiles, extensions: &[&str]) -> Vec<&'a String> {
+    let mut paths: Vec<&String> = code
+        .files
+        .keys()
+        .filter(|path| extensions.iter().any(|extension| path.ends_with(extension)))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Python files `PYTHON_AUDIT_SCRIPT` is run with
+pub fn python_audit_paths(code: &CodeFiles) -> Vec<String> {
+    code_paths(code, &[".py"]).into_iter().cloned().collect()
+}
+
+/// Diagnostics of the findings `PYTHON_AUDIT_SCRIPT` printed
+pub fn parse_python_audit_output(
+    output: &str,
+    policy: &AuditPolicy,
+) -> anyhow::Result<Vec<Diagnostic>> {
+    let findings: Vec<AuditFinding> = serde_json::from_str(output.trim())?;
+    Ok(audit_diagnostics(findings, PYTHON_RULES, policy))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Identifier,
+    Punctuator,
+    /// String, or template literal without substitutions, with its value
+    String(String),
+    /// Part of a template literal with substitutions
+    Template,
+    /// Numbers and regular expressions
+    Other,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    line: u32,
+    column: u32,
+    end_line: u32,
+    end_column: u32,
+}
+
+impl Token {
+    fn is(&self, punctuator: &str) -> bool {
+        self.kind == TokenKind::Punctuator && self.text == punctuator
+    }
+
+    fn is_identifier(&self, name: &str) -> bool {
+        self.kind == TokenKind::Identifier && self.text == name
+    }
+}
+
+/// Keywords a regular expression literal can follow, a `/` after other identifiers is a division
+const REGEX_PRECEDING_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "case",
+    "do",
+    "else",
+    "yield",
+    "await",
+];
+
+/// Splits TypeScript and JavaScript code into tokens, without comments and whitespace. Template
+/// literal substitutions are tokenized as code.
+struct Lexer {
+    chars: Vec<char>,
+    index: usize,
+    line: u32,
+    column: u32,
+    tokens: Vec<Token>,
+    /// Depth of the open braces, `template_depths` has the depths template literals continue at
+    brace_depth: usize,
+    template_depths: Vec<usize>,
+}
+
+impl Lexer {
+    fn tokenize(source: &str) -> Vec<Token> {
+        let mut lexer = Lexer {
+            chars: source.chars().collect(),
+            index: 0,
+            line: 1,
+            column: 1,
+            tokens: Vec::new(),
+            brace_depth: 0,
+            template_depths: Vec::new(),
+        };
+        lexer.run();
+        lexer.tokens
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.index + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.get(self.index).copied()?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize, line: u32, column: u32) {
+        self.tokens.push(Token {
+            kind,
+            text: self.chars[start..self.index].iter().collect(),
+            line,
+            column,
+            end_line: self.line,
+            end_column: self.column,
+        });
+    }
+
+    fn regex_allowed(&self) -> bool {
+        match self.tokens.last() {
+            None => true,
+            Some(token) => match &token.kind {
+                TokenKind::Identifier => REGEX_PRECEDING_KEYWORDS.contains(&token.text.as_str()),
+                TokenKind::Punctuator => token.text != ")" && token.text != "]",
+                _ => false,
+            },
+        }
+    }
+
+    /// Reads a template literal part up to its closing backtick or its next substitution
+    fn template(&mut self, start: usize, line: u32, column: u32, opened: bool) {
+        let mut value = String::new();
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => {
+                    if let Some(escaped) = self.bump() {
+                        value.push(escaped);
+                    }
+                }
+                '`' => {
+                    let kind = if opened {
+                        TokenKind::String(value)
+                    } else {
+                        TokenKind::Template
+                    };
+                    self.push(kind, start, line, column);
+                    return;
+                }
+                '$' if self.peek(0) == Some('{') => {
+                    self.bump();
+                    self.push(TokenKind::Template, start, line, column);
+                    self.template_depths.push(self.brace_depth);
+                    self.brace_depth += 1;
+                    return;
+                }
+                c => value.push(c),
+            }
+        }
+        self.push(TokenKind::Template, start, line, column);
+    }
+
+    fn run(&mut self) {
+        while let Some(c) = self.peek(0) {
+            let (start, line, column) = (self.index, self.line, self.column);
+            match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                }
+                '/' if self.peek(1) == Some('/') => {
+                    while self.peek(0).is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                '/' if self.peek(1) == Some('*') => {
+                    self.bump();
+                    self.bump();
+                    while self.peek(0).is_some()
+                        && !(self.peek(0) == Some('*') && self.peek(1) == Some('/'))
+                    {
+                        self.bump();
+                    }
+                    self.bump();
+                    self.bump();
+                }
+                '"' | '\'' => {
+                    self.bump();
+                    let mut value = String::new();
+                    while let Some(c) = self.bump() {
+                        match c {
+                            '\\' => {
+                                if let Some(escaped) = self.bump() {
+                                    value.push(escaped);
+                                }
+                            }
+                            '\n' => break,
+                            c if c == *self.chars.get(start).unwrap() => break,
+                            c => value.push(c),
+                        }
+                    }
+                    self.push(TokenKind::String(value), start, line, column);
+                }
+                '`' => {
+                    self.bump();
+                    self.template(start, line, column, true);
+                }
+                '}' if self.template_depths.last()
+                    == Some(&(self.brace_depth.saturating_sub(1))) =>
+                {
+                    self.bump();
+                    self.brace_depth -= 1;
+                    self.template_depths.pop();
+                    self.template(start, line, column, false);
+                }
+                c if c.is_alphabetic() || c == '_' || c == '$' => {
+                    while self
+                        .peek(0)
+                        .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+                    {
+                        self.bump();
+                    }
+                    self.push(TokenKind::Identifier, start, line, column);
+                }
+                c if c.is_ascii_digit() => {
+                    while self
+                        .peek(0)
+                        .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.')
+                    {
+                        self.bump();
+                    }
+                    self.push(TokenKind::Other, start, line, column);
+                }
+                '/' if self.regex_allowed() => {
+                    self.bump();
+                    let mut in_class = false;
+                    while let Some(c) = self.bump() {
+                        match c {
+                            '\\' => {
+                                self.bump();
+                            }
+                            '[' => in_class = true,
+                            ']' => in_class = false,
+                            '/' if !in_class => break,
+                            '\n' => break,
+                            _ => {}
+                        }
+                    }
+                    while self.peek(0).is_some_and(|c| c.is_alphanumeric()) {
+                        self.bump();
+                    }
+                    self.push(TokenKind::Other, start, line, column);
+                }
+                _ => {
+                    self.bump();
+                    if c == '?'
+                        && self.peek(0) == Some('.')
+                        && !self.peek(1).is_some_and(|c| c.is_ascii_digit())
+                    {
+                        self.bump();
+                    } else if c == '.' && self.peek(0) == Some('.') && self.peek(1) == Some('.') {
+                        self.bump();
+                        self.bump();
+                    } else if c == '{' {
+                        self.brace_depth += 1;
+                    } else if c == '}' {
+                        self.brace_depth = self.brace_depth.saturating_sub(1);
+                    }
+                    self.push(TokenKind::Punctuator, start, line, column);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PathSegment {
+    Name(String),
+    /// Member accessed with a computed name
+    Computed,
+}
+
+/// Objects whose members are globals, `globalThis.Deno` is `Deno`
+const GLOBAL_OBJECTS: &[&str] = &["globalThis", "window", "self"];
+
+/// Globals the audited APIs are reached from, names bound to them are tracked as aliases
+const AUDITED_GLOBALS: &[&str] = &["Deno", "eval", "Function"];
+
+/// Index of the token after the bracket that closes the one at `open`
+fn closing_bracket(tokens: &[Token], open: usize) -> usize {
+    let (opening, closing) = match tokens[open].text.as_str() {
+        "(" => ("(", ")"),
+        "[" => ("[", "]"),
+        _ => ("{", "}"),
+    };
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        if token.is(opening) {
+            depth += 1;
+        } else if token.is(closing) {
+            depth -= 1;
+            if depth == 0 {
+                return index + 1;
+            }
+        }
+    }
+    tokens.len()
+}
+
+/// Member chain starting at the identifier at `start`, like `globalThis.Deno["Command"]`, with
+/// the index of the token after it
+fn member_chain(tokens: &[Token], start: usize) -> (Vec<PathSegment>, usize) {
+    let mut path = vec![PathSegment::Name(tokens[start].text.clone())];
+    let mut index = start + 1;
+    while index < tokens.len() {
+        let token = &tokens[index];
+        let next = tokens.get(index + 1);
+        if (token.is(".") || token.is("?."))
+            && next.is_some_and(|next| next.kind == TokenKind::Identifier)
+        {
+            path.push(PathSegment::Name(next.unwrap().text.clone()));
+            index += 2;
+        } else if token.is("?.") && next.is_some_and(|next| next.is("[")) {
+            index += 1;
+        } else if token.is("[") {
+            let end = closing_bracket(tokens, index);
+            match (&tokens[index + 1].kind, end == index + 3) {
+                (TokenKind::String(value), true) => path.push(PathSegment::Name(value.clone())),
+                _ => path.push(PathSegment::Computed),
+            }
+            index = end;
+        } else {
+            break;
+        }
+    }
+    (path, index)
+}
+
+/// Resolves the aliases and global objects at the start of `path`
+fn resolve_path(
+    path: Vec<PathSegment>,
+    aliases: &HashMap<String, Vec<PathSegment>>,
+) -> Vec<PathSegment> {
+    let mut path = path;
+    if let Some(PathSegment::Name(name)) = path.first() {
+        if let Some(alias) = aliases.get(name) {
+            path.splice(0..1, alias.clone());
+        }
+    }
+    while path.len() > 1
+        && matches!(path.first(), Some(PathSegment::Name(name)) if GLOBAL_OBJECTS.contains(&name.as_str()))
+    {
+        path.remove(0);
+    }
+    path
+}
+
+fn is_audited_global(path: &[PathSegment]) -> bool {
+    matches!(path.first(), Some(PathSegment::Name(name)) if AUDITED_GLOBALS.contains(&name.as_str()))
+}
+
+/// Rule a reference to `path` breaks, `called` when it's called or constructed
+fn typescript_rule(path: &[PathSegment], called: bool) -> Option<&'static str> {
+    let name = |index: usize| match path.get(index) {
+        Some(PathSegment::Name(name)) => Some(name.as_str()),
+        _ => None,
+    };
+    match (name(0), path.get(1)) {
+        (Some("Deno"), Some(PathSegment::Computed)) => Some("deno-dynamic-access"),
+        (Some("Deno"), Some(_)) => match name(1) {
+            Some("Command") | Some("run") => Some("deno-command"),
+            Some("dlopen") => Some("deno-dlopen"),
+            _ => None,
+        },
+        (Some("eval"), _) => Some("eval"),
+        (Some("Function"), None) if called => Some("eval"),
+        _ => None,
+    }
+}
+
+fn finding(file: &str, tokens: &[Token], start: usize, end: usize, rule: &str) -> AuditFinding {
+    let last = &tokens[end.max(start + 1) - 1];
+    AuditFinding {
+        file: file.to_string(),
+        line: tokens[start].line,
+        column: tokens[start].column,
+        end_line: last.end_line,
+        end_column: last.end_column,
+        rule: rule.to_string(),
+        text: tokens[start..end]
+            .iter()
+            .map(|token| token.text.as_str())
+            .collect(),
+    }
+}
+
+/// Finds the audited APIs referenced by a TypeScript file. `Deno` members are resolved through
+/// global objects, computed member names, aliases and destructuring, so `const { Command } =
+/// globalThis.Deno` followed by `new Command(...)` is reported like `new Deno.Command(...)`.
+fn audit_typescript_file(file: &str, source: &str) -> Vec<AuditFinding> {
+    let tokens = Lexer::tokenize(source);
+    let mut aliases: HashMap<String, Vec<PathSegment>> = HashMap::new();
+    // Names being declared, they aren't references
+    let mut bindings: HashSet<usize> = HashSet::new();
+    let mut findings = Vec::new();
+
+    for index in 0..tokens.len() {
+        let token = &tokens[index];
+        let previous = index.checked_sub(1).map(|previous| &tokens[previous]);
+        let next = tokens.get(index + 1);
+
+        // Destructuring: `const { Command, run: r } = Deno`
+        if ["const", "let", "var"]
+            .iter()
+            .any(|keyword| token.is_identifier(keyword))
+            && next.is_some_and(|next| next.is("{"))
+        {
+            let pattern_end = closing_bracket(&tokens, index + 1);
+            if tokens.get(pattern_end).is_some_and(|token| token.is("="))
+                && tokens
+                    .get(pattern_end + 1)
+                    .is_some_and(|token| token.kind == TokenKind::Identifier)
+            {
+                let (source_path, _) = member_chain(&tokens, pattern_end + 1);
+                let source_path = resolve_path(source_path, &aliases);
+                let mut entry = index + 2;
+                while entry < pattern_end - 1 {
+                    let key = match &tokens[entry].kind {
+                        TokenKind::Identifier => tokens[entry].text.clone(),
+                        TokenKind::String(value) => value.clone(),
+                        _ => {
+                            entry += 1;
+                            continue;
+                        }
+                    };
+                    let local = if tokens[entry + 1].is(":") {
+                        entry + 2
+                    } else {
+                        entry
+                    };
+                    let path = [source_path.clone(), vec![PathSegment::Name(key)]].concat();
+                    if let Some(rule) = typescript_rule(&path, false) {
+                        findings.push(finding(file, &tokens, entry, entry + 1, rule));
+                    }
+                    if is_audited_global(&path) && tokens[local].kind == TokenKind::Identifier {
+                        aliases.insert(tokens[local].text.clone(), path);
+                    }
+                    bindings.insert(entry);
+                    bindings.insert(local);
+                    // Skips the default value up to the next entry
+                    entry = local + 1;
+                    while entry < pattern_end - 1 && !tokens[entry].is(",") {
+                        entry += 1;
+                    }
+                    entry += 1;
+                }
+            }
+            continue;
+        }
+
+        if token.kind != TokenKind::Identifier
+            || bindings.contains(&index)
+            || previous.is_some_and(|previous| previous.is(".") || previous.is("?."))
+        {
+            continue;
+        }
+        // Object literal keys: `{ eval: true }`
+        if next.is_some_and(|next| next.is(":"))
+            && previous.is_some_and(|previous| previous.is("{") || previous.is(","))
+        {
+            continue;
+        }
+
+        if token.is_identifier("import") && next.is_some_and(|next| next.is("(")) {
+            let end = closing_bracket(&tokens, index + 1);
+            let literal = match (tokens.get(index + 2), tokens.get(index + 3)) {
+                (Some(argument), Some(after)) if after.is(")") || after.is(",") => {
+                    match &argument.kind {
+                        TokenKind::String(value) => Some(value.clone()),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            match literal {
+                Some(specifier)
+                    if specifier.starts_with("https://") || specifier.starts_with("http://") =>
+                {
+                    findings.push(finding(file, &tokens, index, end, "remote-dynamic-import"));
+                }
+                Some(_) => {}
+                None => findings.push(finding(file, &tokens, index, end, "dynamic-import")),
+            }
+            continue;
+        }
+
+        let (path, end) = member_chain(&tokens, index);
+        let path = resolve_path(path, &aliases);
+        let called = tokens.get(end).is_some_and(|token| token.is("("))
+            || previous.is_some_and(|previous| previous.is_identifier("new"));
+        if let Some(rule) = typescript_rule(&path, called) {
+            findings.push(finding(file, &tokens, index, end, rule));
+        }
+
+        // Aliases: `const command = Deno.Command` or `run = globalThis.eval`
+        if let (Some(assign), Some(value)) = (tokens.get(index + 1), tokens.get(index + 2)) {
+            if assign.is("=")
+                && !value.is("=")
+                && !value.is(">")
+                && value.kind == TokenKind::Identifier
+                && end == index + 1
+            {
+                let (value_path, value_end) = member_chain(&tokens, index + 2);
+                let value_path = resolve_path(value_path, &aliases);
+                let is_call = tokens.get(value_end).is_some_and(|token| token.is("("));
+                if is_audited_global(&value_path) && !is_call {
+                    aliases.insert(token.text.clone(), value_path);
+                } else {
+                    aliases.remove(&token.text);
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// Flags risky APIs in TypeScript and JavaScript code: subprocesses, native libraries,
+/// dynamic code evaluation and dynamic imports. Comments and strings are skipped.
+pub fn audit_typescript(code: &CodeFiles, policy: &AuditPolicy) -> Vec<Diagnostic> {
+    let findings = code_paths(code, &[".ts", ".tsx", ".js", ".jsx", ".mjs", ".mts"])
+        .into_iter()
+        .flat_map(|path| audit_typescript_file(path, &code.files[path]))
+        .collect();
+    audit_diagnostics(findings, TYPESCRIPT_RULES, policy)
+}
+
+#[cfg(test)]
+#[path = "audit.test.rs"]
+mod tests;
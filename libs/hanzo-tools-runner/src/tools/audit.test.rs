@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use crate::tools::{
+    audit::{
+        audit_typescript, parse_python_audit_output, python_audit_paths, AuditPolicy,
+        PYTHON_AUDIT_SCRIPT, PYTHON_AUDIT_SCRIPT_FILE_NAME,
+    },
+    code_files::CodeFiles,
+    diagnostic::{Diagnostic, DiagnosticPosition, DiagnosticSeverity},
+};
+
+fn rules(diagnostics: &[Diagnostic]) -> Vec<(String, u32)> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.code.clone().unwrap(), diagnostic.line))
+        .collect()
+}
+
+fn audit_typescript_code(code: &str) -> Vec<(String, u32)> {
+    let code = CodeFiles {
+        files: HashMap::from([("main.ts".to_string(), code.to_string())]),
+        entrypoint: "main.ts".to_string(),
+    };
+    rules(&audit_typescript(&code, &AuditPolicy::default()))
+}
+
+#[test]
+fn test_audit_typescript() {
+    let code = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.ts".to_string(),
+                r#"import { helper } from "./helper.ts";
+// Deno.Command is mentioned in a comment
+async function run(c, p) {
+    const output = await new Deno.Command("ls").output();
+    const lib = Deno.dlopen("./lib.so", {});
+    const value = eval(p.expression);
+    const module = await import("https://example.com/mod.ts");
+    return helper.evaluate(value);
+}"#
+                .to_string(),
+            ),
+            (
+                "helper.ts".to_string(),
+                "export const helper = new Function('return 1');".to_string(),
+            ),
+            ("README.md".to_string(), "eval(\"not code\")".to_string()),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let diagnostics = audit_typescript(&code, &AuditPolicy::default());
+    assert_eq!(
+        rules(&diagnostics),
+        vec![
+            ("eval".to_string(), 1),
+            ("deno-command".to_string(), 4),
+            ("deno-dlopen".to_string(), 5),
+            ("eval".to_string(), 6),
+            ("remote-dynamic-import".to_string(), 7),
+        ]
+    );
+    assert_eq!(diagnostics[0].file, "helper.ts");
+    assert_eq!(diagnostics[1].file, "main.ts");
+    assert_eq!(diagnostics[1].column, 30);
+    assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Warning);
+    assert_eq!(diagnostics[1].source_tool, "audit");
+    assert_eq!(diagnostics[3].column, 19);
+    assert_eq!(
+        diagnostics[3].end,
+        Some(DiagnosticPosition {
+            line: 6,
+            column: 23
+        })
+    );
+    assert_eq!(diagnostics[3].message, "`eval` evaluates dynamic code");
+}
+
+#[test]
+fn test_audit_typescript_resolves_aliases() {
+    let code = r#"const { Command } = Deno;
+new Command("ls");
+const { dlopen: open } = globalThis.Deno;
+const D = Deno;
+D.run({ cmd: ["ls"] });
+Deno["Command"];
+window["eval"]("1");
+globalThis.eval("1");
+const e = eval;
+Deno[name];
+await import(specifier);
+await import("./local.ts");
+"#;
+    assert_eq!(
+        audit_typescript_code(code),
+        vec![
+            ("deno-command".to_string(), 1),
+            ("deno-command".to_string(), 2),
+            ("deno-dlopen".to_string(), 3),
+            ("deno-command".to_string(), 5),
+            ("deno-command".to_string(), 6),
+            ("eval".to_string(), 7),
+            ("eval".to_string(), 8),
+            ("eval".to_string(), 9),
+            ("deno-dynamic-access".to_string(), 10),
+            ("dynamic-import".to_string(), 11),
+        ]
+    );
+}
+
+#[test]
+fn test_audit_typescript_skips_comments_and_strings() {
+    let code = r#"/* new Deno.Command("ls") */
+const text = "Deno.Command";
+const template = `Deno.dlopen ${Deno.run} eval`;
+const pattern = /eval\(/;
+const options = { eval: true };
+"#;
+    assert_eq!(
+        audit_typescript_code(code),
+        vec![("deno-command".to_string(), 3)]
+    );
+}
+
+#[test]
+fn test_parse_python_audit_output() {
+    let output = r#"[{"file": "main.py", "line": 3, "column": 1, "end_line": 3, "end_column": 18, "rule": "subprocess", "text": "import subprocess"}, {"file": "main.py", "line": 1, "column": 5, "end_line": 1, "end_column": 9, "rule": "eval", "text": "eval"}]
+"#;
+    let diagnostics = parse_python_audit_output(output, &AuditPolicy::default()).unwrap();
+    assert_eq!(
+        rules(&diagnostics),
+        vec![("eval".to_string(), 1), ("subprocess".to_string(), 3)]
+    );
+    assert_eq!(diagnostics[1].column, 1);
+    assert_eq!(
+        diagnostics[1].end,
+        Some(DiagnosticPosition {
+            line: 3,
+            column: 18
+        })
+    );
+    assert_eq!(
+        diagnostics[1].message,
+        "`import subprocess` spawns a subprocess"
+    );
+
+    let policy = AuditPolicy {
+        severities: HashMap::from([("subprocess".to_string(), DiagnosticSeverity::Error)]),
+        ignore: vec!["eval".to_string()],
+    };
+    let diagnostics = parse_python_audit_output(output, &policy).unwrap();
+    assert_eq!(rules(&diagnostics), vec![("subprocess".to_string(), 3)]);
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+
+    assert!(parse_python_audit_output("Traceback", &AuditPolicy::default()).is_err());
+}
+
+#[test]
+fn test_python_audit_script() {
+    let folder = tempfile::tempdir().unwrap();
+    let script_path = folder.path().join(PYTHON_AUDIT_SCRIPT_FILE_NAME);
+    std::fs::write(&script_path, PYTHON_AUDIT_SCRIPT).unwrap();
+    let code = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.py".to_string(),
+                r#""""os.system("in a docstring")"""
+import importlib
+import builtins
+from os import system as shell
+from pickle import loads
+# os.system("commented out")
+
+def run(configurations, parameters):
+    shell("ls")
+    data = loads(parameters.payload)
+    process = __import__("subprocess")
+    module = importlib.import_module(parameters.module)
+    getattr(__import__("os"), "system")("ls")
+    evaluate = builtins.eval
+    return literal_eval(data)
+"#
+                .to_string(),
+            ),
+            ("helper.py".to_string(), "import ctypes\n".to_string()),
+            ("README.md".to_string(), "import subprocess\n".to_string()),
+        ]),
+        entrypoint: "main.py".to_string(),
+    };
+    let paths = python_audit_paths(&code);
+    assert_eq!(paths, vec!["helper.py", "main.py"]);
+    for (path, content) in code.files.iter() {
+        std::fs::write(folder.path().join(path), content).unwrap();
+    }
+
+    let output = std::process::Command::new("python3")
+        .arg(&script_path)
+        .args(&paths)
+        .current_dir(folder.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let diagnostics = parse_python_audit_output(
+        &String::from_utf8_lossy(&output.stdout),
+        &AuditPolicy::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        rules(&diagnostics),
+        vec![
+            ("ctypes".to_string(), 1),
+            ("os-system".to_string(), 9),
+            ("pickle-loads".to_string(), 10),
+            ("subprocess".to_string(), 11),
+            ("dynamic-import".to_string(), 12),
+            ("os-system".to_string(), 13),
+            ("eval".to_string(), 14),
+        ]
+    );
+    assert_eq!(diagnostics[0].file, "helper.py");
+    assert_eq!(diagnostics[1].message, "`shell` runs a shell command");
+}
@@ -0,0 +1,152 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use super::{
+    check_result::CheckResult, code_files::CodeFiles, execution_storage::ExecutionStorage,
+    hash_utils::sha256_hex,
+};
+
+/// Check results not read or written for this long are removed
+pub const CHECK_CACHE_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Everything a check result depends on: the code with its lockfile, so the resolved dependencies,
+/// and the tool versions. Any change produces a different key, so stale results are never read.
+pub fn check_cache_key(code: &CodeFiles, inputs: &BTreeMap<String, String>) -> String {
+    let mut data = code.content_hash().into_bytes();
+    for (name, value) in inputs {
+        data.push(0);
+        data.extend(name.as_bytes());
+        data.push(0);
+        data.extend(value.len().to_string().as_bytes());
+        data.push(0);
+        data.extend(value.as_bytes());
+    }
+    sha256_hex(&data)
+}
+
+/// Id of the local docker image, so a rebuilt image under the same tag invalidates the results.
+/// Falls back to the image name when docker can't inspect it.
+pub async fn docker_image_id(image_name: &str) -> String {
+    match tokio::process::Command::new("docker")
+        .args(["image", "inspect", "--format", "{{.Id}}", image_name])
+        .kill_on_drop(true)
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        Ok(output) => {
+            log::warn!(
+                "failed to inspect docker image {}: {}",
+                image_name,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            image_name.to_string()
+        }
+        Err(e) => {
+            log::warn!("failed to inspect docker image {}: {}", image_name, e);
+            image_name.to_string()
+        }
+    }
+}
+
+impl ExecutionStorage {
+    pub fn check_cache_folder_path(&self) -> PathBuf {
+        self.global_cache_folder_path.join("check-cache")
+    }
+
+    /// Check result stored under `key`, flagged as a cache hit
+    pub fn read_cached_check_result(&self, key: &str) -> Option<CheckResult> {
+        let path = self.check_cache_folder_path().join(format!("{}.json", key));
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str::<CheckResult>(&content) {
+            Ok(mut check_result) => {
+                // Results in use are kept by the cleanup
+                if let Err(e) = std::fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|file| file.set_modified(std::time::SystemTime::now()))
+                {
+                    log::warn!(
+                        "failed to touch cached check result {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+                check_result.cache_hit = true;
+                Some(check_result)
+            }
+            Err(e) => {
+                log::warn!(
+                    "ignoring invalid cached check result {}: {}",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Stores a check result under `key`. Results with diagnostics that couldn't be parsed are
+    /// usually caused by the environment (network, missing tools) and aren't stored.
+    pub fn write_cached_check_result(
+        &self,
+        key: &str,
+        check_result: &CheckResult,
+    ) -> anyhow::Result<()> {
+        if check_result
+            .errors
+            .iter()
+            .chain(check_result.warnings.iter())
+            .any(|diagnostic| diagnostic.file.is_empty())
+        {
+            log::info!("not caching check result with unparsed diagnostics");
+            return Ok(());
+        }
+        let check_cache_folder_path = self.check_cache_folder_path();
+        std::fs::create_dir_all(&check_cache_folder_path)?;
+        let check_result = CheckResult {
+            cache_hit: false,
+            ..check_result.clone()
+        };
+        let temp_path = check_cache_folder_path.join(format!(".tmp-{}-{}", key, nanoid::nanoid!()));
+        std::fs::write(&temp_path, serde_json::to_string(&check_result)?)?;
+        std::fs::rename(
+            temp_path,
+            check_cache_folder_path.join(format!("{}.json", key)),
+        )?;
+        if let Err(e) = self.cleanup_stale_check_cache(CHECK_CACHE_RETENTION) {
+            log::warn!("failed to clean up stale check results: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Removes the check results not read or written for at least `max_age`
+    ///
+    /// # Returns
+    ///
+    /// The amount of removed check results
+    pub fn cleanup_stale_check_cache(&self, max_age: Duration) -> anyhow::Result<usize> {
+        let check_cache_folder_path = self.check_cache_folder_path();
+        if !check_cache_folder_path.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for entry in std::fs::read_dir(check_cache_folder_path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file()
+                || entry.metadata()?.modified()?.elapsed().unwrap_or_default() < max_age
+            {
+                continue;
+            }
+            log::info!("removing stale check result {}", entry.path().display());
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+#[path = "check_cache.test.rs"]
+mod tests;
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::tools::{
+    check_cache::check_cache_key, check_result::CheckResult, code_files::CodeFiles,
+    diagnostic::Diagnostic, execution_context::ExecutionContext,
+    execution_storage::ExecutionStorage,
+};
+
+#[test]
+fn check_cache_key_changes_with_code_and_toolchain() {
+    let code = CodeFiles {
+        files: HashMap::from([
+            ("main.py".to_string(), "print(1)".to_string()),
+            ("uv.lock".to_string(), "requests 2.32.3".to_string()),
+        ]),
+        entrypoint: "main.py".to_string(),
+    };
+    let inputs = BTreeMap::from([
+        ("ruff".to_string(), "ruff 0.8.0".to_string()),
+        ("pyright".to_string(), "1.1.390".to_string()),
+    ]);
+    let key = check_cache_key(&code, &inputs);
+    assert_eq!(key, check_cache_key(&code.clone(), &inputs.clone()));
+
+    let mut changed = code.clone();
+    changed
+        .files
+        .insert("main.py".to_string(), "print(2)".to_string());
+    assert_ne!(key, check_cache_key(&changed, &inputs));
+
+    // A new resolution of the dependencies is a new lockfile
+    let mut relocked = code.clone();
+    relocked
+        .files
+        .insert("uv.lock".to_string(), "requests 2.32.4".to_string());
+    assert_ne!(key, check_cache_key(&relocked, &inputs));
+
+    let mut upgraded = inputs.clone();
+    upgraded.insert("ruff".to_string(), "ruff 0.8.1".to_string());
+    assert_ne!(key, check_cache_key(&code, &upgraded));
+}
+
+#[tokio::test]
+async fn read_and_write_cached_check_results() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let execution_storage = ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.py".to_string(), "print(1)".to_string())]),
+            entrypoint: "main.py".to_string(),
+        },
+        ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            ..Default::default()
+        },
+    );
+    assert!(execution_storage.read_cached_check_result("key").is_none());
+
+    let check_result = CheckResult::from_diagnostics(vec![Diagnostic {
+        file: "main.py".to_string(),
+        line: 1,
+        column: 1,
+        ..Diagnostic::unparsed("ruff", "Undefined name `hello`".to_string())
+    }]);
+    execution_storage
+        .write_cached_check_result("key", &check_result)
+        .unwrap();
+    let cached = execution_storage.read_cached_check_result("key").unwrap();
+    assert!(cached.cache_hit);
+    assert_eq!(cached.errors, check_result.errors);
+
+    // Unparsed diagnostics usually come from the environment and aren't cached
+    let check_result = CheckResult::from_diagnostics(vec![Diagnostic::unparsed(
+        "deno",
+        "network error".to_string(),
+    )]);
+    execution_storage
+        .write_cached_check_result("other-key", &check_result)
+        .unwrap();
+    assert!(execution_storage
+        .read_cached_check_result("other-key")
+        .is_none());
+
+    // Results are kept while they are used and removed when they aren't anymore
+    assert_eq!(
+        execution_storage
+            .cleanup_stale_check_cache(std::time::Duration::from_secs(60))
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        execution_storage
+            .cleanup_stale_check_cache(std::time::Duration::ZERO)
+            .unwrap(),
+        1
+    );
+    assert!(execution_storage.read_cached_check_result("key").is_none());
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use super::diagnostic::{Diagnostic, DiagnosticSeverity};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+    /// The result was read from the check cache instead of running the tools
+    #[serde(default)]
+    pub cache_hit: bool,
+}
+
+impl CheckResult {
+    /// Splits diagnostics by severity, information diagnostics are reported as warnings
+    pub fn from_diagnostics(diagnostics: Vec<Diagnostic>) -> Self {
+        let (errors, warnings) = diagnostics
+            .into_iter()
+            .partition(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error);
+        Self {
+            errors,
+            warnings,
+            cache_hit: false,
+        }
+    }
+
+    /// Adds diagnostics reported by another tool
+    pub fn extend(&mut self, diagnostics: Vec<Diagnostic>) {
+        let other = Self::from_diagnostics(diagnostics);
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
@@ -1,9 +1,327 @@
 use std::path::PathBuf;
 
-use super::path_buf_ext::PathBufExt;
+use serde::Deserialize;
+
+use super::{
+    diagnostic::{Diagnostic, DiagnosticPosition, DiagnosticSeverity},
+    path_buf_ext::PathBufExt,
+};
 
 pub fn normalize_error_message(error_message: String, code_folder_path: &PathBuf) -> String {
     let file_prefix_runner = code_folder_path.as_normalized_string() + "/";
     let file_regex = regex::Regex::new(format!("file:/+{file_prefix_runner}").as_str()).unwrap();
     file_regex.replace_all(&error_message, "./").to_string()
 }
+
+/// Makes a file reported by a tool relative to the code folder
+fn relative_file(file: &str, code_folder_path: &PathBuf) -> String {
+    let file = file.replace('\\', "/");
+    let file_prefix_runner = code_folder_path.as_normalized_string() + "/";
+    let file = file.strip_prefix("file://").unwrap_or(&file);
+    let file = file
+        .strip_prefix(&file_prefix_runner)
+        .or_else(|| file.strip_prefix(file_prefix_runner.trim_start_matches('/')))
+        .unwrap_or(file);
+    file.strip_prefix("./").unwrap_or(file).to_string()
+}
+
+#[derive(Deserialize)]
+struct RuffLocation {
+    row: u32,
+    column: u32,
+}
+
+#[derive(Deserialize)]
+struct RuffMessage {
+    code: Option<String>,
+    message: String,
+    filename: String,
+    location: Option<RuffLocation>,
+    end_location: Option<RuffLocation>,
+}
+
+/// Parses the output of `ruff check --output-format json`
+pub fn parse_ruff_json(
+    output: &str,
+    code_folder_path: &PathBuf,
+) -> anyhow::Result<Vec<Diagnostic>> {
+    let messages: Vec<RuffMessage> = serde_json::from_str(output)?;
+    Ok(messages
+        .into_iter()
+        .map(|message| Diagnostic {
+            file: relative_file(&message.filename, code_folder_path),
+            line: message.location.as_ref().map_or(0, |location| location.row),
+            column: message
+                .location
+                .as_ref()
+                .map_or(0, |location| location.column),
+            end: message.end_location.map(|location| DiagnosticPosition {
+                line: location.row,
+                column: location.column,
+            }),
+            severity: DiagnosticSeverity::Error,
+            code: message.code,
+            source_tool: String::from("ruff"),
+            message: message.message,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct PyrightPosition {
+    line: u32,
+    character: u32,
+}
+
+#[derive(Deserialize)]
+struct PyrightRange {
+    start: PyrightPosition,
+    end: PyrightPosition,
+}
+
+#[derive(Deserialize)]
+struct PyrightDiagnostic {
+    file: String,
+    severity: String,
+    message: String,
+    range: Option<PyrightRange>,
+    rule: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PyrightOutput {
+    general_diagnostics: Vec<PyrightDiagnostic>,
+}
+
+/// Parses the output of `pyright --outputjson`. Pyright positions are 0-based.
+pub fn parse_pyright_json(
+    output: &str,
+    code_folder_path: &PathBuf,
+) -> anyhow::Result<Vec<Diagnostic>> {
+    let output: PyrightOutput = serde_json::from_str(output)?;
+    Ok(output
+        .general_diagnostics
+        .into_iter()
+        .map(|diagnostic| Diagnostic {
+            file: relative_file(&diagnostic.file, code_folder_path),
+            line: diagnostic
+                .range
+                .as_ref()
+                .map_or(0, |range| range.start.line + 1),
+            column: diagnostic
+                .range
+                .as_ref()
+                .map_or(0, |range| range.start.character + 1),
+            end: diagnostic.range.map(|range| DiagnosticPosition {
+                line: range.end.line + 1,
+                column: range.end.character + 1,
+            }),
+            severity: match diagnostic.severity.as_str() {
+                "error" => DiagnosticSeverity::Error,
+                "warning" => DiagnosticSeverity::Warning,
+                _ => DiagnosticSeverity::Information,
+            },
+            code: diagnostic.rule,
+            source_tool: String::from("pyright"),
+            message: diagnostic.message,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct DenoLintPosition {
+    line: u32,
+    col: u32,
+}
+
+#[derive(Deserialize)]
+struct DenoLintRange {
+    start: DenoLintPosition,
+    end: DenoLintPosition,
+}
+
+#[derive(Deserialize)]
+struct DenoLintDiagnostic {
+    filename: String,
+    range: Option<DenoLintRange>,
+    message: String,
+    code: String,
+    hint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DenoLintError {
+    file_path: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct DenoLintOutput {
+    diagnostics: Vec<DenoLintDiagnostic>,
+    errors: Vec<DenoLintError>,
+}
+
+/// Parses the output of `deno lint --json`. Lint violations are warnings, files that couldn't be linted are errors.
+/// Deno lint lines are 1-based and columns 0-based.
+pub fn parse_deno_lint_json(
+    output: &str,
+    code_folder_path: &PathBuf,
+) -> anyhow::Result<Vec<Diagnostic>> {
+    let output: DenoLintOutput = serde_json::from_str(output)?;
+    let mut diagnostics: Vec<Diagnostic> = output
+        .errors
+        .into_iter()
+        .map(|error| Diagnostic {
+            file: relative_file(&error.file_path, code_folder_path),
+            severity: DiagnosticSeverity::Error,
+            ..Diagnostic::unparsed("deno", error.message)
+        })
+        .collect();
+    diagnostics.extend(output.diagnostics.into_iter().map(|diagnostic| {
+        Diagnostic {
+            file: relative_file(&diagnostic.filename, code_folder_path),
+            line: diagnostic
+                .range
+                .as_ref()
+                .map_or(0, |range| range.start.line),
+            column: diagnostic
+                .range
+                .as_ref()
+                .map_or(0, |range| range.start.col + 1),
+            end: diagnostic.range.map(|range| DiagnosticPosition {
+                line: range.end.line,
+                column: range.end.col + 1,
+            }),
+            severity: DiagnosticSeverity::Warning,
+            code: Some(diagnostic.code),
+            source_tool: String::from("deno"),
+            message: match diagnostic.hint {
+                Some(hint) => format!("{}\nhint: {}", diagnostic.message, hint),
+                None => diagnostic.message,
+            },
+        }
+    }));
+    Ok(diagnostics)
+}
+
+/// Splits a `<file>:<line>:<column>` location
+fn parse_location(location: &str) -> Option<(String, u32, u32)> {
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    Some((parts.next()?.to_string(), line, column))
+}
+
+/// Parses the stderr of `deno check`, already normalized with [`normalize_error_message`]
+///
+/// TS errors look like:
+/// ```text
+/// error: TS2322 [ERROR]: Type 'string' is not assignable to type 'number'.
+/// const a: number = "s";
+///       ~
+///     at ./main.ts:1:7
+/// ```
+/// Other errors (parse errors, missing modules) are a single `error:` line with an optional location.
+pub fn parse_deno_check_output(output: &str, code_folder_path: &PathBuf) -> Vec<Diagnostic> {
+    let ts_header_regex =
+        regex::Regex::new(r"^(?:error: )?TS(\d+) \[(ERROR|WARNING)\]: (.*)$").unwrap();
+    let at_regex = regex::Regex::new(r"^\s+at (\S+:\d+:\d+)$").unwrap();
+    let inline_at_regex = regex::Regex::new(r"^(.*) at (\S+:\d+:\d+)$").unwrap();
+
+    let lines: Vec<&str> = output.lines().collect();
+    let mut diagnostics = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        if let Some(captures) = ts_header_regex.captures(line) {
+            // Everything until the `at` line: message continuation, code snippet and marker
+            let mut body = Vec::new();
+            let mut location = None;
+            let mut next = index + 1;
+            while next < lines.len() && !ts_header_regex.is_match(lines[next]) {
+                if let Some(at) = at_regex.captures(lines[next]) {
+                    location = parse_location(&at[1]);
+                    next += 1;
+                    break;
+                }
+                body.push(lines[next]);
+                next += 1;
+            }
+            let mut end = None;
+            if location.is_some() && body.len() >= 2 {
+                let marker = body[body.len() - 1];
+                if !marker.trim().is_empty() && marker.trim().chars().all(|c| c == '~' || c == '^')
+                {
+                    let marker_length = marker.trim().len() as u32;
+                    if let Some((_, line, column)) = &location {
+                        if marker_length > 1 {
+                            end = Some(DiagnosticPosition {
+                                line: *line,
+                                column: column + marker_length,
+                            });
+                        }
+                    }
+                    body.truncate(body.len() - 2);
+                }
+            }
+            let mut message = captures[3].to_string();
+            for continuation in body.iter().filter(|line| !line.trim().is_empty()) {
+                message.push('\n');
+                message.push_str(continuation.trim());
+            }
+            let (file, line, column) = location.unwrap_or_default();
+            diagnostics.push(Diagnostic {
+                file: relative_file(&file, code_folder_path),
+                line,
+                column,
+                end,
+                severity: if &captures[2] == "ERROR" {
+                    DiagnosticSeverity::Error
+                } else {
+                    DiagnosticSeverity::Warning
+                },
+                code: Some(format!("TS{}", &captures[1])),
+                source_tool: String::from("deno"),
+                message,
+            });
+            index = next;
+            continue;
+        }
+
+        // Summary line printed after the TS errors
+        if line.starts_with("error: Type checking failed") {
+            index += 1;
+            continue;
+        }
+        if let Some(message) = line.strip_prefix("error: ") {
+            let (message, mut location) = match inline_at_regex.captures(message) {
+                Some(captures) => (captures[1].to_string(), parse_location(&captures[2])),
+                None => (message.to_string(), None),
+            };
+            if location.is_none() {
+                location = lines
+                    .get(index + 1)
+                    .and_then(|next| at_regex.captures(next))
+                    .and_then(|at| parse_location(&at[1]));
+            }
+            let (file, line, column) = location.unwrap_or_default();
+            diagnostics.push(Diagnostic {
+                file: relative_file(&file, code_folder_path),
+                line,
+                column,
+                end: None,
+                severity: DiagnosticSeverity::Error,
+                code: None,
+                source_tool: String::from("deno"),
+                message,
+            });
+        }
+        index += 1;
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+#[path = "check_utils.test.rs"]
+mod tests;
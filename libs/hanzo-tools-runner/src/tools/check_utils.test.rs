@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use crate::tools::{
+    check_utils::{
+        normalize_error_message, parse_deno_check_output, parse_deno_lint_json, parse_pyright_json,
+        parse_ruff_json,
+    },
+    diagnostic::{DiagnosticPosition, DiagnosticSeverity},
+};
+
+fn code_folder_path() -> PathBuf {
+    PathBuf::from("/storage/context/code/abc")
+}
+
+#[test]
+fn parse_deno_ts_errors() {
+    let output = r#"Check file:///storage/context/code/abc/main.ts
+error: TS2322 [ERROR]: Type 'string' is not assignable to type 'number'.
+const a: number = "s";
+      ^
+    at file:///storage/context/code/abc/main.ts:1:7
+
+TS2345 [ERROR]: Argument of type 'string' is not assignable to parameter of type 'number'.
+  Type 'string' is not assignable to type 'number'.
+add("1", 2);
+    ~~~
+    at file:///storage/context/code/abc/utils/math.ts:5:5
+
+Found 2 errors.
+"#;
+    let output = normalize_error_message(output.to_string(), &code_folder_path());
+    let diagnostics = parse_deno_check_output(&output, &code_folder_path());
+    assert_eq!(diagnostics.len(), 2);
+
+    assert_eq!(diagnostics[0].file, "main.ts");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 7));
+    assert_eq!(diagnostics[0].end, None);
+    assert_eq!(diagnostics[0].code.as_deref(), Some("TS2322"));
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+    assert_eq!(
+        diagnostics[0].message,
+        "Type 'string' is not assignable to type 'number'."
+    );
+
+    assert_eq!(diagnostics[1].file, "utils/math.ts");
+    assert_eq!((diagnostics[1].line, diagnostics[1].column), (5, 5));
+    assert_eq!(
+        diagnostics[1].end,
+        Some(DiagnosticPosition { line: 5, column: 8 })
+    );
+    assert_eq!(
+        diagnostics[1].message,
+        "Argument of type 'string' is not assignable to parameter of type 'number'.\nType 'string' is not assignable to type 'number'."
+    );
+}
+
+#[test]
+fn parse_deno_other_errors() {
+    let output = r#"error: The module's source code could not be parsed: Expected ',', got 's' at file:///storage/context/code/abc/main.ts:2:14
+
+  console.log('test's)
+                    ~
+"#;
+    let output = normalize_error_message(output.to_string(), &code_folder_path());
+    let diagnostics = parse_deno_check_output(&output, &code_folder_path());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].file, "main.ts");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 14));
+    assert_eq!(
+        diagnostics[0].message,
+        "The module's source code could not be parsed: Expected ',', got 's'"
+    );
+
+    let output = r#"error: Module not found "file:///storage/context/code/abc/libraries/axios".
+    at file:///storage/context/code/abc/main.ts:1:19
+Stack backtrace:
+   0: std::backtrace
+"#;
+    let output = normalize_error_message(output.to_string(), &code_folder_path());
+    let diagnostics = parse_deno_check_output(&output, &code_folder_path());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].file, "main.ts");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 19));
+    assert_eq!(
+        diagnostics[0].message,
+        "Module not found \"./libraries/axios\"."
+    );
+}
+
+#[test]
+fn parse_ruff_output() {
+    let output = r#"[
+        {
+            "cell": null,
+            "code": "F821",
+            "end_location": { "column": 21, "row": 2 },
+            "filename": "/storage/context/code/abc/main.py",
+            "fix": null,
+            "location": { "column": 16, "row": 2 },
+            "message": "Undefined name `hello`",
+            "noqa_row": 2,
+            "url": "https://docs.astral.sh/ruff/rules/undefined-name"
+        }
+    ]"#;
+    let diagnostics = parse_ruff_json(output, &code_folder_path()).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].file, "main.py");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 16));
+    assert_eq!(
+        diagnostics[0].end,
+        Some(DiagnosticPosition {
+            line: 2,
+            column: 21
+        })
+    );
+    assert_eq!(diagnostics[0].code.as_deref(), Some("F821"));
+    assert_eq!(diagnostics[0].source_tool, "ruff");
+
+    assert!(parse_ruff_json("All checks passed!", &code_folder_path()).is_err());
+}
+
+#[test]
+fn parse_pyright_output() {
+    let output = r#"{
+        "version": "1.1.390",
+        "time": "1733500000000",
+        "generalDiagnostics": [
+            {
+                "file": "/storage/context/code/abc/main.py",
+                "severity": "error",
+                "message": "No parameter named \"success\"",
+                "range": {
+                    "start": { "line": 9, "character": 20 },
+                    "end": { "line": 9, "character": 27 }
+                },
+                "rule": "reportCallIssue"
+            },
+            {
+                "file": "/storage/context/code/abc/main.py",
+                "severity": "warning",
+                "message": "Import \"requests\" could not be resolved from source",
+                "range": {
+                    "start": { "line": 0, "character": 7 },
+                    "end": { "line": 0, "character": 15 }
+                },
+                "rule": "reportMissingModuleSource"
+            }
+        ],
+        "summary": { "filesAnalyzed": 1, "errorCount": 1, "warningCount": 1, "informationCount": 0 }
+    }"#;
+    let diagnostics = parse_pyright_json(output, &code_folder_path()).unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].file, "main.py");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (10, 21));
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+    assert_eq!(diagnostics[0].code.as_deref(), Some("reportCallIssue"));
+    assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Warning);
+    assert_eq!((diagnostics[1].line, diagnostics[1].column), (1, 8));
+}
+
+#[test]
+fn parse_deno_lint_output() {
+    let output = r#"{
+        "version": 1,
+        "diagnostics": [
+            {
+                "filename": "file:///storage/context/code/abc/main.ts",
+                "range": {
+                    "start": { "line": 2, "col": 6, "bytePos": 20 },
+                    "end": { "line": 2, "col": 7, "bytePos": 21 }
+                },
+                "message": "`a` is never used",
+                "code": "no-unused-vars",
+                "hint": "If this is intentional, prefix it with an underscore like `_a`"
+            }
+        ],
+        "errors": [
+            {
+                "file_path": "/storage/context/code/abc/broken.ts",
+                "message": "Expected ';', got 'x'"
+            }
+        ]
+    }"#;
+    let diagnostics = parse_deno_lint_json(output, &code_folder_path()).unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].file, "broken.ts");
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+    assert_eq!(diagnostics[1].file, "main.ts");
+    assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Warning);
+    assert_eq!((diagnostics[1].line, diagnostics[1].column), (2, 7));
+    assert_eq!(diagnostics[1].code.as_deref(), Some("no-unused-vars"));
+    assert!(diagnostics[1]
+        .message
+        .starts_with("`a` is never used\nhint: "));
+}
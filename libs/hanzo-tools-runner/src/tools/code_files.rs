@@ -1,7 +1,45 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
+
+use super::hash_utils::sha256_hex;
 
 #[derive(Default, Clone)]
 pub struct CodeFiles {
     pub files: HashMap<String, String>,
     pub entrypoint: String,
 }
+
+impl CodeFiles {
+    /// Hash of the entrypoint and every file path and content, independent of the files order
+    pub fn content_hash(&self) -> String {
+        let mut paths: Vec<&String> = self.files.keys().collect();
+        paths.sort();
+        let mut data = Vec::new();
+        data.extend(self.entrypoint.as_bytes());
+        data.push(0);
+        for path in paths {
+            let content = &self.files[path];
+            data.extend(path.as_bytes());
+            data.push(0);
+            data.extend(content.len().to_string().as_bytes());
+            data.push(0);
+            data.extend(content.as_bytes());
+        }
+        sha256_hex(&data)
+    }
+
+    /// Same files with their content read from `folder`, e.g. after a tool rewrote them
+    pub fn read_from_folder(&self, folder: &Path) -> std::io::Result<CodeFiles> {
+        let mut files = HashMap::new();
+        for path in self.files.keys() {
+            files.insert(path.clone(), std::fs::read_to_string(folder.join(path))?);
+        }
+        Ok(CodeFiles {
+            files,
+            entrypoint: self.entrypoint.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "code_files.test.rs"]
+mod tests;
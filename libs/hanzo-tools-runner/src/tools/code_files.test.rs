@@ -0,0 +1,26 @@
+use std::collections::HashMap;
+
+use crate::tools::code_files::CodeFiles;
+
+#[test]
+fn test_content_hash() {
+    let code_files = CodeFiles {
+        files: HashMap::from([
+            ("main.ts".to_string(), "run()".to_string()),
+            ("utils.ts".to_string(), "".to_string()),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    assert_eq!(code_files.content_hash(), code_files.clone().content_hash());
+    assert_eq!(code_files.content_hash().len(), 64);
+
+    let mut changed = code_files.clone();
+    changed
+        .files
+        .insert("utils.ts".to_string(), "export {}".to_string());
+    assert_ne!(code_files.content_hash(), changed.content_hash());
+
+    let mut renamed = code_files.clone();
+    renamed.entrypoint = "utils.ts".to_string();
+    assert_ne!(code_files.content_hash(), renamed.content_hash());
+}
@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    execution_storage::ExecutionStorage, file_name_utils::sanitize_for_file_name,
+    hash_utils::sha256_file_hex, path_buf_ext::PathBufExt,
+};
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextPart {
+    Home,
+    Cache,
+    Logs,
+    Code,
+    Outputs,
+}
+
+impl ContextPart {
+    pub fn all() -> Vec<ContextPart> {
+        vec![
+            ContextPart::Home,
+            ContextPart::Cache,
+            ContextPart::Logs,
+            ContextPart::Code,
+            ContextPart::Outputs,
+        ]
+    }
+
+    /// Folder of the part inside the context root folder and inside the archive
+    pub fn folder_name(&self) -> &'static str {
+        match self {
+            ContextPart::Home => "home",
+            ContextPart::Cache => "cache",
+            ContextPart::Logs => "logs",
+            ContextPart::Code => "code",
+            ContextPart::Outputs => "outputs",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// Storage folder the context lives in
+    pub storage: PathBuf,
+    pub parts: Vec<ContextPart>,
+    pub format: ArchiveFormat,
+    /// Archive file to create
+    pub destination: PathBuf,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            storage: PathBuf::from("./hanzo-tools-runner-execution-storage"),
+            parts: ContextPart::all(),
+            format: ArchiveFormat::TarGz,
+            destination: PathBuf::from("./context-export.tar.gz"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    /// Storage folder the context is recreated in
+    pub storage: PathBuf,
+    /// Imports the context with a different id than the exported one
+    pub context_id: Option<String>,
+    /// Replace the context if it already exists in the storage
+    pub overwrite: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            storage: PathBuf::from("./hanzo-tools-runner-execution-storage"),
+            context_id: None,
+            overwrite: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Path inside the archive, always starting with the part folder
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContextManifest {
+    pub version: u32,
+    pub context_id: String,
+    pub created_at: String,
+    pub parts: Vec<ContextPart>,
+    pub files: Vec<ManifestFile>,
+}
+
+fn list_files(root: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Rejects absolute paths, `..` and anything outside the exported part folders
+fn validate_entry_path(path: &Path, parts: &[ContextPart]) -> anyhow::Result<()> {
+    let mut components = path.components();
+    let first = match components.next() {
+        Some(Component::Normal(first)) => first.to_string_lossy().to_string(),
+        _ => return Err(anyhow::anyhow!("invalid archive entry {}", path.display())),
+    };
+    if components.any(|component| !matches!(component, Component::Normal(_))) {
+        return Err(anyhow::anyhow!("invalid archive entry {}", path.display()));
+    }
+    if first == MANIFEST_FILE_NAME && path.components().count() == 1 {
+        return Ok(());
+    }
+    if !parts.iter().any(|part| part.folder_name() == first) {
+        return Err(anyhow::anyhow!(
+            "archive entry {} is not part of the exported context",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn extract_tar_gz(archive_path: &Path, destination: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::open(archive_path)?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        validate_entry_path(&path, &ContextPart::all())?;
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => std::fs::create_dir_all(destination.join(&path))?,
+            tar::EntryType::Regular => {
+                let file_path = destination.join(&path);
+                if let Some(parent) = file_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut file = std::fs::File::create(file_path)?;
+                std::io::copy(&mut entry, &mut file)?;
+            }
+            entry_type => {
+                return Err(anyhow::anyhow!(
+                    "unsupported archive entry {} of type {:?}",
+                    path.display(),
+                    entry_type
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip(archive_path: &Path, destination: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let path = PathBuf::from(entry.name());
+        validate_entry_path(&path, &ContextPart::all())?;
+        if entry.is_symlink() {
+            return Err(anyhow::anyhow!(
+                "unsupported symlink archive entry {}",
+                path.display()
+            ));
+        }
+        if entry.is_dir() {
+            std::fs::create_dir_all(destination.join(&path))?;
+            continue;
+        }
+        let file_path = destination.join(&path);
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::create(file_path)?;
+        std::io::copy(&mut entry, &mut file)?;
+    }
+    Ok(())
+}
+
+/// Checks every extracted file against the manifest and that nothing else was extracted
+fn verify_extracted_files(manifest: &ContextManifest, root: &Path) -> anyhow::Result<()> {
+    for file in manifest.files.iter() {
+        validate_entry_path(Path::new(&file.path), &manifest.parts)?;
+    }
+    let mut expected: HashMap<&str, &ManifestFile> = manifest
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file))
+        .collect();
+
+    let mut files = Vec::new();
+    list_files(root, &mut files)?;
+    for file in files {
+        let path = file
+            .strip_prefix(root)?
+            .to_path_buf()
+            .as_normalized_string();
+        if path == MANIFEST_FILE_NAME {
+            continue;
+        }
+        let manifest_file = expected
+            .remove(path.as_str())
+            .ok_or_else(|| anyhow::anyhow!("file {} is not in the manifest", path))?;
+        let size = std::fs::metadata(&file)?.len();
+        if size != manifest_file.size || sha256_file_hex(&file)? != manifest_file.sha256 {
+            return Err(anyhow::anyhow!("file {} doesn't match the manifest", path));
+        }
+    }
+    if let Some(path) = expected.keys().next() {
+        return Err(anyhow::anyhow!("file {} is missing from the archive", path));
+    }
+    Ok(())
+}
+
+impl ExecutionStorage {
+    /// Packs the selected parts of a context into an archive with a manifest of every file
+    pub fn export(context_id: &str, options: &ExportOptions) -> anyhow::Result<ContextManifest> {
+        let root_folder_path = options
+            .storage
+            .join(sanitize_for_file_name(context_id.to_string()));
+        if !root_folder_path.exists() {
+            return Err(anyhow::anyhow!("context {} doesn't exist", context_id));
+        }
+
+        let mut files = Vec::new();
+        for part in options.parts.iter() {
+            let part_folder_path = root_folder_path.join(part.folder_name());
+            if part_folder_path.exists() {
+                list_files(&part_folder_path, &mut files)?;
+            }
+        }
+        let mut manifest_files = Vec::new();
+        for file in files.iter() {
+            manifest_files.push(ManifestFile {
+                path: file
+                    .strip_prefix(&root_folder_path)?
+                    .to_path_buf()
+                    .as_normalized_string(),
+                size: std::fs::metadata(file)?.len(),
+                sha256: sha256_file_hex(file)?,
+            });
+        }
+        let manifest = ContextManifest {
+            version: MANIFEST_VERSION,
+            context_id: context_id.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            parts: options.parts.clone(),
+            files: manifest_files,
+        };
+        let manifest_content = serde_json::to_vec_pretty(&manifest)?;
+
+        if let Some(parent) = options.destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let archive_file = std::fs::File::create(&options.destination)?;
+        match options.format {
+            ArchiveFormat::TarGz => {
+                let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+                    archive_file,
+                    flate2::Compression::default(),
+                ));
+                let mut header = tar::Header::new_gnu();
+                header.set_size(manifest_content.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append_data(
+                    &mut header,
+                    MANIFEST_FILE_NAME,
+                    manifest_content.as_slice(),
+                )?;
+                for (file, manifest_file) in files.iter().zip(manifest.files.iter()) {
+                    builder.append_path_with_name(file, &manifest_file.path)?;
+                }
+                builder.into_inner()?.finish()?;
+            }
+            ArchiveFormat::Zip => {
+                let mut writer = zip::ZipWriter::new(archive_file);
+                let file_options = zip::write::SimpleFileOptions::default();
+                writer.start_file(MANIFEST_FILE_NAME, file_options)?;
+                writer.write_all(&manifest_content)?;
+                for (file, manifest_file) in files.iter().zip(manifest.files.iter()) {
+                    writer.start_file(manifest_file.path.as_str(), file_options)?;
+                    std::io::copy(&mut std::fs::File::open(file)?, &mut writer)?;
+                }
+                writer.finish()?;
+            }
+        }
+        log::info!(
+            "exported context {} with {} files to {}",
+            context_id,
+            manifest.files.len(),
+            options.destination.display()
+        );
+        Ok(manifest)
+    }
+
+    /// Recreates a context from an archive created by [`ExecutionStorage::export`].
+    /// The archive is extracted and verified against its manifest before the context is replaced.
+    pub fn import(archive_path: &Path, options: &ImportOptions) -> anyhow::Result<ContextManifest> {
+        let mut magic = [0u8; 2];
+        std::fs::File::open(archive_path)?.read_exact(&mut magic)?;
+
+        std::fs::create_dir_all(&options.storage)?;
+        let staging = tempfile::Builder::new()
+            .prefix(".import-")
+            .tempdir_in(&options.storage)?;
+        match magic {
+            [0x1f, 0x8b] => extract_tar_gz(archive_path, staging.path())?,
+            [b'P', b'K'] => extract_zip(archive_path, staging.path())?,
+            _ => return Err(anyhow::anyhow!("unknown archive format")),
+        }
+
+        let manifest_path = staging.path().join(MANIFEST_FILE_NAME);
+        if !manifest_path.exists() {
+            return Err(anyhow::anyhow!("archive has no {}", MANIFEST_FILE_NAME));
+        }
+        let manifest: ContextManifest =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path)?)?;
+        if manifest.version != MANIFEST_VERSION {
+            return Err(anyhow::anyhow!(
+                "unsupported manifest version {}",
+                manifest.version
+            ));
+        }
+        verify_extracted_files(&manifest, staging.path())?;
+        std::fs::remove_file(manifest_path)?;
+
+        let context_id = options
+            .context_id
+            .clone()
+            .unwrap_or(manifest.context_id.clone());
+        let root_folder_path = options
+            .storage
+            .join(sanitize_for_file_name(context_id.clone()));
+        if root_folder_path.exists() {
+            if !options.overwrite {
+                return Err(anyhow::anyhow!("context {} already exists", context_id));
+            }
+            std::fs::remove_dir_all(&root_folder_path)?;
+        }
+        std::fs::rename(staging.into_path(), &root_folder_path)?;
+        log::info!(
+            "imported context {} with {} files into {}",
+            context_id,
+            manifest.files.len(),
+            root_folder_path.display()
+        );
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+#[path = "context_archive.test.rs"]
+mod tests;
@@ -0,0 +1,170 @@
+use std::{collections::HashMap, io::Write};
+
+use rstest::rstest;
+
+use crate::tools::{
+    code_files::CodeFiles,
+    context_archive::{ArchiveFormat, ContextPart, ExportOptions, ImportOptions},
+    execution_context::ExecutionContext,
+    execution_storage::ExecutionStorage,
+};
+
+fn create_context(storage: &std::path::Path, context_id: &str) -> ExecutionStorage {
+    let execution_storage = ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.ts".to_string(), "console.log('test');".to_string())]),
+            entrypoint: "main.ts".to_string(),
+        },
+        ExecutionContext {
+            storage: storage.to_path_buf(),
+            context_id: context_id.to_string(),
+            ..Default::default()
+        },
+    );
+    execution_storage.init(None).unwrap();
+    std::fs::create_dir_all(execution_storage.home_folder_path.join("nested")).unwrap();
+    std::fs::write(
+        execution_storage.home_folder_path.join("nested/state.json"),
+        "{\"counter\":1}",
+    )
+    .unwrap();
+    execution_storage
+}
+
+#[rstest]
+#[case::tar_gz(ArchiveFormat::TarGz)]
+#[case::zip(ArchiveFormat::Zip)]
+#[tokio::test]
+async fn export_and_import_context(#[case] format: ArchiveFormat) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let source_storage = tempfile::tempdir().unwrap();
+    let execution_storage = create_context(source_storage.path(), "source-context");
+
+    let archive_path = source_storage.path().join("export/context.archive");
+    let manifest = ExecutionStorage::export(
+        "source-context",
+        &ExportOptions {
+            storage: source_storage.path().to_path_buf(),
+            parts: vec![ContextPart::Home, ContextPart::Code],
+            format,
+            destination: archive_path.clone(),
+        },
+    )
+    .unwrap();
+    let mut paths = manifest
+        .files
+        .iter()
+        .map(|file| file.path.clone())
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            format!(
+                "code/{}/main.ts",
+                execution_storage.code_files.content_hash()
+            ),
+            format!("code/ids/{}", execution_storage.code_id),
+            format!(
+                "code/last-used/{}",
+                execution_storage.code_files.content_hash()
+            ),
+            "home/nested/state.json".to_string()
+        ]
+    );
+
+    let target_storage = tempfile::tempdir().unwrap();
+    let import_options = ImportOptions {
+        storage: target_storage.path().to_path_buf(),
+        context_id: Some("imported-context".to_string()),
+        overwrite: false,
+    };
+    ExecutionStorage::import(&archive_path, &import_options).unwrap();
+    let imported_root = target_storage.path().join("imported-context");
+    assert_eq!(
+        std::fs::read_to_string(imported_root.join("home/nested/state.json")).unwrap(),
+        "{\"counter\":1}"
+    );
+    assert!(!imported_root.join("logs").exists());
+    assert!(!imported_root.join("manifest.json").exists());
+
+    assert!(ExecutionStorage::import(&archive_path, &import_options).is_err());
+    ExecutionStorage::import(
+        &archive_path,
+        &ImportOptions {
+            overwrite: true,
+            ..import_options
+        },
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn import_rejects_unsafe_or_tampered_archives() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let write_zip = |name: &str, entries: &[(&str, &str)]| {
+        let path = storage.path().join(name);
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for (entry, content) in entries {
+            writer
+                .start_file(*entry, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        path
+    };
+    let manifest = r#"{
+        "version": 1,
+        "context_id": "tampered",
+        "created_at": "2024-01-01T00:00:00Z",
+        "parts": ["home"],
+        "files": [{ "path": "home/state.txt", "size": 1, "sha256": "6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b" }]
+    }"#;
+    let import_options = ImportOptions {
+        storage: storage.path().join("storage"),
+        ..Default::default()
+    };
+
+    let traversal = write_zip(
+        "traversal.zip",
+        &[("manifest.json", manifest), ("home/../../evil.txt", "1")],
+    );
+    assert!(ExecutionStorage::import(&traversal, &import_options).is_err());
+    assert!(!storage.path().join("evil.txt").exists());
+
+    let tampered = write_zip(
+        "tampered.zip",
+        &[("manifest.json", manifest), ("home/state.txt", "2")],
+    );
+    assert!(ExecutionStorage::import(&tampered, &import_options).is_err());
+
+    let unlisted = write_zip(
+        "unlisted.zip",
+        &[
+            ("manifest.json", manifest),
+            ("home/state.txt", "1"),
+            ("home/extra.txt", "1"),
+        ],
+    );
+    assert!(ExecutionStorage::import(&unlisted, &import_options).is_err());
+
+    let valid = write_zip(
+        "valid.zip",
+        &[("manifest.json", manifest), ("home/state.txt", "1")],
+    );
+    ExecutionStorage::import(&valid, &import_options).unwrap();
+    assert!(import_options
+        .storage
+        .join("tampered/home/state.txt")
+        .exists());
+}
@@ -0,0 +1,287 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    diagnostic::{Diagnostic, DiagnosticSeverity},
+    tool_definition::ToolDefinition,
+};
+
+/// Name of the function the runners call with `(configurations, parameters)`
+pub const ENTRY_FUNCTION_NAME: &str = "run";
+
+/// Script that checks the contract of the Python file it gets as argument with the `ast` module
+/// and prints the violations as JSON
+pub const PYTHON_CONTRACT_SCRIPT: &str = include_str!("python_contract.py");
+
+pub const PYTHON_CONTRACT_SCRIPT_FILE_NAME: &str = "__hanzo_contract__.py";
+
+fn contract_diagnostic(
+    file: &str,
+    line: u32,
+    column: u32,
+    severity: DiagnosticSeverity,
+    rule: &str,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        file: file.to_string(),
+        line,
+        column,
+        end: None,
+        severity,
+        code: Some(rule.to_string()),
+        source_tool: String::from("contract"),
+        message,
+    }
+}
+
+#[derive(Deserialize)]
+struct PythonContractViolation {
+    line: u32,
+    column: u32,
+    severity: DiagnosticSeverity,
+    rule: String,
+    message: String,
+}
+
+/// Diagnostics of the violations `PYTHON_CONTRACT_SCRIPT` printed for `file`
+pub fn parse_python_contract_output(file: &str, output: &str) -> anyhow::Result<Vec<Diagnostic>> {
+    let violations: Vec<PythonContractViolation> = serde_json::from_str(output.trim())?;
+    Ok(violations
+        .into_iter()
+        .map(|violation| {
+            contract_diagnostic(
+                file,
+                violation.line,
+                violation.column,
+                violation.severity,
+                &violation.rule,
+                violation.message,
+            )
+        })
+        .collect())
+}
+
+/// TypeScript type of the values a JSON schema allows, `any` when the schema doesn't constrain them
+fn typescript_type(schema: &Value) -> String {
+    let literals = match (schema.get("const"), schema.get("enum")) {
+        (Some(value), _) => Some(vec![value.clone()]),
+        (None, Some(Value::Array(values))) => Some(values.clone()),
+        _ => None,
+    };
+    if let Some(literals) = literals {
+        // JSON strings, numbers, booleans and null are also TypeScript literal types
+        if literals
+            .iter()
+            .all(|literal| !literal.is_array() && !literal.is_object())
+        {
+            return literals
+                .iter()
+                .map(|literal| literal.to_string())
+                .collect::<Vec<String>>()
+                .join(" | ");
+        }
+        return String::from("any");
+    }
+
+    let schema_types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(schema_type)) => vec![schema_type.as_str()],
+        Some(Value::Array(schema_types)) => schema_types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if schema_types.is_empty() {
+        return String::from("any");
+    }
+    schema_types
+        .into_iter()
+        .map(|schema_type| match schema_type {
+            "string" => String::from("string"),
+            "number" | "integer" => String::from("number"),
+            "boolean" => String::from("boolean"),
+            "null" => String::from("null"),
+            "array" => format!(
+                "Array<{}>",
+                schema
+                    .get("items")
+                    .map_or(String::from("any"), typescript_type)
+            ),
+            "object" => typescript_object_type(schema),
+            _ => String::from("any"),
+        })
+        .collect::<Vec<String>>()
+        .join(" | ")
+}
+
+fn typescript_object_type(schema: &Value) -> String {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return String::from("Record<string, any>");
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let members: Vec<String> = properties
+        .iter()
+        .map(|(name, property)| {
+            format!(
+                "{}{}: {}",
+                Value::String(name.clone()),
+                if required.contains(&name.as_str()) {
+                    ""
+                } else {
+                    "?"
+                },
+                typescript_type(property)
+            )
+        })
+        .collect();
+    format!("{{ {} }}", members.join("; "))
+}
+
+struct ContractAssertion {
+    /// Line of the assertion in the entrypoint it's appended to
+    line: u32,
+    rule: &'static str,
+    message: String,
+}
+
+/// Assertions appended to a TypeScript entrypoint so `deno check` verifies that `run` can be called
+/// with `(configurations, parameters)` and, when a tool definition is given, that its parameter
+/// types accept every value the schemas of the definition allow
+pub struct TypescriptContract {
+    file: String,
+    code: String,
+    assertions: Vec<ContractAssertion>,
+}
+
+impl TypescriptContract {
+    pub fn new(file: &str, code: &str, tool_definition: Option<&ToolDefinition>) -> Self {
+        let mut assertions = vec![
+            (
+                format!("const __hanzoContractRun = {};", ENTRY_FUNCTION_NAME),
+                "missing-entry-function",
+                format!(
+                    "no `{}(configurations, parameters)` function found",
+                    ENTRY_FUNCTION_NAME
+                ),
+            ),
+            (
+                format!(
+                    "const __hanzoContractArity: (configurations: any, parameters: any) => unknown = {};",
+                    ENTRY_FUNCTION_NAME
+                ),
+                "entry-function-arity",
+                format!(
+                    "`{}` can't be called with (configurations, parameters)",
+                    ENTRY_FUNCTION_NAME
+                ),
+            ),
+        ];
+        if let Some(tool_definition) = tool_definition {
+            assertions.extend([
+                (
+                    format!(
+                        "const __hanzoContractConfigurations: (configurations: {}, parameters: any) => unknown = {};",
+                        typescript_type(&tool_definition.configurations),
+                        ENTRY_FUNCTION_NAME
+                    ),
+                    "schema-mismatch",
+                    format!(
+                        "the configurations type of `{}` doesn't match the tool definition configurations",
+                        ENTRY_FUNCTION_NAME
+                    ),
+                ),
+                (
+                    format!(
+                        "const __hanzoContractParameters: (configurations: any, parameters: {}) => unknown = {};",
+                        typescript_type(&tool_definition.parameters),
+                        ENTRY_FUNCTION_NAME
+                    ),
+                    "schema-mismatch",
+                    format!(
+                        "the parameters type of `{}` doesn't match the tool definition parameters",
+                        ENTRY_FUNCTION_NAME
+                    ),
+                ),
+            ]);
+        }
+
+        let mut contract_code = format!("{}\n// Runner contract\n", code);
+        let mut line = contract_code.matches('\n').count() as u32 + 1;
+        let assertions = assertions
+            .into_iter()
+            .map(|(assertion, rule, message)| {
+                contract_code.push_str(&assertion);
+                contract_code.push('\n');
+                line += 1;
+                ContractAssertion {
+                    line: line - 1,
+                    rule,
+                    message,
+                }
+            })
+            .collect();
+        Self {
+            file: file.to_string(),
+            code: contract_code,
+            assertions,
+        }
+    }
+
+    /// The entrypoint code with the assertions appended
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Replaces the `deno check` diagnostics of the assertions with contract diagnostics, the
+    /// diagnostics of the code are returned as they are
+    pub fn resolve_diagnostics(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let first_line = self.assertions.first().map_or(u32::MAX, |a| a.line);
+        let (contract_diagnostics, mut diagnostics): (Vec<Diagnostic>, Vec<Diagnostic>) =
+            diagnostics.into_iter().partition(|diagnostic| {
+                diagnostic.file == self.file && diagnostic.line >= first_line
+            });
+
+        let suggestion_regex = Regex::new(r"Did you mean '([\w$]+)'").unwrap();
+        for (index, assertion) in self.assertions.iter().enumerate() {
+            let Some(failure) = contract_diagnostics
+                .iter()
+                .find(|diagnostic| diagnostic.line == assertion.line)
+            else {
+                continue;
+            };
+            let (rule, message) = match suggestion_regex.captures(&failure.message) {
+                Some(captures) if index == 0 => (
+                    "misnamed-entry-function",
+                    format!(
+                        "the entry function must be named `{}`, found `{}`",
+                        ENTRY_FUNCTION_NAME, &captures[1]
+                    ),
+                ),
+                _ => (
+                    assertion.rule,
+                    format!("{}: {}", assertion.message, failure.message),
+                ),
+            };
+            diagnostics.push(contract_diagnostic(
+                &self.file,
+                0,
+                0,
+                DiagnosticSeverity::Error,
+                rule,
+                message,
+            ));
+            // Every later assertion fails too when `run` is missing or can't be called
+            if assertion.rule != "schema-mismatch" {
+                break;
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+#[path = "contract.test.rs"]
+mod tests;
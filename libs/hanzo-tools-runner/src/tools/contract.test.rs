@@ -0,0 +1,254 @@
+use serde_json::json;
+
+use crate::tools::{
+    contract::{
+        parse_python_contract_output, TypescriptContract, PYTHON_CONTRACT_SCRIPT,
+        PYTHON_CONTRACT_SCRIPT_FILE_NAME,
+    },
+    diagnostic::{Diagnostic, DiagnosticSeverity},
+    tool_definition::ToolDefinition,
+};
+
+fn tool_definition(
+    configurations: serde_json::Value,
+    parameters: serde_json::Value,
+) -> ToolDefinition {
+    ToolDefinition {
+        id: String::from("tool"),
+        name: String::from("tool"),
+        description: String::new(),
+        author: String::new(),
+        keywords: Vec::new(),
+        configurations,
+        parameters,
+        result: json!({}),
+        code: None,
+        embedding_metadata: None,
+    }
+}
+
+fn codes(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.code.clone().unwrap_or_default())
+        .collect()
+}
+
+/// 1-based line of the contract code that starts with `prefix`
+fn assertion_line(contract: &TypescriptContract, prefix: &str) -> u32 {
+    contract
+        .code()
+        .lines()
+        .position(|line| line.starts_with(prefix))
+        .unwrap() as u32
+        + 1
+}
+
+fn deno_diagnostic(line: u32, code: &str, message: &str) -> Diagnostic {
+    Diagnostic {
+        file: String::from("main.ts"),
+        line,
+        column: 7,
+        end: None,
+        severity: DiagnosticSeverity::Error,
+        code: Some(code.to_string()),
+        source_tool: String::from("deno"),
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn test_typescript_contract_code() {
+    let definition = tool_definition(
+        json!({
+            "type": "object",
+            "properties": { "apiKey": { "type": "string" } },
+            "required": ["apiKey"]
+        }),
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "retries": { "type": "integer" },
+                "mode": { "enum": ["fast", "slow"] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["url"]
+        }),
+    );
+    let code = "export async function run(c: CONFIG, p: INPUTS) {}";
+    let contract = TypescriptContract::new("main.ts", code, Some(&definition));
+    assert!(contract.code().starts_with(code));
+    assert!(contract.code().contains("const __hanzoContractRun = run;"));
+    assert!(contract.code().contains(
+        r#"const __hanzoContractConfigurations: (configurations: { "apiKey": string }, parameters: any) => unknown = run;"#
+    ));
+    assert!(contract.code().contains(
+        r#"const __hanzoContractParameters: (configurations: any, parameters: { "mode"?: "fast" | "slow"; "retries"?: number; "tags"?: Array<string>; "url": string }) => unknown = run;"#
+    ));
+
+    // Without a tool definition only the entry function is asserted
+    let contract = TypescriptContract::new("main.ts", code, None);
+    assert!(contract.code().contains("__hanzoContractArity"));
+    assert!(!contract.code().contains("__hanzoContractParameters"));
+}
+
+#[test]
+fn test_typescript_contract_missing_and_misnamed_run() {
+    let code = "async function runTool(c, p) {}";
+    let contract = TypescriptContract::new("main.ts", code, None);
+    let run_line = assertion_line(&contract, "const __hanzoContractRun");
+    let arity_line = assertion_line(&contract, "const __hanzoContractArity");
+
+    // Every assertion fails when `run` is missing, only the first one is reported
+    let diagnostics = contract.resolve_diagnostics(vec![
+        deno_diagnostic(
+            run_line,
+            "TS2552",
+            "Cannot find name 'run'. Did you mean 'runTool'?",
+        ),
+        deno_diagnostic(
+            arity_line,
+            "TS2552",
+            "Cannot find name 'run'. Did you mean 'runTool'?",
+        ),
+    ]);
+    assert_eq!(codes(&diagnostics), vec!["misnamed-entry-function"]);
+    assert!(diagnostics[0].message.contains("`runTool`"));
+    assert_eq!(diagnostics[0].source_tool, "contract");
+    assert_eq!(diagnostics[0].line, 0);
+
+    let diagnostics = contract.resolve_diagnostics(vec![deno_diagnostic(
+        run_line,
+        "TS2304",
+        "Cannot find name 'run'.",
+    )]);
+    assert_eq!(codes(&diagnostics), vec!["missing-entry-function"]);
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+}
+
+#[test]
+fn test_typescript_contract_arity_and_schema_mismatch() {
+    let definition = tool_definition(
+        json!({ "type": "object", "properties": {} }),
+        json!({
+            "type": "object",
+            "properties": { "retries": { "type": "number" } }
+        }),
+    );
+    let code = "type INPUTS = { retries?: string };\nexport function run(c: any, p: INPUTS) {}\nconst x: number = 'a';";
+    let contract = TypescriptContract::new("main.ts", code, Some(&definition));
+    let arity_line = assertion_line(&contract, "const __hanzoContractArity");
+    let parameters_line = assertion_line(&contract, "const __hanzoContractParameters");
+
+    let code_diagnostic = deno_diagnostic(
+        3,
+        "TS2322",
+        "Type 'string' is not assignable to type 'number'.",
+    );
+    let diagnostics = contract.resolve_diagnostics(vec![
+        code_diagnostic.clone(),
+        deno_diagnostic(
+            parameters_line,
+            "TS2322",
+            "Types of property 'retries' are incompatible.",
+        ),
+    ]);
+    assert_eq!(diagnostics[0], code_diagnostic);
+    assert_eq!(codes(&diagnostics[1..]), vec!["schema-mismatch"]);
+    assert!(diagnostics[1].message.contains("parameters type of `run`"));
+    assert!(diagnostics[1].message.contains("'retries'"));
+
+    // The schema assertions fail too when `run` takes more than two parameters
+    let diagnostics = contract.resolve_diagnostics(vec![
+        deno_diagnostic(
+            arity_line,
+            "TS2322",
+            "Target signature provides too few arguments. Expected 3 or more, but got 2.",
+        ),
+        deno_diagnostic(
+            parameters_line,
+            "TS2322",
+            "Target signature provides too few arguments. Expected 3 or more, but got 2.",
+        ),
+    ]);
+    assert_eq!(codes(&diagnostics), vec!["entry-function-arity"]);
+}
+
+#[test]
+fn test_parse_python_contract_output() {
+    let output = r#"[{"line": 5, "column": 1, "severity": "error", "rule": "misnamed-entry-function", "message": "the entry function must be named `run`, found `run_tool`"}, {"line": 5, "column": 1, "severity": "warning", "rule": "missing-contract-class", "message": "no `CONFIG` class found"}]
+"#;
+    let diagnostics = parse_python_contract_output("main.py", output).unwrap();
+    assert_eq!(
+        codes(&diagnostics),
+        vec!["misnamed-entry-function", "missing-contract-class"]
+    );
+    assert_eq!(diagnostics[0].file, "main.py");
+    assert_eq!(diagnostics[0].line, 5);
+    assert_eq!(diagnostics[0].source_tool, "contract");
+    assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Warning);
+
+    assert!(parse_python_contract_output("main.py", "[]")
+        .unwrap()
+        .is_empty());
+    assert!(parse_python_contract_output("main.py", "Traceback").is_err());
+}
+
+#[test]
+fn test_python_contract_script() {
+    let folder = tempfile::tempdir().unwrap();
+    let script_path = folder.path().join(PYTHON_CONTRACT_SCRIPT_FILE_NAME);
+    std::fs::write(&script_path, PYTHON_CONTRACT_SCRIPT).unwrap();
+    let check = |code: &str| {
+        let entrypoint = folder.path().join("main.py");
+        std::fs::write(&entrypoint, code).unwrap();
+        let output = std::process::Command::new("python3")
+            .arg(&script_path)
+            .arg(&entrypoint)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        parse_python_contract_output("main.py", &String::from_utf8_lossy(&output.stdout)).unwrap()
+    };
+
+    let valid = r#"
+class CONFIG:
+    pass
+
+class INPUTS:
+    pass
+
+async def run(c: CONFIG, p: INPUTS):
+    async def fetch():
+        return 1
+    return await fetch()
+"#;
+    assert!(check(valid).is_empty());
+
+    let diagnostics = check("def run_tool(c, p):\n    pass\n");
+    assert_eq!(
+        codes(&diagnostics),
+        vec![
+            "misnamed-entry-function",
+            "missing-contract-class",
+            "missing-contract-class"
+        ]
+    );
+    assert_eq!(diagnostics[0].line, 1);
+
+    let diagnostics = check("from models import CONFIG, INPUTS\n\ndef run(c):\n    await x()\n");
+    assert_eq!(
+        codes(&diagnostics),
+        vec!["entry-function-arity", "sync-async-mismatch"]
+    );
+    assert_eq!(diagnostics[0].line, 3);
+
+    let diagnostics =
+        check("CONFIG = dict\nINPUTS = dict\nasync def run(c, p):\n    asyncio.run(x())\n");
+    assert_eq!(codes(&diagnostics), vec!["sync-async-mismatch"]);
+
+    // Docstrings and strings that look like code are not parsed as code
+    let diagnostics = check("\"\"\"def run(c, p): pass\"\"\"\nCONFIG = INPUTS = dict\n");
+    assert_eq!(codes(&diagnostics), vec!["missing-entry-function"]);
+}
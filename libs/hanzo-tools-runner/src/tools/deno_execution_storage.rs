@@ -1,4 +1,44 @@
-use super::{execution_storage::ExecutionStorage, runner_type::RunnerType};
+use std::path::Path;
+
+use super::{execution_storage::ExecutionStorage, hash_utils::sha256_hex, runner_type::RunnerType};
+
+fn copy_missing_files(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(destination)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let destination_path = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_missing_files(&entry.path(), &destination_path)?;
+        } else if !destination_path.exists() {
+            std::fs::copy(entry.path(), destination_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fingerprint of a vendored modules folder, made of its path and the modification times of its
+/// folders. Adding or removing modules changes the modification time of their parent folder, so
+/// the files themselves don't have to be read.
+fn vendored_modules_fingerprint(vendored_modules_path: &Path) -> std::io::Result<String> {
+    let mut entries = Vec::new();
+    let mut pending = vec![vendored_modules_path.to_path_buf()];
+    while let Some(folder) = pending.pop() {
+        let modified = std::fs::metadata(&folder)?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        entries.push(format!("{}:{}", folder.display(), modified));
+        for entry in std::fs::read_dir(&folder)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                pending.push(entry.path());
+            }
+        }
+    }
+    entries.sort();
+    Ok(sha256_hex(entries.join("\n").as_bytes()))
+}
 
 impl ExecutionStorage {
     fn deno_cache_folder_path_host(&self) -> std::path::PathBuf {
@@ -13,6 +53,71 @@ impl ExecutionStorage {
             RunnerType::Docker => self.deno_cache_folder_path_docker(),
         }
     }
+    /// Copies the modules of a vendored deno cache that are missing from the deno cache.
+    /// Modules already in the cache are kept, so the vendored folder can be read-only.
+    /// A marker keyed by the fingerprint of the vendored folder is left in the deno cache, so the
+    /// folder is only copied again when modules are added to or removed from it.
+    pub fn seed_deno_cache(
+        &self,
+        runner_type: RunnerType,
+        vendored_modules_path: &Path,
+    ) -> anyhow::Result<()> {
+        let deno_cache_folder_path = self.deno_cache_folder_path(runner_type);
+        let fingerprint = vendored_modules_fingerprint(vendored_modules_path).map_err(|e| {
+            anyhow::anyhow!(
+                "failed to read vendored modules from {}: {}",
+                vendored_modules_path.display(),
+                e
+            )
+        })?;
+        let marker_path = deno_cache_folder_path.join(format!(".vendored-{}", fingerprint));
+        if marker_path.exists() {
+            log::info!(
+                "deno cache already seeded from {}",
+                vendored_modules_path.display()
+            );
+            return Ok(());
+        }
+
+        log::info!(
+            "seeding deno cache from {}",
+            vendored_modules_path.display()
+        );
+        copy_missing_files(vendored_modules_path, &deno_cache_folder_path).map_err(|e| {
+            anyhow::anyhow!(
+                "failed to copy vendored modules from {}: {}",
+                vendored_modules_path.display(),
+                e
+            )
+        })?;
+        std::fs::write(
+            &marker_path,
+            vendored_modules_path.to_string_lossy().as_bytes(),
+        )?;
+        Ok(())
+    }
+    pub fn deno_locks_folder_path(&self) -> std::path::PathBuf {
+        self.global_cache_folder_path.join("deno-locks")
+    }
+
+    /// `deno.lock` stored by `DenoRunner::lock` for the code with `key`
+    pub fn read_deno_lock(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(self.deno_locks_folder_path().join(format!("{}.lock", key))).ok()
+    }
+
+    /// Stores a `deno.lock` under `key`, replacing the previous one atomically
+    pub fn write_deno_lock(&self, key: &str, lock: &str) -> anyhow::Result<()> {
+        let deno_locks_folder_path = self.deno_locks_folder_path();
+        std::fs::create_dir_all(&deno_locks_folder_path)?;
+        let temp_path = deno_locks_folder_path.join(format!(".tmp-{}-{}", key, nanoid::nanoid!()));
+        std::fs::write(&temp_path, lock)?;
+        std::fs::rename(
+            temp_path,
+            deno_locks_folder_path.join(format!("{}.lock", key)),
+        )?;
+        Ok(())
+    }
+
     pub fn init_for_deno(
         &self,
         pristine_cache: Option<bool>,
@@ -51,13 +156,10 @@ impl ExecutionStorage {
             e
         })?;
 
-        log::info!("creating deno.json file");
-        let deno_json_path = self.code_folder_path.join("deno.json");
-        std::fs::write(&deno_json_path, "").map_err(|e| {
-            log::error!("failed to write deno.json file: {}", e);
-            e
-        })?;
-
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[path = "deno_execution_storage.test.rs"]
+mod tests;
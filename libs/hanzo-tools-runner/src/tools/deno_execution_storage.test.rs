@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use crate::tools::{
+    code_files::CodeFiles, execution_context::ExecutionContext,
+    execution_storage::ExecutionStorage, runner_type::RunnerType,
+};
+
+#[tokio::test]
+async fn seed_deno_cache_copies_once_per_vendored_folder_state() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage_folder = tempfile::tempdir().unwrap();
+    let storage = ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.ts".to_string(), "console.log('test');".to_string())]),
+            entrypoint: "main.ts".to_string(),
+        },
+        ExecutionContext {
+            storage: storage_folder.path().to_path_buf(),
+            ..Default::default()
+        },
+    );
+    storage.init_for_deno(None, RunnerType::Host).unwrap();
+    let deno_cache = storage.deno_cache_folder_path(RunnerType::Host);
+
+    let vendored = tempfile::tempdir().unwrap();
+    let module_folder = vendored.path().join("remote/https/example.com");
+    std::fs::create_dir_all(&module_folder).unwrap();
+    std::fs::write(module_folder.join("mod"), "export const a = 1;").unwrap();
+
+    storage
+        .seed_deno_cache(RunnerType::Host, vendored.path())
+        .unwrap();
+    let cached_module = deno_cache.join("remote/https/example.com/mod");
+    assert!(cached_module.exists());
+
+    // The folder isn't copied again while it's unchanged
+    std::fs::remove_file(&cached_module).unwrap();
+    storage
+        .seed_deno_cache(RunnerType::Host, vendored.path())
+        .unwrap();
+    assert!(!cached_module.exists());
+
+    // Adding a module changes the fingerprint of the folder, after the timestamp granularity
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    std::fs::write(module_folder.join("other"), "export const b = 2;").unwrap();
+    storage
+        .seed_deno_cache(RunnerType::Host, vendored.path())
+        .unwrap();
+    assert!(cached_module.exists());
+    assert!(deno_cache.join("remote/https/example.com/other").exists());
+}
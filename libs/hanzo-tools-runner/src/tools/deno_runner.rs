@@ -1,26 +1,36 @@
 use regex::Regex;
-use serde_json::Value;
-use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    sync::Mutex,
-};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedSender;
 
 use crate::tools::{
-    check_utils::normalize_error_message,
+    audit::audit_typescript,
+    check_cache::{check_cache_key, docker_image_id},
+    check_result::CheckResult,
+    check_utils::{normalize_error_message, parse_deno_check_output, parse_deno_lint_json},
+    contract::TypescriptContract,
+    dependency_policy::{deno_dependencies, deno_graph_dependencies, DependencyPolicyError},
+    diagnostic::Diagnostic,
     execution_storage::ExecutionStorage,
-    file_name_utils::{adapt_paths_in_value, normalize_for_docker_path},
+    file_changes::ChangeTracker,
+    file_name_utils::adapt_paths_in_value,
+    fix_result::FixResult,
+    home_transaction::HomeTransactionMode,
+    mounts::{docker_mount_params, docker_path_mappings, mount_envs, MountMode, ResolvedMount},
+    offline::explain_offline_error,
     path_buf_ext::PathBufExt,
+    prepare::{with_prepare_timeout, PrepareEvent, PrepareProgress, PrepareStep},
+    process_utils::run_process,
     runner_type::{resolve_runner_type, RunnerType},
+    sbom::{cyclonedx_json, deno_info_components},
 };
 
 use super::{
     code_files::CodeFiles, deno_runner_options::DenoRunnerOptions, execution_error::ExecutionError,
-    run_result::RunResult,
+    run_artifact::RunArtifact, run_result::RunResult,
 };
 use std::{
-    collections::{HashMap, HashSet},
-    path::{self, PathBuf},
-    sync::Arc,
+    collections::{BTreeMap, HashMap},
+    path::{self, Path, PathBuf},
     time::Duration,
 };
 
@@ -33,6 +43,9 @@ pub struct DenoRunner {
 
 impl DenoRunner {
     pub const MAX_EXECUTION_TIME_MS_INTERNAL_OPS: u64 = 1000;
+    pub const DENO_JSON_FILE_NAME: &'static str = "deno.json";
+    pub const DENO_LOCK_FILE_NAME: &'static str = "deno.lock";
+    pub const PACKAGE_JSON_FILE_NAME: &'static str = "package.json";
 
     pub fn new(
         code_files: CodeFiles,
@@ -47,44 +60,207 @@ impl DenoRunner {
         }
     }
 
+    /// Merges the settings the runner needs into the deno.json shipped with the code, or into an
+    /// empty one so deno doesn't pick up a config file from a parent folder. Tool settings like
+    /// `imports`, `compilerOptions` and `nodeModulesDir` are kept.
+    /// It's part of the code files so it's included in the immutable code snapshot.
+    /// With a tool `nodeModulesDir` deno installs `node_modules` next to the code, guarded by its
+    /// own lock on the folder.
+    pub fn extend_with_deno_json(code_files: CodeFiles) -> anyhow::Result<CodeFiles> {
+        let mut code_files = code_files;
+        let mut deno_json = match code_files.files.get(Self::DENO_JSON_FILE_NAME) {
+            Some(content) if !content.trim().is_empty() => {
+                serde_json::from_str::<Value>(content)
+                    .map_err(|e| anyhow::anyhow!("invalid deno.json: {}", e))?
+            }
+            _ => json!({}),
+        };
+        let deno_json_object = deno_json
+            .as_object_mut()
+            .ok_or_else(|| anyhow::anyhow!("invalid deno.json: expected an object"))?;
+
+        // npm packages listed in package.json are resolved from the deno cache, so nothing is
+        // installed next to the code in the immutable snapshot
+        if code_files.files.contains_key(Self::PACKAGE_JSON_FILE_NAME)
+            && !deno_json_object.contains_key("nodeModulesDir")
+        {
+            deno_json_object.insert(String::from("nodeModulesDir"), json!("none"));
+        }
+        // The lockfile is always next to deno.json so `lock` creates it and `--frozen` enforces it,
+        // code without one runs with `--no-lock` so deno doesn't write it into the snapshot
+        deno_json_object.insert(String::from("lock"), json!(Self::DENO_LOCK_FILE_NAME));
+
+        log::info!("autogenerated deno.json: {}", deno_json);
+        code_files.files.insert(
+            Self::DENO_JSON_FILE_NAME.to_string(),
+            serde_json::to_string_pretty(&deno_json)?,
+        );
+        Ok(code_files)
+    }
+
+    /// Adds an empty deno.json when the code has none so `deno lint` and `deno fmt` don't pick up a
+    /// config file from a parent folder. The deno.json shipped with the code is used as is.
+    fn ensure_deno_json(code_files: CodeFiles) -> CodeFiles {
+        let mut code_files = code_files;
+        code_files
+            .files
+            .entry(Self::DENO_JSON_FILE_NAME.to_string())
+            .or_default();
+        code_files
+    }
+
+    /// Fails with a `DependencyPolicyError` when a module the code imports violates
+    /// `dependency_policy`. The specifiers in the code are checked before anything is downloaded,
+    /// then every module of the graph `deno info` resolves, with their transitive imports.
+    async fn enforce_dependency_policy(&self, runner_type: RunnerType) -> anyhow::Result<()> {
+        let policy = &self.options.dependency_policy;
+        policy.enforce(&deno_dependencies(&self.code)?)?;
+        if policy.is_unrestricted() {
+            return Ok(());
+        }
+        let code = Self::extend_with_deno_json(self.code_with_lock())?;
+        let execution_storage = ExecutionStorage::new(code.clone(), self.options.context.clone());
+        self.init_execution_storage(&execution_storage, runner_type.clone())?;
+        let scratch = execution_storage.create_scratch_code_folder()?;
+        let deno_info = self
+            .deno_info(
+                &execution_storage,
+                scratch.path(),
+                &code.entrypoint,
+                runner_type,
+            )
+            .await?;
+        policy.enforce(&deno_graph_dependencies(&deno_info, &self.jsr_url()))?;
+        Ok(())
+    }
+
+    /// URL of the registry `jsr:` specifiers resolve to
+    fn jsr_url(&self) -> String {
+        self.options
+            .jsr_registry
+            .clone()
+            .unwrap_or_else(|| String::from("https://jsr.io/"))
+    }
+
+    /// Code without its lockfile, its hash is the key lockfiles are stored under
+    fn code_without_lock(&self) -> CodeFiles {
+        let mut code = self.code.clone();
+        code.files.remove(Self::DENO_LOCK_FILE_NAME);
+        code
+    }
+
+    /// Code with its `deno.lock`, when there is one.
+    /// A lockfile supplied with the code wins over the one stored by `lock`.
+    fn code_with_lock(&self) -> CodeFiles {
+        let mut code = self.code.clone();
+        if !code.files.contains_key(Self::DENO_LOCK_FILE_NAME) {
+            let lock = ExecutionStorage::new(self.code.clone(), self.options.context.clone())
+                .read_deno_lock(&self.code_without_lock().content_hash());
+            if let Some(lock) = lock {
+                log::info!("using stored deno.lock");
+                code.files
+                    .insert(Self::DENO_LOCK_FILE_NAME.to_string(), lock);
+            }
+        }
+        code
+    }
+
+    /// Flags risky patterns in the code (subprocesses, native libraries, dynamic code evaluation,
+    /// remote dynamic imports) with the severities of `audit_policy`, without running it
+    pub fn audit(&self) -> Vec<Diagnostic> {
+        audit_typescript(&self.code, &self.options.audit_policy)
+    }
+
     /// Checks the code for errors without running it
     ///
     /// # Returns
     ///
     /// Returns a Result containing:
-    /// - Ok(Vec<String>): The list of errors found in the code
+    /// - Ok(CheckResult): The errors and warnings found in the code
     /// - Err(anyhow::Error): Any errors that occurred during setup or execution
-    pub async fn check(&self) -> anyhow::Result<Vec<String>> {
-        let execution_storage =
-            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
-        execution_storage.init_for_deno(None, RunnerType::Host)?;
+    pub async fn check(&self) -> anyhow::Result<CheckResult> {
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        self.enforce_dependency_policy(runner_type.clone()).await?;
+        let mut code = Self::extend_with_deno_json(self.code_with_lock())?;
+        // Unlocked code is resolved first so the key covers the remote modules the check uses, the
+        // check runs uncached when they can't be resolved
+        let mut cacheable = true;
+        if !code.files.contains_key(Self::DENO_LOCK_FILE_NAME) {
+            match self.resolve_lock(&code, runner_type.clone()).await {
+                Ok(Some(lock)) => {
+                    code.files
+                        .insert(Self::DENO_LOCK_FILE_NAME.to_string(), lock);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("not caching check result, dependencies not resolved: {}", e);
+                    cacheable = false;
+                }
+            }
+        }
+        // The contract is type checked with the code, as assertions appended to the entrypoint
+        let contract = self
+            .code
+            .files
+            .get(&self.code.entrypoint)
+            .map(|entrypoint_code| {
+                TypescriptContract::new(
+                    &self.code.entrypoint,
+                    entrypoint_code,
+                    self.options.tool_definition.as_ref(),
+                )
+            });
+        if let Some(contract) = &contract {
+            code.files
+                .insert(self.code.entrypoint.clone(), contract.code().to_string());
+        }
+        let execution_storage = ExecutionStorage::new(code.clone(), self.options.context.clone());
+        self.init_execution_storage(&execution_storage, runner_type.clone())?;
+
+        let cache_key = check_cache_key(&code, &self.check_cache_inputs(&runner_type).await);
+        if cacheable {
+            if let Some(check_result) = execution_storage.read_cached_check_result(&cache_key) {
+                log::info!("using cached check result {}", cache_key);
+                return Ok(check_result);
+            }
+        }
 
-        let binary_path = path::absolute(self.options.deno_binary_path.clone())
-            .unwrap()
-            .to_string_lossy()
-            .to_string();
-        let mut command = tokio::process::Command::new(binary_path);
+        // Reported file paths are relative to where the code folder is seen by deno
+        let (mut command, code_folder_path) = match runner_type {
+            RunnerType::Host => (
+                self.check_command_in_host(&execution_storage),
+                execution_storage.code_folder_path.clone(),
+            ),
+            RunnerType::Docker => (
+                self.deno_command_in_docker(
+                    &execution_storage,
+                    &execution_storage.code_folder_path,
+                    "check",
+                    [
+                        self.package_args(&execution_storage.code_files),
+                        vec![Self::docker_entrypoint_path(
+                            &execution_storage,
+                            &execution_storage.code_folder_path,
+                        )],
+                    ]
+                    .concat(),
+                ),
+                PathBuf::from(format!(
+                    "/app/{}",
+                    execution_storage.relative_to_root(execution_storage.code_folder_path.clone())
+                )),
+            ),
+        };
         command
-            .args([
-                "check",
-                execution_storage
-                    .code_entrypoint_file_path
-                    .to_str()
-                    .unwrap(),
-            ])
-            .env_clear()
-            .env("NO_COLOR", "true")
-            .current_dir(execution_storage.code_folder_path.clone())
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .kill_on_drop(true);
         let output = command.spawn()?.wait_with_output().await?;
-        match output.status.success() {
-            true => Ok(Vec::new()),
+        let check_result = match output.status.success() {
+            true => CheckResult::default(),
             false => {
                 let error_message = String::from_utf8(output.stderr)?;
-                let mut error_message =
-                    normalize_error_message(error_message, &execution_storage.code_folder_path);
+                let mut error_message = normalize_error_message(error_message, &code_folder_path);
                 log::error!("deno check error: {}", error_message);
 
                 // Replace node_modules warning with empty string (it was confusing the llm)
@@ -95,23 +271,671 @@ impl DenoRunner {
                     .replace_all(&error_message, "")
                     .to_string();
 
-                let error_match_regex =
-                    Regex::new(r"(?:TS\d+ \[ERROR\]:.*(?:\n.*){2,4}at .*:\d+:\d+)").unwrap();
-                let matched_errors = error_match_regex
-                    .find_iter(&error_message)
-                    .map(|m| m.as_str())
-                    .collect::<Vec<_>>();
-                if matched_errors.is_empty() {
+                let diagnostics = parse_deno_check_output(&error_message, &code_folder_path);
+                if diagnostics.is_empty() {
                     log::warn!(
                         "no errors found in deno check but the command failed, this could be a bug"
                     );
-                    let error_lines: Vec<String> =
-                        error_message.lines().map(|s| s.to_string()).collect();
-                    return Ok(error_lines);
+                    CheckResult::from_diagnostics(vec![Diagnostic::unparsed("deno", error_message)])
+                } else {
+                    CheckResult::from_diagnostics(match &contract {
+                        Some(contract) => contract.resolve_diagnostics(diagnostics),
+                        None => diagnostics,
+                    })
                 }
-                Ok(matched_errors.iter().map(|s| s.to_string()).collect())
             }
+        };
+        if cacheable {
+            if let Err(e) = execution_storage.write_cached_check_result(&cache_key, &check_result) {
+                log::warn!("failed to cache check result: {}", e);
+            }
+        }
+        Ok(check_result)
+    }
+
+    /// Deno version and tool definition the check result depends on, besides the code
+    async fn check_cache_inputs(&self, runner_type: &RunnerType) -> BTreeMap<String, String> {
+        let deno_version = match runner_type {
+            RunnerType::Host => {
+                let binary_path = path::absolute(self.options.deno_binary_path.clone())
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                match tokio::process::Command::new(binary_path)
+                    .arg("--version")
+                    .kill_on_drop(true)
+                    .output()
+                    .await
+                {
+                    Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+                    Err(e) => {
+                        log::warn!("failed to get deno version: {}", e);
+                        String::from("unknown")
+                    }
+                }
+            }
+            // The image pins the deno version
+            RunnerType::Docker => {
+                docker_image_id(&self.options.code_runner_docker_image_name).await
+            }
+        };
+        BTreeMap::from([
+            (String::from("deno"), deno_version),
+            (
+                String::from("tool_definition"),
+                serde_json::to_string(&self.options.tool_definition).unwrap_or_default(),
+            ),
+        ])
+    }
+
+    /// `deno check` on the host, with `DENO_DIR` pointing to the storage deno cache like `run` so
+    /// checked modules are downloaded once and vendored modules and offline mode apply to it too
+    fn check_command_in_host(
+        &self,
+        execution_storage: &ExecutionStorage,
+    ) -> tokio::process::Command {
+        let binary_path = path::absolute(self.options.deno_binary_path.clone())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut command = tokio::process::Command::new(binary_path);
+        command
+            .arg("check")
+            .args(self.package_args(&execution_storage.code_files))
+            .arg(
+                execution_storage
+                    .code_entrypoint_file_path
+                    .to_str()
+                    .unwrap(),
+            )
+            .env_clear()
+            .env("NO_COLOR", "true")
+            .env(
+                "DENO_DIR",
+                execution_storage.deno_cache_folder_path(RunnerType::Host),
+            )
+            .envs(self.package_envs())
+            .current_dir(execution_storage.code_folder_path.clone());
+        command
+    }
+
+    /// Initializes the storage and copies the vendored modules missing from its deno cache
+    fn init_execution_storage(
+        &self,
+        execution_storage: &ExecutionStorage,
+        runner_type: RunnerType,
+    ) -> anyhow::Result<()> {
+        execution_storage.init_for_deno(None, runner_type.clone())?;
+        if let Some(vendored_modules_path) = &self.options.vendored_modules_path {
+            execution_storage
+                .seed_deno_cache(runner_type, &path::absolute(vendored_modules_path)?)?;
+        }
+        Ok(())
+    }
+
+    /// Arguments of the deno subcommands that download modules
+    fn download_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.options.offline {
+            args.push(String::from("--cached-only"));
+        }
+        args
+    }
+
+    /// Arguments of `deno info`, which downloads the modules of the graph it prints
+    fn info_args(&self) -> Vec<String> {
+        [vec![String::from("--json")], self.download_args()].concat()
+    }
+
+    /// Arguments of the deno subcommands that download modules for `code_files`, which enforce its
+    /// `deno.lock` or, without one, keep deno from writing one into the code snapshot
+    fn package_args(&self, code_files: &CodeFiles) -> Vec<String> {
+        let mut args = self.download_args();
+        if code_files.files.contains_key(Self::DENO_LOCK_FILE_NAME) {
+            args.push(String::from("--frozen"));
+        } else {
+            args.push(String::from("--no-lock"));
+        }
+        args
+    }
+
+    /// Environment variables pointing deno to the package mirrors
+    fn package_envs(&self) -> Vec<(String, String)> {
+        let mut envs = Vec::new();
+        if let Some(npm_registry) = &self.options.npm_registry {
+            envs.push((String::from("NPM_CONFIG_REGISTRY"), npm_registry.clone()));
+        }
+        if let Some(jsr_registry) = &self.options.jsr_registry {
+            envs.push((String::from("JSR_URL"), jsr_registry.clone()));
+        }
+        envs
+    }
+
+    /// Path of the entrypoint inside the container when the code in `code_folder_path` is mounted by
+    /// `deno_command_in_docker`
+    fn docker_entrypoint_path(
+        execution_storage: &ExecutionStorage,
+        code_folder_path: &Path,
+    ) -> String {
+        format!(
+            "/app/{}/{}",
+            execution_storage.relative_to_root(code_folder_path.to_path_buf()),
+            execution_storage.code_files.entrypoint
+        )
+    }
+
+    /// Runs a deno subcommand (`check`, `cache`, `info`, `lint`, `fmt`) with its `args` in the code
+    /// runner image, working in the code in `code_folder_path`, with the code and deno cache
+    /// mounted as in `run_in_docker`
+    fn deno_command_in_docker(
+        &self,
+        execution_storage: &ExecutionStorage,
+        code_folder_path: &Path,
+        subcommand: &str,
+        args: Vec<String>,
+    ) -> tokio::process::Command {
+        let code_folder = execution_storage.relative_to_root(code_folder_path.to_path_buf());
+        let deno_cache_folder = execution_storage
+            .relative_to_global_cache(execution_storage.deno_cache_folder_path(RunnerType::Docker));
+        let mount_dirs = [
+            (
+                code_folder_path.to_path_buf().as_normalized_string(),
+                code_folder.clone(),
+            ),
+            (
+                execution_storage
+                    .deno_cache_folder_path(RunnerType::Docker)
+                    .as_normalized_string(),
+                deno_cache_folder.clone(),
+            ),
+        ];
+        let mut docker_args = vec![String::from("run"), String::from("--rm")];
+        for (dir, relative_path) in mount_dirs {
+            docker_args.extend([
+                String::from("--mount"),
+                format!(r#"type=bind,source={},target=/app/{}"#, dir, relative_path),
+            ]);
+        }
+        docker_args.extend([
+            String::from("-e"),
+            String::from("NO_COLOR=true"),
+            String::from("-e"),
+            format!("DENO_DIR=/app/{}", deno_cache_folder),
+        ]);
+        for (key, value) in self.package_envs() {
+            docker_args.extend([String::from("-e"), format!("{}={}", key, value)]);
+        }
+        docker_args.extend([
+            String::from("--workdir"),
+            format!("/app/{}", code_folder),
+            self.options.code_runner_docker_image_name.clone(),
+            String::from("deno"),
+            subcommand.to_string(),
+        ]);
+        docker_args.extend(args);
+        log::info!(
+            "running deno {} in docker with args: {:?}",
+            subcommand,
+            docker_args
+        );
+        let mut command = tokio::process::Command::new("docker");
+        command.args(docker_args);
+        command
+    }
+
+    /// Runs a deno subcommand that works on every file of the scratch code folder (`lint`, `fmt`)
+    /// in the host or, when the runner type is Docker, in the code runner image
+    ///
+    /// # Returns
+    ///
+    /// The command and the path of the scratch folder as seen by deno
+    fn scratch_deno_command(
+        &self,
+        execution_storage: &ExecutionStorage,
+        scratch_folder_path: &Path,
+        subcommand: &str,
+        args: Vec<String>,
+    ) -> anyhow::Result<(tokio::process::Command, PathBuf)> {
+        match resolve_runner_type(self.options.force_runner_type.clone()) {
+            RunnerType::Host => {
+                let binary_path = path::absolute(self.options.deno_binary_path.clone())
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                let mut command = tokio::process::Command::new(binary_path);
+                command
+                    .arg(subcommand)
+                    .args(args)
+                    .env_clear()
+                    .env("NO_COLOR", "true")
+                    .current_dir(scratch_folder_path);
+                Ok((command, scratch_folder_path.to_path_buf()))
+            }
+            RunnerType::Docker => {
+                // The deno cache is mounted even if these subcommands don't use it
+                std::fs::create_dir_all(
+                    execution_storage.deno_cache_folder_path(RunnerType::Docker),
+                )?;
+                let command = self.deno_command_in_docker(
+                    execution_storage,
+                    scratch_folder_path,
+                    subcommand,
+                    args,
+                );
+                Ok((
+                    command,
+                    PathBuf::from(format!(
+                        "/app/{}",
+                        execution_storage.relative_to_root(scratch_folder_path.to_path_buf())
+                    )),
+                ))
+            }
+        }
+    }
+
+    fn lint_rules_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.options.lint_rules.include.is_empty() {
+            args.push(format!(
+                "--rules-include={}",
+                self.options.lint_rules.include.join(",")
+            ));
+        }
+        if !self.options.lint_rules.exclude.is_empty() {
+            args.push(format!(
+                "--rules-exclude={}",
+                self.options.lint_rules.exclude.join(",")
+            ));
+        }
+        args
+    }
+
+    /// Lints the code with `deno lint` and the rules configured in the options
+    ///
+    /// # Returns
+    ///
+    /// Lint violations as warnings and files that couldn't be linted as errors
+    pub async fn lint(&self) -> anyhow::Result<Vec<Diagnostic>> {
+        let execution_storage = ExecutionStorage::new(
+            Self::ensure_deno_json(self.code.clone()),
+            self.options.context.clone(),
+        );
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let args = [vec![String::from("--json")], self.lint_rules_args()].concat();
+        let (mut command, scratch_folder_path) =
+            self.scratch_deno_command(&execution_storage, scratch.path(), "lint", args)?;
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        let lint_output = String::from_utf8(output.stdout)?;
+        log::info!("deno lint output: {}", lint_output);
+        parse_deno_lint_json(&lint_output, &scratch_folder_path).map_err(|e| {
+            anyhow::anyhow!(
+                "failed to parse deno lint output: {} {}",
+                e,
+                String::from_utf8_lossy(&output.stderr)
+            )
+        })
+    }
+
+    /// Applies the `deno lint --fix` autofixes in a copy of the code
+    ///
+    /// # Returns
+    ///
+    /// The fixed code files and a unified diff of every changed file. The runner code is not modified.
+    pub async fn fix(&self) -> anyhow::Result<FixResult> {
+        let execution_storage = ExecutionStorage::new(
+            Self::ensure_deno_json(self.code.clone()),
+            self.options.context.clone(),
+        );
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let args = [vec![String::from("--fix")], self.lint_rules_args()].concat();
+        let (mut command, scratch_folder_path) =
+            self.scratch_deno_command(&execution_storage, scratch.path(), "lint", args)?;
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        let fix_output = String::from_utf8_lossy(&output.stderr).to_string()
+            + &String::from_utf8_lossy(&output.stdout);
+        log::info!("deno lint fix output: {}", fix_output);
+        // Deno exits with an error when issues without autofix remain, those are reported by lint.
+        // Any other failure (a broken binary, an invalid config) has no problems summary.
+        let remaining_problems_regex = Regex::new(r"Found \d+ problems?").unwrap();
+        if !output.status.success() && !remaining_problems_regex.is_match(&fix_output) {
+            let error_message = normalize_error_message(fix_output, &scratch_folder_path);
+            log::error!("deno lint fix error: {}", error_message);
+            return Err(anyhow::anyhow!("failed to fix code: {}", error_message));
         }
+        let fixed = self.code.read_from_folder(scratch.path())?;
+        Ok(FixResult::new(&self.code, fixed))
+    }
+
+    /// Formats the code with `deno fmt`
+    ///
+    /// # Returns
+    ///
+    /// The same code files with their formatted content
+    pub async fn format(&self) -> anyhow::Result<CodeFiles> {
+        let execution_storage = ExecutionStorage::new(
+            Self::ensure_deno_json(self.code.clone()),
+            self.options.context.clone(),
+        );
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let (mut command, scratch_folder_path) =
+            self.scratch_deno_command(&execution_storage, scratch.path(), "fmt", Vec::new())?;
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message =
+                normalize_error_message(String::from_utf8(output.stderr)?, &scratch_folder_path);
+            log::error!("deno fmt error: {}", error_message);
+            return Err(anyhow::anyhow!("failed to format code: {}", error_message));
+        }
+        Ok(self.code.read_from_folder(scratch.path())?)
+    }
+
+    /// Resolves the modules the code imports into a `deno.lock` and stores it for the code, so later
+    /// runs use exactly the same versions with `--frozen`
+    ///
+    /// # Returns
+    ///
+    /// The code files with the `deno.lock` file added
+    pub async fn lock(&self) -> anyhow::Result<CodeFiles> {
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        self.enforce_dependency_policy(runner_type.clone()).await?;
+        let code = self.code_without_lock();
+        let lock = self
+            .resolve_lock(&Self::extend_with_deno_json(code.clone())?, runner_type)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "failed to lock dependencies: deno didn't write a {}, the code imports no modules to lock",
+                    Self::DENO_LOCK_FILE_NAME
+                )
+            })?;
+        ExecutionStorage::new(code.clone(), self.options.context.clone())
+            .write_deno_lock(&code.content_hash(), &lock)?;
+        let mut code_files = self.code.clone();
+        code_files
+            .files
+            .insert(Self::DENO_LOCK_FILE_NAME.to_string(), lock);
+        Ok(code_files)
+    }
+
+    /// Resolves the modules `code`, which has the generated `deno.json`, imports into the content of
+    /// a `deno.lock`, `None` when deno doesn't write one because there is nothing to lock
+    async fn resolve_lock(
+        &self,
+        code: &CodeFiles,
+        runner_type: RunnerType,
+    ) -> anyhow::Result<Option<String>> {
+        let execution_storage = ExecutionStorage::new(code.clone(), self.options.context.clone());
+        self.init_execution_storage(&execution_storage, runner_type.clone())?;
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let binary_path = path::absolute(self.options.deno_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(binary_path);
+                command
+                    .arg("cache")
+                    .args(self.download_args())
+                    .arg(scratch.path().join(&code.entrypoint))
+                    .env("NO_COLOR", "true")
+                    .env(
+                        "DENO_DIR",
+                        execution_storage.deno_cache_folder_path(RunnerType::Host),
+                    )
+                    .envs(self.package_envs())
+                    .current_dir(scratch.path());
+                command
+            }
+            RunnerType::Docker => self.deno_command_in_docker(
+                &execution_storage,
+                scratch.path(),
+                "cache",
+                [
+                    self.download_args(),
+                    vec![Self::docker_entrypoint_path(
+                        &execution_storage,
+                        scratch.path(),
+                    )],
+                ]
+                .concat(),
+            ),
+        };
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        log::info!("locking deno dependencies: {:?}", command);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = explain_offline_error(
+                normalize_error_message(
+                    String::from_utf8_lossy(&output.stderr).to_string(),
+                    &scratch.path().to_path_buf(),
+                ),
+                self.options.offline,
+            );
+            log::error!("deno lock error: {}", error_message);
+            return Err(anyhow::anyhow!(
+                "failed to lock dependencies: {}",
+                error_message
+            ));
+        }
+
+        Ok(std::fs::read_to_string(scratch.path().join(Self::DENO_LOCK_FILE_NAME)).ok())
+    }
+
+    /// Software bill of materials of the code: the npm packages, jsr packages and remote modules it
+    /// imports, resolved with `deno info` against the code's `deno.lock`, resolved first when the
+    /// code has none
+    ///
+    /// # Returns
+    ///
+    /// A CycloneDX JSON document with the name, version, license (for npm packages) and hashes of
+    /// every dependency
+    pub async fn sbom(&self) -> anyhow::Result<Value> {
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        self.enforce_dependency_policy(runner_type.clone()).await?;
+        let mut locked_code = self.code_with_lock();
+        if !locked_code.files.contains_key(Self::DENO_LOCK_FILE_NAME) {
+            // Code without imported modules has no lockfile and no dependencies
+            if let Some(lock) = self
+                .resolve_lock(
+                    &Self::extend_with_deno_json(locked_code.clone())?,
+                    runner_type.clone(),
+                )
+                .await?
+            {
+                locked_code
+                    .files
+                    .insert(Self::DENO_LOCK_FILE_NAME.to_string(), lock);
+            }
+        }
+        let execution_storage = ExecutionStorage::new(
+            Self::extend_with_deno_json(locked_code.clone())?,
+            self.options.context.clone(),
+        );
+        self.init_execution_storage(&execution_storage, runner_type.clone())?;
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let deno_info = self
+            .deno_info(
+                &execution_storage,
+                scratch.path(),
+                &locked_code.entrypoint,
+                runner_type.clone(),
+            )
+            .await?;
+        let deno_lock = locked_code
+            .files
+            .get(Self::DENO_LOCK_FILE_NAME)
+            .and_then(|lock| serde_json::from_str::<Value>(lock).ok())
+            .unwrap_or_default();
+
+        // npm packages are extracted to the deno cache, or to node_modules when the tool sets
+        // `nodeModulesDir`
+        let npm_folder_path = execution_storage
+            .deno_cache_folder_path(runner_type)
+            .join("npm")
+            .join("registry.npmjs.org");
+        let node_modules_folder_path = scratch.path().join("node_modules");
+        let npm_license = |name: &str, version: &str| {
+            [
+                npm_folder_path
+                    .join(name)
+                    .join(version)
+                    .join("package.json"),
+                node_modules_folder_path.join(name).join("package.json"),
+            ]
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str::<Value>(&content).ok())
+            .find_map(|package_json| package_json["license"].as_str().map(String::from))
+        };
+        let components = deno_info_components(&deno_info, &deno_lock, npm_license);
+        let tool_name = self
+            .options
+            .tool_definition
+            .as_ref()
+            .map(|tool_definition| tool_definition.name.clone())
+            .unwrap_or_else(|| self.code.entrypoint.clone());
+        Ok(cyclonedx_json(&tool_name, &components))
+    }
+
+    /// Module graph of `entrypoint` in `scratch_path` as printed by `deno info --json`
+    async fn deno_info(
+        &self,
+        execution_storage: &ExecutionStorage,
+        scratch_path: &Path,
+        entrypoint: &str,
+        runner_type: RunnerType,
+    ) -> anyhow::Result<Value> {
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let binary_path = path::absolute(self.options.deno_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(binary_path);
+                command
+                    .arg("info")
+                    .args(self.info_args())
+                    .arg(scratch_path.join(entrypoint))
+                    .env("NO_COLOR", "true")
+                    .env(
+                        "DENO_DIR",
+                        execution_storage.deno_cache_folder_path(RunnerType::Host),
+                    )
+                    .envs(self.package_envs())
+                    .current_dir(scratch_path);
+                command
+            }
+            RunnerType::Docker => self.deno_command_in_docker(
+                execution_storage,
+                scratch_path,
+                "info",
+                [
+                    self.info_args(),
+                    vec![Self::docker_entrypoint_path(
+                        execution_storage,
+                        scratch_path,
+                    )],
+                ]
+                .concat(),
+            ),
+        };
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = normalize_error_message(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+                &scratch_path.to_path_buf(),
+            );
+            log::error!("deno info error: {}", error_message);
+            return Err(anyhow::anyhow!(
+                "failed to resolve dependencies: {}",
+                error_message
+            ));
+        }
+        Ok(serde_json::from_slice::<Value>(&output.stdout)?)
+    }
+
+    /// Creates the storage and downloads the npm/jsr/remote modules the code imports into the deno
+    /// cache, so `run` only executes the code. Progress is reported to `progress` when given.
+    pub async fn prepare(
+        &self,
+        max_prepare_timeout: Option<Duration>,
+        progress: Option<UnboundedSender<PrepareEvent>>,
+    ) -> anyhow::Result<()> {
+        let progress = PrepareProgress::new(progress);
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        with_prepare_timeout(max_prepare_timeout, async {
+            let execution_storage = progress
+                .step(PrepareStep::CreateStorage, async {
+                    self.enforce_dependency_policy(runner_type.clone()).await?;
+                    let execution_storage = ExecutionStorage::new(
+                        Self::extend_with_deno_json(self.code_with_lock())?,
+                        self.options.context.clone(),
+                    );
+                    self.init_execution_storage(&execution_storage, runner_type.clone())?;
+                    Ok(execution_storage)
+                })
+                .await?;
+            let command = match runner_type {
+                RunnerType::Host => {
+                    let binary_path = path::absolute(self.options.deno_binary_path.clone())?;
+                    let mut command = tokio::process::Command::new(binary_path);
+                    command
+                        .arg("cache")
+                        .args(self.package_args(&execution_storage.code_files))
+                        .arg(execution_storage.code_entrypoint_file_path.clone())
+                        .env("NO_COLOR", "true")
+                        .env(
+                            "DENO_DIR",
+                            execution_storage.deno_cache_folder_path(RunnerType::Host),
+                        )
+                        .envs(self.package_envs())
+                        .current_dir(execution_storage.code_folder_path.clone());
+                    command
+                }
+                RunnerType::Docker => self.deno_command_in_docker(
+                    &execution_storage,
+                    &execution_storage.code_folder_path,
+                    "cache",
+                    [
+                        self.package_args(&execution_storage.code_files),
+                        vec![Self::docker_entrypoint_path(
+                            &execution_storage,
+                            &execution_storage.code_folder_path,
+                        )],
+                    ]
+                    .concat(),
+                ),
+            };
+            progress
+                .step(
+                    PrepareStep::InstallDependencies,
+                    progress.run_command(command),
+                )
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!(explain_offline_error(e.to_string(), self.options.offline))
+                })
+        })
+        .await
     }
 
     pub async fn run(
@@ -125,39 +949,28 @@ impl DenoRunner {
         log::info!("parameters: {}", parameters.to_string());
 
         let resolved_runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        self.enforce_dependency_policy(resolved_runner_type.clone())
+            .await
+            .map_err(|e| match e.downcast::<DependencyPolicyError>() {
+                Ok(policy_error) => ExecutionError::from(policy_error),
+                Err(e) => ExecutionError::new(e.to_string(), None),
+            })?;
 
         let mut adapted_configurations = self.configurations.clone();
-        if !self.options.context.mount_files.is_empty()
-            && matches!(resolved_runner_type, RunnerType::Docker)
-        {
-            let mount_files = self
-                .options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<HashSet<String>>();
-
-            adapted_configurations = adapt_paths_in_value(&adapted_configurations, &mount_files);
-        }
-
         let mut adapted_parameters = parameters.clone();
-        // Deep traverse adapted_parameters and normalize mount file paths
-        if !self.options.context.mount_files.is_empty()
-            && matches!(resolved_runner_type, RunnerType::Docker)
-        {
-            let mount_files = self
-                .options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<HashSet<String>>();
-
-            adapted_parameters = adapt_paths_in_value(&adapted_parameters, &mount_files);
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        // Deep traverse configurations and parameters and map mounted paths to their container path
+        if matches!(resolved_runner_type, RunnerType::Docker) {
+            let mounts = execution_storage.resolved_mounts().map_err(|e| {
+                ExecutionError::new(format!("failed to resolve mounts: {}", e), None)
+            })?;
+            let path_mappings = docker_path_mappings(&mounts);
+            adapted_configurations = adapt_paths_in_value(&adapted_configurations, &path_mappings);
+            adapted_parameters = adapt_paths_in_value(&adapted_parameters, &path_mappings);
         }
 
-        let mut code = self.code.clone();
+        let mut code = self.code_with_lock();
         let entrypoint_code = code.files.get(&self.code.entrypoint.clone());
         if let Some(entrypoint_code) = entrypoint_code {
             let adapted_entrypoint_code = format!(
@@ -191,29 +1004,73 @@ impl DenoRunner {
                 .insert(self.code.entrypoint.clone(), adapted_entrypoint_code);
         }
 
-        let result = match resolved_runner_type {
-            RunnerType::Host => self.run_in_host(code, envs, max_execution_timeout).await,
-            RunnerType::Docker => self.run_in_docker(code, envs, max_execution_timeout).await,
+        execution_storage.begin_home_transaction().map_err(|e| {
+            ExecutionError::new(format!("failed to stage home folder: {}", e), None)
+        })?;
+
+        let change_tracker =
+            ChangeTracker::start(&execution_storage, self.options.track_changes.as_ref())?;
+
+        let run_result: Result<RunResult, ExecutionError> = async {
+            let result = match resolved_runner_type {
+                RunnerType::Host => self.run_in_host(code, envs, max_execution_timeout).await,
+                RunnerType::Docker => self.run_in_docker(code, envs, max_execution_timeout).await,
+            }
+            .map_err(|e| {
+                ExecutionError::new(
+                    explain_offline_error(e.to_string(), self.options.offline),
+                    None,
+                )
+            })?;
+
+            let result_text = result
+                .iter()
+                .skip_while(|line| !line.contains("<hanzo-code-result>"))
+                .skip(1)
+                .take_while(|line| !line.contains("</hanzo-code-result>"))
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            log::info!("result text: {:?}", result);
+
+            let result: Value = serde_json::from_str(&result_text).map_err(|e| {
+                log::info!("failed to parse result: {}", e);
+                ExecutionError::new(format!("failed to parse result: {}", e), None)
+            })?;
+            log::info!("successfully parsed run result: {:?}", result);
+
+            let artifacts = RunArtifact::collect(
+                &execution_storage.outputs_folder_path,
+                self.options.inline_artifacts_max_size,
+            )
+            .map_err(|e| {
+                ExecutionError::new(format!("failed to collect artifacts: {}", e), None)
+            })?;
+            Ok(RunResult {
+                data: result,
+                artifacts,
+                changes: Vec::new(),
+                inferred_dependencies: Vec::new(),
+            })
         }
-        .map_err(|e| ExecutionError::new(e.to_string(), None))?;
+        .await;
+        let run_result = match change_tracker {
+            Some(change_tracker) => change_tracker.finish(run_result),
+            None => run_result,
+        };
 
-        let result_text = result
-            .iter()
-            .skip_while(|line| !line.contains("<hanzo-code-result>"))
-            .skip(1)
-            .take_while(|line| !line.contains("</hanzo-code-result>"))
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>()
-            .join("\n");
-
-        log::info!("result text: {:?}", result);
-
-        let result: Value = serde_json::from_str(&result_text).map_err(|e| {
-            log::info!("failed to parse result: {}", e);
-            ExecutionError::new(format!("failed to parse result: {}", e), None)
-        })?;
-        log::info!("successfully parsed run result: {:?}", result);
-        Ok(RunResult { data: result })
+        if self.options.context.home_transaction.mode == HomeTransactionMode::CommitOnSuccess {
+            let home_transaction_result = if run_result.is_ok() {
+                execution_storage.commit_home()
+            } else {
+                execution_storage.discard_home()
+            };
+            home_transaction_result.map_err(|e| {
+                ExecutionError::new(format!("failed to finish home transaction: {}", e), None)
+            })?;
+        }
+        run_result
     }
 
     async fn run_in_docker(
@@ -227,8 +1084,11 @@ impl DenoRunner {
             self.options.code_runner_docker_image_name
         );
 
-        let execution_storage = ExecutionStorage::new(code_files, self.options.context.clone());
-        execution_storage.init_for_deno(None, RunnerType::Docker)?;
+        let execution_storage = ExecutionStorage::new(
+            Self::extend_with_deno_json(code_files)?,
+            self.options.context.clone(),
+        );
+        self.init_execution_storage(&execution_storage, RunnerType::Docker)?;
 
         let mut mount_params = Vec::<String>::new();
 
@@ -249,7 +1109,12 @@ impl DenoRunner {
             ),
             (
                 execution_storage.home_folder_path.as_normalized_string(),
-                execution_storage.relative_to_root(execution_storage.home_folder_path.clone()),
+                // Staged homes are mounted at the same path so SHINKAI_HOME doesn't change
+                String::from("home"),
+            ),
+            (
+                execution_storage.outputs_folder_path.as_normalized_string(),
+                execution_storage.relative_to_root(execution_storage.outputs_folder_path.clone()),
             ),
         ];
         for (dir, relative_path) in mount_dirs {
@@ -258,36 +1123,9 @@ impl DenoRunner {
             mount_params.extend([String::from("--mount"), mount_param]);
         }
 
-        let mut mount_env = String::from("");
-        log::info!("mount files: {:?}", self.options.context.mount_files);
-        // Mount each writable file to /app/mount
-        for file in &self.options.context.mount_files {
-            // Copy the files to the exact same path in the volume.
-            // This will allow to run the same code in the host and in the container.
-            let path = normalize_for_docker_path(file.to_path_buf());
-            let mount_param = format!(r#"type=bind,source={},target={}"#, path, path);
-            log::info!("mount parameter created: {}", mount_param);
-            mount_env += &format!("{},", path);
-            mount_params.extend([String::from("--mount"), mount_param]);
-        }
-
-        let mut mount_assets_env = String::from("");
-        // Mount each asset file to /app/assets
-        for file in &self.options.context.assets_files {
-            let target_path = format!(
-                "/app/{}/{}",
-                execution_storage.relative_to_root(execution_storage.assets_folder_path.clone()),
-                file.file_name().unwrap().to_str().unwrap()
-            );
-            let mount_param = format!(
-                r#"type=bind,readonly=true,source={},target={}"#,
-                path::absolute(file).unwrap().as_normalized_string(),
-                target_path,
-            );
-            log::debug!("mount parameter created: {}", mount_param);
-            mount_assets_env += &format!("{},", target_path);
-            mount_params.extend([String::from("--mount"), mount_param]);
-        }
+        let mounts = execution_storage.resolved_mounts()?;
+        mount_params.extend(docker_mount_params(&mounts));
+        let (mount_env, mount_assets_env) = mount_envs(&mounts, &RunnerType::Docker);
 
         let mut container_envs = Vec::<String>::new();
 
@@ -304,6 +1142,11 @@ impl DenoRunner {
             )
         ));
 
+        for (key, value) in self.package_envs() {
+            container_envs.push(String::from("-e"));
+            container_envs.push(format!("{}={}", key, value));
+        }
+
         container_envs.push(String::from("-e"));
         container_envs.push(format!(
             "SHINKAI_NODE_LOCATION={}://host.docker.internal:{}",
@@ -313,6 +1156,11 @@ impl DenoRunner {
         container_envs.push(String::from("-e"));
         container_envs.push(String::from("SHINKAI_HOME=/app/home"));
         container_envs.push(String::from("-e"));
+        container_envs.push(format!(
+            "SHINKAI_OUTPUTS=/app/{}",
+            execution_storage.relative_to_root(execution_storage.outputs_folder_path.clone())
+        ));
+        container_envs.push(String::from("-e"));
         container_envs.push(format!("SHINKAI_ASSETS={}", mount_assets_env));
         container_envs.push(String::from("-e"));
         container_envs.push(format!("SHINKAI_MOUNT={}", mount_env));
@@ -335,32 +1183,16 @@ impl DenoRunner {
             }
         }
 
+        let outputs_path_in_docker = format!(
+            "/app/{}",
+            execution_storage.relative_to_root(execution_storage.outputs_folder_path.clone())
+        );
         let deno_permissions = self.get_deno_permissions(
             RunnerType::Docker,
             "/usr/bin/deno",
             "/app/home",
-            &self
-                .options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap())
-                .collect::<Vec<_>>(),
-            &self
-                .options
-                .context
-                .assets_files
-                .iter()
-                .map(|p| {
-                    let path_in_docker = format!(
-                        "/app/{}/{}",
-                        execution_storage
-                            .relative_to_root(execution_storage.assets_folder_path.clone()),
-                        p.file_name().unwrap().to_str().unwrap()
-                    );
-                    PathBuf::from(path_in_docker)
-                })
-                .collect::<Vec<_>>(),
+            outputs_path_in_docker.as_str(),
+            &mounts,
         );
 
         let code_entrypoint =
@@ -378,89 +1210,21 @@ impl DenoRunner {
             "--ext",
             "ts",
         ]);
+        let package_args = self.package_args(&execution_storage.code_files);
+        args.extend(package_args.iter().map(|s| s.as_str()));
         args.extend(deno_permissions.iter().map(|s| s.as_str()));
         args.extend([code_entrypoint.as_str()]);
-        let command = command
-            .args(args)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
+        let command = command.args(args);
 
         log::info!("spawning docker command");
-        let mut child = command.spawn().map_err(|e| {
-            let error_msg = format!("failed to spawn command: {:?}, error: {}", command, e);
-            log::error!("{}", error_msg);
-            anyhow::anyhow!("{}", error_msg)
-        })?;
-
-        let stdout = child.stdout.take().expect("Failed to get stdout");
-        let mut stdout_stream = BufReader::new(stdout).lines();
-
-        let stderr = child.stderr.take().expect("Failed to get stderr");
-        let mut stderr_stream = BufReader::new(stderr).lines();
-
-        let stdout_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let stderr_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let execution_storage_clone = execution_storage.clone();
-
-        let stdout_lines_clone = stdout_lines.clone();
-        let stderr_lines_clone = stderr_lines.clone();
-        let execution_storage_clone2 = execution_storage_clone.clone();
-
-        let stdout_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stdout_stream.next_line().await {
-                    log::info!("from deno: {}", line);
-                    stdout_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone.append_log(line.as_str());
-                }
-            });
-        });
-
-        let stderr_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stderr_stream.next_line().await {
-                    log::info!("from deno: {}", line);
-                    stderr_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone2.append_log(line.as_str());
-                }
-            });
-        });
-
-        #[allow(clippy::let_underscore_future)]
-        let std_tasks = tokio::spawn(async move {
-            let _ = futures::future::join_all(vec![stdout_task, stderr_task]).await;
-        });
-
-        let output = if let Some(timeout) = max_execution_timeout {
-            log::info!("executing command with {}[s] timeout", timeout.as_secs());
-            match tokio::time::timeout(timeout, child.wait_with_output()).await {
-                Ok(result) => result?,
-                Err(_) => {
-                    log::error!("command execution timed out after {}[s]", timeout.as_secs());
-                    return Err(anyhow::anyhow!(
-                        "process timed out after {}[s]",
-                        timeout.as_secs()
-                    ));
-                }
-            }
-        } else {
-            log::info!("executing command without timeout");
-            child.wait_with_output().await?
-        };
-        let _ = std_tasks.await;
-        if !output.status.success() {
-            let stderr = stderr_lines.lock().await.to_vec().join("\n");
-            log::error!("command execution failed: {}", stderr);
-            return Err(anyhow::Error::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                stderr.to_string(),
-            )));
-        }
-
-        let stdout: Vec<String> = stdout_lines.lock().await.to_vec();
-        log::info!("command completed successfully with output: {:?}", stdout);
-        Ok(stdout)
+        run_process(
+            &execution_storage,
+            command,
+            None,
+            "deno",
+            max_execution_timeout,
+        )
+        .await
     }
 
     async fn run_in_host(
@@ -469,8 +1233,11 @@ impl DenoRunner {
         envs: Option<HashMap<String, String>>,
         max_execution_timeout: Option<Duration>,
     ) -> anyhow::Result<Vec<String>> {
-        let execution_storage = ExecutionStorage::new(code_files, self.options.context.clone());
-        execution_storage.init_for_deno(None, RunnerType::Host)?;
+        let execution_storage = ExecutionStorage::new(
+            Self::extend_with_deno_json(code_files)?,
+            self.options.context.clone(),
+        );
+        self.init_execution_storage(&execution_storage, RunnerType::Host)?;
 
         let binary_path = path::absolute(self.options.deno_binary_path.clone())
             .unwrap()
@@ -478,6 +1245,8 @@ impl DenoRunner {
             .to_string();
         log::info!("using deno from host at path: {:?}", binary_path.clone());
 
+        let mounts = execution_storage.resolved_mounts()?;
+        let (mount_env, mount_assets_env) = mount_envs(&mounts, &RunnerType::Host);
         let deno_permissions: Vec<String> = self.get_deno_permissions(
             RunnerType::Host,
             binary_path.clone().as_str(),
@@ -486,31 +1255,21 @@ impl DenoRunner {
                 .to_string_lossy()
                 .to_string()
                 .as_str(),
-            &self
-                .options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap())
-                .collect::<Vec<_>>(),
-            &self
-                .options
-                .context
-                .assets_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap())
-                .collect::<Vec<_>>(),
+            execution_storage
+                .outputs_folder_path
+                .to_string_lossy()
+                .to_string()
+                .as_str(),
+            &mounts,
         );
 
         let mut command = tokio::process::Command::new(binary_path);
         let command = command
             .args(["run", "--ext", "ts"])
+            .args(self.package_args(&execution_storage.code_files))
             .args(deno_permissions)
             .arg(execution_storage.code_entrypoint_file_path.clone())
-            .current_dir(execution_storage.root_folder_path.clone())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
+            .current_dir(execution_storage.root_folder_path.clone());
 
         command.env("NO_COLOR", "true");
         command.env(
@@ -519,6 +1278,7 @@ impl DenoRunner {
                 .deno_cache_folder_path(RunnerType::Host)
                 .clone(),
         );
+        command.envs(self.package_envs());
         command.env(
             "SHINKAI_NODE_LOCATION",
             format!(
@@ -531,25 +1291,11 @@ impl DenoRunner {
 
         command.env("SHINKAI_HOME", execution_storage.home_folder_path.clone());
         command.env(
-            "SHINKAI_ASSETS",
-            self.options
-                .context
-                .assets_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<Vec<_>>()
-                .join(","),
-        );
-        command.env(
-            "SHINKAI_MOUNT",
-            self.options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<Vec<_>>()
-                .join(","),
+            "SHINKAI_OUTPUTS",
+            execution_storage.outputs_folder_path.clone(),
         );
+        command.env("SHINKAI_ASSETS", mount_assets_env);
+        command.env("SHINKAI_MOUNT", mount_env);
 
         command.env(
             "SHINKAI_CONTEXT_ID",
@@ -564,79 +1310,14 @@ impl DenoRunner {
             command.envs(envs);
         }
         log::info!("prepared command with arguments: {:?}", command);
-        let mut child = command.spawn().map_err(|e| {
-            let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
-            log::error!("{}", error_msg);
-            anyhow::anyhow!("{}", error_msg)
-        })?;
-
-        let stdout = child.stdout.take().expect("Failed to get stdout");
-        let mut stdout_stream = BufReader::new(stdout).lines();
-
-        let stderr = child.stderr.take().expect("Failed to get stderr");
-        let mut stderr_stream = BufReader::new(stderr).lines();
-
-        let stdout_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let stderr_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let execution_storage_clone = execution_storage.clone();
-
-        let stdout_lines_clone = stdout_lines.clone();
-        let stderr_lines_clone = stderr_lines.clone();
-        let execution_storage_clone2 = execution_storage_clone.clone();
-
-        let stdout_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stdout_stream.next_line().await {
-                    log::info!("from deno: {}", line);
-                    stdout_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone.append_log(line.as_str());
-                }
-            });
-        });
-
-        let stderr_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stderr_stream.next_line().await {
-                    log::info!("from deno: {}", line);
-                    stderr_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone2.append_log(line.as_str());
-                }
-            });
-        });
-
-        #[allow(clippy::let_underscore_future)]
-        let std_tasks = tokio::spawn(async move {
-            let _ = futures::future::join_all(vec![stdout_task, stderr_task]).await;
-        });
-
-        let output = if let Some(timeout) = max_execution_timeout {
-            log::info!("executing command with {}[s] timeout", timeout.as_secs());
-            match tokio::time::timeout(timeout, child.wait_with_output()).await {
-                Ok(result) => result?,
-                Err(_) => {
-                    log::error!("command execution timed out after {}[s]", timeout.as_secs());
-                    return Err(anyhow::Error::new(std::io::Error::new(
-                        std::io::ErrorKind::TimedOut,
-                        format!("process timed out after {}[s]", timeout.as_secs()),
-                    )));
-                }
-            }
-        } else {
-            log::info!("executing command without timeout");
-            child.wait_with_output().await?
-        };
-        let _ = std_tasks.await;
-        if !output.status.success() {
-            let stderr = stderr_lines.lock().await.to_vec().join("\n");
-            log::error!("command execution failed: {}", stderr);
-            return Err(anyhow::Error::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                stderr.to_string(),
-            )));
-        }
-        let stdout: Vec<String> = stdout_lines.lock().await.to_vec();
-        log::info!("command completed successfully with output: {:?}", stdout);
-        Ok(stdout)
+        run_process(
+            &execution_storage,
+            command,
+            None,
+            "deno",
+            max_execution_timeout,
+        )
+        .await
     }
 
     fn get_deno_permissions(
@@ -644,11 +1325,10 @@ impl DenoRunner {
         runner_type: RunnerType,
         exec_path: &str,
         home_path: &str,
-        mount_files: &[PathBuf],
-        assets_files: &[PathBuf],
+        outputs_path: &str,
+        mounts: &[ResolvedMount],
     ) -> Vec<String> {
-        log::info!("mount files: {:?}", mount_files);
-        log::info!("assets files: {:?}", assets_files);
+        log::info!("mounts: {:?}", mounts);
         let mut deno_permissions: Vec<String> = vec![
             // Basically all non-file related permissions
             "--allow-env".to_string(),
@@ -657,11 +1337,11 @@ impl DenoRunner {
             "--allow-sys".to_string(),
             "--allow-scripts".to_string(),
             "--allow-ffi".to_string(),
-            "--allow-import".to_string(),
 
             // Engine folders
             "--allow-read=.".to_string(),
             format!("--allow-write={}", home_path.to_string()),
+            format!("--allow-write={}", outputs_path.to_string()),
 
             // Playwright/Chrome folders
             format!("--allow-read={}", exec_path.to_string()),
@@ -683,22 +1363,28 @@ impl DenoRunner {
             "--allow-read=/usr/bin/chromium".to_string(),
         ];
 
-        if matches!(runner_type, RunnerType::Docker) {
-            deno_permissions.push("--allow-read=/".to_string());
+        // Remote modules can only be imported from the registries the dependency policy allows,
+        // including the computed specifiers its module graph check can't see
+        match self
+            .options
+            .dependency_policy
+            .allowed_import_hosts(&self.jsr_url())
+        {
+            Some(hosts) if hosts.is_empty() => deno_permissions.push("--no-remote".to_string()),
+            Some(hosts) => deno_permissions.push(format!("--allow-import={}", hosts.join(","))),
+            None => deno_permissions.push("--allow-import".to_string()),
         }
 
-        for file in mount_files {
-            let path = match runner_type {
-                RunnerType::Host => file.to_string_lossy().to_string(),
-                RunnerType::Docker => normalize_for_docker_path(file.to_path_buf()),
-            };
-            let mount_param = format!(r#"--allow-read={},--allow-write={}"#, path, path);
-            deno_permissions.extend(mount_param.split(',').map(String::from));
+        if matches!(runner_type, RunnerType::Docker) {
+            deno_permissions.push("--allow-read=/".to_string());
         }
 
-        for file in assets_files {
-            let asset_param = format!(r#"--allow-read={}"#, file.to_string_lossy());
-            deno_permissions.push(asset_param);
+        for mount in mounts {
+            let path = mount.path(&runner_type);
+            deno_permissions.push(format!("--allow-read={}", path));
+            if mount.mode == MountMode::ReadWrite {
+                deno_permissions.push(format!("--allow-write={}", path));
+            }
         }
         log::info!("deno permissions: {}", deno_permissions.join(" "));
         deno_permissions
@@ -3,9 +3,14 @@ use serde_json::json;
 use serde_json::Value;
 
 use crate::tools::{
-    code_files::CodeFiles, deno_runner::DenoRunner, deno_runner_options::DenoRunnerOptions,
+    audit::AuditPolicy, code_files::CodeFiles,
+    dependency_policy::{DependencyPolicy, DependencyViolationReason}, deno_runner::DenoRunner,
+    deno_runner_options::DenoRunnerOptions, diagnostic::DiagnosticSeverity,
     execution_context::ExecutionContext, execution_storage::ExecutionStorage,
-    runner_type::RunnerType, hanzo_node_location::HanzoNodeLocation,
+    file_changes::{ChangeTrackingOptions, FileChangeKind},
+    home_transaction::{HomeTransactionMode, HomeTransactionOptions}, lint_rules::LintRules,
+    mounts::Mount, prepare::{PrepareEvent, PrepareStep}, runner_type::RunnerType,
+    hanzo_node_location::HanzoNodeLocation, tool_definition::ToolDefinition,
 };
 
 use std::collections::HashMap;
@@ -371,7 +376,7 @@ async fn check_code_success() {
     );
 
     let check_result = deno_runner.check().await.unwrap();
-    assert_eq!(check_result.len(), 0);
+    assert_eq!(check_result.errors.len(), 0);
 }
 
 #[rstest]
@@ -412,10 +417,11 @@ async fn check_code_with_errors(#[case] runner_type: RunnerType) {
     );
 
     let check_result = deno_runner.check().await.unwrap();
-    assert!(!check_result.is_empty());
+    assert!(check_result.has_errors());
     assert!(check_result
+        .errors
         .iter()
-        .any(|err| err.contains("Expected ',', got 's'")));
+        .any(|err| err.message.contains("Expected ',', got 's'")));
 }
 
 #[rstest]
@@ -459,10 +465,11 @@ async fn code_check_no_warnings(#[case] runner_type: RunnerType) {
 
     let check_result = deno_runner.check().await.unwrap();
     println!("check_result: {:?}", check_result);
-    assert!(!check_result.is_empty());
+    assert!(check_result.has_errors());
     assert!(!check_result
+        .errors
         .iter()
-        .any(|err| err.to_lowercase().contains("warning")));
+        .any(|err| err.message.to_lowercase().contains("warning")));
 }
 
 #[rstest]
@@ -506,10 +513,11 @@ async fn code_check_no_warnings_when_unparseable_code(#[case] runner_type: Runne
 
     let check_result = deno_runner.check().await.unwrap();
     println!("check_result: {:?}", check_result);
-    assert!(!check_result.is_empty());
+    assert!(check_result.has_errors());
     assert!(!check_result
+        .errors
         .iter()
-        .any(|err| err.to_lowercase().contains("warning")));
+        .any(|err| err.message.to_lowercase().contains("warning")));
 }
 
 #[rstest]
@@ -551,7 +559,7 @@ async fn check_with_wrong_import_path(#[case] runner_type: RunnerType) {
     );
 
     let check_result = deno_runner.check().await.unwrap();
-    assert!(!check_result.is_empty());
+    assert!(check_result.has_errors());
 }
 
 #[tokio::test]
@@ -586,10 +594,11 @@ async fn check_with_wrong_lib_version() {
     );
 
     let check_result = deno_runner.check().await.unwrap();
-    assert!(!check_result.is_empty());
-    assert!(check_result.iter().any(|line| line
+    assert!(check_result.has_errors());
+    assert!(check_result.errors.iter().any(|error| error
+        .message
         .contains("Could not find npm package 'axios' matching '3.4.2'")
-        || line.contains(
+        || error.message.contains(
             "Error getting response at https://registry.npmjs.org/axios for package \"axios\""
         )));
 }
@@ -1247,11 +1256,15 @@ async fn check_doesnt_include_stacktrace_in_error_message() {
     );
     let result = tool.check().await.unwrap();
 
-    assert!(!result.is_empty());
+    assert!(result.has_errors());
     assert!(result
+        .errors
         .iter()
-        .any(|line| line.contains("error: Module not found")));
-    assert!(!result.iter().any(|line| line.contains("Stack backtrace:")));
+        .any(|error| error.message.contains("Module not found")));
+    assert!(!result
+        .errors
+        .iter()
+        .any(|error| error.message.contains("Stack backtrace:")));
 }
 
 #[rstest]
@@ -1302,12 +1315,16 @@ async fn check_file_names_are_normalized() {
     );
     let result = tool.check().await.unwrap();
 
-    assert!(!result.is_empty());
-    assert!(!result.iter().any(|line| line.contains("file://")));
+    assert!(result.has_errors());
+    assert!(!result
+        .errors
+        .iter()
+        .any(|error| error.message.contains("file://")));
     assert!(result
+        .errors
         .iter()
-        .any(|line| line.contains("\"./libraries/axios\"")));
-    assert!(result.iter().any(|line| line.contains(" ./main.ts")));
+        .any(|error| error.message.contains("\"./libraries/axios\"")));
+    assert!(result.errors.iter().any(|error| error.file == "main.ts"));
 }
 
 #[rstest]
@@ -1453,3 +1470,1194 @@ export async function run(config: Config, inputs: Inputs): Promise<Output> {
         .unwrap()
         .contains("Hello, world parameters!"));
 }
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn collect_output_artifacts(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+                async function run(configurations, params) {
+                    const outputs = Deno.env.get("SHINKAI_OUTPUTS");
+                    await Deno.writeTextFile(`${outputs}/report.txt`, "hello artifact");
+                    return { done: true };
+                }
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            force_runner_type: Some(runner_type),
+            inline_artifacts_max_size: Some(1024),
+            ..Default::default()
+        }),
+    );
+
+    let result = deno_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(result.data["done"], true);
+    assert_eq!(result.artifacts.len(), 1);
+    assert_eq!(result.artifacts[0].path, "report.txt");
+    assert_eq!(result.artifacts[0].size, 14);
+    assert_eq!(result.artifacts[0].mime_type, "text/plain");
+    assert_eq!(
+        result.artifacts[0].content_base64.as_deref(),
+        Some("aGVsbG8gYXJ0aWZhY3Q=")
+    );
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn mount_folder_read_write(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_folder = tempfile::tempdir().unwrap();
+    std::fs::write(mount_folder.path().join("input.txt"), "1").unwrap();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+                async function run(configurations, params) {
+                    const folder = Deno.env.get("SHINKAI_MOUNT").split(',')[0];
+                    const content = await Deno.readTextFile(`${folder}/input.txt`);
+                    await Deno.writeTextFile(`${folder}/output.txt`, content + "2");
+                    return params.folder === folder;
+                }
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            context: ExecutionContext {
+                mounts: vec![Mount::read_write(mount_folder.path()).with_target("/data")],
+                ..Default::default()
+            },
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let result = deno_runner
+        .run(
+            None,
+            json!({ "folder": mount_folder.path().to_string_lossy() }),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.data, true);
+    let content = std::fs::read_to_string(mount_folder.path().join("output.txt")).unwrap();
+    assert_eq!(content, "12");
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn mount_glob_read_only(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_folder = tempfile::tempdir().unwrap();
+    std::fs::write(mount_folder.path().join("a.csv"), "a").unwrap();
+    std::fs::write(mount_folder.path().join("b.csv"), "b").unwrap();
+    std::fs::write(mount_folder.path().join("c.json"), "c").unwrap();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+                async function run(configurations, params) {
+                    const assets = Deno.env.get("SHINKAI_ASSETS").split(",").filter((asset) => asset);
+                    let content = "";
+                    for (const asset of assets) {
+                        content += await Deno.readTextFile(asset);
+                    }
+                    try {
+                        await Deno.writeTextFile(assets[0], "x");
+                    } catch {
+                        return content;
+                    }
+                    return "write should fail";
+                }
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            context: ExecutionContext {
+                mounts: vec![Mount::read_only(mount_folder.path().join("*.csv"))],
+                ..Default::default()
+            },
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let result = deno_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(result.data, "ab");
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn report_changes_in_mounted_files(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_folder = tempfile::tempdir().unwrap();
+    std::fs::write(mount_folder.path().join("edit.txt"), "1\n").unwrap();
+    std::fs::write(mount_folder.path().join("delete.txt"), "1\n").unwrap();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+                async function run(configurations, params) {
+                    const folder = Deno.env.get("SHINKAI_MOUNT").split(',')[0];
+                    await Deno.writeTextFile(`${folder}/edit.txt`, "2\n");
+                    await Deno.remove(`${folder}/delete.txt`);
+                    await Deno.writeTextFile(`${folder}/create.txt`, "3\n");
+                    return null;
+                }
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            context: ExecutionContext {
+                mounts: vec![Mount::read_write(mount_folder.path())],
+                ..Default::default()
+            },
+            track_changes: Some(ChangeTrackingOptions {
+                text_diffs: true,
+                ..Default::default()
+            }),
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let result = deno_runner.run(None, json!({}), None).await.unwrap();
+    let kinds = result
+        .changes
+        .iter()
+        .map(|change| change.kind.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            FileChangeKind::Created,
+            FileChangeKind::Deleted,
+            FileChangeKind::Modified
+        ]
+    );
+    assert!(result.changes[2].diff.as_ref().unwrap().contains("-1\n+2"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn home_is_committed_only_on_success(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let context = ExecutionContext {
+        home_transaction: HomeTransactionOptions {
+            mode: HomeTransactionMode::CommitOnSuccess,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let run_with_code = |code: &str| {
+        DenoRunner::new(
+            CodeFiles {
+                files: HashMap::from([("main.ts".to_string(), code.to_string())]),
+                entrypoint: "main.ts".to_string(),
+            },
+            json!({}),
+            Some(DenoRunnerOptions {
+                context: ExecutionContext {
+                    execution_id: nanoid::nanoid!(),
+                    ..context.clone()
+                },
+                force_runner_type: Some(runner_type.clone()),
+                ..Default::default()
+            }),
+        )
+    };
+
+    run_with_code(
+        r#"
+            async function run() {
+                await Deno.writeTextFile(`${Deno.env.get("SHINKAI_HOME")}/state.txt`, "committed");
+                return null;
+            }
+        "#,
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap();
+
+    let result = run_with_code(
+        r#"
+            async function run() {
+                await Deno.writeTextFile(`${Deno.env.get("SHINKAI_HOME")}/state.txt`, "half written");
+                throw new Error("crashed");
+            }
+        "#,
+    )
+    .run(None, json!({}), None)
+    .await;
+    assert!(result.is_err());
+
+    let execution_storage = ExecutionStorage::new(CodeFiles::default(), context);
+    let state = std::fs::read_to_string(
+        execution_storage
+            .context_home_folder_path()
+            .join("state.txt"),
+    )
+    .unwrap();
+    assert_eq!(state, "committed");
+    assert_eq!(execution_storage.home_history().unwrap().len(), 1);
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn committed_run_keeps_home_content(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let context = ExecutionContext {
+        home_transaction: HomeTransactionOptions {
+            mode: HomeTransactionMode::CommitOnSuccess,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+                async function run() {
+                    await Deno.writeTextFile(`${Deno.env.get("SHINKAI_HOME")}/new.txt`, "new");
+                    return null;
+                }
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let context_home_folder_path =
+        ExecutionStorage::new(code_files.clone(), context.clone()).context_home_folder_path();
+    std::fs::create_dir_all(&context_home_folder_path).unwrap();
+    std::fs::write(context_home_folder_path.join("state.txt"), "seeded").unwrap();
+
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            context,
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+    deno_runner.prepare(None, None).await.unwrap();
+    deno_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("state.txt")).unwrap(),
+        "seeded"
+    );
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("new.txt")).unwrap(),
+        "new"
+    );
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn lint_code_with_rules(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+                async function run() {
+                    const unused = 1;
+                    var legacy = 2;
+                    return legacy;
+                }
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let diagnostics = DenoRunner::new(
+        code_files.clone(),
+        json!({}),
+        Some(DenoRunnerOptions {
+            force_runner_type: Some(runner_type.clone()),
+            ..Default::default()
+        }),
+    )
+    .lint()
+    .await
+    .unwrap();
+    let unused = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.code.as_deref() == Some("no-unused-vars"))
+        .unwrap();
+    assert_eq!((unused.file.as_str(), unused.line), ("main.ts", 3));
+
+    let diagnostics = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            lint_rules: LintRules {
+                include: vec!["no-var".to_string()],
+                exclude: vec!["no-unused-vars".to_string()],
+            },
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    )
+    .lint()
+    .await
+    .unwrap();
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.code.as_deref() == Some("no-var")));
+    assert!(!diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.code.as_deref() == Some("no-unused-vars")));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn format_code(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.ts".to_string(),
+                "import {sum} from './utils.ts'\nasync function run(){ return sum(1,2) }\n"
+                    .to_string(),
+            ),
+            (
+                "utils.ts".to_string(),
+                "export function sum(a:number,b:number){return a+b}".to_string(),
+            ),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let formatted = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    )
+    .format()
+    .await
+    .unwrap();
+    assert_eq!(formatted.entrypoint, "main.ts");
+    assert_eq!(
+        formatted.files["main.ts"],
+        "import { sum } from \"./utils.ts\";\nasync function run() {\n  return sum(1, 2);\n}\n"
+    );
+    assert_eq!(
+        formatted.files["utils.ts"],
+        "export function sum(a: number, b: number) {\n  return a + b;\n}\n"
+    );
+}
+
+#[tokio::test]
+async fn fix_code() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code = "async function run() {\n  return window.location;\n}\n";
+    let code_files = CodeFiles {
+        files: HashMap::from([("main.ts".to_string(), code.to_string())]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let deno_runner = DenoRunner::new(code_files, json!({}), None);
+    let fix_result = deno_runner.fix().await.unwrap();
+    assert!(fix_result.has_changes());
+    assert_eq!(
+        fix_result.code.files["main.ts"],
+        "async function run() {\n  return globalThis.location;\n}\n"
+    );
+    assert!(fix_result.diffs["main.ts"].contains("-  return window.location;"));
+    assert!(fix_result.diffs["main.ts"].contains("+  return globalThis.location;"));
+
+    // Issues without autofix are tolerated, other failures like an invalid config are reported
+    let code_files = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.ts".to_string(),
+                "async function run() {\n  const unused = 1;\n}\n".to_string(),
+            ),
+            ("deno.json".to_string(), "{ invalid".to_string()),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let error = DenoRunner::new(code_files, json!({}), None)
+        .fix()
+        .await
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("failed to fix code"));
+}
+
+#[tokio::test]
+async fn check_verifies_tool_contract() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+type CONFIG = {};
+type INPUTS = {
+    url: string;
+    retries: string;
+};
+
+export async function run(config: CONFIG, inputs: INPUTS): Promise<string> {
+    return inputs.url + inputs.retries;
+}
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let deno_runner = DenoRunner::new(
+        code_files,
+        Value::Null,
+        Some(DenoRunnerOptions {
+            tool_definition: Some(ToolDefinition {
+                id: String::from("fetch"),
+                name: String::from("fetch"),
+                description: String::new(),
+                author: String::new(),
+                keywords: Vec::new(),
+                configurations: json!({ "type": "object", "properties": {} }),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "url": { "type": "string" },
+                        "retries": { "type": "integer" }
+                    },
+                    "required": ["url", "retries"]
+                }),
+                result: json!({}),
+                code: None,
+                embedding_metadata: None,
+            }),
+            ..Default::default()
+        }),
+    );
+
+    let check_result = deno_runner.check().await.unwrap();
+    assert_eq!(check_result.errors.len(), 1);
+    assert_eq!(check_result.errors[0].source_tool, "contract");
+    assert_eq!(
+        check_result.errors[0].code,
+        Some(String::from("schema-mismatch"))
+    );
+    assert!(check_result.errors[0]
+        .message
+        .contains("parameters type of `run`"));
+}
+
+#[tokio::test]
+async fn check_results_are_cached() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let check = |code: &str| {
+        DenoRunner::new(
+            CodeFiles {
+                files: HashMap::from([("main.ts".to_string(), code.to_string())]),
+                entrypoint: "main.ts".to_string(),
+            },
+            Value::Null,
+            Some(DenoRunnerOptions {
+                context: ExecutionContext {
+                    storage: storage.path().to_path_buf(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        )
+    };
+    let code = "async function run(c: {}, p: {}) { const a: number = 'a'; return a; }";
+
+    let first = check(code).check().await.unwrap();
+    assert!(!first.cache_hit);
+    assert!(first.has_errors());
+
+    let second = check(code).check().await.unwrap();
+    assert!(second.cache_hit);
+    assert_eq!(second.errors, first.errors);
+
+    let changed = check("async function run(c: {}, p: {}) { return 1; }")
+        .check()
+        .await
+        .unwrap();
+    assert!(!changed.cache_hit);
+    assert!(!changed.has_errors());
+}
+
+#[tokio::test]
+async fn audit_reports_risky_patterns() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+async function run(configurations, parameters) {
+    const output = await new Deno.Command("ls").output();
+    const plugin = await import("https://example.com/plugin.ts");
+    return eval(parameters.expression);
+}
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+
+    let deno_runner = DenoRunner::new(
+        code_files,
+        Value::Null,
+        Some(DenoRunnerOptions {
+            audit_policy: AuditPolicy {
+                severities: HashMap::from([(
+                    "deno-command".to_string(),
+                    DiagnosticSeverity::Error,
+                )]),
+                ignore: vec!["eval".to_string()],
+            },
+            ..Default::default()
+        }),
+    );
+    let diagnostics = deno_runner.audit();
+    let codes: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.code.clone().unwrap())
+        .collect();
+    assert_eq!(codes, vec!["deno-command", "remote-dynamic-import"]);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error));
+    assert_eq!(diagnostics[0].file, "main.ts");
+    assert_eq!(diagnostics[0].line, 3);
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn prepare_caches_dependencies(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+import { camelCase } from "npm:lodash-es@4.17.21";
+
+async function run(configurations, parameters) {
+    return camelCase("hello world");
+}
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    deno_runner
+        .prepare(Some(std::time::Duration::from_secs(120)), Some(sender))
+        .await
+        .unwrap();
+    let mut events = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        events.push(event);
+    }
+    assert_eq!(events[0], PrepareEvent::Started(PrepareStep::CreateStorage));
+    assert!(matches!(
+        events.last().unwrap(),
+        PrepareEvent::Finished {
+            step: PrepareStep::InstallDependencies,
+            ..
+        }
+    ));
+
+    // Dependencies are installed, so running fits in a timeout a cold run wouldn't
+    let result = deno_runner
+        .run(None, json!({}), Some(std::time::Duration::from_secs(5)))
+        .await
+        .unwrap();
+    assert_eq!(result.data, json!("helloWorld"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn offline_run_uses_vendored_modules(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+import { kebabCase } from "npm:lodash-es@4.17.21";
+
+async function run(configurations, parameters) {
+    return kebabCase("hello world");
+}
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let runner =
+        |storage: &std::path::Path, offline: bool, vendored: Option<std::path::PathBuf>| {
+            DenoRunner::new(
+                code_files.clone(),
+                json!({}),
+                Some(DenoRunnerOptions {
+                    context: ExecutionContext {
+                        storage: storage.to_path_buf(),
+                        ..Default::default()
+                    },
+                    force_runner_type: Some(runner_type.clone()),
+                    offline,
+                    vendored_modules_path: vendored,
+                    ..Default::default()
+                }),
+            )
+        };
+
+    // Modules downloaded by a connected machine
+    let connected_storage = tempfile::tempdir().unwrap();
+    runner(connected_storage.path(), false, None)
+        .prepare(None, None)
+        .await
+        .unwrap();
+    let vendored_modules_path = ExecutionStorage::new(
+        code_files.clone(),
+        ExecutionContext {
+            storage: connected_storage.path().to_path_buf(),
+            ..Default::default()
+        },
+    )
+    .deno_cache_folder_path(runner_type.clone());
+
+    let offline_storage = tempfile::tempdir().unwrap();
+    let result = runner(offline_storage.path(), true, Some(vendored_modules_path))
+        .run(None, json!({}), None)
+        .await
+        .unwrap();
+    assert_eq!(result.data, json!("hello-world"));
+
+    let empty_storage = tempfile::tempdir().unwrap();
+    let error = runner(empty_storage.path(), true, None)
+        .run(None, json!({}), None)
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .message()
+            .starts_with("dependency not available offline:"),
+        "{}",
+        error.message()
+    );
+}
+
+#[test]
+fn offline_info_only_uses_cached_modules() {
+    let code_files = CodeFiles {
+        files: HashMap::from([("main.ts".to_string(), String::new())]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let runner = |offline: bool| {
+        DenoRunner::new(
+            code_files.clone(),
+            json!({}),
+            Some(DenoRunnerOptions {
+                offline,
+                ..Default::default()
+            }),
+        )
+    };
+
+    assert_eq!(runner(false).info_args(), vec!["--json"]);
+    assert_eq!(runner(true).info_args(), vec!["--json", "--cached-only"]);
+}
+
+#[test]
+fn deno_json_is_merged_with_runner_settings() {
+    let code_files = CodeFiles {
+        files: HashMap::from([
+            ("main.ts".to_string(), String::new()),
+            (
+                "deno.json".to_string(),
+                r#"{ "imports": { "lodash": "npm:lodash-es@4.17.21" }, "compilerOptions": { "strict": false } }"#
+                    .to_string(),
+            ),
+            (
+                "package.json".to_string(),
+                r#"{ "dependencies": { "zod": "3.23.8" } }"#.to_string(),
+            ),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let code_files = DenoRunner::extend_with_deno_json(code_files).unwrap();
+    let deno_json: Value = serde_json::from_str(&code_files.files["deno.json"]).unwrap();
+    assert_eq!(
+        deno_json,
+        json!({
+            "imports": { "lodash": "npm:lodash-es@4.17.21" },
+            "compilerOptions": { "strict": false },
+            "nodeModulesDir": "none",
+            "lock": "deno.lock",
+        })
+    );
+
+    let code_files = CodeFiles {
+        files: HashMap::from([("main.ts".to_string(), String::new())]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let code_files = DenoRunner::extend_with_deno_json(code_files).unwrap();
+    let deno_json: Value = serde_json::from_str(&code_files.files["deno.json"]).unwrap();
+    assert_eq!(deno_json, json!({ "lock": "deno.lock" }));
+
+    let code_files = CodeFiles {
+        files: HashMap::from([
+            ("main.ts".to_string(), String::new()),
+            ("deno.json".to_string(), "{ imports".to_string()),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let Err(error) = DenoRunner::extend_with_deno_json(code_files) else {
+        panic!("expected an invalid deno.json error");
+    };
+    assert!(error.to_string().starts_with("invalid deno.json:"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn lock_pins_dependencies(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.ts".to_string(),
+                r#"
+import { kebabCase } from "lodash";
+
+async function run(configurations, parameters) {
+    return kebabCase("hello world");
+}
+"#
+                .to_string(),
+            ),
+            (
+                "deno.json".to_string(),
+                r#"{ "imports": { "lodash": "npm:lodash-es@^4.17.0" } }"#.to_string(),
+            ),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let options = DenoRunnerOptions {
+        force_runner_type: Some(runner_type),
+        ..Default::default()
+    };
+
+    let deno_runner = DenoRunner::new(code_files.clone(), json!({}), Some(options.clone()));
+    let locked_code = deno_runner.lock().await.unwrap();
+    let lock = locked_code.files[DenoRunner::DENO_LOCK_FILE_NAME].clone();
+    assert!(lock.contains("lodash-es@4.17."));
+    assert_eq!(
+        locked_code.files["deno.json"], code_files.files["deno.json"],
+        "the tool deno.json isn't changed"
+    );
+
+    // The stored lock is used for the same code, a supplied one wins
+    let result = DenoRunner::new(code_files, json!({}), Some(options.clone()))
+        .run(None, json!({}), None)
+        .await
+        .unwrap();
+    assert_eq!(result.data, json!("hello-world"));
+    let result = DenoRunner::new(locked_code.clone(), json!({}), Some(options.clone()))
+        .run(None, json!({}), None)
+        .await
+        .unwrap();
+    assert_eq!(result.data, json!("hello-world"));
+
+    // A lock that doesn't match the imports is rejected instead of being updated
+    let mut outdated_code = locked_code;
+    outdated_code.files.insert(
+        "deno.json".to_string(),
+        r#"{ "imports": { "lodash": "npm:lodash-es@^4.17.0", "zod": "npm:zod@3.23.8" } }"#
+            .to_string(),
+    );
+    let error = DenoRunner::new(outdated_code, json!({}), Some(options))
+        .run(None, json!({}), None)
+        .await
+        .unwrap_err();
+    assert!(
+        error.message().contains("lockfile is out of date"),
+        "{}",
+        error.message()
+    );
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn unlocked_run_keeps_code_snapshot_unchanged(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let code_files = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.ts".to_string(),
+                r#"
+import { z } from "zod";
+
+async function run(configurations, parameters) {
+    return z.string().parse("hello");
+}
+"#
+                .to_string(),
+            ),
+            (
+                "package.json".to_string(),
+                r#"{ "dependencies": { "zod": "3.23.8" } }"#.to_string(),
+            ),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let context = ExecutionContext {
+        storage: storage.path().to_path_buf(),
+        ..Default::default()
+    };
+    let options = DenoRunnerOptions {
+        context: context.clone(),
+        force_runner_type: Some(runner_type),
+        ..Default::default()
+    };
+
+    let deno_runner = DenoRunner::new(code_files.clone(), json!({}), Some(options));
+    deno_runner.check().await.unwrap();
+    let result = deno_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(result.data, json!("hello"));
+
+    let code_folder_path = ExecutionStorage::new(
+        DenoRunner::extend_with_deno_json(code_files).unwrap(),
+        context,
+    )
+    .code_folder_path;
+    assert!(code_folder_path.join("main.ts").exists());
+    assert!(!code_folder_path
+        .join(DenoRunner::DENO_LOCK_FILE_NAME)
+        .exists());
+    assert!(!code_folder_path.join("node_modules").exists());
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn lock_fails_without_modules_to_lock(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            "async function run(configurations, parameters) { return 1; }".to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let options = DenoRunnerOptions {
+        force_runner_type: Some(runner_type),
+        ..Default::default()
+    };
+    let Err(error) = DenoRunner::new(code_files, json!({}), Some(options))
+        .lock()
+        .await
+    else {
+        panic!("expected a lock error");
+    };
+    assert!(error.to_string().contains("didn't write a deno.lock"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_rejects_dependencies_violating_policy(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+import { kebabCase } from "npm:lodash-es@4.17.21";
+import { camelCase } from "https://deno.land/x/case@2.2.0/mod.ts";
+
+async function run(configurations, parameters) {
+    return kebabCase(camelCase("never runs"));
+}
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            force_runner_type: Some(runner_type),
+            dependency_policy: DependencyPolicy {
+                deny: vec!["lodash-es".to_string()],
+                allowed_registries: Some(vec!["npm".to_string(), "jsr".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    );
+
+    let error = deno_runner.run(None, json!({}), None).await.unwrap_err();
+    let violations: Vec<(String, DependencyViolationReason)> = error
+        .dependency_violations()
+        .iter()
+        .map(|violation| (violation.package.clone(), violation.reason.clone()))
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            ("lodash-es".to_string(), DependencyViolationReason::Denied),
+            (
+                "https://deno.land/x/case@2.2.0/mod.ts".to_string(),
+                DependencyViolationReason::RegistryNotAllowed
+            ),
+        ]
+    );
+    assert!(deno_runner.check().await.is_err());
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_rejects_transitive_dependencies_violating_policy(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+import express from "npm:express@4.21.1";
+
+async function run(configurations, parameters) {
+    return typeof express;
+}
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            force_runner_type: Some(runner_type),
+            dependency_policy: DependencyPolicy {
+                allow: Some(vec!["express".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    );
+
+    // express is allowed, the packages it depends on aren't
+    let error = deno_runner.run(None, json!({}), None).await.unwrap_err();
+    let violations: Vec<(String, DependencyViolationReason)> = error
+        .dependency_violations()
+        .iter()
+        .map(|violation| (violation.package.clone(), violation.reason.clone()))
+        .collect();
+    assert!(violations.contains(&(
+        "body-parser".to_string(),
+        DependencyViolationReason::NotAllowed
+    )));
+    assert!(!violations.iter().any(|(package, _)| package == "express"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn sbom_lists_dependencies(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.ts".to_string(),
+            r#"
+import { kebabCase } from "npm:lodash-es@4.17.21";
+import { join } from "jsr:@std/path@1.0.8";
+
+async function run(configurations, parameters) {
+    return join("a", kebabCase("hello world"));
+}
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let deno_runner = DenoRunner::new(
+        code_files,
+        json!({}),
+        Some(DenoRunnerOptions {
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let sbom = deno_runner.sbom().await.unwrap();
+    assert_eq!(sbom["bomFormat"], "CycloneDX");
+    assert_eq!(sbom["metadata"]["component"]["name"], "main.ts");
+    let components = sbom["components"].as_array().unwrap();
+    let lodash = components
+        .iter()
+        .find(|component| component["name"] == "lodash-es")
+        .unwrap();
+    assert_eq!(lodash["version"], "4.17.21");
+    assert_eq!(lodash["purl"], "pkg:npm/lodash-es@4.17.21");
+    assert_eq!(lodash["licenses"], json!([{ "license": { "id": "MIT" } }]));
+    assert_eq!(lodash["hashes"][0]["alg"], "SHA-512");
+    let std_path = components
+        .iter()
+        .find(|component| component["name"] == "@std/path")
+        .unwrap();
+    assert_eq!(std_path["version"], "1.0.8");
+    assert_eq!(std_path["hashes"][0]["alg"], "SHA-256");
+}
@@ -1,8 +1,9 @@
 use std::path::PathBuf;
 
 use super::{
-    execution_context::ExecutionContext, runner_type::RunnerType,
-    hanzo_node_location::HanzoNodeLocation,
+    audit::AuditPolicy, dependency_policy::DependencyPolicy, execution_context::ExecutionContext,
+    file_changes::ChangeTrackingOptions, hanzo_node_location::HanzoNodeLocation,
+    lint_rules::LintRules, runner_type::RunnerType, tool_definition::ToolDefinition,
 };
 
 #[derive(Clone)]
@@ -12,6 +13,28 @@ pub struct DenoRunnerOptions {
     pub code_runner_docker_image_name: String,
     pub force_runner_type: Option<RunnerType>,
     pub hanzo_node_location: HanzoNodeLocation,
+    /// Output artifacts up to this size (in bytes) are returned inline as base64
+    pub inline_artifacts_max_size: Option<u64>,
+    /// Report files created, modified or deleted by the tool in its read-write mounts
+    pub track_changes: Option<ChangeTrackingOptions>,
+    /// Rules used by `lint`
+    pub lint_rules: LintRules,
+    /// Definition of the tool, `check` verifies that the types of `run` parameters match its schemas
+    pub tool_definition: Option<ToolDefinition>,
+    /// Severities of the risky patterns reported by `audit`
+    pub audit_policy: AuditPolicy,
+    /// npm registry used instead of the public one, e.g. a local mirror
+    pub npm_registry: Option<String>,
+    /// JSR registry used instead of the public one, e.g. a local mirror (`JSR_URL`)
+    pub jsr_registry: Option<String>,
+    /// Deno cache folder (`DENO_DIR` layout) with modules downloaded ahead of time, e.g. by `prepare`
+    /// on a connected machine. Modules missing from the runner deno cache are copied from it.
+    pub vendored_modules_path: Option<PathBuf>,
+    /// Only use cached and vendored modules (`--cached-only`), modules that aren't available fail
+    /// instead of being downloaded
+    pub offline: bool,
+    /// Packages the tool may install, checked before any dependency is downloaded
+    pub dependency_policy: DependencyPolicy,
 }
 
 impl Default for DenoRunnerOptions {
@@ -30,6 +53,16 @@ impl Default for DenoRunnerOptions {
                 host: String::from("127.0.0.1"),
                 port: 9550,
             },
+            inline_artifacts_max_size: None,
+            track_changes: None,
+            lint_rules: LintRules::default(),
+            tool_definition: None,
+            audit_policy: AuditPolicy::default(),
+            npm_registry: None,
+            jsr_registry: None,
+            vendored_modules_path: None,
+            offline: false,
+            dependency_policy: DependencyPolicy::default(),
         }
     }
 }
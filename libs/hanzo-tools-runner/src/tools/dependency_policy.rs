@@ -0,0 +1,656 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use super::code_files::CodeFiles;
+
+/// Packages tools may install, evaluated before the code is checked, prepared or run
+#[derive(Clone, Debug, Default)]
+pub struct DependencyPolicy {
+    /// Only these packages may be installed, every package is allowed when `None`
+    pub allow: Option<Vec<String>>,
+    /// Packages that may never be installed
+    pub deny: Vec<String>,
+    /// Versions packages must satisfy by name, as comma separated comparisons like `>=2.31, <3`.
+    /// Packages with a constraint must be pinned to an exact version.
+    pub version_constraints: HashMap<String, String>,
+    /// Registries packages may come from: `pypi`, `npm`, `jsr` or URL prefixes, which cover
+    /// custom package indexes and `https:` imports. Every registry is allowed when `None`.
+    pub allowed_registries: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DependencyViolationReason {
+    NotAllowed,
+    Denied,
+    VersionNotAllowed { constraint: String },
+    UnpinnedVersion { constraint: String },
+    InvalidConstraint { constraint: String, message: String },
+    RegistryNotAllowed,
+}
+
+/// A dependency the policy doesn't allow
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DependencyViolation {
+    /// Package name, or the module URL of `https:` imports
+    pub package: String,
+    /// `pypi`, `npm`, `jsr`, the package index URL or the module URL
+    pub registry: String,
+    /// Pinned version, or the version requirement declared by the tool
+    pub version: Option<String>,
+    pub reason: DependencyViolationReason,
+}
+
+impl std::fmt::Display for DependencyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.package, self.registry)?;
+        match &self.reason {
+            DependencyViolationReason::NotAllowed => write!(f, " isn't in the allowlist"),
+            DependencyViolationReason::Denied => write!(f, " is denied"),
+            DependencyViolationReason::VersionNotAllowed { constraint } => write!(
+                f,
+                " version {} doesn't satisfy {}",
+                self.version.clone().unwrap_or_default(),
+                constraint
+            ),
+            DependencyViolationReason::UnpinnedVersion { constraint } => {
+                write!(f, " must be pinned to a version satisfying {}", constraint)
+            }
+            DependencyViolationReason::InvalidConstraint { message, .. } => {
+                write!(f, " can't be checked: {}", message)
+            }
+            DependencyViolationReason::RegistryNotAllowed => write!(f, " registry isn't allowed"),
+        }
+    }
+}
+
+/// Dependencies of a tool rejected by its `DependencyPolicy`
+#[derive(Clone, Debug, PartialEq)]
+pub struct DependencyPolicyError {
+    pub violations: Vec<DependencyViolation>,
+}
+
+impl std::fmt::Display for DependencyPolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let violations: Vec<String> = self.violations.iter().map(|v| v.to_string()).collect();
+        write!(f, "dependency policy violations: {}", violations.join("; "))
+    }
+}
+
+impl std::error::Error for DependencyPolicyError {}
+
+/// A dependency declared by a tool
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeclaredDependency {
+    pub name: String,
+    pub registry: String,
+    /// Version requirement, e.g. `==2.32.3`, `^4.17.0` or `4.17.21`
+    pub version: Option<String>,
+    /// Version the requirement pins, if it allows exactly one
+    pub exact_version: Option<String>,
+}
+
+/// Normalized package name, PyPI names are case insensitive and treat `-`, `_` and `.` alike
+fn normalize_name(name: &str, registry: &str) -> String {
+    match registry {
+        "npm" | "jsr" => name.to_string(),
+        _ => Regex::new(r"[-_.]+")
+            .unwrap()
+            .replace_all(&name.to_lowercase(), "-")
+            .to_string(),
+    }
+}
+
+/// Numeric components of a version, `1.2rc1` is `[1, 2]`
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().unwrap_or(0)
+        })
+        .collect()
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_parts(a), version_parts(b));
+    for index in 0..a.len().max(b.len()) {
+        let ordering = a.get(index).unwrap_or(&0).cmp(b.get(index).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Whether the first components of `version` are the components of `prefix`
+fn version_has_prefix(version: &str, prefix: &str) -> bool {
+    let (version, prefix) = (version_parts(version), version_parts(prefix));
+    (0..prefix.len()).all(|index| version.get(index).unwrap_or(&0) == &prefix[index])
+}
+
+/// Whether `version` satisfies every comparison of `constraint`: `>=`, `<=`, `>`, `<`, `==` and
+/// `!=` (with `.*` prefix matching), `~=` (compatible release), `===` (string equality) or a bare
+/// version. Fails on other operators, so constraints that can't be evaluated don't pass.
+pub fn version_satisfies(version: &str, constraint: &str) -> anyhow::Result<bool> {
+    for comparison in constraint
+        .split(',')
+        .map(|comparison| comparison.trim())
+        .filter(|comparison| !comparison.is_empty())
+    {
+        let operator_length = comparison
+            .find(|c: char| c.is_ascii_alphanumeric())
+            .unwrap_or(comparison.len());
+        let (operator, bound) = comparison.split_at(operator_length);
+        let bound = bound.trim();
+        let satisfied = match (operator.trim(), bound.strip_suffix(".*")) {
+            ("==", Some(prefix)) => version_has_prefix(version, prefix),
+            ("!=", Some(prefix)) => !version_has_prefix(version, prefix),
+            (">=", None) => compare_versions(version, bound) != Ordering::Less,
+            ("<=", None) => compare_versions(version, bound) != Ordering::Greater,
+            (">", None) => compare_versions(version, bound) == Ordering::Greater,
+            ("<", None) => compare_versions(version, bound) == Ordering::Less,
+            ("!=", None) => compare_versions(version, bound) != Ordering::Equal,
+            ("==", None) | ("", None) => compare_versions(version, bound) == Ordering::Equal,
+            ("===", None) => version == bound,
+            // `~=2.31.1` is `>=2.31.1, ==2.31.*`
+            ("~=", None) => {
+                let Some((prefix, _)) = bound.rsplit_once('.') else {
+                    return Err(anyhow::anyhow!(
+                        "invalid version constraint `{}`: `~=` needs a version with at least two components",
+                        comparison
+                    ));
+                };
+                compare_versions(version, bound) != Ordering::Less
+                    && version_has_prefix(version, prefix)
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "invalid version constraint `{}`: unsupported operator `{}`",
+                    comparison,
+                    operator.trim()
+                ))
+            }
+        };
+        if !satisfied {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Strings of a TOML value that is either a string or an array of strings
+fn toml_strings(item: Option<&Item>) -> Vec<String> {
+    match item {
+        Some(item) => match item.as_array() {
+            Some(array) => array
+                .iter()
+                .filter_map(|value| value.as_str())
+                .map(String::from)
+                .collect(),
+            None => item.as_str().map(String::from).into_iter().collect(),
+        },
+        None => Vec::new(),
+    }
+}
+
+/// Tables of a TOML value that is either a table, an array of tables or an array of inline tables
+fn toml_tables(item: Option<&Item>) -> Vec<&dyn TableLike> {
+    match item {
+        Some(Item::ArrayOfTables(tables)) => {
+            tables.iter().map(|table| table as &dyn TableLike).collect()
+        }
+        Some(item) => match item.as_array() {
+            Some(array) => array
+                .iter()
+                .filter_map(|value| value.as_inline_table())
+                .map(|table| table as &dyn TableLike)
+                .collect(),
+            None => item.as_table_like().into_iter().collect(),
+        },
+        None => Vec::new(),
+    }
+}
+
+/// Where a `[tool.uv.sources]` entry installs its package from: the git repository, URL or path,
+/// or the URL of the named index
+fn uv_source_registry(source: &dyn TableLike, index_urls: &HashMap<String, String>) -> String {
+    let value = |key: &str| {
+        source
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(String::from)
+    };
+    if let Some(index) = value("index") {
+        return index_urls.get(&index).cloned().unwrap_or(index);
+    }
+    value("git")
+        .or_else(|| value("url"))
+        .or_else(|| value("path"))
+        .unwrap_or_else(|| String::from("workspace"))
+}
+
+/// Dependencies of the merged `pyproject.toml`, resolved from `registry` unless they are direct
+/// URL references or have a `[tool.uv.sources]` entry. Every requirement uv installs for the
+/// project is read: `project.dependencies`, development dependencies and dependency groups, and
+/// overrides. The package indexes the project adds are returned as dependencies named after their
+/// URL, since any package without a source can be resolved from them.
+pub fn python_dependencies(
+    pyproject_toml: &str,
+    registry: &str,
+) -> anyhow::Result<Vec<DeclaredDependency>> {
+    let pyproject_toml = pyproject_toml.parse::<DocumentMut>()?;
+    let requirement_regex =
+        Regex::new(r"^\s*([A-Za-z0-9][A-Za-z0-9._-]*)\s*(?:\[[^\]]*\])?\s*([^;]*)").unwrap();
+    let exact_regex = Regex::new(r"^===?\s*([^,*\s]+)$").unwrap();
+    let uv = pyproject_toml.get("tool").and_then(|tool| tool.get("uv"));
+    let uv_setting = |key: &str| uv.and_then(|uv| uv.get(key));
+
+    let mut requirements = toml_strings(
+        pyproject_toml
+            .get("project")
+            .and_then(|project| project.get("dependencies")),
+    );
+    requirements.extend(toml_strings(uv_setting("dev-dependencies")));
+    requirements.extend(toml_strings(uv_setting("override-dependencies")));
+    if let Some(groups) = pyproject_toml
+        .get("dependency-groups")
+        .and_then(|groups| groups.as_table_like())
+    {
+        for (_, group) in groups.iter() {
+            requirements.extend(toml_strings(Some(group)));
+        }
+    }
+
+    let mut index_urls = HashMap::new();
+    let mut dependencies = Vec::new();
+    for index in toml_tables(uv_setting("index")) {
+        let Some(url) = index.get("url").and_then(|url| url.as_str()) else {
+            continue;
+        };
+        if let Some(name) = index.get("name").and_then(|name| name.as_str()) {
+            index_urls.insert(name.to_string(), url.to_string());
+        }
+        // Explicit indexes are only used by the packages whose source they are
+        let explicit = index
+            .get("explicit")
+            .and_then(|explicit| explicit.as_bool())
+            .unwrap_or(false);
+        if !explicit {
+            dependencies.push(url.to_string());
+        }
+    }
+    for key in ["index-url", "extra-index-url", "find-links"] {
+        dependencies.extend(toml_strings(uv_setting(key)));
+    }
+    let mut dependencies: Vec<DeclaredDependency> = dependencies
+        .into_iter()
+        .map(|url| DeclaredDependency {
+            name: url.clone(),
+            registry: url,
+            version: None,
+            exact_version: None,
+        })
+        .collect();
+
+    let sources: HashMap<String, Vec<String>> = uv_setting("sources")
+        .and_then(|sources| sources.as_table_like())
+        .map(|sources| {
+            sources
+                .iter()
+                .map(|(name, source)| {
+                    (
+                        normalize_name(name, "pypi"),
+                        toml_tables(Some(source))
+                            .into_iter()
+                            .map(|source| uv_source_registry(source, &index_urls))
+                            .collect(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for requirement in requirements {
+        let Some(captures) = requirement_regex.captures(&requirement) else {
+            continue;
+        };
+        let name = captures[1].to_string();
+        let specifier = captures[2].trim().to_string();
+        if let Some(url) = specifier.strip_prefix('@') {
+            dependencies.push(DeclaredDependency {
+                name,
+                registry: url.trim().to_string(),
+                version: None,
+                exact_version: None,
+            });
+            continue;
+        }
+        let exact_version = exact_regex
+            .captures(&specifier)
+            .map(|captures| captures[1].to_string());
+        let version = (!specifier.is_empty()).then_some(specifier);
+        let registries = sources
+            .get(&normalize_name(&name, "pypi"))
+            .cloned()
+            .unwrap_or_else(|| vec![registry.to_string()]);
+        for registry in registries {
+            dependencies.push(DeclaredDependency {
+                name: name.clone(),
+                registry,
+                version: version.clone(),
+                exact_version: exact_version.clone(),
+            });
+        }
+    }
+    Ok(dependencies)
+}
+
+/// `npm:`/`jsr:` specifier as a dependency, e.g. `npm:@scope/name@^1.2.0/subpath`
+fn registry_dependency(registry: &str, specifier: &str) -> DeclaredDependency {
+    let specifier = specifier.trim_start_matches('/');
+    // Scoped names contain a `/`, the version starts after the `@` that follows the name
+    let name_length = match specifier.strip_prefix('@') {
+        Some(rest) => rest
+            .find('/')
+            .map(|slash| slash + 2)
+            .unwrap_or(specifier.len()),
+        None => 0,
+    };
+    let (name, version) = match specifier[name_length..].find(['@', '/']) {
+        Some(index) if specifier[name_length + index..].starts_with('@') => {
+            let version = &specifier[name_length + index + 1..];
+            (
+                &specifier[..name_length + index],
+                Some(version.split('/').next().unwrap_or_default()),
+            )
+        }
+        Some(index) => (&specifier[..name_length + index], None),
+        None => (specifier, None),
+    };
+    let version = version
+        .filter(|version| !version.is_empty())
+        .map(|version| version.to_string());
+    DeclaredDependency {
+        name: name.to_string(),
+        registry: registry.to_string(),
+        exact_version: version.clone().filter(|version| {
+            Regex::new(r"^v?\d+(\.\d+)*([-+][\w.]+)?$")
+                .unwrap()
+                .is_match(version)
+        }),
+        version,
+    }
+}
+
+fn module_dependency(specifier: &str) -> Option<DeclaredDependency> {
+    if let Some(specifier) = specifier.strip_prefix("npm:") {
+        Some(registry_dependency("npm", specifier))
+    } else if let Some(specifier) = specifier.strip_prefix("jsr:") {
+        Some(registry_dependency("jsr", specifier))
+    } else if specifier.starts_with("https://") || specifier.starts_with("http://") {
+        Some(DeclaredDependency {
+            name: specifier.to_string(),
+            registry: specifier.to_string(),
+            version: None,
+            exact_version: None,
+        })
+    } else {
+        None
+    }
+}
+
+/// Modules imported by the code: `npm:`, `jsr:` and `https:` specifiers in the source files and
+/// the `deno.json` import map, and the `package.json` dependencies. Transitive dependencies are
+/// pinned by the lockfile.
+pub fn deno_dependencies(code: &CodeFiles) -> anyhow::Result<Vec<DeclaredDependency>> {
+    let specifier_regex = Regex::new(r#"["'`]((?:npm:|jsr:|https?://)[^"'`\s]+)["'`]"#).unwrap();
+    let mut paths: Vec<&String> = code.files.keys().collect();
+    paths.sort();
+
+    let mut dependencies = Vec::new();
+    for path in paths {
+        let content = &code.files[path];
+        if path == "deno.json" {
+            if content.trim().is_empty() {
+                continue;
+            }
+            let deno_json: Value = serde_json::from_str(content)
+                .map_err(|e| anyhow::anyhow!("invalid deno.json: {}", e))?;
+            for specifier in deno_json["imports"]
+                .as_object()
+                .into_iter()
+                .flat_map(|i| i.values())
+            {
+                dependencies.extend(specifier.as_str().and_then(module_dependency));
+            }
+        } else if path == "package.json" {
+            let package_json: Value = serde_json::from_str(content)
+                .map_err(|e| anyhow::anyhow!("invalid package.json: {}", e))?;
+            for section in ["dependencies", "devDependencies"] {
+                for (name, version) in package_json[section].as_object().into_iter().flatten() {
+                    let version = version.as_str().unwrap_or_default();
+                    dependencies.push(module_dependency(version).unwrap_or_else(|| {
+                        registry_dependency("npm", &format!("{}@{}", name, version))
+                    }));
+                }
+            }
+        } else if [".ts", ".tsx", ".js", ".jsx", ".mjs", ".mts"]
+            .iter()
+            .any(|extension| path.ends_with(extension))
+        {
+            for line in content.lines() {
+                if line.trim_start().starts_with("//") {
+                    continue;
+                }
+                for captures in specifier_regex.captures_iter(line) {
+                    dependencies.extend(module_dependency(&captures[1]));
+                }
+            }
+        }
+    }
+    Ok(dependencies)
+}
+
+/// Modules of the `deno info --json` module graph: the npm packages it resolved and the remote
+/// modules, the ones served from `jsr_url` being jsr packages. Unlike `deno_dependencies` this
+/// covers the transitive imports of every module and dynamic imports of literal specifiers.
+pub fn deno_graph_dependencies(deno_info: &Value, jsr_url: &str) -> Vec<DeclaredDependency> {
+    let mut dependencies: Vec<DeclaredDependency> = deno_info["npmPackages"]
+        .as_object()
+        .into_iter()
+        .flat_map(|packages| packages.values())
+        .filter_map(|package| {
+            let version = package["version"].as_str()?.to_string();
+            Some(DeclaredDependency {
+                name: package["name"].as_str()?.to_string(),
+                registry: String::from("npm"),
+                version: Some(version.clone()),
+                exact_version: Some(version),
+            })
+        })
+        .collect();
+
+    let jsr_regex = Regex::new(&format!(
+        r"^{}/(@[^/]+/[^/]+)/([^/]+)/",
+        regex::escape(jsr_url.trim_end_matches('/'))
+    ))
+    .unwrap();
+    for module in deno_info["modules"].as_array().into_iter().flatten() {
+        let Some(specifier) = module["specifier"].as_str() else {
+            continue;
+        };
+        if let Some(captures) = jsr_regex.captures(specifier) {
+            dependencies.push(DeclaredDependency {
+                name: captures[1].to_string(),
+                registry: String::from("jsr"),
+                version: Some(captures[2].to_string()),
+                exact_version: Some(captures[2].to_string()),
+            });
+        } else if specifier.starts_with("https://") || specifier.starts_with("http://") {
+            dependencies.extend(module_dependency(specifier));
+        }
+    }
+    let mut seen = HashSet::new();
+    dependencies.retain(|dependency| {
+        seen.insert((
+            dependency.name.clone(),
+            dependency.registry.clone(),
+            dependency.version.clone(),
+        ))
+    });
+    dependencies
+}
+
+/// Whether `url` is under the URL prefix `prefix`: same scheme, host and port, and a path equal to
+/// the prefix path or below it, so `https://registry.corp` doesn't cover
+/// `https://registry.corp.evil.com`
+fn url_has_prefix(url: &str, prefix: &str) -> bool {
+    if !prefix.contains("://") {
+        return false;
+    }
+    let (Ok(url), Ok(prefix)) = (reqwest::Url::parse(url), reqwest::Url::parse(prefix)) else {
+        return false;
+    };
+    if url.scheme() != prefix.scheme()
+        || url.host_str() != prefix.host_str()
+        || url.port_or_known_default() != prefix.port_or_known_default()
+    {
+        return false;
+    }
+    let prefix_path = prefix.path().trim_end_matches('/');
+    url.path() == prefix_path || url.path().starts_with(&format!("{}/", prefix_path))
+}
+
+impl DependencyPolicy {
+    /// Whether the policy allows every dependency, so there's nothing to evaluate
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow.is_none()
+            && self.deny.is_empty()
+            && self.version_constraints.is_empty()
+            && self.allowed_registries.is_none()
+    }
+
+    /// Hosts remote modules may be imported from while the code runs, which covers the imports
+    /// no module graph resolves ahead of time, e.g. computed specifiers. `None` when every
+    /// registry is allowed.
+    pub fn allowed_import_hosts(&self, jsr_url: &str) -> Option<Vec<String>> {
+        let allowed_registries = self.allowed_registries.as_ref()?;
+        let mut hosts: Vec<String> = allowed_registries
+            .iter()
+            .filter_map(|registry| match registry.as_str() {
+                "jsr" => Some(jsr_url),
+                registry if registry.contains("://") => Some(registry),
+                _ => None,
+            })
+            .filter_map(|url| reqwest::Url::parse(url).ok())
+            .filter_map(|url| {
+                let host = url.host_str()?.to_string();
+                Some(match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host,
+                })
+            })
+            .collect();
+        hosts.sort();
+        hosts.dedup();
+        Some(hosts)
+    }
+
+    fn registry_allowed(&self, registry: &str) -> bool {
+        match &self.allowed_registries {
+            Some(allowed_registries) => allowed_registries
+                .iter()
+                .any(|allowed| allowed == registry || url_has_prefix(registry, allowed)),
+            None => true,
+        }
+    }
+
+    /// Violations of the policy by `dependencies`, `https:` imports are only checked against
+    /// `allowed_registries`
+    pub fn evaluate(&self, dependencies: &[DeclaredDependency]) -> Vec<DependencyViolation> {
+        let mut violations = Vec::new();
+        for dependency in dependencies {
+            let violation = |reason| DependencyViolation {
+                package: dependency.name.clone(),
+                registry: dependency.registry.clone(),
+                version: dependency
+                    .exact_version
+                    .clone()
+                    .or(dependency.version.clone()),
+                reason,
+            };
+            if !self.registry_allowed(&dependency.registry) {
+                violations.push(violation(DependencyViolationReason::RegistryNotAllowed));
+                continue;
+            }
+            if dependency.name == dependency.registry {
+                continue;
+            }
+            let name = normalize_name(&dependency.name, &dependency.registry);
+            let matches = |entry: &String| normalize_name(entry, &dependency.registry) == name;
+            if self.deny.iter().any(matches) {
+                violations.push(violation(DependencyViolationReason::Denied));
+                continue;
+            }
+            if let Some(allow) = &self.allow {
+                if !allow.iter().any(matches) {
+                    violations.push(violation(DependencyViolationReason::NotAllowed));
+                    continue;
+                }
+            }
+            let constraint = self
+                .version_constraints
+                .iter()
+                .find(|(entry, _)| matches(entry))
+                .map(|(_, constraint)| constraint.clone());
+            if let Some(constraint) = constraint {
+                match &dependency.exact_version {
+                    Some(version) => match version_satisfies(version, &constraint) {
+                        Ok(true) => {}
+                        Ok(false) => violations.push(violation(
+                            DependencyViolationReason::VersionNotAllowed { constraint },
+                        )),
+                        Err(e) => violations.push(violation(
+                            DependencyViolationReason::InvalidConstraint {
+                                constraint,
+                                message: e.to_string(),
+                            },
+                        )),
+                    },
+                    None => {
+                        violations.push(violation(DependencyViolationReason::UnpinnedVersion {
+                            constraint,
+                        }))
+                    }
+                }
+            }
+        }
+        violations
+    }
+
+    /// Fails with a `DependencyPolicyError` when any of `dependencies` violates the policy
+    pub fn enforce(
+        &self,
+        dependencies: &[DeclaredDependency],
+    ) -> Result<(), DependencyPolicyError> {
+        let violations = self.evaluate(dependencies);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        log::error!("dependency policy violations: {:?}", violations);
+        Err(DependencyPolicyError { violations })
+    }
+}
+
+#[cfg(test)]
+#[path = "dependency_policy.test.rs"]
+mod tests;
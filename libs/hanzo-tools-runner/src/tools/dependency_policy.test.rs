@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use crate::tools::{
+    code_files::CodeFiles,
+    dependency_policy::{
+        deno_dependencies, deno_graph_dependencies, python_dependencies, version_satisfies,
+        DeclaredDependency, DependencyPolicy, DependencyViolation, DependencyViolationReason,
+    },
+};
+
+#[test]
+fn test_version_satisfies() {
+    let satisfies =
+        |version: &str, constraint: &str| version_satisfies(version, constraint).unwrap();
+    assert!(satisfies("2.32.3", ">=2.31, <3"));
+    assert!(!satisfies("2.30.0", ">=2.31, <3"));
+    assert!(!satisfies("3.0", ">=2.31, <3"));
+    assert!(satisfies("4.17.21", "==4.17.21"));
+    assert!(!satisfies("4.17.20", "!=4.17.20"));
+    assert!(satisfies("2.32.3", "~=2.31"));
+    assert!(!satisfies("3.0.0", "~=2.31"));
+    assert!(satisfies("2.31.4", "~=2.31.1"));
+    assert!(!satisfies("2.32.0", "~=2.31.1"));
+    assert!(satisfies("2.32.3", "==2.*"));
+    assert!(!satisfies("2.32.3", "!=2.32.*"));
+    assert!(satisfies("1.0+local", "===1.0+local"));
+    assert!(!satisfies("1.0", "===1.0+local"));
+
+    assert_eq!(
+        version_satisfies("2.32.3", "^2.31")
+            .unwrap_err()
+            .to_string(),
+        "invalid version constraint `^2.31`: unsupported operator `^`"
+    );
+    assert!(version_satisfies("2.32.3", "~=2").is_err());
+}
+
+#[test]
+fn test_python_dependencies() {
+    let pyproject_toml = r#"
+[project]
+name = "hanzo-tool"
+dependencies = [
+    "jsonpickle~=4.0.0",
+    "requests[socks]==2.32.3",
+    "Flask_Cors ; python_version >= '3.10'",
+    "pandas @ https://example.com/pandas-2.2.3.whl",
+]
+"#;
+    assert_eq!(
+        python_dependencies(pyproject_toml, "pypi").unwrap(),
+        vec![
+            DeclaredDependency {
+                name: "jsonpickle".to_string(),
+                registry: "pypi".to_string(),
+                version: Some("~=4.0.0".to_string()),
+                exact_version: None,
+            },
+            DeclaredDependency {
+                name: "requests".to_string(),
+                registry: "pypi".to_string(),
+                version: Some("==2.32.3".to_string()),
+                exact_version: Some("2.32.3".to_string()),
+            },
+            DeclaredDependency {
+                name: "Flask_Cors".to_string(),
+                registry: "pypi".to_string(),
+                version: None,
+                exact_version: None,
+            },
+            DeclaredDependency {
+                name: "pandas".to_string(),
+                registry: "https://example.com/pandas-2.2.3.whl".to_string(),
+                version: None,
+                exact_version: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_python_dependencies_reads_uv_sources_indexes_and_groups() {
+    let pyproject_toml = r#"
+[project]
+name = "hanzo-tool"
+dependencies = ["requests==2.32.3", "httpx"]
+
+[dependency-groups]
+dev = ["pytest>=8"]
+
+[tool.uv]
+extra-index-url = ["https://extra.example.com/simple"]
+override-dependencies = ["urllib3<2"]
+
+[tool.uv.sources]
+requests = { git = "https://evil.example.com/requests" }
+httpx = { index = "internal" }
+
+[[tool.uv.index]]
+name = "internal"
+url = "https://internal.example.com/simple"
+explicit = true
+
+[[tool.uv.index]]
+url = "https://mirror.example.com/simple"
+"#;
+    let dependencies: Vec<(String, String)> = python_dependencies(pyproject_toml, "pypi")
+        .unwrap()
+        .into_iter()
+        .map(|d| (d.name, d.registry))
+        .collect();
+    let dependency = |name: &str, registry: &str| (name.to_string(), registry.to_string());
+    assert_eq!(
+        dependencies,
+        vec![
+            dependency(
+                "https://mirror.example.com/simple",
+                "https://mirror.example.com/simple"
+            ),
+            dependency(
+                "https://extra.example.com/simple",
+                "https://extra.example.com/simple"
+            ),
+            dependency("requests", "https://evil.example.com/requests"),
+            dependency("httpx", "https://internal.example.com/simple"),
+            dependency("urllib3", "pypi"),
+            dependency("pytest", "pypi"),
+        ]
+    );
+}
+
+#[test]
+fn test_deno_dependencies() {
+    let code = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.ts".to_string(),
+                r#"import { kebabCase } from "npm:lodash-es@4.17.21";
+import { join } from "jsr:@std/path@^1.0.0/join";
+import { z } from "zod";
+// import chalk from "npm:chalk";
+const mod = await import("https://deno.land/x/case@2.2.0/mod.ts");
+"#
+                .to_string(),
+            ),
+            (
+                "deno.json".to_string(),
+                r#"{ "imports": { "zod": "npm:zod@^3.23.0", "utils/": "./utils/" } }"#.to_string(),
+            ),
+            (
+                "package.json".to_string(),
+                r#"{ "dependencies": { "@types/node": "22.9.0" } }"#.to_string(),
+            ),
+        ]),
+        entrypoint: "main.ts".to_string(),
+    };
+    let dependencies: Vec<(String, String, Option<String>, Option<String>)> =
+        deno_dependencies(&code)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.name, d.registry, d.version, d.exact_version))
+            .collect();
+    let dependency = |name: &str, registry: &str, version: Option<&str>, exact: Option<&str>| {
+        (
+            name.to_string(),
+            registry.to_string(),
+            version.map(|v| v.to_string()),
+            exact.map(|v| v.to_string()),
+        )
+    };
+    assert_eq!(
+        dependencies,
+        vec![
+            dependency("zod", "npm", Some("^3.23.0"), None),
+            dependency("lodash-es", "npm", Some("4.17.21"), Some("4.17.21")),
+            dependency("@std/path", "jsr", Some("^1.0.0"), None),
+            dependency(
+                "https://deno.land/x/case@2.2.0/mod.ts",
+                "https://deno.land/x/case@2.2.0/mod.ts",
+                None,
+                None
+            ),
+            dependency("@types/node", "npm", Some("22.9.0"), Some("22.9.0")),
+        ]
+    );
+}
+
+#[test]
+fn test_deno_graph_dependencies() {
+    let deno_info = json!({
+        "modules": [
+            { "specifier": "file:///code/main.ts" },
+            { "specifier": "https://jsr.io/@std/path/1.0.8/mod.ts" },
+            { "specifier": "https://jsr.io/@std/path/1.0.8/join.ts" },
+            { "specifier": "https://deno.land/x/case@2.2.0/mod.ts" },
+            { "specifier": "npm:/chalk@5.3.0" }
+        ],
+        "npmPackages": {
+            "chalk@5.3.0": { "name": "chalk", "version": "5.3.0", "dependencies": [] }
+        }
+    });
+    let dependencies: Vec<(String, String, Option<String>)> =
+        deno_graph_dependencies(&deno_info, "https://jsr.io/")
+            .into_iter()
+            .map(|d| (d.name, d.registry, d.exact_version))
+            .collect();
+    let dependency = |name: &str, registry: &str, exact: Option<&str>| {
+        (
+            name.to_string(),
+            registry.to_string(),
+            exact.map(|v| v.to_string()),
+        )
+    };
+    assert_eq!(
+        dependencies,
+        vec![
+            dependency("chalk", "npm", Some("5.3.0")),
+            dependency("@std/path", "jsr", Some("1.0.8")),
+            dependency(
+                "https://deno.land/x/case@2.2.0/mod.ts",
+                "https://deno.land/x/case@2.2.0/mod.ts",
+                None
+            ),
+        ]
+    );
+
+    // Modules served by a JSR mirror are jsr packages too
+    let deno_info = json!({
+        "modules": [{ "specifier": "http://localhost:8000/@std/path/1.0.8/mod.ts" }]
+    });
+    assert_eq!(
+        deno_graph_dependencies(&deno_info, "http://localhost:8000")[0].registry,
+        "jsr"
+    );
+}
+
+#[test]
+fn test_allowed_import_hosts() {
+    let policy = DependencyPolicy {
+        allowed_registries: Some(vec![
+            "npm".to_string(),
+            "jsr".to_string(),
+            "https://deno.land/x/".to_string(),
+            "http://localhost:8000/modules/".to_string(),
+        ]),
+        ..Default::default()
+    };
+    assert_eq!(
+        policy.allowed_import_hosts("https://jsr.io/"),
+        Some(vec![
+            "deno.land".to_string(),
+            "jsr.io".to_string(),
+            "localhost:8000".to_string(),
+        ])
+    );
+    let policy = DependencyPolicy {
+        allowed_registries: Some(vec!["npm".to_string()]),
+        ..Default::default()
+    };
+    assert_eq!(policy.allowed_import_hosts("https://jsr.io/"), Some(vec![]));
+    assert_eq!(
+        DependencyPolicy::default().allowed_import_hosts("https://jsr.io/"),
+        None
+    );
+}
+
+#[test]
+fn test_registry_url_prefixes_match_whole_hosts_and_path_segments() {
+    let policy = DependencyPolicy {
+        allowed_registries: Some(vec![
+            "https://registry.corp".to_string(),
+            "https://mirror.example.com/simple/".to_string(),
+        ]),
+        ..Default::default()
+    };
+    let not_allowed = |registry: &str| {
+        !policy
+            .evaluate(&[DeclaredDependency {
+                name: "requests".to_string(),
+                registry: registry.to_string(),
+                version: None,
+                exact_version: None,
+            }])
+            .is_empty()
+    };
+    assert!(!not_allowed("https://registry.corp"));
+    assert!(!not_allowed("https://registry.corp/packages/requests"));
+    assert!(!not_allowed("https://REGISTRY.corp:443/packages"));
+    assert!(!not_allowed("https://mirror.example.com/simple"));
+    assert!(!not_allowed("https://mirror.example.com/simple/requests/"));
+    assert!(not_allowed("https://registry.corp.evil.com"));
+    assert!(not_allowed("https://registry.corp.evil.com/packages"));
+    assert!(not_allowed("https://registry.corp@evil.com/packages"));
+    assert!(not_allowed("http://registry.corp/packages"));
+    assert!(not_allowed("https://registry.corp:8443/packages"));
+    assert!(not_allowed(
+        "https://mirror.example.com/simple-evil/requests"
+    ));
+    assert!(not_allowed("https://mirror.example.com/"));
+}
+
+#[test]
+fn test_dependency_policy_evaluate() {
+    let dependency = |name: &str, registry: &str, exact: Option<&str>| DeclaredDependency {
+        name: name.to_string(),
+        registry: registry.to_string(),
+        version: exact.map(|v| format!("=={}", v)),
+        exact_version: exact.map(|v| v.to_string()),
+    };
+    let policy = DependencyPolicy {
+        allow: Some(vec![
+            "requests".to_string(),
+            "numpy".to_string(),
+            "python-dateutil".to_string(),
+        ]),
+        deny: vec!["numpy".to_string()],
+        version_constraints: HashMap::from([("requests".to_string(), ">=2.31".to_string())]),
+        allowed_registries: Some(vec![
+            "pypi".to_string(),
+            "https://mirror.example.com/".to_string(),
+        ]),
+    };
+    let violations = policy.evaluate(&[
+        dependency("Python_Dateutil", "pypi", None),
+        dependency(
+            "requests",
+            "https://mirror.example.com/simple",
+            Some("2.32.3"),
+        ),
+        dependency("requests", "pypi", Some("2.30.0")),
+        dependency("requests", "pypi", None),
+        dependency("numpy", "pypi", None),
+        dependency("pandas", "pypi", None),
+        dependency(
+            "https://evil.example.com/x.whl",
+            "https://evil.example.com/x.whl",
+            None,
+        ),
+    ]);
+    let violation = |package: &str, version: Option<&str>, reason| DependencyViolation {
+        package: package.to_string(),
+        registry: "pypi".to_string(),
+        version: version.map(|v| v.to_string()),
+        reason,
+    };
+    assert_eq!(
+        violations,
+        vec![
+            violation(
+                "requests",
+                Some("2.30.0"),
+                DependencyViolationReason::VersionNotAllowed {
+                    constraint: ">=2.31".to_string()
+                }
+            ),
+            violation(
+                "requests",
+                None,
+                DependencyViolationReason::UnpinnedVersion {
+                    constraint: ">=2.31".to_string()
+                }
+            ),
+            violation("numpy", None, DependencyViolationReason::Denied),
+            violation("pandas", None, DependencyViolationReason::NotAllowed),
+            DependencyViolation {
+                package: "https://evil.example.com/x.whl".to_string(),
+                registry: "https://evil.example.com/x.whl".to_string(),
+                version: None,
+                reason: DependencyViolationReason::RegistryNotAllowed,
+            },
+        ]
+    );
+    assert_eq!(policy.enforce(&[]).map_err(|e| e.to_string()), Ok(()));
+    assert_eq!(
+        policy
+            .enforce(&[dependency("pandas", "pypi", None)])
+            .unwrap_err()
+            .to_string(),
+        "dependency policy violations: pandas (pypi) isn't in the allowlist"
+    );
+}
+
+#[test]
+fn test_dependency_policy_fails_closed_on_invalid_constraints() {
+    let policy = DependencyPolicy {
+        version_constraints: HashMap::from([("requests".to_string(), "^2.31".to_string())]),
+        ..Default::default()
+    };
+    let violations = policy.evaluate(&[DeclaredDependency {
+        name: "requests".to_string(),
+        registry: "pypi".to_string(),
+        version: Some("==2.32.3".to_string()),
+        exact_version: Some("2.32.3".to_string()),
+    }]);
+    assert_eq!(
+        violations,
+        vec![DependencyViolation {
+            package: "requests".to_string(),
+            registry: "pypi".to_string(),
+            version: Some("2.32.3".to_string()),
+            reason: DependencyViolationReason::InvalidConstraint {
+                constraint: "^2.31".to_string(),
+                message: "invalid version constraint `^2.31`: unsupported operator `^`".to_string(),
+            },
+        }]
+    );
+    assert_eq!(
+        violations[0].to_string(),
+        "requests (pypi) can't be checked: invalid version constraint `^2.31`: unsupported operator `^`"
+    );
+}
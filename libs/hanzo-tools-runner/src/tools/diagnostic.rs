@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticPosition {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// A problem found in the code by a checker or linter
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Path relative to the code folder, empty when the tool didn't report a file
+    pub file: String,
+    /// 1-based line, 0 when unknown
+    pub line: u32,
+    /// 1-based column, 0 when unknown
+    pub column: u32,
+    pub end: Option<DiagnosticPosition>,
+    pub severity: DiagnosticSeverity,
+    /// Rule or error code like `F821` or `TS2345`
+    pub code: Option<String>,
+    /// Tool that reported it: `deno`, `ruff` or `pyright`
+    pub source_tool: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Diagnostic for tool output that couldn't be parsed into a location
+    pub fn unparsed(source_tool: &str, message: String) -> Self {
+        Self {
+            file: String::new(),
+            line: 0,
+            column: 0,
+            end: None,
+            severity: DiagnosticSeverity::Error,
+            code: None,
+            source_tool: source_tool.to_string(),
+            message,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::borrow::Cow;
+
+use patch::{File, Hunk, Line, Patch, Range};
+
+const CONTEXT_LINES: usize = 3;
+// Above this amount of cells the LCS table is too expensive, so the changed block is replaced as a whole
+const MAX_LCS_TABLE_CELLS: usize = 4_000_000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Remove,
+    Add,
+}
+
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut ops = vec![Op::Equal; prefix];
+    if old_middle.len() * new_middle.len() > MAX_LCS_TABLE_CELLS {
+        ops.extend(vec![Op::Remove; old_middle.len()]);
+        ops.extend(vec![Op::Add; new_middle.len()]);
+    } else {
+        // lcs[i][j] is the LCS length of old_middle[i..] and new_middle[j..]
+        let columns = new_middle.len() + 1;
+        let mut lcs = vec![0u32; (old_middle.len() + 1) * columns];
+        for i in (0..old_middle.len()).rev() {
+            for j in (0..new_middle.len()).rev() {
+                lcs[i * columns + j] = if old_middle[i] == new_middle[j] {
+                    lcs[(i + 1) * columns + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * columns + j].max(lcs[i * columns + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_middle.len() && j < new_middle.len() {
+            if old_middle[i] == new_middle[j] {
+                ops.push(Op::Equal);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * columns + j] >= lcs[i * columns + j + 1] {
+                ops.push(Op::Remove);
+                i += 1;
+            } else {
+                ops.push(Op::Add);
+                j += 1;
+            }
+        }
+        ops.extend(vec![Op::Remove; old_middle.len() - i]);
+        ops.extend(vec![Op::Add; new_middle.len() - j]);
+    }
+    ops.extend(vec![Op::Equal; suffix]);
+    ops
+}
+
+/// Builds a unified diff between two versions of a text file
+///
+/// # Returns
+///
+/// `None` when both contents are equal, otherwise the rendered unified diff
+pub fn unified_diff(old_path: &str, new_path: &str, old: &str, new: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&old_lines, &new_lines);
+
+    // Old and new line index at the beginning of every op
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_index, mut new_index) = (0usize, 0usize);
+    for op in &ops {
+        positions.push((old_index, new_index));
+        match op {
+            Op::Equal => {
+                old_index += 1;
+                new_index += 1;
+            }
+            Op::Remove => old_index += 1,
+            Op::Add => new_index += 1,
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut op_index = 0;
+    while op_index < ops.len() {
+        if ops[op_index] == Op::Equal {
+            op_index += 1;
+            continue;
+        }
+        let start = op_index.saturating_sub(CONTEXT_LINES);
+        // Extend the hunk while the next change is close enough to share context
+        let mut last_change = op_index;
+        for (index, op) in ops.iter().enumerate().skip(op_index) {
+            if *op != Op::Equal {
+                last_change = index;
+            } else if index - last_change > CONTEXT_LINES * 2 {
+                break;
+            }
+        }
+        let end = (last_change + 1 + CONTEXT_LINES).min(ops.len());
+
+        let mut lines = Vec::new();
+        let (mut old_count, mut new_count) = (0, 0);
+        for index in start..end {
+            let (old_position, new_position) = positions[index];
+            match ops[index] {
+                Op::Equal => {
+                    lines.push(Line::Context(old_lines[old_position]));
+                    old_count += 1;
+                    new_count += 1;
+                }
+                Op::Remove => {
+                    lines.push(Line::Remove(old_lines[old_position]));
+                    old_count += 1;
+                }
+                Op::Add => {
+                    lines.push(Line::Add(new_lines[new_position]));
+                    new_count += 1;
+                }
+            }
+        }
+        let (old_start, new_start) = positions[start];
+        hunks.push(Hunk {
+            old_range: Range {
+                start: if old_count == 0 {
+                    old_start
+                } else {
+                    old_start + 1
+                } as u64,
+                count: old_count,
+            },
+            new_range: Range {
+                start: if new_count == 0 {
+                    new_start
+                } else {
+                    new_start + 1
+                } as u64,
+                count: new_count,
+            },
+            range_hint: "",
+            lines,
+        });
+        op_index = end;
+    }
+
+    let patch = Patch {
+        old: File {
+            path: Cow::Borrowed(old_path),
+            meta: None,
+        },
+        new: File {
+            path: Cow::Borrowed(new_path),
+            meta: None,
+        },
+        hunks,
+        end_newline: new.is_empty() || new.ends_with('\n'),
+    };
+    Some(format!("{}\n", patch))
+}
+
+#[cfg(test)]
+#[path = "diff_utils.test.rs"]
+mod tests;
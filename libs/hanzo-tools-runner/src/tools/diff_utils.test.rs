@@ -0,0 +1,41 @@
+use crate::tools::diff_utils::unified_diff;
+
+#[test]
+fn unified_diff_equal_content() {
+    assert!(unified_diff("a/main.py", "b/main.py", "a\nb\n", "a\nb\n").is_none());
+}
+
+#[test]
+fn unified_diff_single_change() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n";
+    let diff = unified_diff("a/file.txt", "b/file.txt", old, new).unwrap();
+    assert_eq!(
+        diff,
+        "--- a/file.txt\n+++ b/file.txt\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+    );
+    // The output must be readable by the patch parser
+    let parsed = patch::Patch::from_single(&diff).unwrap();
+    assert_eq!(parsed.hunks.len(), 1);
+}
+
+#[test]
+fn unified_diff_distant_changes_split_hunks() {
+    let old = (1..=30).map(|i| format!("{}\n", i)).collect::<String>();
+    let new = (1..=30)
+        .map(|i| match i {
+            2 => "two\n".to_string(),
+            28 => "twenty eight\n".to_string(),
+            _ => format!("{}\n", i),
+        })
+        .collect::<String>();
+    let diff = unified_diff("a/file.txt", "b/file.txt", &old, &new).unwrap();
+    let parsed = patch::Patch::from_single(&diff).unwrap();
+    assert_eq!(parsed.hunks.len(), 2);
+}
+
+#[test]
+fn unified_diff_created_file() {
+    let diff = unified_diff("/dev/null", "b/new.txt", "", "hello\n").unwrap();
+    assert!(diff.contains("@@ -0,0 +1,1 @@\n+hello"));
+}
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use super::{home_transaction::HomeTransactionOptions, mounts::Mount};
+
 #[derive(Clone)]
 pub struct ExecutionContext {
     pub context_id: String,
@@ -8,6 +10,10 @@ pub struct ExecutionContext {
     pub storage: PathBuf,
     pub assets_files: Vec<PathBuf>,
     pub mount_files: Vec<PathBuf>,
+    /// Files, folders or glob patterns with explicit mode and optional target path
+    pub mounts: Vec<Mount>,
+    /// Run tools against a staged copy of the home folder committed atomically
+    pub home_transaction: HomeTransactionOptions,
 }
 
 impl Default for ExecutionContext {
@@ -19,6 +25,8 @@ impl Default for ExecutionContext {
             storage: PathBuf::from("./hanzo-tools-runner-execution-storage"),
             assets_files: Vec::new(),
             mount_files: Vec::new(),
+            mounts: Vec::new(),
+            home_transaction: HomeTransactionOptions::default(),
         }
     }
 }
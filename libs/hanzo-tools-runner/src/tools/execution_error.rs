@@ -1,12 +1,30 @@
+use super::{
+    dependency_policy::{DependencyPolicyError, DependencyViolation},
+    file_changes::FileChange,
+};
+
 #[derive(Clone, Debug)]
 pub struct ExecutionError {
     message: String,
     stack: Option<String>,
+    dependency_violations: Vec<DependencyViolation>,
+    changes: Vec<FileChange>,
 }
 
 impl ExecutionError {
     pub fn new(message: String, stack: Option<String>) -> Self {
-        ExecutionError { message, stack }
+        ExecutionError {
+            message,
+            stack,
+            dependency_violations: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Same error with the files the failed run changed
+    pub fn with_changes(mut self, changes: Vec<FileChange>) -> Self {
+        self.changes = changes;
+        self
     }
 
     pub fn message(&self) -> &str {
@@ -16,6 +34,27 @@ impl ExecutionError {
     pub fn stack(&self) -> Option<&str> {
         self.stack.as_deref()
     }
+
+    /// Dependencies rejected by the runner `DependencyPolicy`, the run didn't start when any
+    pub fn dependency_violations(&self) -> &[DependencyViolation] {
+        &self.dependency_violations
+    }
+
+    /// Files created, modified or deleted before the run failed, only when changes are tracked
+    pub fn changes(&self) -> &[FileChange] {
+        &self.changes
+    }
+}
+
+impl From<DependencyPolicyError> for ExecutionError {
+    fn from(error: DependencyPolicyError) -> Self {
+        ExecutionError {
+            message: error.to_string(),
+            stack: None,
+            dependency_violations: error.violations,
+            changes: Vec::new(),
+        }
+    }
 }
 
 impl std::fmt::Display for ExecutionError {
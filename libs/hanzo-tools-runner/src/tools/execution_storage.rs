@@ -1,11 +1,20 @@
 use std::{
     io::Write,
     path::{self, PathBuf},
+    time::Duration,
 };
 
+use super::home_transaction::HomeTransactionMode;
 use super::{code_files::CodeFiles, path_buf_ext::PathBufExt};
 use super::{execution_context::ExecutionContext, file_name_utils::sanitize_for_file_name};
 
+/// Unused code snapshots are kept at least this long so running executions never lose their code
+pub const STALE_CODE_MIN_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Outputs of previous executions are kept this long so callers can still read the artifacts that
+/// weren't inlined in their result
+pub const OUTPUTS_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 #[derive(Default, Clone)]
 pub struct ExecutionStorage {
     pub global_cache_folder_path: PathBuf,
@@ -22,6 +31,7 @@ pub struct ExecutionStorage {
     pub home_folder_path: PathBuf,
     pub assets_folder_path: PathBuf,
     pub mount_folder_path: PathBuf,
+    pub outputs_folder_path: PathBuf,
 }
 
 impl ExecutionStorage {
@@ -37,7 +47,9 @@ impl ExecutionStorage {
         let global_cache_folder_path =
             path::absolute(context.storage.join("global-cache")).unwrap();
         let root_code_folder_path = path::absolute(root_folder_path.join("code")).unwrap();
-        let code_folder_path = path::absolute(root_code_folder_path.join(code_id.clone())).unwrap();
+        // Code is stored by content so identical code is written once and snapshots are immutable
+        let code_folder_path =
+            path::absolute(root_code_folder_path.join(code.content_hash())).unwrap();
         let logs_folder_path = path::absolute(root_folder_path.join("logs")).unwrap();
         let log_file_path = path::absolute(logs_folder_path.join(format!(
             "log_{}_{}.log",
@@ -47,6 +59,16 @@ impl ExecutionStorage {
         .unwrap();
         let cache_folder_path = path::absolute(root_folder_path.join("cache")).unwrap();
         let code_entrypoint_file_path = code_folder_path.join(&code.entrypoint);
+        let outputs_folder_path = root_folder_path
+            .join("outputs")
+            .join(sanitize_for_file_name(context.execution_id.clone()));
+        // Transactional executions work on their own staged copy of home
+        let home_folder_path = match context.home_transaction.mode {
+            HomeTransactionMode::Disabled => root_folder_path.join("home"),
+            _ => root_folder_path
+                .join("home-staging")
+                .join(sanitize_for_file_name(context.execution_id.clone())),
+        };
         Self {
             code_files: code,
             context,
@@ -58,22 +80,27 @@ impl ExecutionStorage {
             cache_folder_path,
             logs_folder_path: logs_folder_path.clone(),
             log_file_path,
-            home_folder_path: root_folder_path.join("home"),
+            home_folder_path,
             assets_folder_path: root_folder_path.join("assets"),
             mount_folder_path: root_folder_path.join("mount"),
+            outputs_folder_path,
             global_cache_folder_path,
         }
     }
 
     pub fn init(&self, pristine_cache: Option<bool>) -> anyhow::Result<()> {
-        for dir in [
+        let mut dirs = vec![
             &self.root_folder_path,
             &self.root_code_folder_path,
-            &self.code_folder_path,
             &self.cache_folder_path,
             &self.logs_folder_path,
-            &self.home_folder_path,
-        ] {
+            &self.outputs_folder_path,
+        ];
+        // Transactional homes are created by begin_home_transaction, staging the committed home
+        if !self.is_home_transactional() {
+            dirs.push(&self.home_folder_path);
+        }
+        for dir in dirs {
             log::info!("creating directory: {}", dir.display());
             std::fs::create_dir_all(dir).map_err(|e| {
                 log::error!("failed to create directory {}: {}", dir.display(), e);
@@ -81,28 +108,9 @@ impl ExecutionStorage {
             })?;
         }
 
-        log::info!(
-            "creating project files, entrypoint: {}",
-            self.code_files.entrypoint
-        );
-
-        for (path, content) in self.code_files.files.iter() {
-            let file_path = self.code_folder_path.join(path);
-            log::info!("writing file: {}", file_path.display());
-            if let Some(parent) = file_path.parent() {
-                std::fs::create_dir_all(parent).map_err(|e| {
-                    log::error!(
-                        "failed to create parent directory {}: {}",
-                        parent.display(),
-                        e
-                    );
-                    e
-                })?;
-            }
-            std::fs::write(&file_path, content).map_err(|e| {
-                log::error!("failed to write file {}: {}", file_path.display(), e);
-                e
-            })?;
+        self.write_code_snapshot()?;
+        if let Err(e) = self.cleanup_stale_outputs(OUTPUTS_RETENTION) {
+            log::warn!("failed to clean up stale outputs: {}", e);
         }
 
         log::info!(
@@ -128,6 +136,185 @@ impl ExecutionStorage {
         Ok(())
     }
 
+    fn code_ids_folder_path(&self) -> PathBuf {
+        self.root_code_folder_path.join("ids")
+    }
+
+    fn code_last_used_folder_path(&self) -> PathBuf {
+        self.root_code_folder_path.join("last-used")
+    }
+
+    /// Writes the code files into their content addressed folder unless it already exists,
+    /// then points `code_id` to it
+    fn write_code_snapshot(&self) -> anyhow::Result<()> {
+        let code_hash = self.code_files.content_hash();
+        if self.code_folder_path.exists() {
+            log::info!("reusing code snapshot {}", code_hash);
+        } else {
+            log::info!(
+                "creating code snapshot {}, entrypoint: {}",
+                code_hash,
+                self.code_files.entrypoint
+            );
+            // Files are written into a temporary folder and renamed, so no run sees a partial snapshot
+            let temporary_folder_path = self
+                .root_code_folder_path
+                .join(format!(".tmp-{}", nanoid::nanoid!()));
+            for (path, content) in self.code_files.files.iter() {
+                let file_path = temporary_folder_path.join(path);
+                log::info!("writing file: {}", file_path.display());
+                if let Some(parent) = file_path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| {
+                        log::error!(
+                            "failed to create parent directory {}: {}",
+                            parent.display(),
+                            e
+                        );
+                        e
+                    })?;
+                }
+                std::fs::write(&file_path, content).map_err(|e| {
+                    log::error!("failed to write file {}: {}", file_path.display(), e);
+                    e
+                })?;
+            }
+            std::fs::create_dir_all(&temporary_folder_path)?;
+            if let Err(e) = std::fs::rename(&temporary_folder_path, &self.code_folder_path) {
+                std::fs::remove_dir_all(&temporary_folder_path)?;
+                // Another execution wrote the same snapshot in the meantime
+                if !self.code_folder_path.exists() {
+                    log::error!("failed to create code snapshot {}: {}", code_hash, e);
+                    return Err(e.into());
+                }
+            }
+        }
+
+        for (folder, file_name, content) in [
+            (
+                self.code_ids_folder_path(),
+                sanitize_for_file_name(self.code_id.clone()),
+                code_hash.clone(),
+            ),
+            (
+                self.code_last_used_folder_path(),
+                code_hash.clone(),
+                chrono::Utc::now().to_rfc3339(),
+            ),
+        ] {
+            std::fs::create_dir_all(&folder)?;
+            let temporary_file_path = folder.join(format!(".tmp-{}", nanoid::nanoid!()));
+            std::fs::write(&temporary_file_path, content)?;
+            std::fs::rename(&temporary_file_path, folder.join(file_name))?;
+        }
+
+        if let Err(e) = self.cleanup_stale_code(STALE_CODE_MIN_AGE) {
+            log::warn!("failed to clean up stale code snapshots: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Writes the code files into a temporary folder tools can modify, unlike the code snapshots.
+    /// The folder is removed when the returned value is dropped.
+    pub fn create_scratch_code_folder(&self) -> anyhow::Result<tempfile::TempDir> {
+        let scratch_folder_path = self.root_folder_path.join("scratch");
+        std::fs::create_dir_all(&scratch_folder_path)?;
+        let scratch = tempfile::Builder::new()
+            .prefix("code-")
+            .tempdir_in(&scratch_folder_path)?;
+        for (path, content) in self.code_files.files.iter() {
+            let file_path = scratch.path().join(path);
+            if let Some(parent) = file_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&file_path, content)?;
+        }
+        log::info!("created scratch code folder {}", scratch.path().display());
+        Ok(scratch)
+    }
+
+    /// Folder of the code snapshot `code_id` currently points to
+    pub fn code_folder_path_for_id(&self, code_id: &str) -> anyhow::Result<Option<PathBuf>> {
+        let id_file_path = self
+            .code_ids_folder_path()
+            .join(sanitize_for_file_name(code_id.to_string()));
+        if !id_file_path.exists() {
+            return Ok(None);
+        }
+        let code_hash = std::fs::read_to_string(id_file_path)?;
+        Ok(Some(self.root_code_folder_path.join(code_hash.trim())))
+    }
+
+    /// Removes code snapshots no `code_id` points to and that weren't used for at least `min_age`
+    ///
+    /// # Returns
+    ///
+    /// The amount of removed snapshots
+    pub fn cleanup_stale_code(&self, min_age: Duration) -> anyhow::Result<usize> {
+        let ids_folder_path = self.code_ids_folder_path();
+        let last_used_folder_path = self.code_last_used_folder_path();
+        let mut referenced = std::collections::HashSet::new();
+        if ids_folder_path.exists() {
+            for entry in std::fs::read_dir(&ids_folder_path)? {
+                let content = std::fs::read_to_string(entry?.path())?;
+                referenced.insert(content.trim().to_string());
+            }
+        }
+
+        let mut removed = 0;
+        for entry in std::fs::read_dir(&self.root_code_folder_path)? {
+            let entry = entry?;
+            let code_hash = entry.file_name().to_string_lossy().to_string();
+            let is_snapshot =
+                code_hash.len() == 64 && code_hash.chars().all(|c| c.is_ascii_hexdigit());
+            if !is_snapshot || !entry.file_type()?.is_dir() || referenced.contains(&code_hash) {
+                continue;
+            }
+            let last_used_file_path = last_used_folder_path.join(&code_hash);
+            let last_used = match std::fs::metadata(&last_used_file_path) {
+                Ok(metadata) => metadata.modified()?,
+                Err(_) => entry.metadata()?.modified()?,
+            };
+            if last_used.elapsed().unwrap_or_default() < min_age {
+                continue;
+            }
+            log::info!("removing stale code snapshot {}", code_hash);
+            std::fs::remove_dir_all(entry.path())?;
+            if last_used_file_path.exists() {
+                std::fs::remove_file(last_used_file_path)?;
+            }
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Removes the outputs folders of other executions not modified for at least `max_age`
+    ///
+    /// # Returns
+    ///
+    /// The amount of removed outputs folders
+    pub fn cleanup_stale_outputs(&self, max_age: Duration) -> anyhow::Result<usize> {
+        let Some(root_outputs_folder_path) = self.outputs_folder_path.parent() else {
+            return Ok(0);
+        };
+        if !root_outputs_folder_path.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for entry in std::fs::read_dir(root_outputs_folder_path)? {
+            let entry = entry?;
+            if entry.path() == self.outputs_folder_path || !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if entry.metadata()?.modified()?.elapsed().unwrap_or_default() < max_age {
+                continue;
+            }
+            log::info!("removing stale outputs {}", entry.path().display());
+            std::fs::remove_dir_all(entry.path())?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
     pub fn append_log(&self, log: &str) -> anyhow::Result<()> {
         let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
         let log_line = format!(
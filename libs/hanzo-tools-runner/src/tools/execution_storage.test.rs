@@ -85,3 +85,97 @@ async fn execution_storage_clean_cache() {
             == 0
     );
 }
+
+#[tokio::test]
+async fn execution_storage_content_addressed_code() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let test_dir = tempfile::tempdir().unwrap();
+    let storage_with_code = |code: &str| {
+        ExecutionStorage::new(
+            CodeFiles {
+                files: HashMap::from([("main.ts".to_string(), code.to_string())]),
+                entrypoint: "main.ts".to_string(),
+            },
+            ExecutionContext {
+                storage: test_dir.path().to_path_buf(),
+                context_id: "context".to_string(),
+                code_id: "code".to_string(),
+                ..Default::default()
+            },
+        )
+    };
+
+    let first = storage_with_code("console.log('first');");
+    first.init(None).unwrap();
+    let same = storage_with_code("console.log('first');");
+    same.init(None).unwrap();
+    assert_eq!(first.code_folder_path, same.code_folder_path);
+
+    let second = storage_with_code("console.log('second');");
+    second.init(None).unwrap();
+    assert_ne!(first.code_folder_path, second.code_folder_path);
+    assert_eq!(
+        std::fs::read_to_string(&first.code_entrypoint_file_path).unwrap(),
+        "console.log('first');"
+    );
+    assert_eq!(
+        second.code_folder_path_for_id("code").unwrap(),
+        Some(second.code_folder_path.clone())
+    );
+
+    // Recently used snapshots are kept even if no code id points to them
+    assert_eq!(
+        second
+            .cleanup_stale_code(std::time::Duration::from_secs(60))
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        second
+            .cleanup_stale_code(std::time::Duration::ZERO)
+            .unwrap(),
+        1
+    );
+    assert!(!first.code_folder_path.exists());
+    assert!(second.code_folder_path.exists());
+}
+
+#[tokio::test]
+async fn execution_storage_cleanup_stale_outputs() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let storage_for_execution = |execution_id: &str| {
+        ExecutionStorage::new(
+            CodeFiles {
+                files: HashMap::from([("main.ts".to_string(), "console.log('test');".to_string())]),
+                entrypoint: "main.ts".to_string(),
+            },
+            ExecutionContext {
+                storage: test_dir.path().to_path_buf(),
+                context_id: "context".to_string(),
+                execution_id: execution_id.to_string(),
+                ..Default::default()
+            },
+        )
+    };
+
+    let previous = storage_for_execution("previous");
+    previous.init(None).unwrap();
+    std::fs::write(previous.outputs_folder_path.join("report.txt"), "report").unwrap();
+
+    let current = storage_for_execution("current");
+    current.init(None).unwrap();
+    // Recent outputs are kept, the current execution outputs are never removed
+    assert!(previous.outputs_folder_path.exists());
+    assert_eq!(
+        current
+            .cleanup_stale_outputs(std::time::Duration::ZERO)
+            .unwrap(),
+        1
+    );
+    assert!(!previous.outputs_folder_path.exists());
+    assert!(current.outputs_folder_path.exists());
+}
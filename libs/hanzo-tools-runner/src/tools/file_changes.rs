@@ -0,0 +1,266 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    diff_utils::unified_diff, execution_error::ExecutionError, execution_storage::ExecutionStorage,
+    hash_utils::sha256_file_hex, mounts::MountMode, path_buf_ext::PathBufExt,
+    run_result::RunResult,
+};
+
+/// Text files bigger than this are reported without diff
+const MAX_DIFF_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Clone, Debug, Default)]
+pub struct ChangeTrackingOptions {
+    /// Also track the context home folder, besides the read-write mounts
+    pub include_home: bool,
+    /// Add a unified diff to every modified text file
+    pub text_diffs: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Absolute path in the host
+    pub path: String,
+    pub kind: FileChangeKind,
+    pub size_before: Option<u64>,
+    pub size_after: Option<u64>,
+    pub diff: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct SnapshotEntry {
+    size: u64,
+    sha256: String,
+    text: Option<String>,
+}
+
+/// Hashes and metadata of every file under a set of paths, taken before running a tool
+#[derive(Clone, Debug, Default)]
+pub struct FileSnapshot {
+    entries: BTreeMap<PathBuf, SnapshotEntry>,
+    keep_text: bool,
+}
+
+fn read_text(path: &Path, size: u64) -> Option<String> {
+    if size > MAX_DIFF_FILE_SIZE {
+        return None;
+    }
+    let content = std::fs::read(path).ok()?;
+    if content.contains(&0) {
+        return None;
+    }
+    String::from_utf8(content).ok()
+}
+
+fn list_files(root: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if root.is_file() {
+        files.push(root.to_path_buf());
+        return Ok(());
+    }
+    if !root.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+impl FileSnapshot {
+    /// Takes a snapshot of every file (recursively) under `paths`
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Files or folders to snapshot
+    /// * `keep_text` - Keep the content of text files so diffs can be generated later
+    pub fn take(paths: &[PathBuf], keep_text: bool) -> anyhow::Result<Self> {
+        let mut files = Vec::new();
+        for path in paths {
+            list_files(path, &mut files)?;
+        }
+
+        let mut entries = BTreeMap::new();
+        for file in files {
+            let metadata = std::fs::metadata(&file)?;
+            let size = metadata.len();
+            entries.insert(
+                file.clone(),
+                SnapshotEntry {
+                    size,
+                    sha256: sha256_file_hex(&file)?,
+                    text: if keep_text {
+                        read_text(&file, size)
+                    } else {
+                        None
+                    },
+                },
+            );
+        }
+        log::info!("took snapshot of {} files", entries.len());
+        Ok(Self { entries, keep_text })
+    }
+
+    /// Compares the snapshot against the current state of the same paths
+    pub fn changes(&self, paths: &[PathBuf]) -> anyhow::Result<Vec<FileChange>> {
+        let mut files = Vec::new();
+        for path in paths {
+            list_files(path, &mut files)?;
+        }
+        let mut current: HashMap<PathBuf, std::fs::Metadata> = HashMap::new();
+        for file in files {
+            let metadata = std::fs::metadata(&file)?;
+            current.insert(file, metadata);
+        }
+
+        let mut changes = Vec::new();
+        for (path, before) in self.entries.iter() {
+            match current.remove(path) {
+                None => changes.push(FileChange {
+                    path: path.as_normalized_string(),
+                    kind: FileChangeKind::Deleted,
+                    size_before: Some(before.size),
+                    size_after: None,
+                    diff: None,
+                }),
+                Some(after) => {
+                    if sha256_file_hex(path)? == before.sha256 {
+                        continue;
+                    }
+                    let diff = match (&before.text, self.keep_text) {
+                        (Some(old_text), true) => {
+                            read_text(path, after.len()).and_then(|new_text| {
+                                let path = path.as_normalized_string();
+                                unified_diff(&path, &path, old_text, &new_text)
+                            })
+                        }
+                        _ => None,
+                    };
+                    changes.push(FileChange {
+                        path: path.as_normalized_string(),
+                        kind: FileChangeKind::Modified,
+                        size_before: Some(before.size),
+                        size_after: Some(after.len()),
+                        diff,
+                    });
+                }
+            }
+        }
+
+        for (path, after) in current {
+            let diff = if self.keep_text {
+                read_text(&path, after.len()).and_then(|new_text| {
+                    unified_diff("/dev/null", &path.as_normalized_string(), "", &new_text)
+                })
+            } else {
+                None
+            };
+            changes.push(FileChange {
+                path: path.as_normalized_string(),
+                kind: FileChangeKind::Created,
+                size_before: None,
+                size_after: Some(after.len()),
+                diff,
+            });
+        }
+
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        log::info!("detected {} file changes", changes.len());
+        Ok(changes)
+    }
+}
+
+/// Snapshot of the paths a tool could change, taken before running it
+pub struct ChangeTracker {
+    tracked_paths: Vec<PathBuf>,
+    snapshot: FileSnapshot,
+}
+
+impl ChangeTracker {
+    /// Takes the snapshot, `None` when `options` is `None` and changes aren't tracked
+    pub fn start(
+        execution_storage: &ExecutionStorage,
+        options: Option<&ChangeTrackingOptions>,
+    ) -> Result<Option<Self>, ExecutionError> {
+        let Some(options) = options else {
+            return Ok(None);
+        };
+        let tracked_paths = execution_storage
+            .change_tracking_paths(options)
+            .map_err(|e| {
+                ExecutionError::new(format!("failed to resolve tracked paths: {}", e), None)
+            })?;
+        let snapshot = FileSnapshot::take(&tracked_paths, options.text_diffs).map_err(|e| {
+            ExecutionError::new(format!("failed to take files snapshot: {}", e), None)
+        })?;
+        Ok(Some(Self {
+            tracked_paths,
+            snapshot,
+        }))
+    }
+
+    /// Adds the changes made since the snapshot to the result of the run, or to its error when
+    /// the run failed
+    pub fn finish(
+        self,
+        run_result: Result<RunResult, ExecutionError>,
+    ) -> Result<RunResult, ExecutionError> {
+        let changes = self.snapshot.changes(&self.tracked_paths);
+        match (run_result, changes) {
+            (Ok(mut result), Ok(changes)) => {
+                result.changes = changes;
+                Ok(result)
+            }
+            (Ok(_), Err(e)) => Err(ExecutionError::new(
+                format!("failed to detect file changes: {}", e),
+                None,
+            )),
+            (Err(error), Ok(changes)) => Err(error.with_changes(changes)),
+            (Err(error), Err(e)) => {
+                log::warn!("failed to detect file changes of the failed run: {}", e);
+                Err(error)
+            }
+        }
+    }
+}
+
+impl ExecutionStorage {
+    /// Paths a tool could change: every read-write mount and optionally the home folder
+    pub fn change_tracking_paths(
+        &self,
+        options: &ChangeTrackingOptions,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let mut paths: Vec<PathBuf> = self
+            .resolved_mounts()?
+            .into_iter()
+            .filter(|mount| mount.mode == MountMode::ReadWrite)
+            .map(|mount| mount.host_path)
+            .collect();
+        if options.include_home {
+            paths.push(self.home_folder_path.clone());
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+#[path = "file_changes.test.rs"]
+mod tests;
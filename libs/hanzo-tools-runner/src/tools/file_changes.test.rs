@@ -0,0 +1,57 @@
+use crate::tools::file_changes::{FileChangeKind, FileSnapshot};
+
+#[tokio::test]
+async fn detect_created_modified_and_deleted_files() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let folder = tempfile::tempdir().unwrap();
+    let modified = folder.path().join("modified.txt");
+    let deleted = folder.path().join("deleted.txt");
+    let untouched = folder.path().join("untouched.txt");
+    std::fs::write(&modified, "1\n2\n3\n").unwrap();
+    std::fs::write(&deleted, "bye").unwrap();
+    std::fs::write(&untouched, "same").unwrap();
+
+    let paths = vec![folder.path().to_path_buf()];
+    let snapshot = FileSnapshot::take(&paths, true).unwrap();
+
+    std::fs::write(&modified, "1\ntwo\n3\n").unwrap();
+    std::fs::remove_file(&deleted).unwrap();
+    std::fs::create_dir_all(folder.path().join("sub")).unwrap();
+    std::fs::write(folder.path().join("sub/created.bin"), [0u8, 1, 2]).unwrap();
+
+    let changes = snapshot.changes(&paths).unwrap();
+    assert_eq!(changes.len(), 3);
+
+    assert_eq!(changes[0].kind, FileChangeKind::Deleted);
+    assert_eq!(changes[0].size_before, Some(3));
+    assert_eq!(changes[0].size_after, None);
+
+    assert_eq!(changes[1].kind, FileChangeKind::Modified);
+    assert_eq!(changes[1].size_before, Some(6));
+    assert_eq!(changes[1].size_after, Some(8));
+    let diff = changes[1].diff.as_ref().unwrap();
+    assert!(diff.contains("-2\n+two\n"));
+
+    assert_eq!(changes[2].kind, FileChangeKind::Created);
+    assert!(changes[2].path.ends_with("sub/created.bin"));
+    // Binary files never get a diff
+    assert!(changes[2].diff.is_none());
+}
+
+#[tokio::test]
+async fn no_diffs_unless_requested() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "1").unwrap();
+    let paths = vec![file.path().to_path_buf()];
+    let snapshot = FileSnapshot::take(&paths, false).unwrap();
+    std::fs::write(file.path(), "2").unwrap();
+
+    let changes = snapshot.changes(&paths).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind, FileChangeKind::Modified);
+    assert!(changes[0].diff.is_none());
+}
@@ -0,0 +1,32 @@
+use std::{fs::File, path::Path};
+
+fn open_lock_file(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Takes an exclusive OS lock on `path`, creating it if needed, waiting for other holders.
+/// The lock is released when the returned file is dropped or its process dies.
+pub fn lock_file(path: &Path) -> std::io::Result<File> {
+    let file = open_lock_file(path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Takes an exclusive OS lock on `path` like `lock_file`, without waiting when someone else holds
+/// it
+pub fn try_lock_file(path: &Path) -> std::io::Result<Option<File>> {
+    let file = open_lock_file(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
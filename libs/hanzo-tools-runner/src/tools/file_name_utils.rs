@@ -21,15 +21,20 @@ pub fn normalize_for_docker_path(path: PathBuf) -> String {
 
 pub fn adapt_paths_in_value(
     value: &Value,
-    mount_files: &std::collections::HashSet<String>,
+    path_mappings: &std::collections::HashMap<String, String>,
 ) -> Value {
     match value {
         Value::String(s) => {
-            // Check if the string is in the mount_files list
-            if mount_files.contains(s) {
-                // Use normalize_for_docker_path if we're in Docker mode
-                let normalized = normalize_for_docker_path(PathBuf::from(s.clone()));
-                Value::String(normalized)
+            // Check if the string is a mounted path or lives inside a mounted folder
+            if let Some(mapped) = path_mappings.get(s) {
+                return Value::String(mapped.clone());
+            }
+            let mounted_folder = path_mappings
+                .iter()
+                .filter(|(host_path, _)| s.starts_with(&format!("{}/", host_path)))
+                .max_by_key(|(host_path, _)| host_path.len());
+            if let Some((host_path, docker_path)) = mounted_folder {
+                Value::String(format!("{}{}", docker_path, &s[host_path.len()..]))
             } else {
                 value.clone()
             }
@@ -37,14 +42,14 @@ pub fn adapt_paths_in_value(
         Value::Array(arr) => {
             let mut new_arr = Vec::with_capacity(arr.len());
             for item in arr.iter() {
-                new_arr.push(adapt_paths_in_value(item, mount_files));
+                new_arr.push(adapt_paths_in_value(item, path_mappings));
             }
             Value::Array(new_arr)
         }
         Value::Object(obj) => {
             let mut new_obj = serde_json::Map::new();
             for (key, val) in obj {
-                new_obj.insert(key.clone(), adapt_paths_in_value(val, mount_files));
+                new_obj.insert(key.clone(), adapt_paths_in_value(val, path_mappings));
             }
             Value::Object(new_obj)
         }
@@ -63,4 +68,26 @@ mod tests {
             "//c/Users/John/Documents/test.txt".to_string()
         );
     }
+
+    #[test]
+    fn test_adapt_paths_in_value() {
+        let path_mappings = std::collections::HashMap::from([
+            (
+                "/host/file.txt".to_string(),
+                "/app/assets/file.txt".to_string(),
+            ),
+            ("/host/data".to_string(), "/data".to_string()),
+        ]);
+        let value = serde_json::json!({
+            "file": "/host/file.txt",
+            "nested": ["/host/data/a/b.csv", "/host/database.db", 1],
+        });
+        assert_eq!(
+            adapt_paths_in_value(&value, &path_mappings),
+            serde_json::json!({
+                "file": "/app/assets/file.txt",
+                "nested": ["/data/a/b.csv", "/host/database.db", 1],
+            })
+        );
+    }
 }
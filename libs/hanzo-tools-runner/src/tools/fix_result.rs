@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use super::{code_files::CodeFiles, diff_utils::unified_diff};
+
+#[derive(Clone)]
+pub struct FixResult {
+    /// Code files with every fix applied
+    pub code: CodeFiles,
+    /// Unified diff of every changed file, keyed by file path
+    pub diffs: BTreeMap<String, String>,
+}
+
+impl FixResult {
+    pub fn new(original: &CodeFiles, fixed: CodeFiles) -> Self {
+        let mut diffs = BTreeMap::new();
+        for (path, content) in fixed.files.iter() {
+            let original_content = original
+                .files
+                .get(path)
+                .map(|content| content.as_str())
+                .unwrap_or_default();
+            if let Some(diff) = unified_diff(
+                &format!("a/{}", path),
+                &format!("b/{}", path),
+                original_content,
+                content,
+            ) {
+                diffs.insert(path.clone(), diff);
+            }
+        }
+        Self { code: fixed, diffs }
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.diffs.is_empty()
+    }
+}
+
+#[cfg(test)]
+#[path = "fix_result.test.rs"]
+mod tests;
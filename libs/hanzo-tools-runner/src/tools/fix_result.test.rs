@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+
+use crate::tools::{code_files::CodeFiles, fix_result::FixResult};
+
+#[test]
+fn test_fix_result_diffs() {
+    let original = CodeFiles {
+        files: HashMap::from([
+            ("main.py".to_string(), "import os\nprint(1)\n".to_string()),
+            ("utils.py".to_string(), "x = 1\n".to_string()),
+        ]),
+        entrypoint: "main.py".to_string(),
+    };
+    let mut fixed = original.clone();
+    fixed
+        .files
+        .insert("main.py".to_string(), "print(1)\n".to_string());
+
+    let fix_result = FixResult::new(&original, fixed);
+    assert!(fix_result.has_changes());
+    assert_eq!(fix_result.diffs.len(), 1);
+    assert_eq!(
+        fix_result.diffs["main.py"],
+        "--- a/main.py\n+++ b/main.py\n@@ -1,2 +1,1 @@\n-import os\n print(1)\n"
+    );
+    assert_eq!(fix_result.code.files["main.py"], "print(1)\n");
+}
@@ -0,0 +1,28 @@
+use std::{io::Read, path::Path};
+
+use sha2::{Digest, Sha256};
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex_simd::encode_to_string(Sha256::digest(data), hex_simd::AsciiCase::Lower)
+}
+
+pub fn sha256_file_hex(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex_simd::encode_to_string(
+        hasher.finalize(),
+        hex_simd::AsciiCase::Lower,
+    ))
+}
+
+#[cfg(test)]
+#[path = "hash_utils.test.rs"]
+mod tests;
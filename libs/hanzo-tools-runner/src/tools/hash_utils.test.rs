@@ -0,0 +1,19 @@
+use crate::tools::hash_utils::{sha256_file_hex, sha256_hex};
+
+#[test]
+fn test_sha256_hex() {
+    assert_eq!(
+        sha256_hex(b"hello world"),
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+    );
+}
+
+#[test]
+fn test_sha256_file_hex() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "hello world").unwrap();
+    assert_eq!(
+        sha256_file_hex(file.path()).unwrap(),
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+    );
+}
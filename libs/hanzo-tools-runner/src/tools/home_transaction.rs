@@ -0,0 +1,349 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    execution_storage::ExecutionStorage, file_lock_utils::lock_file,
+    file_name_utils::sanitize_for_file_name,
+};
+
+/// Staged homes left by executions nobody committed or discarded are removed after this time
+pub const HOME_STAGING_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum HomeTransactionMode {
+    /// The tool writes straight into the context home folder
+    #[default]
+    Disabled,
+    /// The tool works on a staged copy of home that replaces it only when the run succeeds
+    CommitOnSuccess,
+    /// The tool works on a staged copy of home that the caller has to commit or discard
+    Manual,
+}
+
+#[derive(Clone, Debug)]
+pub struct HomeTransactionOptions {
+    pub mode: HomeTransactionMode,
+    /// How many previous versions of home are kept to roll back to
+    pub history_limit: usize,
+}
+
+impl Default for HomeTransactionOptions {
+    fn default() -> Self {
+        Self {
+            mode: HomeTransactionMode::Disabled,
+            history_limit: 5,
+        }
+    }
+}
+
+/// Copies the folder `source` into `destination` without following symlinks out of it: links
+/// resolving inside `source` are recreated, rebased onto `destination` when absolute, and any
+/// other link is skipped
+fn copy_dir_all(source: &Path, destination: &Path) -> std::io::Result<()> {
+    copy_dir_entries(&source.canonicalize()?, destination, Path::new(""))
+}
+
+fn copy_dir_entries(root: &Path, destination_root: &Path, relative: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(destination_root.join(relative))?;
+    for entry in std::fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let relative_path = relative.join(entry.file_name());
+        let destination_path = destination_root.join(&relative_path);
+        if file_type.is_dir() {
+            copy_dir_entries(root, destination_root, &relative_path)?;
+        } else if file_type.is_symlink() {
+            let resolved = match entry.path().canonicalize() {
+                Ok(resolved) if resolved.starts_with(root) => resolved,
+                _ => {
+                    log::warn!(
+                        "skipping symlink {} that doesn't resolve inside home",
+                        entry.path().display()
+                    );
+                    continue;
+                }
+            };
+            let mut target = std::fs::read_link(entry.path())?;
+            if target.is_absolute() {
+                target = destination_root.join(resolved.strip_prefix(root).unwrap());
+            }
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(target, destination_path)?;
+            #[cfg(windows)]
+            if resolved.is_dir() {
+                std::os::windows::fs::symlink_dir(target, destination_path)?;
+            } else {
+                std::os::windows::fs::symlink_file(target, destination_path)?;
+            }
+        } else {
+            std::fs::copy(entry.path(), destination_path)?;
+        }
+    }
+    Ok(())
+}
+
+impl ExecutionStorage {
+    pub fn is_home_transactional(&self) -> bool {
+        self.context.home_transaction.mode != HomeTransactionMode::Disabled
+    }
+
+    /// The committed home folder of the context, shared by every execution
+    pub fn context_home_folder_path(&self) -> PathBuf {
+        self.root_folder_path.join("home")
+    }
+
+    pub fn home_history_folder_path(&self) -> PathBuf {
+        self.root_folder_path.join("home-history")
+    }
+
+    /// Holds a random id of the committed home, replaced by every commit and rollback
+    fn home_generation_file_path(&self) -> PathBuf {
+        self.root_folder_path.join("home.generation")
+    }
+
+    /// Id of the committed home, empty when nothing has been committed yet
+    fn home_generation(&self) -> anyhow::Result<String> {
+        let path = self.home_generation_file_path();
+        if !path.exists() {
+            return Ok(String::new());
+        }
+        Ok(std::fs::read_to_string(path)?)
+    }
+
+    fn write_home_generation(&self) -> anyhow::Result<()> {
+        std::fs::write(self.home_generation_file_path(), nanoid::nanoid!())?;
+        Ok(())
+    }
+
+    /// File next to the staged home of this execution, written once the committed home has been
+    /// copied into it. It holds the generation of the committed home it was staged from.
+    fn home_staging_marker_path(&self) -> PathBuf {
+        let mut file_name = self.home_folder_path.file_name().unwrap().to_os_string();
+        file_name.push(".staged");
+        self.home_folder_path.with_file_name(file_name)
+    }
+
+    /// Whether the committed home has been copied into the staging folder of this execution
+    pub fn is_home_staged(&self) -> bool {
+        self.home_staging_marker_path().exists() && self.home_folder_path.exists()
+    }
+
+    /// Copies the committed home into the staging folder of this execution.
+    /// Does nothing when transactions are disabled or home is already staged.
+    pub fn begin_home_transaction(&self) -> anyhow::Result<()> {
+        if !self.is_home_transactional() || self.is_home_staged() {
+            return Ok(());
+        }
+        let context_home_folder_path = self.context_home_folder_path();
+        log::info!(
+            "staging home {} into {}",
+            context_home_folder_path.display(),
+            self.home_folder_path.display()
+        );
+        // A staging folder without marker was left by an interrupted staging
+        if self.home_folder_path.exists() {
+            std::fs::remove_dir_all(&self.home_folder_path)?;
+        }
+        let generation = {
+            let _lock = self.lock_home()?;
+            if context_home_folder_path.exists() {
+                copy_dir_all(&context_home_folder_path, &self.home_folder_path)?;
+            } else {
+                std::fs::create_dir_all(&self.home_folder_path)?;
+            }
+            self.home_generation()?
+        };
+        std::fs::write(self.home_staging_marker_path(), generation)?;
+        if let Err(e) = self.cleanup_stale_home_staging(HOME_STAGING_RETENTION) {
+            log::warn!("failed to clean up stale staged homes: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Removes the staged homes of other executions not modified for at least `max_age`, left
+    /// behind by manual transactions that were never committed or discarded
+    ///
+    /// # Returns
+    ///
+    /// The amount of removed staged homes
+    pub fn cleanup_stale_home_staging(&self, max_age: Duration) -> anyhow::Result<usize> {
+        let Some(home_staging_folder_path) = self.home_folder_path.parent() else {
+            return Ok(0);
+        };
+        if !self.is_home_transactional() || !home_staging_folder_path.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for entry in std::fs::read_dir(home_staging_folder_path)? {
+            let entry = entry?;
+            if entry.path() == self.home_folder_path
+                || entry.path() == self.home_staging_marker_path()
+            {
+                continue;
+            }
+            if entry.metadata()?.modified()?.elapsed().unwrap_or_default() < max_age {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                log::info!("removing stale staged home {}", entry.path().display());
+                std::fs::remove_dir_all(entry.path())?;
+                removed += 1;
+            } else {
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Lock held while the committed home or its history change
+    fn lock_home(&self) -> anyhow::Result<std::fs::File> {
+        Ok(lock_file(&self.root_folder_path.join("home.lock"))?)
+    }
+
+    /// Replaces the committed home with the staged one, keeping the previous version in the history.
+    /// Commits of executions of the same context are serialized. Fails when another execution
+    /// committed home after this one was staged, its changes would be lost otherwise, leaving the
+    /// staged home to inspect or discard.
+    pub fn commit_home(&self) -> anyhow::Result<()> {
+        if !self.is_home_transactional() {
+            return Ok(());
+        }
+        if !self.is_home_staged() {
+            return Err(anyhow::anyhow!(
+                "there is no staged home for execution {}",
+                self.context.execution_id
+            ));
+        }
+        let _lock = self.lock_home()?;
+        let staged_generation = std::fs::read_to_string(self.home_staging_marker_path())?;
+        if staged_generation != self.home_generation()? {
+            log::warn!(
+                "home changed since execution {} staged it, not committing it",
+                self.context.execution_id
+            );
+            return Err(anyhow::anyhow!(
+                "home was committed by another execution after execution {} staged it",
+                self.context.execution_id
+            ));
+        }
+        let context_home_folder_path = self.context_home_folder_path();
+        let history = self.home_history()?;
+        let history_folder_path = self.home_history_folder_path();
+        std::fs::create_dir_all(&history_folder_path)?;
+
+        let mut previous_home_path = None;
+        if context_home_folder_path.exists() {
+            let sequence = history
+                .first()
+                .and_then(|entry| entry.split('_').next())
+                .and_then(|sequence| sequence.parse::<u64>().ok())
+                .map_or(1, |sequence| sequence + 1);
+            let path = history_folder_path.join(format!(
+                "{:08}_{}",
+                sequence,
+                sanitize_for_file_name(self.context.execution_id.clone())
+            ));
+            std::fs::rename(&context_home_folder_path, &path)?;
+            previous_home_path = Some(path);
+        }
+        if let Err(e) = std::fs::rename(&self.home_folder_path, &context_home_folder_path) {
+            log::error!("failed to commit home, restoring previous version: {}", e);
+            if let Some(previous_home_path) = previous_home_path {
+                std::fs::rename(previous_home_path, &context_home_folder_path)?;
+            }
+            return Err(e.into());
+        }
+        self.write_home_generation()?;
+        std::fs::remove_file(self.home_staging_marker_path())?;
+        log::info!("committed home of execution {}", self.context.execution_id);
+        self.prune_home_history()
+    }
+
+    /// Drops the staged home, leaving the committed one untouched
+    pub fn discard_home(&self) -> anyhow::Result<()> {
+        if !self.is_home_transactional() {
+            return Ok(());
+        }
+        if self.home_folder_path.exists() {
+            log::info!("discarding staged home {}", self.home_folder_path.display());
+            std::fs::remove_dir_all(&self.home_folder_path)?;
+        }
+        let marker_path = self.home_staging_marker_path();
+        if marker_path.exists() {
+            std::fs::remove_file(marker_path)?;
+        }
+        Ok(())
+    }
+
+    /// Versions of home kept in the history, newest first
+    pub fn home_history(&self) -> anyhow::Result<Vec<String>> {
+        let history_folder_path = self.home_history_folder_path();
+        if !history_folder_path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut history = Vec::new();
+        for entry in std::fs::read_dir(history_folder_path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                history.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        history.sort();
+        history.reverse();
+        Ok(history)
+    }
+
+    /// Restores the home committed `steps` commits ago, dropping every newer version
+    pub fn rollback_home(&self, steps: usize) -> anyhow::Result<()> {
+        let _lock = self.lock_home()?;
+        let history = self.home_history()?;
+        if steps == 0 || steps > history.len() {
+            return Err(anyhow::anyhow!(
+                "can't roll back {} commits, only {} available",
+                steps,
+                history.len()
+            ));
+        }
+        let history_folder_path = self.home_history_folder_path();
+        let context_home_folder_path = self.context_home_folder_path();
+        let discarded_home_path = self
+            .root_folder_path
+            .join(format!("home-discarded-{}", nanoid::nanoid!()));
+        if context_home_folder_path.exists() {
+            std::fs::rename(&context_home_folder_path, &discarded_home_path)?;
+        }
+        std::fs::rename(
+            history_folder_path.join(&history[steps - 1]),
+            &context_home_folder_path,
+        )?;
+        self.write_home_generation()?;
+        log::info!("rolled back home to {}", history[steps - 1]);
+
+        if discarded_home_path.exists() {
+            std::fs::remove_dir_all(discarded_home_path)?;
+        }
+        for entry in history.iter().take(steps - 1) {
+            std::fs::remove_dir_all(history_folder_path.join(entry))?;
+        }
+        Ok(())
+    }
+
+    fn prune_home_history(&self) -> anyhow::Result<()> {
+        let history_folder_path = self.home_history_folder_path();
+        for entry in self
+            .home_history()?
+            .iter()
+            .skip(self.context.home_transaction.history_limit)
+        {
+            log::info!("removing old home version {}", entry);
+            std::fs::remove_dir_all(history_folder_path.join(entry))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "home_transaction.test.rs"]
+mod tests;
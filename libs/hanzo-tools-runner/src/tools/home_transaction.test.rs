@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::tools::{
+    code_files::CodeFiles,
+    execution_context::ExecutionContext,
+    execution_storage::ExecutionStorage,
+    home_transaction::{HomeTransactionMode, HomeTransactionOptions},
+};
+
+fn transactional_storage(storage: &std::path::Path, context_id: &str) -> ExecutionStorage {
+    ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.ts".to_string(), "".to_string())]),
+            entrypoint: "main.ts".to_string(),
+        },
+        ExecutionContext {
+            storage: storage.to_path_buf(),
+            context_id: context_id.to_string(),
+            home_transaction: HomeTransactionOptions {
+                mode: HomeTransactionMode::Manual,
+                history_limit: 2,
+            },
+            ..Default::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn commit_and_discard_staged_home() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+
+    let first = transactional_storage(storage.path(), "context");
+    first.begin_home_transaction().unwrap();
+    first.init(None).unwrap();
+    assert_ne!(first.home_folder_path, first.context_home_folder_path());
+    std::fs::write(first.home_folder_path.join("state.txt"), "1").unwrap();
+    assert!(!first.context_home_folder_path().join("state.txt").exists());
+    first.commit_home().unwrap();
+    assert!(!first.home_folder_path.exists());
+    assert_eq!(
+        std::fs::read_to_string(first.context_home_folder_path().join("state.txt")).unwrap(),
+        "1"
+    );
+
+    let second = transactional_storage(storage.path(), "context");
+    second.begin_home_transaction().unwrap();
+    std::fs::write(second.home_folder_path.join("state.txt"), "2").unwrap();
+    second.discard_home().unwrap();
+    assert!(!second.home_folder_path.exists());
+    assert_eq!(
+        std::fs::read_to_string(second.context_home_folder_path().join("state.txt")).unwrap(),
+        "1"
+    );
+}
+
+#[tokio::test]
+async fn home_is_staged_even_when_storage_is_initialized_first() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let first = transactional_storage(storage.path(), "context");
+    first.begin_home_transaction().unwrap();
+    std::fs::write(first.home_folder_path.join("state.txt"), "1").unwrap();
+    first.commit_home().unwrap();
+
+    let second = transactional_storage(storage.path(), "context");
+    second.init(None).unwrap();
+    assert!(!second.is_home_staged());
+    assert!(second.commit_home().is_err());
+    // A staging folder left by an interrupted staging is staged again
+    std::fs::create_dir_all(&second.home_folder_path).unwrap();
+    second.begin_home_transaction().unwrap();
+    assert!(second.is_home_staged());
+    assert_eq!(
+        std::fs::read_to_string(second.home_folder_path.join("state.txt")).unwrap(),
+        "1"
+    );
+    std::fs::write(second.home_folder_path.join("other.txt"), "2").unwrap();
+    second.commit_home().unwrap();
+    assert!(!second.is_home_staged());
+    assert_eq!(
+        std::fs::read_to_string(second.context_home_folder_path().join("state.txt")).unwrap(),
+        "1"
+    );
+    assert!(second.context_home_folder_path().join("other.txt").exists());
+}
+
+#[tokio::test]
+async fn rollback_home_to_previous_commits() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    for state in ["1", "2", "3", "4"] {
+        let execution = transactional_storage(storage.path(), "context");
+        execution.begin_home_transaction().unwrap();
+        std::fs::write(execution.home_folder_path.join("state.txt"), state).unwrap();
+        execution.commit_home().unwrap();
+    }
+
+    let storage = transactional_storage(storage.path(), "context");
+    let read_state =
+        || std::fs::read_to_string(storage.context_home_folder_path().join("state.txt")).unwrap();
+    // The first commit had no previous home and the history keeps only the last 2 versions
+    assert_eq!(storage.home_history().unwrap().len(), 2);
+    assert_eq!(read_state(), "4");
+    assert!(storage.rollback_home(3).is_err());
+
+    storage.rollback_home(2).unwrap();
+    assert_eq!(read_state(), "2");
+    assert!(storage.home_history().unwrap().is_empty());
+    assert!(storage.rollback_home(1).is_err());
+}
+
+#[tokio::test]
+async fn concurrent_commits_of_the_same_home_conflict() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let executions: Vec<ExecutionStorage> = (0..8)
+        .map(|_| transactional_storage(storage.path(), "context"))
+        .collect();
+    for (index, execution) in executions.iter().enumerate() {
+        execution.begin_home_transaction().unwrap();
+        std::fs::write(
+            execution.home_folder_path.join("state.txt"),
+            index.to_string(),
+        )
+        .unwrap();
+    }
+    let results: Vec<anyhow::Result<()>> = std::thread::scope(|scope| {
+        let commits: Vec<_> = executions
+            .iter()
+            .map(|execution| scope.spawn(|| execution.commit_home()))
+            .collect();
+        commits
+            .into_iter()
+            .map(|commit| commit.join().unwrap())
+            .collect()
+    });
+    // Only the first commit lands, the others were staged from a home that no longer exists
+    let committed: Vec<usize> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.is_ok())
+        .map(|(index, _)| index)
+        .collect();
+    assert_eq!(committed.len(), 1);
+    assert_eq!(
+        std::fs::read_to_string(executions[0].context_home_folder_path().join("state.txt"))
+            .unwrap(),
+        committed[0].to_string()
+    );
+    for (index, execution) in executions.iter().enumerate() {
+        if index != committed[0] {
+            assert!(execution.is_home_staged());
+        }
+    }
+}
+
+#[tokio::test]
+async fn commit_fails_when_home_changed_since_staging() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let first = transactional_storage(storage.path(), "context");
+    let second = transactional_storage(storage.path(), "context");
+    first.begin_home_transaction().unwrap();
+    second.begin_home_transaction().unwrap();
+    std::fs::write(first.home_folder_path.join("first.txt"), "1").unwrap();
+    std::fs::write(second.home_folder_path.join("second.txt"), "2").unwrap();
+    first.commit_home().unwrap();
+
+    let error = second.commit_home().unwrap_err();
+    assert!(error.to_string().contains("staged it"));
+    assert!(first.context_home_folder_path().join("first.txt").exists());
+    assert!(!first.context_home_folder_path().join("second.txt").exists());
+
+    // Staging again picks up the committed home
+    second.discard_home().unwrap();
+    second.begin_home_transaction().unwrap();
+    std::fs::write(second.home_folder_path.join("second.txt"), "2").unwrap();
+    second.commit_home().unwrap();
+    assert!(first.context_home_folder_path().join("first.txt").exists());
+    assert!(first.context_home_folder_path().join("second.txt").exists());
+
+    // Rolling back also changes the committed home
+    let third = transactional_storage(storage.path(), "context");
+    third.begin_home_transaction().unwrap();
+    third.rollback_home(1).unwrap();
+    assert!(third.commit_home().is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn staged_home_does_not_follow_symlinks_out_of_home() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+
+    let first = transactional_storage(storage.path(), "context");
+    first.begin_home_transaction().unwrap();
+    std::fs::write(first.home_folder_path.join("state.txt"), "1").unwrap();
+    std::os::unix::fs::symlink("state.txt", first.home_folder_path.join("relative.txt")).unwrap();
+    std::os::unix::fs::symlink(
+        outside.path().join("secret.txt"),
+        first.home_folder_path.join("secret.txt"),
+    )
+    .unwrap();
+    first.commit_home().unwrap();
+    std::os::unix::fs::symlink(
+        first.context_home_folder_path().join("state.txt"),
+        first.context_home_folder_path().join("absolute.txt"),
+    )
+    .unwrap();
+
+    let second = transactional_storage(storage.path(), "context");
+    second.begin_home_transaction().unwrap();
+    assert!(!second.home_folder_path.join("secret.txt").exists());
+    assert_eq!(
+        std::fs::read_link(second.home_folder_path.join("relative.txt")).unwrap(),
+        std::path::PathBuf::from("state.txt")
+    );
+    // Absolute links into home point to the staged copy, not to the committed home
+    assert!(
+        std::fs::read_link(second.home_folder_path.join("absolute.txt"))
+            .unwrap()
+            .starts_with(&second.home_folder_path)
+    );
+}
+
+#[tokio::test]
+async fn cleanup_stale_home_staging_of_abandoned_executions() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let abandoned = transactional_storage(storage.path(), "context");
+    abandoned.begin_home_transaction().unwrap();
+
+    let current = transactional_storage(storage.path(), "context");
+    current.begin_home_transaction().unwrap();
+    assert_eq!(
+        current
+            .cleanup_stale_home_staging(std::time::Duration::ZERO)
+            .unwrap(),
+        1
+    );
+    assert!(!abandoned.home_folder_path.exists());
+    assert!(current.home_folder_path.exists());
+}
@@ -0,0 +1,8 @@
+/// Lint rules added to or removed from the tool defaults, e.g. to apply an organisation rule set
+#[derive(Clone, Debug, Default)]
+pub struct LintRules {
+    /// Rules enabled on top of the defaults (`deno lint` rule names or ruff rule codes/prefixes)
+    pub include: Vec<String>,
+    /// Rules disabled even if enabled by default
+    pub exclude: Vec<String>,
+}
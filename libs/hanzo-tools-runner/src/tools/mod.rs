@@ -1,18 +1,46 @@
+pub mod audit;
+pub mod check_cache;
+pub mod check_result;
 pub mod check_utils;
 pub mod code_files;
 pub mod container_utils;
+pub mod context_archive;
+pub mod contract;
 pub mod deno_execution_storage;
 pub mod deno_runner;
 pub mod deno_runner_options;
+pub mod dependency_policy;
+pub mod diagnostic;
+mod diff_utils;
 pub mod execution_context;
 pub mod execution_error;
 pub mod execution_storage;
+pub mod file_changes;
+mod file_lock_utils;
 mod file_name_utils;
+pub mod fix_result;
+mod hash_utils;
+pub mod home_transaction;
+pub mod lint_rules;
+pub mod mounts;
+pub mod offline;
 mod path_buf_ext;
+pub mod prepare;
+mod process_utils;
+pub mod python_dependencies;
 pub mod python_execution_storage;
+pub mod python_interpreters;
 pub mod python_runner;
 pub mod python_runner_options;
+pub mod run_artifact;
 pub mod run_result;
 pub mod runner_type;
+pub mod sbom;
+pub mod shell_runner;
+pub mod shell_runner_options;
 pub mod hanzo_node_location;
 pub mod tool_definition;
+#[cfg(feature = "wasm")]
+pub mod wasm_runner;
+#[cfg(feature = "wasm")]
+pub mod wasm_runner_options;
@@ -0,0 +1,259 @@
+use std::{
+    collections::HashMap,
+    path::{self, Path, PathBuf},
+};
+
+use super::{
+    execution_storage::ExecutionStorage, file_name_utils::normalize_for_docker_path,
+    path_buf_ext::PathBufExt, runner_type::RunnerType,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MountMode {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// A file, directory or glob pattern made available to the tool
+#[derive(Clone, Debug)]
+pub struct Mount {
+    /// Path in the host. Glob patterns (`*`, `?`, `[...]`) are expanded before running
+    pub source: PathBuf,
+    pub mode: MountMode,
+    /// Path inside the container, only used in Docker runs.
+    /// When `source` is a glob pattern it's used as the folder every match is mounted into, keeping
+    /// the path of the match relative to the folders of the pattern before the first glob.
+    /// Defaults to the same host path for read-write mounts and to the assets folder for read-only ones.
+    pub target: Option<PathBuf>,
+}
+
+impl Mount {
+    pub fn read_only(source: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            mode: MountMode::ReadOnly,
+            target: None,
+        }
+    }
+
+    pub fn read_write(source: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            mode: MountMode::ReadWrite,
+            target: None,
+        }
+    }
+
+    pub fn with_target(mut self, target: impl Into<PathBuf>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+}
+
+/// A mount with globs expanded and both host and container paths resolved
+#[derive(Clone, Debug)]
+pub struct ResolvedMount {
+    pub host_path: PathBuf,
+    pub docker_path: String,
+    pub mode: MountMode,
+    pub is_dir: bool,
+}
+
+impl ResolvedMount {
+    pub fn path(&self, runner_type: &RunnerType) -> String {
+        match runner_type {
+            RunnerType::Host => self.host_path.to_string_lossy().to_string(),
+            RunnerType::Docker => self.docker_path.clone(),
+        }
+    }
+
+    pub fn docker_mount_param(&self) -> String {
+        match self.mode {
+            MountMode::ReadWrite => format!(
+                r#"type=bind,source={},target={}"#,
+                normalize_for_docker_path(self.host_path.clone()),
+                self.docker_path
+            ),
+            MountMode::ReadOnly => format!(
+                r#"type=bind,readonly=true,source={},target={}"#,
+                self.host_path.as_normalized_string(),
+                self.docker_path
+            ),
+        }
+    }
+}
+
+fn is_glob_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Leading folders of a glob pattern, up to the first component with a glob
+fn glob_base(pattern: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        if is_glob_pattern(&component.as_os_str().to_string_lossy()) {
+            break;
+        }
+        base.push(component);
+    }
+    base
+}
+
+impl ExecutionStorage {
+    /// Every mount of the execution context: `mount_files` (read-write), `assets_files` (read-only) and `mounts`
+    pub fn mounts(&self) -> Vec<Mount> {
+        let mut mounts = Vec::new();
+        mounts.extend(
+            self.context
+                .mount_files
+                .iter()
+                .map(|p| Mount::read_write(p.clone())),
+        );
+        mounts.extend(
+            self.context
+                .assets_files
+                .iter()
+                .map(|p| Mount::read_only(p.clone())),
+        );
+        mounts.extend(self.context.mounts.iter().cloned());
+        mounts
+    }
+
+    /// Expands glob patterns and computes the host and container path of every mount
+    pub fn resolved_mounts(&self) -> anyhow::Result<Vec<ResolvedMount>> {
+        let docker_assets_path = format!(
+            "/app/{}",
+            self.relative_to_root(self.assets_folder_path.clone())
+        );
+        let mut resolved = Vec::new();
+        for mount in self.mounts() {
+            let source = mount.source.to_string_lossy().to_string();
+            if !is_glob_pattern(&source) {
+                let host_path = path::absolute(&mount.source)?;
+                let docker_path = match (&mount.target, &mount.mode) {
+                    (Some(target), _) => target.as_normalized_string(),
+                    (None, MountMode::ReadWrite) => normalize_for_docker_path(host_path.clone()),
+                    (None, MountMode::ReadOnly) => format!(
+                        "{}/{}",
+                        docker_assets_path,
+                        host_path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default()
+                    ),
+                };
+                resolved.push(ResolvedMount {
+                    is_dir: host_path.is_dir(),
+                    host_path,
+                    docker_path,
+                    mode: mount.mode.clone(),
+                });
+                continue;
+            }
+
+            let pattern = path::absolute(&mount.source)?.as_normalized_string();
+            let base = glob_base(&pattern);
+            let mut matches = 0;
+            for entry in glob::glob(&pattern)
+                .map_err(|e| anyhow::anyhow!("invalid mount pattern {}: {}", source, e))?
+            {
+                let host_path = entry?;
+                // Matches in different folders can share a file name, so the folders are kept
+                let relative_path = host_path
+                    .strip_prefix(&base)
+                    .map(|relative_path| relative_path.to_path_buf())
+                    .unwrap_or_else(|_| PathBuf::from(host_path.file_name().unwrap_or_default()))
+                    .as_normalized_string();
+                let docker_path = match (&mount.target, &mount.mode) {
+                    (Some(target), _) => {
+                        format!("{}/{}", target.as_normalized_string(), relative_path)
+                    }
+                    (None, MountMode::ReadWrite) => normalize_for_docker_path(host_path.clone()),
+                    (None, MountMode::ReadOnly) => {
+                        format!("{}/{}", docker_assets_path, relative_path)
+                    }
+                };
+                resolved.push(ResolvedMount {
+                    is_dir: host_path.is_dir(),
+                    host_path,
+                    docker_path,
+                    mode: mount.mode.clone(),
+                });
+                matches += 1;
+            }
+            if matches == 0 {
+                log::warn!("mount pattern {} didn't match any file", source);
+            }
+        }
+
+        // The same path mounted twice is mounted once, different paths can't share a target
+        let mut docker_paths = HashMap::<String, PathBuf>::new();
+        let mut unique = Vec::new();
+        for mount in resolved {
+            match docker_paths.get(&mount.docker_path) {
+                Some(host_path) if *host_path == mount.host_path => continue,
+                Some(host_path) => {
+                    return Err(anyhow::anyhow!(
+                        "mounts {} and {} have the same container path {}",
+                        host_path.display(),
+                        mount.host_path.display(),
+                        mount.docker_path
+                    ));
+                }
+                None => {
+                    docker_paths.insert(mount.docker_path.clone(), mount.host_path.clone());
+                    unique.push(mount);
+                }
+            }
+        }
+        log::info!("resolved mounts: {:?}", unique);
+        Ok(unique)
+    }
+}
+
+/// Docker `--mount` arguments for every mount
+pub fn docker_mount_params(mounts: &[ResolvedMount]) -> Vec<String> {
+    let mut mount_params = Vec::new();
+    for mount in mounts {
+        let mount_param = mount.docker_mount_param();
+        log::info!("mount parameter created: {}", mount_param);
+        mount_params.extend([String::from("--mount"), mount_param]);
+    }
+    mount_params
+}
+
+/// Comma separated paths for the `SHINKAI_MOUNT` (read-write) and `SHINKAI_ASSETS` (read-only) env vars.
+/// Docker paths keep the trailing comma tools got before mounts were resolved.
+pub fn mount_envs(mounts: &[ResolvedMount], runner_type: &RunnerType) -> (String, String) {
+    let paths_with_mode = |mode: MountMode| {
+        let paths = mounts
+            .iter()
+            .filter(|mount| mount.mode == mode)
+            .map(|mount| mount.path(runner_type));
+        match runner_type {
+            RunnerType::Host => paths.collect::<Vec<_>>().join(","),
+            RunnerType::Docker => paths.map(|path| format!("{},", path)).collect(),
+        }
+    };
+    (
+        paths_with_mode(MountMode::ReadWrite),
+        paths_with_mode(MountMode::ReadOnly),
+    )
+}
+
+/// Maps every mounted host path to its path inside the container
+pub fn docker_path_mappings(mounts: &[ResolvedMount]) -> HashMap<String, String> {
+    mounts
+        .iter()
+        .map(|mount| {
+            (
+                mount.host_path.to_string_lossy().to_string(),
+                mount.docker_path.clone(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "mounts.test.rs"]
+mod tests;
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::tools::{
+    code_files::CodeFiles,
+    execution_context::ExecutionContext,
+    execution_storage::ExecutionStorage,
+    mounts::{docker_mount_params, mount_envs, Mount, MountMode},
+    runner_type::RunnerType,
+};
+
+fn storage_with_context(context: ExecutionContext) -> ExecutionStorage {
+    ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::new(),
+            entrypoint: "main.ts".to_string(),
+        },
+        context,
+    )
+}
+
+#[tokio::test]
+async fn resolve_legacy_files_and_folders() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let folder = tempfile::tempdir().unwrap();
+    let mount_file = folder.path().join("mount.txt");
+    let asset_file = folder.path().join("asset.txt");
+    std::fs::write(&mount_file, "1").unwrap();
+    std::fs::write(&asset_file, "2").unwrap();
+
+    let storage = storage_with_context(ExecutionContext {
+        mount_files: vec![mount_file.clone()],
+        assets_files: vec![asset_file.clone()],
+        mounts: vec![Mount::read_only(folder.path()).with_target("/data")],
+        ..Default::default()
+    });
+
+    let mounts = storage.resolved_mounts().unwrap();
+    assert_eq!(mounts.len(), 3);
+    assert_eq!(mounts[0].mode, MountMode::ReadWrite);
+    assert_eq!(mounts[0].host_path, mount_file);
+    assert_eq!(mounts[1].docker_path, "/app/assets/asset.txt");
+    assert!(mounts[2].is_dir);
+    assert_eq!(mounts[2].docker_path, "/data");
+
+    let (mount_env, assets_env) = mount_envs(&mounts, &RunnerType::Docker);
+    assert_eq!(assets_env, "/app/assets/asset.txt,/data,");
+    assert_eq!(mount_env, format!("{},", mounts[0].docker_path));
+
+    let (mount_env, assets_env) = mount_envs(&mounts, &RunnerType::Host);
+    assert_eq!(mount_env, mount_file.to_string_lossy());
+    assert!(assets_env.ends_with(&folder.path().to_string_lossy().to_string()));
+
+    let params = docker_mount_params(&mounts);
+    assert_eq!(params.len(), 6);
+    assert!(params[5].starts_with("type=bind,readonly=true,"));
+    assert!(params[5].ends_with("target=/data"));
+}
+
+#[tokio::test]
+async fn resolve_glob_patterns() {
+    let folder = tempfile::tempdir().unwrap();
+    for name in ["a.csv", "b.csv", "c.json"] {
+        std::fs::write(folder.path().join(name), "1").unwrap();
+    }
+
+    let storage = storage_with_context(ExecutionContext {
+        mounts: vec![
+            Mount::read_write(folder.path().join("*.csv")).with_target("/csv"),
+            Mount::read_only(folder.path().join("*.txt")),
+        ],
+        ..Default::default()
+    });
+
+    let mounts = storage.resolved_mounts().unwrap();
+    assert_eq!(mounts.len(), 2);
+    assert_eq!(mounts[0].docker_path, "/csv/a.csv");
+    assert_eq!(mounts[1].docker_path, "/csv/b.csv");
+    assert!(mounts.iter().all(|m| m.mode == MountMode::ReadWrite));
+}
+
+#[tokio::test]
+async fn resolve_recursive_glob_patterns() {
+    let folder = tempfile::tempdir().unwrap();
+    for sub_folder in ["a", "b"] {
+        std::fs::create_dir_all(folder.path().join(sub_folder)).unwrap();
+        std::fs::write(folder.path().join(sub_folder).join("x.csv"), "1").unwrap();
+    }
+
+    let storage = storage_with_context(ExecutionContext {
+        mounts: vec![
+            Mount::read_write(folder.path().join("**/*.csv")).with_target("/csv"),
+            Mount::read_only(folder.path().join("*/x.csv")),
+        ],
+        ..Default::default()
+    });
+
+    let mounts = storage.resolved_mounts().unwrap();
+    assert_eq!(mounts.len(), 4);
+    assert_eq!(mounts[0].docker_path, "/csv/a/x.csv");
+    assert_eq!(mounts[1].docker_path, "/csv/b/x.csv");
+    assert_eq!(mounts[2].docker_path, "/app/assets/a/x.csv");
+    assert_eq!(mounts[3].docker_path, "/app/assets/b/x.csv");
+}
+
+#[tokio::test]
+async fn resolve_conflicting_targets() {
+    let folder = tempfile::tempdir().unwrap();
+    for sub_folder in ["a", "b"] {
+        std::fs::create_dir_all(folder.path().join(sub_folder)).unwrap();
+        std::fs::write(folder.path().join(sub_folder).join("x.csv"), "1").unwrap();
+    }
+
+    // The same file mounted twice is mounted once
+    let storage = storage_with_context(ExecutionContext {
+        assets_files: vec![folder.path().join("a/x.csv")],
+        mounts: vec![Mount::read_only(folder.path().join("a/x.csv"))],
+        ..Default::default()
+    });
+    assert_eq!(storage.resolved_mounts().unwrap().len(), 1);
+
+    let storage = storage_with_context(ExecutionContext {
+        assets_files: vec![folder.path().join("a/x.csv"), folder.path().join("b/x.csv")],
+        ..Default::default()
+    });
+    let error = storage.resolved_mounts().unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with("have the same container path /app/assets/x.csv"));
+}
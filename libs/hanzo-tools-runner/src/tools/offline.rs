@@ -0,0 +1,26 @@
+/// What uv and deno print when a dependency isn't in their caches and the network is disabled
+const OFFLINE_ERROR_MARKERS: &[&str] = &[
+    // uv
+    "network was disabled",
+    "Network connectivity is disabled",
+    // deno
+    "--cached-only is specified",
+];
+
+/// Prefixes errors caused by a dependency missing from the caches and local mirrors in offline mode,
+/// so they aren't mistaken for a bug in the tool or the network being down
+pub fn explain_offline_error(message: String, offline: bool) -> String {
+    if offline
+        && OFFLINE_ERROR_MARKERS
+            .iter()
+            .any(|marker| message.contains(marker))
+    {
+        format!("dependency not available offline: {}", message)
+    } else {
+        message
+    }
+}
+
+#[cfg(test)]
+#[path = "offline.test.rs"]
+mod tests;
@@ -0,0 +1,20 @@
+use crate::tools::offline::explain_offline_error;
+
+#[test]
+fn test_explain_offline_error() {
+    let uv_error = "error: Failed to fetch: `https://pypi.org/simple/requests/`\n  Caused by: Network connectivity is disabled, but the requested data wasn't found in the cache";
+    assert_eq!(
+        explain_offline_error(uv_error.to_string(), true),
+        format!("dependency not available offline: {}", uv_error)
+    );
+    let deno_error =
+        "error: npm package not found in cache: \"lodash-es\", --cached-only is specified.";
+    assert!(explain_offline_error(deno_error.to_string(), true)
+        .starts_with("dependency not available offline: "));
+
+    assert_eq!(explain_offline_error(uv_error.to_string(), false), uv_error);
+    assert_eq!(
+        explain_offline_error("ZeroDivisionError".to_string(), true),
+        "ZeroDivisionError"
+    );
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::mpsc::UnboundedSender,
+};
+
+/// Step of `prepare`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrepareStep {
+    /// Creating the execution storage and writing the code snapshot
+    CreateStorage,
+    /// Installing dependencies and warming caches (`uv sync`, `deno cache`)
+    InstallDependencies,
+}
+
+/// Progress reported by `prepare`
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrepareEvent {
+    Started(PrepareStep),
+    /// A line printed while installing dependencies
+    Output(String),
+    Finished {
+        step: PrepareStep,
+        elapsed: Duration,
+    },
+}
+
+/// Sends progress events to the receiver, if there is one still listening
+#[derive(Clone, Default)]
+pub struct PrepareProgress {
+    sender: Option<UnboundedSender<PrepareEvent>>,
+}
+
+impl PrepareProgress {
+    pub fn new(sender: Option<UnboundedSender<PrepareEvent>>) -> Self {
+        Self { sender }
+    }
+
+    pub fn send(&self, event: PrepareEvent) {
+        if let Some(sender) = &self.sender {
+            // Events are informative, a dropped receiver doesn't stop the preparation
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Runs `step`, reporting when it starts and finishes
+    pub async fn step<T>(
+        &self,
+        step: PrepareStep,
+        future: impl std::future::Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        log::info!("prepare step started: {:?}", step);
+        self.send(PrepareEvent::Started(step.clone()));
+        let start = Instant::now();
+        let result = future.await?;
+        let elapsed = start.elapsed();
+        log::info!("prepare step finished: {:?} in {:?}", step, elapsed);
+        self.send(PrepareEvent::Finished { step, elapsed });
+        Ok(result)
+    }
+
+    async fn forward_lines(&self, reader: impl AsyncRead + Unpin) -> Vec<String> {
+        let mut lines = BufReader::new(reader).lines();
+        let mut output = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            log::info!("from prepare command: {}", line);
+            self.send(PrepareEvent::Output(line.clone()));
+            output.push(line);
+        }
+        output
+    }
+
+    /// Runs a dependency installation command, reporting every line it prints
+    pub async fn run_command(&self, mut command: tokio::process::Command) -> anyhow::Result<()> {
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        log::info!("spawning prepare command: {:?}", command);
+        let mut child = command.spawn().map_err(|e| {
+            let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
+            log::error!("{}", error_msg);
+            anyhow::anyhow!("{}", error_msg)
+        })?;
+        let stdout = child.stdout.take().expect("Failed to get stdout");
+        let stderr = child.stderr.take().expect("Failed to get stderr");
+        let (_, stderr_lines) =
+            tokio::join!(self.forward_lines(stdout), self.forward_lines(stderr));
+        let status = child.wait().await?;
+        if !status.success() {
+            let stderr = stderr_lines.join("\n");
+            log::error!("prepare command failed: {}", stderr);
+            return Err(anyhow::anyhow!(
+                "failed to install dependencies: {}",
+                stderr
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Runs `prepare` within `max_prepare_timeout`, when there is one
+pub async fn with_prepare_timeout(
+    max_prepare_timeout: Option<Duration>,
+    future: impl std::future::Future<Output = anyhow::Result<()>>,
+) -> anyhow::Result<()> {
+    match max_prepare_timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, future).await {
+            Ok(result) => result,
+            Err(_) => {
+                log::error!("prepare timed out after {}[s]", timeout.as_secs());
+                Err(anyhow::anyhow!(
+                    "prepare timed out after {}[s]",
+                    timeout.as_secs()
+                ))
+            }
+        },
+        None => future.await,
+    }
+}
+
+#[cfg(test)]
+#[path = "prepare.test.rs"]
+mod tests;
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use crate::tools::prepare::{with_prepare_timeout, PrepareEvent, PrepareProgress, PrepareStep};
+
+fn shell(script: &str) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("sh");
+    command.args(["-c", script]);
+    command
+}
+
+#[tokio::test]
+async fn test_prepare_progress_events() {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let progress = PrepareProgress::new(Some(sender));
+    progress
+        .step(
+            PrepareStep::InstallDependencies,
+            progress.run_command(shell("echo resolved; echo installed >&2")),
+        )
+        .await
+        .unwrap();
+    drop(progress);
+
+    let mut events = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        events.push(event);
+    }
+    assert_eq!(events.len(), 4);
+    assert_eq!(
+        events[0],
+        PrepareEvent::Started(PrepareStep::InstallDependencies)
+    );
+    assert!(events.contains(&PrepareEvent::Output("resolved".to_string())));
+    assert!(events.contains(&PrepareEvent::Output("installed".to_string())));
+    assert!(matches!(
+        events[3],
+        PrepareEvent::Finished {
+            step: PrepareStep::InstallDependencies,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn test_prepare_command_failure_and_timeout() {
+    let progress = PrepareProgress::default();
+    let error = progress
+        .run_command(shell("echo 'no matching version' >&2; exit 1"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "failed to install dependencies: no matching version"
+    );
+
+    let error = with_prepare_timeout(
+        Some(Duration::from_millis(100)),
+        progress.run_command(shell("sleep 5")),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.to_string().starts_with("prepare timed out"));
+}
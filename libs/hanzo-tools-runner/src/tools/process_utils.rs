@@ -0,0 +1,108 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    sync::Mutex,
+};
+
+use super::execution_storage::ExecutionStorage;
+
+/// Logs every line of `stream` and appends it to the execution log, keeping them in `lines`
+fn pump_lines(
+    stream: impl AsyncRead + Unpin + Send + 'static,
+    source: String,
+    execution_storage: ExecutionStorage,
+    lines: Arc<Mutex<Vec<String>>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut stream = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = stream.next_line().await {
+            log::info!("from {}: {}", source, line);
+            let _ = execution_storage.append_log(line.as_str());
+            lines.lock().await.push(line);
+        }
+    })
+}
+
+/// Runs a tool process until it exits or `max_execution_timeout` passes, writing `input` to its
+/// stdin when given. Its stdout and stderr lines are logged as coming from `source` and appended
+/// to the execution log as they are printed.
+///
+/// # Returns
+///
+/// The stdout lines. Fails with the stderr lines when the process fails and with a `TimedOut` io
+/// error when it times out.
+pub async fn run_process(
+    execution_storage: &ExecutionStorage,
+    command: &mut tokio::process::Command,
+    input: Option<String>,
+    source: &str,
+    max_execution_timeout: Option<Duration>,
+) -> anyhow::Result<Vec<String>> {
+    if input.is_some() {
+        command.stdin(std::process::Stdio::piped());
+    }
+    command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = command.spawn().map_err(|e| {
+        let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
+        log::error!("{}", error_msg);
+        anyhow::anyhow!("{}", error_msg)
+    })?;
+
+    let stdin_task = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => Some(tokio::spawn(async move {
+            // Processes that don't read their input close stdin early, the broken pipe is expected
+            let _ = stdin.write_all(input.as_bytes()).await;
+        })),
+        _ => None,
+    };
+    let stdout_lines = Arc::new(Mutex::new(Vec::<String>::new()));
+    let stderr_lines = Arc::new(Mutex::new(Vec::<String>::new()));
+    let stdout_task = pump_lines(
+        child.stdout.take().expect("Failed to get stdout"),
+        source.to_string(),
+        execution_storage.clone(),
+        stdout_lines.clone(),
+    );
+    let stderr_task = pump_lines(
+        child.stderr.take().expect("Failed to get stderr"),
+        source.to_string(),
+        execution_storage.clone(),
+        stderr_lines.clone(),
+    );
+
+    let status = if let Some(timeout) = max_execution_timeout {
+        log::info!("executing command with {}[s] timeout", timeout.as_secs());
+        match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(result) => result?,
+            Err(_) => {
+                log::error!("command execution timed out after {}[s]", timeout.as_secs());
+                return Err(anyhow::Error::new(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("process timed out after {}[s]", timeout.as_secs()),
+                )));
+            }
+        }
+    } else {
+        log::info!("executing command without timeout");
+        child.wait().await?
+    };
+    if let Some(stdin_task) = stdin_task {
+        let _ = stdin_task.await;
+    }
+    let _ = futures::future::join_all([stdout_task, stderr_task]).await;
+    if !status.success() {
+        let mut stderr = stderr_lines.lock().await.join("\n");
+        if stderr.is_empty() {
+            stderr = format!("process failed with {}", status);
+        }
+        log::error!("command execution failed: {}", stderr);
+        return Err(anyhow::Error::new(std::io::Error::other(stderr)));
+    }
+    let stdout = stdout_lines.lock().await.to_vec();
+    log::info!("command completed successfully with output: {:?}", stdout);
+    Ok(stdout)
+}
@@ -0,0 +1,179 @@
+# Reports the risky APIs used by the Python files given as arguments. Names are resolved through
+# imports, aliases, `__import__`, `importlib.import_module` and `getattr` with constant names, so
+# `from os import system as s; s(...)` is reported like `os.system(...)`.
+# Prints the findings as a JSON list, files with syntax errors are left to the linters.
+import ast
+import json
+import sys
+
+# Rules of modules, reported when they are imported or referenced
+MODULE_RULES = {
+    "subprocess": "subprocess",
+    "ctypes": "ctypes",
+    "_ctypes": "ctypes",
+}
+
+# Rules of qualified names, reported when they are referenced
+NAME_RULES = {
+    "os.system": "os-system",
+    "os.popen": "os-system",
+    "builtins.eval": "eval",
+    "builtins.exec": "eval",
+}
+for module in ["pickle", "_pickle", "cPickle", "dill"]:
+    for name in ["load", "loads", "Unpickler"]:
+        NAME_RULES[f"{module}.{name}"] = "pickle-loads"
+
+NAME_PREFIX_RULES = {
+    "os.exec": "os-system",
+    "os.spawn": "os-system",
+    "os.posix_spawn": "os-system",
+}
+
+BUILTINS = {"eval", "exec", "getattr", "__import__"}
+IMPORT_FUNCTIONS = {"builtins.__import__", "importlib.import_module"}
+
+
+def constant_string(node):
+    if isinstance(node, ast.Constant) and isinstance(node.value, str):
+        return node.value
+    return None
+
+
+def rule_of(qualified_name):
+    module = qualified_name.split(".")[0]
+    if module in MODULE_RULES:
+        return MODULE_RULES[module]
+    if qualified_name in NAME_RULES:
+        return NAME_RULES[qualified_name]
+    for prefix, rule in NAME_PREFIX_RULES.items():
+        if qualified_name.startswith(prefix):
+            return rule
+    return None
+
+
+class Auditor(ast.NodeVisitor):
+    def __init__(self, path, source):
+        self.path = path
+        self.source = source
+        # Local name to the qualified name it's bound to
+        self.aliases = {}
+        self.findings = []
+
+    def report(self, node, rule):
+        segment = ast.get_source_segment(self.source, node) or ""
+        self.findings.append(
+            {
+                "file": self.path,
+                "line": node.lineno,
+                "column": node.col_offset + 1,
+                "end_line": node.end_lineno,
+                "end_column": node.end_col_offset + 1,
+                "rule": rule,
+                "text": segment.splitlines()[0] if segment else "",
+            }
+        )
+
+    def qualified_name(self, node):
+        """Qualified name an expression refers to, `None` when it isn't known"""
+        if isinstance(node, ast.Name):
+            if node.id in self.aliases:
+                return self.aliases[node.id]
+            if node.id in BUILTINS:
+                return f"builtins.{node.id}"
+            return None
+        if isinstance(node, ast.Attribute):
+            base = self.qualified_name(node.value)
+            return f"{base}.{node.attr}" if base else None
+        if isinstance(node, ast.Call):
+            function = self.qualified_name(node.func)
+            if function in IMPORT_FUNCTIONS and node.args:
+                return constant_string(node.args[0])
+            if function == "builtins.getattr" and len(node.args) >= 2:
+                base = self.qualified_name(node.args[0])
+                name = constant_string(node.args[1])
+                return f"{base}.{name}" if base and name else None
+        return None
+
+    def visit_Import(self, node):
+        for alias in node.names:
+            if alias.asname:
+                self.aliases[alias.asname] = alias.name
+            else:
+                top_level = alias.name.split(".")[0]
+                self.aliases[top_level] = top_level
+            rule = MODULE_RULES.get(alias.name.split(".")[0])
+            if rule:
+                self.report(node, rule)
+
+    def visit_ImportFrom(self, node):
+        if node.level or not node.module:
+            return
+        for alias in node.names:
+            if alias.name != "*":
+                self.aliases[alias.asname or alias.name] = f"{node.module}.{alias.name}"
+        rule = MODULE_RULES.get(node.module.split(".")[0])
+        if rule:
+            self.report(node, rule)
+
+    def visit_Assign(self, node):
+        self.visit(node.value)
+        qualified_name = self.qualified_name(node.value)
+        for target in node.targets:
+            if isinstance(target, ast.Name):
+                if qualified_name:
+                    self.aliases[target.id] = qualified_name
+                else:
+                    self.aliases.pop(target.id, None)
+            else:
+                self.visit(target)
+
+    def visit_Call(self, node):
+        function = self.qualified_name(node.func)
+        if function in IMPORT_FUNCTIONS:
+            module = self.qualified_name(node)
+            if module is None:
+                self.report(node, "dynamic-import")
+            elif rule_of(module):
+                self.report(node, rule_of(module))
+            for argument in node.args + [keyword.value for keyword in node.keywords]:
+                self.visit(argument)
+            return
+        if function == "builtins.getattr":
+            qualified_name = self.qualified_name(node)
+            if qualified_name and rule_of(qualified_name):
+                self.report(node, rule_of(qualified_name))
+                return
+        self.generic_visit(node)
+
+    def visit_reference(self, node):
+        qualified_name = self.qualified_name(node)
+        if qualified_name and rule_of(qualified_name):
+            self.report(node, rule_of(qualified_name))
+            return True
+        return False
+
+    def visit_Name(self, node):
+        if isinstance(node.ctx, ast.Load):
+            self.visit_reference(node)
+
+    def visit_Attribute(self, node):
+        if not (isinstance(node.ctx, ast.Load) and self.visit_reference(node)):
+            self.generic_visit(node)
+
+
+def audit(path):
+    with open(path, encoding="utf-8") as file:
+        source = file.read()
+    try:
+        tree = ast.parse(source, path)
+    except SyntaxError:
+        return []
+    auditor = Auditor(path, source)
+    auditor.visit(tree)
+    return auditor.findings
+
+
+if __name__ == "__main__":
+    findings = [finding for path in sys.argv[1:] for finding in audit(path)]
+    print(json.dumps(findings))
@@ -0,0 +1,170 @@
+# Verifies that the Python file given as argument exposes `run(configurations, parameters)` and the
+# `CONFIG` and `INPUTS` classes the runner decodes configurations and parameters into.
+# Prints the violations as a JSON list, syntax errors are left to the linters.
+import ast
+import json
+import sys
+
+ENTRY_FUNCTION_NAME = "run"
+CONTRACT_CLASSES = [("CONFIG", "configurations"), ("INPUTS", "parameters")]
+
+
+def diagnostic(node, severity, rule, message):
+    return {
+        "line": node.lineno if node else 0,
+        "column": node.col_offset + 1 if node else 0,
+        "severity": severity,
+        "rule": rule,
+        "message": message,
+    }
+
+
+def looks_like_entry_function(name):
+    normalized_name = name.strip("_").lower()
+    return name != ENTRY_FUNCTION_NAME and (
+        normalized_name.startswith(ENTRY_FUNCTION_NAME)
+        or normalized_name.endswith(ENTRY_FUNCTION_NAME)
+    )
+
+
+NESTED_SCOPES = (ast.FunctionDef, ast.AsyncFunctionDef, ast.Lambda, ast.ClassDef)
+
+
+def own_nodes(function):
+    """Nodes of a function body, without the ones of nested functions, lambdas and classes"""
+    pending = [node for node in function.body if not isinstance(node, NESTED_SCOPES)]
+    while pending:
+        node = pending.pop()
+        yield node
+        for child in ast.iter_child_nodes(node):
+            if not isinstance(child, NESTED_SCOPES):
+                pending.append(child)
+
+
+def is_asyncio_run(node):
+    return (
+        isinstance(node, ast.Call)
+        and isinstance(node.func, ast.Attribute)
+        and node.func.attr == "run"
+        and isinstance(node.func.value, ast.Name)
+        and node.func.value.id == "asyncio"
+    )
+
+
+def check_run(run):
+    diagnostics = []
+    arguments = run.args
+    positional = getattr(arguments, "posonlyargs", []) + arguments.args
+    required_count = len(positional) - len(arguments.defaults)
+    required_count += sum(1 for default in arguments.kw_defaults if default is None)
+    if required_count > 2 or (len(positional) < 2 and arguments.vararg is None):
+        diagnostics.append(
+            diagnostic(
+                run,
+                "error",
+                "entry-function-arity",
+                f"`{ENTRY_FUNCTION_NAME}` is called with two positional arguments (configurations, parameters)",
+            )
+        )
+
+    is_async = isinstance(run, ast.AsyncFunctionDef)
+    nodes = list(own_nodes(run))
+    if not is_async and any(isinstance(node, ast.Await) for node in nodes):
+        diagnostics.append(
+            diagnostic(
+                run,
+                "error",
+                "sync-async-mismatch",
+                f"`{ENTRY_FUNCTION_NAME}` uses `await` but isn't declared `async def`",
+            )
+        )
+    if is_async and any(is_asyncio_run(node) for node in nodes):
+        diagnostics.append(
+            diagnostic(
+                run,
+                "error",
+                "sync-async-mismatch",
+                f"`{ENTRY_FUNCTION_NAME}` is `async def` and already runs in an event loop, `asyncio.run` will fail",
+            )
+        )
+    return diagnostics
+
+
+def defined_names(tree):
+    """Names bound at the top level by classes, assignments and imports"""
+    names = set()
+    for node in tree.body:
+        if isinstance(node, (ast.ClassDef, ast.FunctionDef, ast.AsyncFunctionDef)):
+            names.add(node.name)
+        elif isinstance(node, ast.Assign):
+            for target in node.targets:
+                if isinstance(target, ast.Name):
+                    names.add(target.id)
+        elif isinstance(node, ast.AnnAssign) and isinstance(node.target, ast.Name):
+            names.add(node.target.id)
+        elif isinstance(node, (ast.Import, ast.ImportFrom)):
+            for alias in node.names:
+                names.add(alias.asname or alias.name.split(".")[0])
+    return names
+
+
+def check_contract(path):
+    with open(path, encoding="utf-8") as file:
+        source = file.read()
+    try:
+        tree = ast.parse(source, path)
+    except SyntaxError:
+        return []
+
+    functions = [
+        node
+        for node in tree.body
+        if isinstance(node, (ast.FunctionDef, ast.AsyncFunctionDef))
+    ]
+    # The last definition is the one bound when the wrapper calls it
+    runs = [function for function in functions if function.name == ENTRY_FUNCTION_NAME]
+    run = runs[-1] if runs else None
+
+    diagnostics = []
+    if run is not None:
+        diagnostics.extend(check_run(run))
+    else:
+        misnamed = next(
+            (function for function in functions if looks_like_entry_function(function.name)),
+            None,
+        )
+        if misnamed is not None:
+            diagnostics.append(
+                diagnostic(
+                    misnamed,
+                    "error",
+                    "misnamed-entry-function",
+                    f"the entry function must be named `{ENTRY_FUNCTION_NAME}`, found `{misnamed.name}`",
+                )
+            )
+        else:
+            diagnostics.append(
+                diagnostic(
+                    None,
+                    "error",
+                    "missing-entry-function",
+                    f"no `{ENTRY_FUNCTION_NAME}(configurations, parameters)` function found",
+                )
+            )
+
+    names = defined_names(tree)
+    for class_name, value_name in CONTRACT_CLASSES:
+        if class_name not in names:
+            diagnostics.append(
+                diagnostic(
+                    run,
+                    "warning",
+                    "missing-contract-class",
+                    f"no `{class_name}` class found, {value_name} will be passed to `{ENTRY_FUNCTION_NAME}` as a dict",
+                )
+            )
+    return diagnostics
+
+
+if __name__ == "__main__":
+    print(json.dumps(check_contract(sys.argv[1])))
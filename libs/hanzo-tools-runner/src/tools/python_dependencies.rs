@@ -0,0 +1,91 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Deserialize;
+
+use super::code_files::CodeFiles;
+
+/// Distributions of the modules whose import name differs from their PyPI name
+/// (`import_name:distribution` per line), used when pipreqs isn't installed and for the submodules
+/// of namespace packages its mapping doesn't cover.
+/// Dotted import names map a submodule of a namespace package, e.g. `google.genai`.
+const IMPORT_MAPPING: &str = include_str!("python_import_mapping.txt");
+
+/// Script that prints the third-party modules imported by the Python files it gets as arguments,
+/// read with the `ast` module, and the import mapping of pipreqs as JSON
+pub const PYTHON_IMPORTS_SCRIPT: &str = include_str!("python_imports.py");
+
+pub const PYTHON_IMPORTS_SCRIPT_FILE_NAME: &str = "__hanzo_imports__.py";
+
+/// Modules imported by the code, as printed by `PYTHON_IMPORTS_SCRIPT`
+#[derive(Debug, Deserialize)]
+pub struct PythonImports {
+    /// Dotted names of the third-party modules, sorted
+    pub imports: Vec<String>,
+    /// Distributions by import name from pipreqs, empty when it isn't installed
+    #[serde(default)]
+    pub mapping: HashMap<String, String>,
+}
+
+/// Python files `PYTHON_IMPORTS_SCRIPT` is run with
+pub fn python_import_paths(code: &CodeFiles) -> Vec<String> {
+    let mut paths: Vec<String> = code
+        .files
+        .keys()
+        .filter(|path| path.ends_with(".py"))
+        .cloned()
+        .collect();
+    paths.sort();
+    paths
+}
+
+pub fn parse_python_imports_output(output: &str) -> anyhow::Result<PythonImports> {
+    Ok(serde_json::from_str(output.trim())?)
+}
+
+/// Distribution that provides `module`, looking up its longest dotted prefix in `import_mapping`,
+/// then in the pipreqs mapping and then in the built-in mapping. Modules that aren't mapped are
+/// installed by their top level name.
+fn distribution_for_import(
+    module: &str,
+    import_mapping: &HashMap<String, String>,
+    pipreqs_mapping: &HashMap<String, String>,
+) -> String {
+    let builtin_mapping: HashMap<&str, &str> = IMPORT_MAPPING
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .collect();
+    let parts: Vec<&str> = module.split('.').collect();
+    for length in (1..=parts.len()).rev() {
+        let prefix = parts[..length].join(".");
+        if let Some(distribution) = import_mapping
+            .get(&prefix)
+            .or_else(|| pipreqs_mapping.get(&prefix))
+        {
+            return distribution.clone();
+        }
+        if let Some(distribution) = builtin_mapping.get(prefix.as_str()) {
+            return distribution.to_string();
+        }
+    }
+    parts[0].to_string()
+}
+
+/// Infers the distributions to install from the modules the code imports.
+/// `import_mapping` adds or overrides distributions by import name, an empty distribution
+/// skips the import.
+pub fn infer_python_dependencies(
+    imports: &PythonImports,
+    import_mapping: &HashMap<String, String>,
+) -> Vec<String> {
+    let dependencies: BTreeSet<String> = imports
+        .imports
+        .iter()
+        .map(|module| distribution_for_import(module, import_mapping, &imports.mapping))
+        .filter(|distribution| !distribution.is_empty())
+        .collect();
+    dependencies.into_iter().collect()
+}
+
+#[cfg(test)]
+#[path = "python_dependencies.test.rs"]
+mod tests;
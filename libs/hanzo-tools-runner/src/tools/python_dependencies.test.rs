@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::tools::{
+    code_files::CodeFiles,
+    python_dependencies::{
+        infer_python_dependencies, parse_python_imports_output, python_import_paths, PythonImports,
+        PYTHON_IMPORTS_SCRIPT, PYTHON_IMPORTS_SCRIPT_FILE_NAME,
+    },
+};
+
+#[test]
+fn test_python_imports_script() {
+    let code = CodeFiles {
+        files: HashMap::from([
+            (
+                "main.py".to_string(),
+                r#""""
+import pandas
+"""
+import os, sys
+import numpy as np, requests
+from typing import List
+from googlesearch import search  # import pandas
+from . import helpers
+from utils import slugify
+from helpers.parsing import parse
+import google.genai
+# import torch
+query = "import scipy"
+
+def run(configurations, parameters):
+    from bs4 import (
+        BeautifulSoup,
+    )
+"#
+                .to_string(),
+            ),
+            ("utils.py".to_string(), "import tomllib\n".to_string()),
+            ("helpers/__init__.py".to_string(), String::new()),
+            ("README.md".to_string(), "import matplotlib".to_string()),
+        ]),
+        entrypoint: "main.py".to_string(),
+    };
+    let paths = python_import_paths(&code);
+    assert_eq!(paths, vec!["helpers/__init__.py", "main.py", "utils.py"]);
+
+    let folder = tempfile::tempdir().unwrap();
+    for (path, content) in code.files.iter() {
+        let file_path = folder.path().join(path);
+        std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        std::fs::write(file_path, content).unwrap();
+    }
+    std::fs::write(
+        folder.path().join(PYTHON_IMPORTS_SCRIPT_FILE_NAME),
+        PYTHON_IMPORTS_SCRIPT,
+    )
+    .unwrap();
+    let output = std::process::Command::new("python3")
+        .arg(PYTHON_IMPORTS_SCRIPT_FILE_NAME)
+        .args(&paths)
+        .current_dir(folder.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let imports = parse_python_imports_output(&String::from_utf8_lossy(&output.stdout)).unwrap();
+    assert_eq!(
+        imports.imports,
+        vec![
+            "bs4.BeautifulSoup",
+            "google.genai",
+            "googlesearch.search",
+            "numpy",
+            "requests",
+        ]
+    );
+}
+
+#[test]
+fn test_infer_python_dependencies() {
+    let imports = PythonImports {
+        imports: vec![
+            "bs4.BeautifulSoup".to_string(),
+            "cv2".to_string(),
+            "google.cloud.storage".to_string(),
+            "googlesearch.search".to_string(),
+            "requests".to_string(),
+            "requests.adapters.HTTPAdapter".to_string(),
+            "yaml".to_string(),
+        ],
+        mapping: HashMap::new(),
+    };
+    assert_eq!(
+        infer_python_dependencies(&imports, &HashMap::new()),
+        vec![
+            "PyYAML",
+            "beautifulsoup4",
+            "google-cloud-storage",
+            "googlesearch-python",
+            "opencv-python",
+            "requests",
+        ]
+    );
+
+    let import_mapping = HashMap::from([
+        ("cv2".to_string(), "opencv-python-headless".to_string()),
+        ("requests".to_string(), "requests[socks]".to_string()),
+        ("yaml".to_string(), String::new()),
+    ]);
+    assert_eq!(
+        infer_python_dependencies(&imports, &import_mapping),
+        vec![
+            "beautifulsoup4",
+            "google-cloud-storage",
+            "googlesearch-python",
+            "opencv-python-headless",
+            "requests[socks]",
+        ]
+    );
+
+    // The pipreqs mapping wins over the built-in one, the given mapping over both
+    let imports = PythonImports {
+        mapping: HashMap::from([
+            ("cv2".to_string(), "opencv-contrib-python".to_string()),
+            ("requests".to_string(), "requests-pipreqs".to_string()),
+        ]),
+        ..imports
+    };
+    let dependencies = infer_python_dependencies(&imports, &import_mapping);
+    assert!(dependencies.contains(&"opencv-python-headless".to_string()));
+    assert!(dependencies.contains(&"requests[socks]".to_string()));
+    let dependencies = infer_python_dependencies(&imports, &HashMap::new());
+    assert!(dependencies.contains(&"opencv-contrib-python".to_string()));
+}
@@ -1,19 +1,254 @@
-use super::execution_storage::ExecutionStorage;
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    execution_storage::ExecutionStorage,
+    file_lock_utils::{lock_file, try_lock_file},
+    runner_type::RunnerType,
+};
+
+/// Leases of runs that crashed without releasing them stop protecting their venv after this long
+pub const STALE_VENV_LEASE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn is_older_than(path: &Path, max_age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(|modified| modified.elapsed().unwrap_or_default() > max_age)
+        .unwrap_or(true)
+}
+
+/// Reference to a shared run venv held while a run uses it, so the venv isn't evicted.
+/// The reference is released when dropped.
+pub struct PythonVenvLease {
+    venv_folder_path: PathBuf,
+    lease_file_path: PathBuf,
+}
+
+impl PythonVenvLease {
+    pub fn venv_folder_path(&self) -> &Path {
+        &self.venv_folder_path
+    }
+}
+
+impl Drop for PythonVenvLease {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.lease_file_path) {
+            log::warn!("failed to release python venv lease: {}", e);
+        }
+    }
+}
+
+fn active_venv_leases(venv_key_folder_path: &Path) -> usize {
+    std::fs::read_dir(venv_key_folder_path.join("leases"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| !is_older_than(&entry.path(), STALE_VENV_LEASE_MAX_AGE))
+                .count()
+        })
+        .unwrap_or(0)
+}
 
 impl ExecutionStorage {
-    pub fn python_run_host_venv_folder_path(&self) -> std::path::PathBuf {
-        self.cache_folder_path.join("python-run-host-venv")
+    /// Run venvs shared by every context, one per dependency set
+    pub fn python_run_venvs_folder_path(&self, runner_type: &RunnerType) -> PathBuf {
+        self.global_cache_folder_path
+            .join("python-venvs")
+            .join(match runner_type {
+                RunnerType::Host => "host",
+                RunnerType::Docker => "docker",
+            })
+    }
+
+    pub fn python_run_venv_folder_path(&self, runner_type: &RunnerType, key: &str) -> PathBuf {
+        self.python_run_venvs_folder_path(runner_type)
+            .join(key)
+            .join("venv")
     }
-    pub fn python_run_docker_venv_folder_path(&self) -> std::path::PathBuf {
-        self.cache_folder_path.join("python-run-docker-venv")
+
+    /// Lock serializing the leases of the run venv for the dependency set `key` with its
+    /// eviction. It's kept outside the venv folder, which eviction moves.
+    fn python_venv_lock_file_path(&self, runner_type: &RunnerType, key: &str) -> PathBuf {
+        self.python_run_venvs_folder_path(runner_type)
+            .join(".locks")
+            .join(format!("{}.lock", key))
+    }
+
+    /// Takes a reference to the run venv for the dependency set `key` and marks it as the most
+    /// recently used
+    pub fn lease_python_venv(
+        &self,
+        runner_type: &RunnerType,
+        key: &str,
+    ) -> anyhow::Result<PythonVenvLease> {
+        let venv_key_folder_path = self.python_run_venvs_folder_path(runner_type).join(key);
+        let leases_folder_path = venv_key_folder_path.join("leases");
+        let _lock = lock_file(&self.python_venv_lock_file_path(runner_type, key))?;
+        std::fs::create_dir_all(&leases_folder_path)?;
+        let lease_file_path = leases_folder_path.join(nanoid::nanoid!());
+        std::fs::write(&lease_file_path, &self.context.execution_id)?;
+        std::fs::write(venv_key_folder_path.join("last-used"), "")?;
+        log::info!("leased python venv {}", key);
+        Ok(PythonVenvLease {
+            venv_folder_path: venv_key_folder_path.join("venv"),
+            lease_file_path,
+        })
+    }
+
+    /// Runs that are using the run venv for the dependency set `key`
+    pub fn python_venv_references(&self, runner_type: &RunnerType, key: &str) -> usize {
+        active_venv_leases(&self.python_run_venvs_folder_path(runner_type).join(key))
     }
-    pub fn python_run_docker_uv_cache_folder_path(&self) -> std::path::PathBuf {
+
+    /// Creates the leased venv with `create` unless it's ready. Concurrent callers for the same
+    /// venv wait for the one creating it instead of installing over each other. The creation holds
+    /// an OS lock, so it's only taken over once its process dies, however long the install takes.
+    pub async fn ensure_python_venv(
+        &self,
+        lease: &PythonVenvLease,
+        create: impl Future<Output = anyhow::Result<()>>,
+    ) -> anyhow::Result<()> {
+        let venv_key_folder_path = lease.venv_folder_path.parent().unwrap().to_path_buf();
+        let ready_file_path = venv_key_folder_path.join("ready");
+        let creating_lock_file_path = venv_key_folder_path.join("creating.lock");
+        loop {
+            if ready_file_path.exists() {
+                return Ok(());
+            }
+            match try_lock_file(&creating_lock_file_path)? {
+                Some(_lock) => {
+                    // Another caller may have finished between the check and the lock
+                    if ready_file_path.exists() {
+                        return Ok(());
+                    }
+                    log::info!("creating python venv {}", venv_key_folder_path.display());
+                    create.await?;
+                    std::fs::write(&ready_file_path, "")?;
+                    return Ok(());
+                }
+                None => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+    }
+
+    /// Removes the least recently used run venvs nobody is using until at most `max_venvs` are
+    /// left, `keep` is never removed. Venvs left half removed by an interrupted eviction are
+    /// removed too.
+    ///
+    /// # Returns
+    ///
+    /// The keys of the removed venvs
+    pub fn evict_python_venvs(
+        &self,
+        runner_type: &RunnerType,
+        max_venvs: usize,
+        keep: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let venvs_folder_path = self.python_run_venvs_folder_path(runner_type);
+        if !venvs_folder_path.exists() {
+            return Ok(Vec::new());
+        }
+        // Evictions run one at a time, so the venvs being evicted are only left by interrupted ones
+        let _eviction_lock = lock_file(&venvs_folder_path.join(".eviction.lock"))?;
+        let mut venvs = Vec::new();
+        for entry in std::fs::read_dir(&venvs_folder_path)? {
+            let entry = entry?;
+            let key = entry.file_name().to_string_lossy().to_string();
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if key.starts_with(".evicting-") {
+                log::info!("removing interrupted python venv eviction {}", key);
+                if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                    log::warn!("failed to remove {}: {}", key, e);
+                }
+                continue;
+            }
+            if key.starts_with('.') {
+                continue;
+            }
+            let last_used = match std::fs::metadata(entry.path().join("last-used")) {
+                Ok(metadata) => metadata.modified()?,
+                Err(_) => entry.metadata()?.modified()?,
+            };
+            venvs.push((last_used, key));
+        }
+        venvs.sort();
+
+        let mut evicted = Vec::new();
+        let mut remaining = venvs.len();
+        for (_, key) in venvs {
+            if remaining <= max_venvs {
+                break;
+            }
+            if key == keep {
+                continue;
+            }
+            let venv_key_folder_path = venvs_folder_path.join(&key);
+            let evicting_folder_path =
+                venvs_folder_path.join(format!(".evicting-{}-{}", key, nanoid::nanoid!()));
+            {
+                // No run can lease the venv while it's checked and moved out of the way
+                let _lock = lock_file(&self.python_venv_lock_file_path(runner_type, &key))?;
+                if active_venv_leases(&venv_key_folder_path) > 0 {
+                    continue;
+                }
+                std::fs::rename(&venv_key_folder_path, &evicting_folder_path)?;
+            }
+            log::info!("evicting python venv {}", key);
+            std::fs::remove_dir_all(&evicting_folder_path)?;
+            evicted.push(key);
+            remaining -= 1;
+        }
+        Ok(evicted)
+    }
+
+    pub fn python_run_docker_uv_cache_folder_path(&self) -> PathBuf {
         self.global_cache_folder_path.join("uv-cache-docker")
     }
 
-    pub fn python_check_venv_folder_path(&self) -> std::path::PathBuf {
+    pub fn python_check_venv_folder_path(&self) -> PathBuf {
         self.cache_folder_path.join("python-check-venv")
     }
+    pub fn python_check_docker_venv_folder_path(&self) -> PathBuf {
+        self.cache_folder_path.join("python-check-docker-venv")
+    }
+
+    /// Interpreters installed by `PythonRunner::install_python_interpreters` for host runs
+    pub fn python_interpreters_folder_path(&self) -> PathBuf {
+        self.global_cache_folder_path.join("python-interpreters")
+    }
+
+    pub fn python_locks_folder_path(&self) -> PathBuf {
+        self.global_cache_folder_path.join("python-locks")
+    }
+
+    /// `uv.lock` stored by `PythonRunner::lock` for the code with `key`
+    pub fn read_python_lock(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(
+            self.python_locks_folder_path()
+                .join(format!("{}.lock", key)),
+        )
+        .ok()
+    }
+
+    /// Stores a `uv.lock` under `key`, replacing the previous one atomically
+    pub fn write_python_lock(&self, key: &str, lock: &str) -> anyhow::Result<()> {
+        let python_locks_folder_path = self.python_locks_folder_path();
+        std::fs::create_dir_all(&python_locks_folder_path)?;
+        let temp_path =
+            python_locks_folder_path.join(format!(".tmp-{}-{}", key, nanoid::nanoid!()));
+        std::fs::write(&temp_path, lock)?;
+        std::fs::rename(
+            temp_path,
+            python_locks_folder_path.join(format!("{}.lock", key)),
+        )?;
+        Ok(())
+    }
+
     pub fn init_for_python(&self, pristine_cache: Option<bool>) -> anyhow::Result<()> {
         self.init(pristine_cache)?;
 
@@ -22,12 +257,8 @@ impl ExecutionStorage {
             log::error!("failed to create python check venv directory: {}", e);
             e
         })?;
-        std::fs::create_dir_all(self.python_run_host_venv_folder_path()).map_err(|e| {
-            log::error!("failed to create python run host venv directory: {}", e);
-            e
-        })?;
-        std::fs::create_dir_all(self.python_run_docker_venv_folder_path()).map_err(|e| {
-            log::error!("failed to create python run docker venv directory: {}", e);
+        std::fs::create_dir_all(self.python_check_docker_venv_folder_path()).map_err(|e| {
+            log::error!("failed to create python check docker venv directory: {}", e);
             e
         })?;
         std::fs::create_dir_all(self.python_run_docker_uv_cache_folder_path()).map_err(|e| {
@@ -37,3 +268,7 @@ impl ExecutionStorage {
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[path = "python_execution_storage.test.rs"]
+mod tests;
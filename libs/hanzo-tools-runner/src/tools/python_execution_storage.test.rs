@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::tools::{
+    code_files::CodeFiles, execution_context::ExecutionContext,
+    execution_storage::ExecutionStorage, runner_type::RunnerType,
+};
+
+#[test]
+fn python_venvs_are_shared_across_contexts() {
+    let storage = tempfile::tempdir().unwrap();
+    let code = CodeFiles {
+        files: HashMap::from([("main.py".to_string(), String::new())]),
+        entrypoint: "main.py".to_string(),
+    };
+    let first_context = ExecutionStorage::new(
+        code.clone(),
+        ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            context_id: "first".to_string(),
+            ..Default::default()
+        },
+    );
+    let second_context = ExecutionStorage::new(
+        code,
+        ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            context_id: "second".to_string(),
+            ..Default::default()
+        },
+    );
+
+    let first_lease = first_context
+        .lease_python_venv(&RunnerType::Host, "key")
+        .unwrap();
+    let second_lease = second_context
+        .lease_python_venv(&RunnerType::Host, "key")
+        .unwrap();
+    assert_eq!(
+        first_lease.venv_folder_path(),
+        second_lease.venv_folder_path()
+    );
+    assert_eq!(
+        first_context.python_venv_references(&RunnerType::Host, "key"),
+        2
+    );
+    assert_eq!(
+        first_context.python_venv_references(&RunnerType::Docker, "key"),
+        0
+    );
+
+    drop(first_lease);
+    assert_eq!(
+        second_context.python_venv_references(&RunnerType::Host, "key"),
+        1
+    );
+    drop(second_lease);
+    assert_eq!(
+        second_context.python_venv_references(&RunnerType::Host, "key"),
+        0
+    );
+}
+
+#[tokio::test]
+async fn python_venv_is_created_once() {
+    let storage = tempfile::tempdir().unwrap();
+    let execution_storage = ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.py".to_string(), String::new())]),
+            entrypoint: "main.py".to_string(),
+        },
+        ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            ..Default::default()
+        },
+    );
+    let creations = Arc::new(AtomicUsize::new(0));
+
+    let create = |lease| {
+        let execution_storage = execution_storage.clone();
+        let creations = creations.clone();
+        async move {
+            execution_storage
+                .ensure_python_venv(&lease, async {
+                    creations.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    Ok(())
+                })
+                .await
+        }
+    };
+    let leases = (0..3).map(|_| {
+        execution_storage
+            .lease_python_venv(&RunnerType::Host, "key")
+            .unwrap()
+    });
+    let results = futures::future::join_all(leases.map(create)).await;
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert_eq!(creations.load(Ordering::SeqCst), 1);
+
+    // A failed creation is retried by the next run
+    let lease = execution_storage
+        .lease_python_venv(&RunnerType::Host, "other-key")
+        .unwrap();
+    let result = execution_storage
+        .ensure_python_venv(&lease, async {
+            Err(anyhow::anyhow!("no matching version"))
+        })
+        .await;
+    assert!(result.is_err());
+    execution_storage
+        .ensure_python_venv(&lease, async {
+            creations.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(creations.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn python_venv_creation_lock_left_by_a_dead_process_is_taken_over() {
+    let storage = tempfile::tempdir().unwrap();
+    let execution_storage = ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.py".to_string(), String::new())]),
+            entrypoint: "main.py".to_string(),
+        },
+        ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            ..Default::default()
+        },
+    );
+    let lease = execution_storage
+        .lease_python_venv(&RunnerType::Host, "key")
+        .unwrap();
+    // The lock file of a creation whose process died is left behind unlocked
+    std::fs::write(
+        lease
+            .venv_folder_path()
+            .parent()
+            .unwrap()
+            .join("creating.lock"),
+        "",
+    )
+    .unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        execution_storage.ensure_python_venv(&lease, async { Ok(()) }),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+}
+
+#[test]
+fn least_recently_used_python_venvs_are_evicted() {
+    let storage = tempfile::tempdir().unwrap();
+    let execution_storage = ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.py".to_string(), String::new())]),
+            entrypoint: "main.py".to_string(),
+        },
+        ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            ..Default::default()
+        },
+    );
+    for key in ["oldest", "in-use", "older", "newest"] {
+        drop(
+            execution_storage
+                .lease_python_venv(&RunnerType::Host, key)
+                .unwrap(),
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let in_use_lease = execution_storage
+        .lease_python_venv(&RunnerType::Host, "in-use")
+        .unwrap();
+
+    let evicted = execution_storage
+        .evict_python_venvs(&RunnerType::Host, 2, "newest")
+        .unwrap();
+    assert_eq!(evicted, vec!["oldest", "older"]);
+    let venvs_folder_path = execution_storage.python_run_venvs_folder_path(&RunnerType::Host);
+    assert!(venvs_folder_path.join("in-use").exists());
+    assert!(venvs_folder_path.join("newest").exists());
+    assert!(!venvs_folder_path.join("oldest").exists());
+
+    drop(in_use_lease);
+    let evicted = execution_storage
+        .evict_python_venvs(&RunnerType::Host, 1, "newest")
+        .unwrap();
+    assert_eq!(evicted, vec!["in-use"]);
+}
+
+#[test]
+fn interrupted_python_venv_evictions_are_removed() {
+    let storage = tempfile::tempdir().unwrap();
+    let execution_storage = ExecutionStorage::new(
+        CodeFiles {
+            files: HashMap::from([("main.py".to_string(), String::new())]),
+            entrypoint: "main.py".to_string(),
+        },
+        ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            ..Default::default()
+        },
+    );
+    drop(
+        execution_storage
+            .lease_python_venv(&RunnerType::Host, "key")
+            .unwrap(),
+    );
+    let venvs_folder_path = execution_storage.python_run_venvs_folder_path(&RunnerType::Host);
+    let evicting_folder_path = venvs_folder_path.join(".evicting-old-key-abc");
+    std::fs::create_dir_all(evicting_folder_path.join("venv")).unwrap();
+
+    let evicted = execution_storage
+        .evict_python_venvs(&RunnerType::Host, 1, "key")
+        .unwrap();
+    assert!(evicted.is_empty());
+    assert!(!evicting_folder_path.exists());
+    assert!(venvs_folder_path.join("key").exists());
+}
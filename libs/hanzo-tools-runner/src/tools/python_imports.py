@@ -0,0 +1,83 @@
+# Prints the modules the Python files given as arguments import that aren't part of the standard
+# library of the interpreter running it or of the code itself, with the import mapping of pipreqs
+# when it's installed. `from a.b import c` is reported as `a.b.c` so submodules of namespace
+# packages like `google.cloud` can be told apart.
+# Imports are read with the `ast` module, files with syntax errors are left to the linters.
+import ast
+import json
+import os
+import sys
+
+
+def stdlib_modules():
+    names = getattr(sys, "stdlib_module_names", None)
+    if names is not None:
+        return set(names)
+    # Python < 3.10 doesn't list them
+    import pkgutil
+    import sysconfig
+
+    stdlib_path = sysconfig.get_paths()["stdlib"]
+    return set(sys.builtin_module_names) | {
+        module.name for module in pkgutil.iter_modules([stdlib_path])
+    }
+
+
+def local_modules(paths):
+    """Modules and packages shipped with the code, e.g. `utils.py` or `helpers/__init__.py`"""
+    modules = set()
+    for path in paths:
+        parts = path.replace(os.sep, "/").split("/")
+        if len(parts) > 1:
+            modules.add(parts[0])
+        elif parts[0].endswith(".py"):
+            modules.add(parts[0][: -len(".py")])
+    return modules
+
+
+def imported_modules(path):
+    with open(path, encoding="utf-8") as file:
+        source = file.read()
+    try:
+        tree = ast.parse(source, path)
+    except SyntaxError:
+        return
+    for node in ast.walk(tree):
+        if isinstance(node, ast.Import):
+            for alias in node.names:
+                yield alias.name
+        # Relative imports are always local
+        elif isinstance(node, ast.ImportFrom) and not node.level and node.module:
+            names = [alias.name for alias in node.names if alias.name != "*"]
+            if names:
+                for name in names:
+                    yield f"{node.module}.{name}"
+            else:
+                yield node.module
+
+
+def pipreqs_mapping():
+    """Distributions by import name from the mapping file shipped with pipreqs"""
+    try:
+        import pipreqs
+    except ImportError:
+        return {}
+    mapping_path = os.path.join(os.path.dirname(pipreqs.__file__), "mapping")
+    try:
+        with open(mapping_path, encoding="utf-8") as file:
+            lines = file.read().splitlines()
+    except OSError:
+        return {}
+    return dict(line.split(":", 1) for line in lines if ":" in line)
+
+
+if __name__ == "__main__":
+    paths = sys.argv[1:]
+    excluded = stdlib_modules() | local_modules(paths)
+    imports = {
+        module
+        for path in paths
+        for module in imported_modules(path)
+        if module.split(".")[0] not in excluded
+    }
+    print(json.dumps({"imports": sorted(imports), "mapping": pipreqs_mapping()}))
@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::dependency_policy::version_satisfies;
+
+/// Python versions tools can be pinned to, the code runner image ships all of them
+pub const SUPPORTED_PYTHON_VERSIONS: &[&str] =
+    &["3.8", "3.9", "3.10", "3.11", "3.12", "3.13", "3.14"];
+
+/// Python interpreter uv can run tools with
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PythonInterpreter {
+    pub version: String,
+    /// `cpython`, `pypy`, ...
+    pub implementation: String,
+    /// Executable of the interpreter, `None` when it's available for download but not installed
+    pub path: Option<PathBuf>,
+    /// Whether it was installed in the managed interpreters folder of the storage
+    pub managed: bool,
+}
+
+impl PythonInterpreter {
+    pub fn is_installed(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+/// Fails with the reason when `version` (`3.12` or `3.12.4`) isn't a supported Python version or
+/// doesn't satisfy the `requires-python` of the tool
+pub fn validate_python_version(version: &str, requires_python: Option<&str>) -> anyhow::Result<()> {
+    let minor_version = Regex::new(r"^(3\.\d+)(?:\.\d+)?$")
+        .unwrap()
+        .captures(version.trim())
+        .map(|captures| captures[1].to_string());
+    if !minor_version
+        .is_some_and(|minor_version| SUPPORTED_PYTHON_VERSIONS.contains(&minor_version.as_str()))
+    {
+        return Err(anyhow::anyhow!(
+            "unsupported python version {}, supported versions are {}",
+            version,
+            SUPPORTED_PYTHON_VERSIONS.join(", ")
+        ));
+    }
+    if let Some(requires_python) = requires_python {
+        if !version_satisfies(version.trim(), requires_python)? {
+            return Err(anyhow::anyhow!(
+                "python version {} doesn't satisfy the requires-python {} of the tool",
+                version,
+                requires_python
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Interpreters printed by `uv python list --output-format json`. Installed interpreters under
+/// `managed_folder_path` are the managed ones.
+pub fn parse_uv_python_list(
+    output: &str,
+    managed_folder_path: Option<&Path>,
+) -> anyhow::Result<Vec<PythonInterpreter>> {
+    let entries = serde_json::from_str::<Vec<Value>>(output)
+        .map_err(|e| anyhow::anyhow!("failed to parse uv python list output: {}", e))?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let path = entry["path"].as_str().map(PathBuf::from);
+            Some(PythonInterpreter {
+                version: entry["version"].as_str()?.to_string(),
+                implementation: entry["implementation"]
+                    .as_str()
+                    .unwrap_or("cpython")
+                    .to_string(),
+                managed: path.as_ref().is_some_and(|path| {
+                    managed_folder_path.is_some_and(|folder| path.starts_with(folder))
+                }),
+                path,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+#[path = "python_interpreters.test.rs"]
+mod tests;
@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+
+use crate::tools::python_interpreters::{
+    parse_uv_python_list, validate_python_version, PythonInterpreter,
+};
+
+#[test]
+fn test_validate_python_version() {
+    assert!(validate_python_version("3.12", Some(">=3.10")).is_ok());
+    assert!(validate_python_version("3.8.20", None).is_ok());
+    assert!(validate_python_version("3.14", Some(">=3.10, <3.15")).is_ok());
+    assert!(validate_python_version("3.12", Some("~=3.10")).is_ok());
+    assert!(validate_python_version("3.10.4", Some("~=3.10.2")).is_ok());
+    assert!(validate_python_version("3.11", Some("~=3.10.2")).is_err());
+
+    let error = validate_python_version("3.7", None).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "unsupported python version 3.7, supported versions are 3.8, 3.9, 3.10, 3.11, 3.12, 3.13, 3.14"
+    );
+    assert!(validate_python_version("python3", None).is_err());
+    assert!(validate_python_version("4.0", None).is_err());
+
+    let error = validate_python_version("3.9", Some(">=3.10")).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "python version 3.9 doesn't satisfy the requires-python >=3.10 of the tool"
+    );
+}
+
+#[test]
+fn test_parse_uv_python_list() {
+    let output = r#"[
+        {
+            "key": "cpython-3.13.1-linux-x86_64-gnu",
+            "version": "3.13.1",
+            "version_parts": { "major": 3, "minor": 13, "patch": 1 },
+            "path": null,
+            "symlink": null,
+            "url": "https://github.com/astral-sh/python-build-standalone/releases/download/cpython-3.13.1.tar.gz",
+            "os": "linux",
+            "variant": "default",
+            "implementation": "cpython",
+            "arch": "x86_64",
+            "libc": "gnu"
+        },
+        {
+            "key": "cpython-3.12.8-linux-x86_64-gnu",
+            "version": "3.12.8",
+            "path": "/storage/global-cache/python-interpreters/cpython-3.12.8-linux-x86_64-gnu/bin/python3.12",
+            "implementation": "cpython"
+        },
+        {
+            "key": "cpython-3.11.2-linux-x86_64-gnu",
+            "version": "3.11.2",
+            "path": "/usr/bin/python3.11",
+            "implementation": "cpython"
+        }
+    ]"#;
+    let interpreters = parse_uv_python_list(
+        output,
+        Some(Path::new("/storage/global-cache/python-interpreters")),
+    )
+    .unwrap();
+    assert_eq!(
+        interpreters,
+        vec![
+            PythonInterpreter {
+                version: "3.13.1".to_string(),
+                implementation: "cpython".to_string(),
+                path: None,
+                managed: false,
+            },
+            PythonInterpreter {
+                version: "3.12.8".to_string(),
+                implementation: "cpython".to_string(),
+                path: Some(PathBuf::from(
+                    "/storage/global-cache/python-interpreters/cpython-3.12.8-linux-x86_64-gnu/bin/python3.12"
+                )),
+                managed: true,
+            },
+            PythonInterpreter {
+                version: "3.11.2".to_string(),
+                implementation: "cpython".to_string(),
+                path: Some(PathBuf::from("/usr/bin/python3.11")),
+                managed: false,
+            },
+        ]
+    );
+    assert!(!interpreters[0].is_installed());
+    assert!(interpreters[2].is_installed());
+
+    assert!(parse_uv_python_list("error: no interpreters", None).is_err());
+}
@@ -1,23 +1,47 @@
 use serde_json::Value;
 use std::{
-    collections::{HashMap, HashSet},
-    path::{self, PathBuf},
-    sync::Arc,
+    collections::{BTreeMap, HashMap},
+    path::{self, Path, PathBuf},
     time::Duration,
 };
-use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    sync::Mutex,
-};
+use tokio::sync::mpsc::UnboundedSender;
 use toml_edit::DocumentMut;
 
+use crate::copy_assets::{pyright_package_folder_name, NODEJS_WHEEL_VERSION, PYRIGHT_VERSION};
 use crate::tools::{
-    check_utils::normalize_error_message,
+    audit::{
+        parse_python_audit_output, python_audit_paths, PYTHON_AUDIT_SCRIPT,
+        PYTHON_AUDIT_SCRIPT_FILE_NAME,
+    },
+    check_cache::{check_cache_key, docker_image_id},
+    check_result::CheckResult,
+    check_utils::{normalize_error_message, parse_pyright_json, parse_ruff_json},
+    contract::{
+        parse_python_contract_output, PYTHON_CONTRACT_SCRIPT, PYTHON_CONTRACT_SCRIPT_FILE_NAME,
+    },
+    dependency_policy::{python_dependencies, DeclaredDependency, DependencyPolicyError},
+    diagnostic::{Diagnostic, DiagnosticSeverity},
     execution_error::ExecutionError,
-    file_name_utils::{adapt_paths_in_value, normalize_for_docker_path},
+    file_changes::ChangeTracker,
+    file_name_utils::adapt_paths_in_value,
+    fix_result::FixResult,
+    hash_utils::sha256_hex,
+    home_transaction::HomeTransactionMode,
+    mounts::{docker_mount_params, docker_path_mappings, mount_envs},
+    offline::explain_offline_error,
     path_buf_ext::PathBufExt,
+    prepare::{with_prepare_timeout, PrepareEvent, PrepareProgress, PrepareStep},
+    process_utils::run_process,
+    python_dependencies::{
+        infer_python_dependencies, parse_python_imports_output, python_import_paths, PythonImports,
+        PYTHON_IMPORTS_SCRIPT, PYTHON_IMPORTS_SCRIPT_FILE_NAME,
+    },
+    python_execution_storage::PythonVenvLease,
+    python_interpreters::{parse_uv_python_list, validate_python_version, PythonInterpreter},
+    run_artifact::RunArtifact,
     run_result::RunResult,
     runner_type::resolve_runner_type,
+    sbom::{cyclonedx_json, python_venv_distributions, uv_lock_components},
 };
 
 use super::{
@@ -34,6 +58,15 @@ pub struct PythonRunner {
 impl PythonRunner {
     pub const MAX_EXECUTION_TIME_MS_INTERNAL_OPS: u64 = 1000;
     pub const PYPROJECT_TOML_FILE_NAME: &'static str = "pyproject.toml";
+    /// Dependency the runner adds to every `pyproject.toml` to serialize results
+    const RUNNER_DEPENDENCY: &'static str = "jsonpickle~=4.0.0";
+    pub const UV_LOCK_FILE_NAME: &'static str = "uv.lock";
+    /// Pins the interpreter uv creates the venvs with
+    pub const PYTHON_VERSION_FILE_NAME: &'static str = ".python-version";
+    /// Where the `find_links_path` wheelhouse is mounted in docker
+    const FIND_LINKS_DOCKER_PATH: &'static str = "/app/python-find-links";
+    /// Where the vendored pyright folder is mounted in docker
+    const PYRIGHT_DOCKER_PATH: &'static str = "/app/pyright";
 
     pub fn new(
         code_files: CodeFiles,
@@ -48,35 +81,165 @@ impl PythonRunner {
         }
     }
 
-    async fn ensure_ruff(&self) -> anyhow::Result<()> {
-        let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
+    /// Absolute path of the pinned ruff binary provisioned by `copy_assets`
+    fn ruff_binary_path(&self) -> anyhow::Result<String> {
+        let ruff_binary_path = path::absolute(self.options.ruff_binary_path.clone())?;
+        if !ruff_binary_path.exists() {
+            return Err(anyhow::anyhow!(
+                "ruff binary not found at {}",
+                ruff_binary_path.display()
+            ));
+        }
+        Ok(ruff_binary_path.to_string_lossy().to_string())
+    }
 
-        let mut install_ruff_command = tokio::process::Command::new(uv_binary_path);
-        let install_ruff_command = install_ruff_command
-            .args(["tool", "install", "ruff"])
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
+    /// Arguments of the uv commands that resolve packages, pointing them to the package mirrors
+    fn package_args(&self, runner_type: &RunnerType) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.options.offline {
+            args.push(String::from("--offline"));
+        }
+        if let Some(index_url) = &self.options.index_url {
+            args.extend([String::from("--index-url"), index_url.clone()]);
+        }
+        if let Some(find_links_path) = &self.options.find_links_path {
+            let find_links = match runner_type {
+                RunnerType::Host => path::absolute(find_links_path)
+                    .unwrap_or(find_links_path.clone())
+                    .to_string_lossy()
+                    .to_string(),
+                RunnerType::Docker => Self::FIND_LINKS_DOCKER_PATH.to_string(),
+            };
+            args.extend([String::from("--find-links"), find_links]);
+        }
+        args
+    }
 
-        log::info!("Installing ruff...");
-        let install_output = install_ruff_command
-            .spawn()?
-            .wait_with_output()
-            .await
-            .map_err(|e| anyhow::anyhow!("failed to install ruff: {}", e))?;
+    /// Environment variables pointing uv in the host to the managed interpreters folder, where it
+    /// finds the preinstalled interpreters and downloads missing ones. Only set when the tool pins
+    /// a version or interpreters were installed with `install_python_interpreters`, otherwise uv
+    /// keeps using the interpreters it already downloaded to its default folder.
+    fn python_envs(&self) -> Vec<(String, String)> {
+        let python_interpreters_folder_path =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone())
+                .python_interpreters_folder_path();
+        if self.options.python_version.is_none() && !python_interpreters_folder_path.exists() {
+            return Vec::new();
+        }
+        vec![(
+            String::from("UV_PYTHON_INSTALL_DIR"),
+            python_interpreters_folder_path
+                .to_string_lossy()
+                .to_string(),
+        )]
+    }
 
-        if !install_output.status.success() {
-            let stderr = String::from_utf8_lossy(&install_output.stderr);
-            log::error!("failed to install ruff: {}", stderr);
-            return Err(anyhow::anyhow!("failed to install ruff: {}", stderr));
+    /// Arguments quoted to be used in a docker bash script
+    fn script_args(args: &[String]) -> String {
+        args.iter()
+            .map(|arg| format!("'{}' ", arg.replace('\'', "'\\''")))
+            .collect()
+    }
+
+    /// `package_args` quoted to be used in a docker bash script
+    fn package_script_args(&self) -> String {
+        Self::script_args(&self.package_args(&RunnerType::Docker))
+    }
+
+    /// Mounts the `find_links_path` wheelhouse read-only where `package_args` points uv to
+    fn find_links_docker_mount_params(&self) -> Vec<String> {
+        match &self.options.find_links_path {
+            Some(find_links_path) => vec![
+                String::from("--mount"),
+                format!(
+                    r#"type=bind,readonly=true,source={},target={}"#,
+                    path::absolute(find_links_path)
+                        .unwrap_or(find_links_path.clone())
+                        .as_normalized_string(),
+                    Self::FIND_LINKS_DOCKER_PATH
+                ),
+            ],
+            None => Vec::new(),
         }
+    }
 
-        log::info!("ruff installed successfully.");
-        Ok(())
+    /// The vendored pyright folder as seen by the runner
+    fn pyright_folder(&self, runner_type: &RunnerType) -> String {
+        match runner_type {
+            RunnerType::Host => path::absolute(&self.options.pyright_folder_path)
+                .unwrap_or(self.options.pyright_folder_path.clone())
+                .to_string_lossy()
+                .to_string(),
+            RunnerType::Docker => Self::PYRIGHT_DOCKER_PATH.to_string(),
+        }
+    }
+
+    /// Arguments of `uv pip install` installing what the pinned pyright runs with: the vendored
+    /// node wheel when copy_assets provided the pyright folder, the pyright wheel from the package
+    /// index otherwise
+    fn pyright_install_args(&self, runner_type: &RunnerType) -> Vec<String> {
+        if !self.options.pyright_folder_path.exists() {
+            log::warn!(
+                "pyright not found at {}, installing pyright from the index",
+                self.options.pyright_folder_path.display()
+            );
+            return [
+                self.package_args(runner_type),
+                vec![format!("pyright[nodejs]=={}", PYRIGHT_VERSION)],
+            ]
+            .concat();
+        }
+        vec![
+            String::from("--no-index"),
+            String::from("--find-links"),
+            self.pyright_folder(runner_type),
+            format!("nodejs-wheel-binaries=={}", NODEJS_WHEEL_VERSION),
+        ]
+    }
+
+    /// Command running the pinned pyright in the check venv: the vendored npm package with the
+    /// vendored node, or the pyright wheel installed by `pyright_install_args` when it's missing
+    fn pyright_command_args(&self, runner_type: &RunnerType) -> Vec<String> {
+        if !self.options.pyright_folder_path.exists() {
+            return vec![String::from("pyright")];
+        }
+        vec![
+            String::from("python"),
+            String::from("-m"),
+            String::from("nodejs_wheel"),
+            format!(
+                "{}/{}/package/index.js",
+                self.pyright_folder(runner_type),
+                pyright_package_folder_name(PYRIGHT_VERSION)
+            ),
+        ]
+    }
+
+    /// Arguments of the `uv run` running pyright, which syncs the code dependencies into the check
+    /// venv from the configured package sources
+    fn pyright_run_args(&self, runner_type: &RunnerType) -> Vec<String> {
+        [
+            vec![String::from("run"), String::from("--quiet")],
+            self.package_args(runner_type),
+            self.pyright_command_args(runner_type),
+        ]
+        .concat()
+    }
+
+    fn pyright_docker_mount_params(&self) -> Vec<String> {
+        if !self.options.pyright_folder_path.exists() {
+            return Vec::new();
+        }
+        vec![
+            String::from("--mount"),
+            format!(
+                r#"type=bind,readonly=true,source={},target={}"#,
+                path::absolute(&self.options.pyright_folder_path)
+                    .unwrap_or(self.options.pyright_folder_path.clone())
+                    .as_normalized_string(),
+                Self::PYRIGHT_DOCKER_PATH
+            ),
+        ]
     }
 
     async fn ensure_pyright(&self, venv_path: PathBuf) -> anyhow::Result<()> {
@@ -88,7 +251,9 @@ impl PythonRunner {
 
         let mut install_ruff_command = tokio::process::Command::new(uv_binary_path);
         let install_ruff_command = install_ruff_command
-            .args(["pip", "install", "pyright"])
+            .args(["pip", "install"])
+            .args(self.pyright_install_args(&RunnerType::Host))
+            .envs(self.python_envs())
             .env(
                 "VIRTUAL_ENV",
                 venv_path.to_string_lossy().to_string().as_str(),
@@ -114,22 +279,131 @@ impl PythonRunner {
         Ok(())
     }
 
-    pub fn extend_with_pyproject_toml(code_files: CodeFiles) -> anyhow::Result<CodeFiles> {
+    /// Whether the entrypoint declares its dependencies in a PEP 723 `# /// script` block
+    fn has_script_block(code_files: &CodeFiles) -> bool {
+        code_files
+            .files
+            .get(&code_files.entrypoint)
+            .is_some_and(|content| content.lines().any(|line| line.trim() == "# /// script"))
+    }
+
+    /// Dependencies installed for code without a `# /// script` block: `options.dependencies` when
+    /// set, otherwise the distributions of the third-party modules the code imports.
+    /// Empty when the code declares its dependencies.
+    pub async fn inferred_dependencies(&self) -> anyhow::Result<Vec<String>> {
+        if Self::has_script_block(&self.code) {
+            return Ok(Vec::new());
+        }
+        let dependencies = match &self.options.dependencies {
+            Some(dependencies) => dependencies.clone(),
+            None => infer_python_dependencies(
+                &self.imported_modules().await?,
+                &self.options.import_mapping,
+            ),
+        };
+        log::info!("inferred dependencies: {:?}", dependencies);
+        Ok(dependencies)
+    }
+
+    /// Third-party modules the code imports, read by `PYTHON_IMPORTS_SCRIPT` with the interpreter
+    /// the code runs with so its standard library modules are left out. pipreqs is installed next
+    /// to the script for its import mapping, unless offline.
+    async fn imported_modules(&self) -> anyhow::Result<PythonImports> {
+        let paths = python_import_paths(&self.code);
+        if paths.is_empty() {
+            return Ok(PythonImports {
+                imports: Vec::new(),
+                mapping: HashMap::new(),
+            });
+        }
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        execution_storage.init_for_python(None)?;
+        let scratch = execution_storage.create_scratch_code_folder()?;
+        std::fs::write(
+            scratch.path().join(PYTHON_IMPORTS_SCRIPT_FILE_NAME),
+            PYTHON_IMPORTS_SCRIPT,
+        )?;
+
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        let mut uv_args = vec![
+            String::from("run"),
+            String::from("--quiet"),
+            String::from("--isolated"),
+            String::from("--no-project"),
+        ];
+        uv_args.extend(self.package_args(&runner_type));
+        if let Some(python_version) = &self.options.python_version {
+            uv_args.extend([String::from("--python"), python_version.trim().to_string()]);
+        }
+        if !self.options.offline {
+            uv_args.extend([String::from("--with"), String::from("pipreqs")]);
+        }
+        uv_args.extend([
+            String::from("python"),
+            PYTHON_IMPORTS_SCRIPT_FILE_NAME.to_string(),
+        ]);
+        uv_args.extend(paths);
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .args(uv_args)
+                    .envs(self.python_envs())
+                    .current_dir(scratch.path());
+                command
+            }
+            RunnerType::Docker => self.check_command_in_docker(
+                &execution_storage,
+                Some(scratch.path()),
+                &format!("uv {}", Self::script_args(&uv_args)),
+            ),
+        };
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = explain_offline_error(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+                self.options.offline,
+            );
+            log::error!("python imports error: {}", error_message);
+            return Err(anyhow::anyhow!(
+                "failed to infer dependencies: {}",
+                error_message
+            ));
+        }
+        parse_python_imports_output(&String::from_utf8_lossy(&output.stdout))
+            .map_err(|e| anyhow::anyhow!("failed to parse imports: {}", e))
+    }
+
+    /// Generates the `pyproject.toml` of the code from the entrypoint `# /// script` block or,
+    /// when there is none, from `inferred_dependencies`
+    pub fn extend_with_pyproject_toml(
+        code_files: CodeFiles,
+        inferred_dependencies: &[String],
+    ) -> anyhow::Result<CodeFiles> {
         let mut code_files = code_files.clone();
         let code_entrypoint = match code_files.files.get(&code_files.entrypoint.clone()) {
             Some(content) => content,
             None => return Err(anyhow::anyhow!("Code entrypoint file is empty")),
         };
 
-        let pyproject_toml_string = r#"
+        let pyproject_toml_string = format!(
+            r#"
 [project]
 name = "hanzo-tool"
 version = "0.0.1"
 dependencies = [
-    "jsonpickle~=4.0.0",
+    "{}",
 ]
 requires-python = ">=3.10"
-        "#;
+        "#,
+            Self::RUNNER_DEPENDENCY
+        );
         let mut pyproject_toml = pyproject_toml_string
             .parse::<DocumentMut>()
             .map_err(anyhow::Error::new)?;
@@ -170,10 +444,16 @@ requires-python = ">=3.10"
                 .insert(code_files.entrypoint.clone(), updated_code_entrypoint);
         }
 
-        let pyproject_toml_from_code_endpoint = script_lines
+        let mut pyproject_toml_from_code_endpoint = script_lines
             .join("\n")
             .parse::<DocumentMut>()
             .map_err(anyhow::Error::new)?;
+        if line_start.is_none() && !inferred_dependencies.is_empty() {
+            pyproject_toml_from_code_endpoint.insert(
+                "dependencies",
+                toml_edit::value(inferred_dependencies.iter().collect::<toml_edit::Array>()),
+            );
+        }
 
         // Merge everything from pyproject_toml_from_code_endpoint into pyproject_toml
         // except for dependencies which we'll handle separately
@@ -244,12 +524,432 @@ requires-python = ">=3.10"
         Ok(code_files)
     }
 
-    pub async fn check(&self) -> anyhow::Result<Vec<String>> {
-        let code = Self::extend_with_pyproject_toml(self.code.clone())
+    /// Generates the `pyproject.toml` of the code with the `inferred_dependencies` and, when
+    /// `python_version` is set, the `.python-version` pinning its interpreter. Fails when the pinned
+    /// version isn't supported or doesn't satisfy the `requires-python` of the code.
+    fn extend_with_project_files(
+        &self,
+        code_files: CodeFiles,
+        inferred_dependencies: &[String],
+    ) -> anyhow::Result<CodeFiles> {
+        let mut code_files = Self::extend_with_pyproject_toml(code_files, inferred_dependencies)?;
+        if let Some(python_version) = &self.options.python_version {
+            let pyproject_toml = code_files.files[Self::PYPROJECT_TOML_FILE_NAME]
+                .parse::<DocumentMut>()
+                .map_err(anyhow::Error::new)?;
+            let requires_python = pyproject_toml
+                .get("project")
+                .and_then(|project| project.get("requires-python"))
+                .and_then(|requires_python| requires_python.as_str());
+            validate_python_version(python_version, requires_python)?;
+            code_files.files.insert(
+                Self::PYTHON_VERSION_FILE_NAME.to_string(),
+                format!("{}\n", python_version.trim()),
+            );
+        }
+        Ok(code_files)
+    }
+
+    /// Fails with a `DependencyPolicyError` when a dependency of the generated `pyproject.toml`
+    /// violates `dependency_policy`, before anything is installed. The runner dependency is exempt.
+    fn enforce_dependency_policy(&self, code: &CodeFiles) -> anyhow::Result<()> {
+        let pyproject_toml = code
+            .files
+            .get(Self::PYPROJECT_TOML_FILE_NAME)
+            .cloned()
+            .unwrap_or_default();
+        let registry = self
+            .options
+            .index_url
+            .clone()
+            .unwrap_or(String::from("pypi"));
+        let dependencies: Vec<DeclaredDependency> =
+            python_dependencies(&pyproject_toml, &registry)?
+                .into_iter()
+                .filter(|dependency| {
+                    format!(
+                        "{}{}",
+                        dependency.name,
+                        dependency.version.clone().unwrap_or_default()
+                    ) != Self::RUNNER_DEPENDENCY
+                })
+                .collect();
+        self.options.dependency_policy.enforce(&dependencies)?;
+        Ok(())
+    }
+
+    /// Flags risky APIs in the code (subprocesses, shell commands, native code, unpickling,
+    /// dynamic code evaluation and imports) with the severities of `audit_policy`, without running
+    /// it. The code is parsed with the Python `ast` module, so imports and aliases are resolved.
+    pub async fn audit(&self) -> anyhow::Result<Vec<Diagnostic>> {
+        let paths = python_audit_paths(&self.code);
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        execution_storage.init_for_python(None)?;
+        let scratch = execution_storage.create_scratch_code_folder()?;
+        std::fs::write(
+            scratch.path().join(PYTHON_AUDIT_SCRIPT_FILE_NAME),
+            PYTHON_AUDIT_SCRIPT,
+        )?;
+
+        // The script only needs an interpreter, not the code dependencies
+        let mut command = match resolve_runner_type(self.options.force_runner_type.clone()) {
+            RunnerType::Host => {
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .args(["run", "--quiet", "--isolated", "--no-project", "python"])
+                    .arg(PYTHON_AUDIT_SCRIPT_FILE_NAME)
+                    .args(&paths)
+                    .envs(self.python_envs())
+                    .current_dir(scratch.path());
+                command
+            }
+            RunnerType::Docker => self.check_command_in_docker(
+                &execution_storage,
+                Some(scratch.path()),
+                &format!(
+                    "uv run --quiet --isolated --no-project python {} {}",
+                    PYTHON_AUDIT_SCRIPT_FILE_NAME,
+                    Self::script_args(&paths)
+                ),
+            ),
+        };
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = String::from_utf8_lossy(&output.stderr).to_string();
+            log::error!("python audit error: {}", error_message);
+            return Err(anyhow::anyhow!("failed to audit code: {}", error_message));
+        }
+        parse_python_audit_output(
+            &String::from_utf8_lossy(&output.stdout),
+            &self.options.audit_policy,
+        )
+        .map_err(|e| anyhow::anyhow!("failed to parse audit output: {}", e))
+    }
+
+    /// Checks the code with ruff and, when ruff finds no errors, with pyright.
+    /// Results are cached by code, resolved dependencies and tool versions.
+    pub async fn check(&self) -> anyhow::Result<CheckResult> {
+        let inferred_dependencies = self.inferred_dependencies().await?;
+        let mut code = self
+            .extend_with_pyproject_toml_and_lock(&inferred_dependencies)
             .map_err(|e| anyhow::anyhow!("failed to create pyproject.toml: {}", e))?;
+        self.enforce_dependency_policy(&code)?;
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+
+        // Unlocked code is resolved first so the key covers the versions the check installs, the
+        // check runs uncached when they can't be resolved
+        let mut cacheable = true;
+        if !code.files.contains_key(Self::UV_LOCK_FILE_NAME) {
+            match self.resolve_lock(&code, &runner_type).await {
+                Ok(lock) => {
+                    code.files.insert(Self::UV_LOCK_FILE_NAME.to_string(), lock);
+                }
+                Err(e) => {
+                    log::warn!("not caching check result, dependencies not resolved: {}", e);
+                    cacheable = false;
+                }
+            }
+        }
         let execution_storage = ExecutionStorage::new(code.clone(), self.options.context.clone());
         execution_storage.init_for_python(None)?;
 
+        let cache_key = check_cache_key(&code, &self.check_cache_inputs(&runner_type).await);
+        if cacheable {
+            if let Some(check_result) = execution_storage.read_cached_check_result(&cache_key) {
+                log::info!("using cached check result {}", cache_key);
+                return Ok(check_result);
+            }
+        }
+
+        let check_result = self
+            .run_check(&execution_storage, runner_type.clone())
+            .await?;
+        if cacheable {
+            if let Err(e) = execution_storage.write_cached_check_result(&cache_key, &check_result) {
+                log::warn!("failed to cache check result: {}", e);
+            }
+        }
+        Ok(check_result)
+    }
+
+    /// Ruff and pyright versions the check result depends on, besides the code
+    async fn check_cache_inputs(&self, runner_type: &RunnerType) -> BTreeMap<String, String> {
+        let ruff_version = match runner_type {
+            RunnerType::Host => {
+                let ruff_binary_path = path::absolute(self.options.ruff_binary_path.clone())
+                    .unwrap_or_else(|_| self.options.ruff_binary_path.clone());
+                match tokio::process::Command::new(ruff_binary_path)
+                    .arg("--version")
+                    .kill_on_drop(true)
+                    .output()
+                    .await
+                {
+                    Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+                    Err(e) => {
+                        log::warn!("failed to get ruff version: {}", e);
+                        String::from("unknown")
+                    }
+                }
+            }
+            // The image pins the ruff version
+            RunnerType::Docker => {
+                docker_image_id(&self.options.code_runner_docker_image_name).await
+            }
+        };
+        BTreeMap::from([
+            (String::from("ruff"), ruff_version),
+            (String::from("pyright"), PYRIGHT_VERSION.to_string()),
+        ])
+    }
+
+    async fn run_check(
+        &self,
+        execution_storage: &ExecutionStorage,
+        runner_type: RunnerType,
+    ) -> anyhow::Result<CheckResult> {
+        // Reported file paths are relative to where the code folder is seen by the tools
+        let code_folder_path = match runner_type {
+            RunnerType::Host => {
+                self.prepare_check_in_host(execution_storage).await?;
+                execution_storage.code_folder_path.clone()
+            }
+            RunnerType::Docker => PathBuf::from(format!(
+                "/app/{}",
+                execution_storage.relative_to_root(execution_storage.code_folder_path.clone())
+            )),
+        };
+
+        log::info!("Starting code check with ruff...");
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let mut command = tokio::process::Command::new(self.ruff_binary_path()?);
+                command
+                    .args(["check", "--output-format", "json"])
+                    .current_dir(execution_storage.code_folder_path.clone());
+                command
+            }
+            RunnerType::Docker => self.check_command_in_docker(
+                execution_storage,
+                None,
+                "ruff check --output-format json",
+            ),
+        };
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = match command.spawn() {
+            Ok(child) => child.wait_with_output().await?,
+            Err(e) => {
+                let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
+                log::error!("{}", error_msg);
+                return Err(anyhow::anyhow!("{}", error_msg));
+            }
+        };
+
+        let lint_message = String::from_utf8(output.stdout)?;
+        log::info!("python ruff lint message: {}", lint_message);
+        let ruff_diagnostics = match parse_ruff_json(&lint_message, &code_folder_path) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                log::warn!("failed to parse ruff output: {}", e);
+                let lint_message = normalize_error_message(
+                    String::from_utf8_lossy(&output.stderr).to_string() + &lint_message,
+                    &code_folder_path,
+                );
+                vec![Diagnostic::unparsed("ruff", lint_message)]
+            }
+        };
+        // Ruff errors are syntax errors, neither pyright nor the contract check can parse the code
+        let ruff_result = CheckResult::from_diagnostics(ruff_diagnostics);
+        if ruff_result.has_errors() {
+            return Ok(ruff_result);
+        }
+
+        log::info!("starting pyright check");
+        // `uv run` syncs the code dependencies into the check venv and writes the uv.lock of
+        // unlocked code into the project, so the project is a scratch copy of the code snapshot
+        let scratch = execution_storage.create_scratch_code_folder()?;
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .args(self.pyright_run_args(&RunnerType::Host))
+                    .args([
+                        "--outputjson",
+                        execution_storage
+                            .code_entrypoint_file_path
+                            .to_string_lossy()
+                            .to_string()
+                            .as_str(),
+                    ])
+                    .envs(self.python_envs())
+                    .env(
+                        "VIRTUAL_ENV",
+                        execution_storage
+                            .python_check_venv_folder_path()
+                            .to_string_lossy()
+                            .to_string()
+                            .as_str(),
+                    )
+                    .env(
+                        "UV_PROJECT_ENVIRONMENT",
+                        execution_storage
+                            .python_check_venv_folder_path()
+                            .to_string_lossy()
+                            .to_string()
+                            .as_str(),
+                    )
+                    .current_dir(scratch.path());
+                command
+            }
+            // The check venv is created inside the container so its interpreter paths are valid there
+            RunnerType::Docker => self.check_command_in_docker(
+                execution_storage,
+                Some(scratch.path()),
+                &format!(
+                    "uv venv --quiet --allow-existing \"$VIRTUAL_ENV\" && uv pip install --quiet {}&& uv {}--outputjson /app/{}",
+                    Self::script_args(&self.pyright_install_args(&RunnerType::Docker)),
+                    Self::script_args(&self.pyright_run_args(&RunnerType::Docker)),
+                    execution_storage
+                        .relative_to_root(execution_storage.code_entrypoint_file_path.clone())
+                ),
+            ),
+        };
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = match command.spawn() {
+            Ok(child) => match child.wait_with_output().await {
+                Ok(output) => output,
+                Err(e) => {
+                    log::error!("failed to get command output: {}", e);
+                    return Err(anyhow::anyhow!(
+                        "failed to get pyright command output: {}",
+                        e
+                    ));
+                }
+            },
+            Err(e) => {
+                let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
+                log::error!("{}", error_msg);
+                return Err(anyhow::anyhow!("{}", error_msg));
+            }
+        };
+        log::info!("pyright check finished");
+        let pyright_message = String::from_utf8(output.stdout)?;
+        let pyright_diagnostics = match parse_pyright_json(&pyright_message, &code_folder_path) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) if !output.status.success() => {
+                log::warn!("failed to parse pyright output: {}", e);
+                vec![Diagnostic::unparsed(
+                    "pyright",
+                    String::from_utf8_lossy(&output.stderr).to_string() + &pyright_message,
+                )]
+            }
+            Err(e) => {
+                log::warn!("failed to parse pyright output: {}", e);
+                Vec::new()
+            }
+        };
+
+        let contract_diagnostics = self
+            .check_contract(execution_storage, &runner_type, scratch.path())
+            .await?;
+
+        let mut check_result = CheckResult::from_diagnostics(pyright_diagnostics);
+        check_result.warnings.extend(ruff_result.warnings);
+        check_result.extend(contract_diagnostics);
+        log::info!(
+            "python check finished with {} errors and {} warnings",
+            check_result.errors.len(),
+            check_result.warnings.len()
+        );
+        Ok(check_result)
+    }
+
+    /// Checks the entrypoint exposes what the runner calls with `PYTHON_CONTRACT_SCRIPT`, run in the
+    /// check venv already synced by the pyright check
+    async fn check_contract(
+        &self,
+        execution_storage: &ExecutionStorage,
+        runner_type: &RunnerType,
+        scratch_folder_path: &Path,
+    ) -> anyhow::Result<Vec<Diagnostic>> {
+        std::fs::write(
+            scratch_folder_path.join(PYTHON_CONTRACT_SCRIPT_FILE_NAME),
+            PYTHON_CONTRACT_SCRIPT,
+        )?;
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .args([
+                        "run",
+                        "--no-sync",
+                        "python",
+                        PYTHON_CONTRACT_SCRIPT_FILE_NAME,
+                    ])
+                    .arg(execution_storage.code_entrypoint_file_path.clone())
+                    .envs(self.python_envs())
+                    .env(
+                        "VIRTUAL_ENV",
+                        execution_storage.python_check_venv_folder_path(),
+                    )
+                    .env(
+                        "UV_PROJECT_ENVIRONMENT",
+                        execution_storage.python_check_venv_folder_path(),
+                    )
+                    .current_dir(scratch_folder_path);
+                command
+            }
+            RunnerType::Docker => self.check_command_in_docker(
+                execution_storage,
+                Some(scratch_folder_path),
+                &format!(
+                    "uv run --quiet --no-sync python {} /app/{}",
+                    PYTHON_CONTRACT_SCRIPT_FILE_NAME,
+                    execution_storage
+                        .relative_to_root(execution_storage.code_entrypoint_file_path.clone())
+                ),
+            ),
+        };
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        let contract_output = String::from_utf8_lossy(&output.stdout).to_string();
+        match parse_python_contract_output(&self.code.entrypoint, &contract_output) {
+            Ok(diagnostics) if output.status.success() => Ok(diagnostics),
+            _ => {
+                let error_message = String::from_utf8_lossy(&output.stderr).to_string();
+                log::error!("python contract check error: {}", error_message);
+                Ok(vec![Diagnostic::unparsed(
+                    "contract",
+                    error_message + &contract_output,
+                )])
+            }
+        }
+    }
+
+    /// Creates the check venv and installs the pinned pyright in the host
+    async fn prepare_check_in_host(
+        &self,
+        execution_storage: &ExecutionStorage,
+    ) -> anyhow::Result<()> {
         let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())
             .unwrap()
             .to_str()
@@ -259,6 +959,7 @@ requires-python = ">=3.10"
         let mut create_check_venv_command = tokio::process::Command::new(uv_binary_path);
         let command = create_check_venv_command
             .env_clear()
+            .envs(self.python_envs())
             .args([
                 "venv",
                 execution_storage
@@ -277,110 +978,741 @@ requires-python = ">=3.10"
             }
         };
 
-        self.ensure_ruff().await?;
         self.ensure_pyright(execution_storage.python_check_venv_folder_path())
             .await?;
+        Ok(())
+    }
 
-        log::info!("Starting code check with ruff...");
-        let mut command = tokio::process::Command::new("ruff");
+    /// Runs a check script in the code runner image with the code, the check venv and the uv cache
+    /// mounted. The script runs in the code folder or, when given, in the mounted `project_folder_path`.
+    fn check_command_in_docker(
+        &self,
+        execution_storage: &ExecutionStorage,
+        project_folder_path: Option<&Path>,
+        script: &str,
+    ) -> tokio::process::Command {
+        let code_folder =
+            execution_storage.relative_to_root(execution_storage.code_folder_path.clone());
+        let venv_folder = execution_storage
+            .relative_to_root(execution_storage.python_check_docker_venv_folder_path());
+        let uv_cache_folder = execution_storage
+            .relative_to_global_cache(execution_storage.python_run_docker_uv_cache_folder_path());
+        let mount_dirs = [
+            (
+                execution_storage.code_folder_path.as_normalized_string(),
+                code_folder.clone(),
+            ),
+            (
+                execution_storage
+                    .python_check_docker_venv_folder_path()
+                    .as_normalized_string(),
+                venv_folder.clone(),
+            ),
+            (
+                execution_storage
+                    .python_run_docker_uv_cache_folder_path()
+                    .as_normalized_string(),
+                uv_cache_folder.clone(),
+            ),
+        ];
+        let mut args = vec![String::from("run"), String::from("--rm")];
+        for (dir, relative_path) in mount_dirs {
+            args.extend([
+                String::from("--mount"),
+                format!(r#"type=bind,source={},target=/app/{}"#, dir, relative_path),
+            ]);
+        }
+        let workdir = match project_folder_path {
+            Some(project_folder_path) => {
+                let project_folder =
+                    execution_storage.relative_to_root(project_folder_path.to_path_buf());
+                args.extend([
+                    String::from("--mount"),
+                    format!(
+                        r#"type=bind,source={},target=/app/{}"#,
+                        project_folder_path.to_path_buf().as_normalized_string(),
+                        project_folder
+                    ),
+                ]);
+                project_folder
+            }
+            None => code_folder,
+        };
+        args.extend(self.find_links_docker_mount_params());
+        args.extend(self.pyright_docker_mount_params());
+        args.extend([
+            String::from("-e"),
+            format!("VIRTUAL_ENV=/app/{}", venv_folder),
+            String::from("-e"),
+            format!("UV_PROJECT_ENVIRONMENT=/app/{}", venv_folder),
+            String::from("-e"),
+            format!("UV_CACHE_DIR=/app/{}", uv_cache_folder),
+            String::from("--workdir"),
+            format!("/app/{}", workdir),
+            self.options.code_runner_docker_image_name.clone(),
+            String::from("/bin/bash"),
+            String::from("-c"),
+            script.to_string(),
+        ]);
+        log::info!("checking code in docker with args: {:?}", args);
+        let mut command = tokio::process::Command::new("docker");
+        command.args(args);
         command
-            .args(["check"])
-            .current_dir(execution_storage.code_folder_path.clone())
+    }
+
+    fn lint_rules_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.options.lint_rules.include.is_empty() {
+            args.push(String::from("--extend-select"));
+            args.push(self.options.lint_rules.include.join(","));
+        }
+        if !self.options.lint_rules.exclude.is_empty() {
+            args.push(String::from("--ignore"));
+            args.push(self.options.lint_rules.exclude.join(","));
+        }
+        args
+    }
+
+    /// Lints the code with `ruff check` and the rules configured in the options.
+    /// Configuration files around the code are ignored so only the options apply.
+    ///
+    /// # Returns
+    ///
+    /// Rule violations as warnings and syntax errors as errors
+    pub async fn lint(&self) -> anyhow::Result<Vec<Diagnostic>> {
+        let ruff_binary_path = self.ruff_binary_path()?;
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let mut command = tokio::process::Command::new(ruff_binary_path);
+        command
+            .args([
+                "check",
+                "--output-format",
+                "json",
+                "--no-cache",
+                "--isolated",
+                "--exit-zero",
+            ])
+            .args(self.lint_rules_args())
+            .current_dir(scratch.path())
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .kill_on_drop(true);
-        let output = match command.spawn() {
-            Ok(child) => child.wait_with_output().await?,
-            Err(e) => {
-                let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
-                log::error!("{}", error_msg);
-                return Err(anyhow::anyhow!("{}", error_msg));
-            }
-        };
+        let output = command.spawn()?.wait_with_output().await?;
+        let lint_output = String::from_utf8(output.stdout)?;
+        log::info!("python ruff lint output: {}", lint_output);
+        let diagnostics =
+            parse_ruff_json(&lint_output, &scratch.path().to_path_buf()).map_err(|e| {
+                anyhow::anyhow!(
+                    "failed to parse ruff output: {} {}",
+                    e,
+                    String::from_utf8_lossy(&output.stderr)
+                )
+            })?;
+        // Ruff reports syntax errors without a rule code
+        Ok(diagnostics
+            .into_iter()
+            .map(|diagnostic| Diagnostic {
+                severity: match diagnostic.code {
+                    Some(_) => DiagnosticSeverity::Warning,
+                    None => DiagnosticSeverity::Error,
+                },
+                ..diagnostic
+            })
+            .collect())
+    }
 
-        let mut lint_message = String::from_utf8(output.stdout)?;
-        lint_message = normalize_error_message(lint_message, &execution_storage.code_folder_path);
-        let lint_message_lines: Vec<String> = lint_message.lines().map(|s| s.to_string()).collect();
+    /// Applies the `ruff check --fix` autofixes in a copy of the code
+    ///
+    /// # Returns
+    ///
+    /// The fixed code files and a unified diff of every changed file. The runner code is not modified.
+    pub async fn fix(&self) -> anyhow::Result<FixResult> {
+        let ruff_binary_path = self.ruff_binary_path()?;
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let mut command = tokio::process::Command::new(ruff_binary_path);
+        command
+            .args(["check", "--fix", "--no-cache", "--isolated", "--exit-zero"])
+            .args(self.lint_rules_args())
+            .current_dir(scratch.path())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = normalize_error_message(
+                String::from_utf8(output.stderr)?,
+                &scratch.path().to_path_buf(),
+            );
+            log::error!("ruff fix error: {}", error_message);
+            return Err(anyhow::anyhow!("failed to fix code: {}", error_message));
+        }
+        let fixed = self.code.read_from_folder(scratch.path())?;
+        Ok(FixResult::new(&self.code, fixed))
+    }
 
-        for line in &lint_message_lines {
-            log::info!("python ruff lint message: {}", line);
+    /// Formats the code with `ruff format`
+    ///
+    /// # Returns
+    ///
+    /// The same code files with their formatted content
+    pub async fn format(&self) -> anyhow::Result<CodeFiles> {
+        let ruff_binary_path = self.ruff_binary_path()?;
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let mut command = tokio::process::Command::new(ruff_binary_path);
+        command
+            .args(["format", "--no-cache", "--isolated"])
+            .current_dir(scratch.path())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = normalize_error_message(
+                String::from_utf8(output.stderr)?,
+                &scratch.path().to_path_buf(),
+            );
+            log::error!("ruff format error: {}", error_message);
+            return Err(anyhow::anyhow!("failed to format code: {}", error_message));
         }
+        Ok(self.code.read_from_folder(scratch.path())?)
+    }
+
+    /// Code without its lockfile, lockfiles are stored by the hash of this code with its generated
+    /// `pyproject.toml`
+    fn code_without_lock(&self) -> CodeFiles {
+        let mut code = self.code.clone();
+        code.files.remove(Self::UV_LOCK_FILE_NAME);
+        code
+    }
 
-        // When success, ruff returns 1 line with "All check passed!"
-        if lint_message_lines.len() > 1 {
-            return Ok(lint_message_lines);
+    /// Code with the generated `pyproject.toml` and, when there is one, its `uv.lock`.
+    /// A lockfile supplied with the code wins over the one stored by `lock`.
+    fn extend_with_pyproject_toml_and_lock(
+        &self,
+        inferred_dependencies: &[String],
+    ) -> anyhow::Result<CodeFiles> {
+        let mut code = self.extend_with_project_files(self.code.clone(), inferred_dependencies)?;
+        if !code.files.contains_key(Self::UV_LOCK_FILE_NAME) {
+            // Keyed by the generated pyproject.toml too, so overriding the dependencies relocks
+            let lock = ExecutionStorage::new(self.code.clone(), self.options.context.clone())
+                .read_python_lock(&code.content_hash());
+            if let Some(lock) = lock {
+                log::info!("using stored uv.lock");
+                code.files.insert(Self::UV_LOCK_FILE_NAME.to_string(), lock);
+            }
         }
+        Ok(code)
+    }
 
-        log::info!("starting pyright check");
-        let mut command = tokio::process::Command::new("uv");
+    /// Resolves the dependencies of the generated `pyproject.toml` into a `uv.lock` and stores it
+    /// for the code, so later runs install exactly the same versions with `--frozen`
+    ///
+    /// # Returns
+    ///
+    /// The code files with the `uv.lock` file added
+    pub async fn lock(&self) -> anyhow::Result<CodeFiles> {
+        let code = self.extend_with_project_files(
+            self.code_without_lock(),
+            &self.inferred_dependencies().await?,
+        )?;
+        self.enforce_dependency_policy(&code)?;
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        let lock = self.resolve_lock(&code, &runner_type).await?;
+        ExecutionStorage::new(code.clone(), self.options.context.clone())
+            .write_python_lock(&code.content_hash(), &lock)?;
+        let mut code_files = self.code.clone();
+        code_files
+            .files
+            .insert(Self::UV_LOCK_FILE_NAME.to_string(), lock);
+        Ok(code_files)
+    }
+
+    /// Resolves the dependencies of `code`, which has the generated `pyproject.toml`, into the
+    /// content of a `uv.lock`
+    async fn resolve_lock(
+        &self,
+        code: &CodeFiles,
+        runner_type: &RunnerType,
+    ) -> anyhow::Result<String> {
+        let execution_storage = ExecutionStorage::new(code.clone(), self.options.context.clone());
+        execution_storage.init_for_python(None)?;
+        let scratch = execution_storage.create_scratch_code_folder()?;
+
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .arg("lock")
+                    .args(self.package_args(&RunnerType::Host))
+                    .envs(self.python_envs())
+                    .current_dir(scratch.path());
+                command
+            }
+            RunnerType::Docker => {
+                let scratch_folder =
+                    execution_storage.relative_to_root(scratch.path().to_path_buf());
+                let uv_cache_folder = execution_storage.relative_to_global_cache(
+                    execution_storage.python_run_docker_uv_cache_folder_path(),
+                );
+                let mut command = tokio::process::Command::new("docker");
+                command.args(["run", "--rm"]);
+                command.args(self.find_links_docker_mount_params());
+                command.args([
+                    String::from("--mount"),
+                    format!(
+                        r#"type=bind,source={},target=/app/{}"#,
+                        scratch.path().to_path_buf().as_normalized_string(),
+                        scratch_folder
+                    ),
+                    String::from("--mount"),
+                    format!(
+                        r#"type=bind,source={},target=/app/{}"#,
+                        execution_storage
+                            .python_run_docker_uv_cache_folder_path()
+                            .as_normalized_string(),
+                        uv_cache_folder
+                    ),
+                    String::from("-e"),
+                    format!("UV_CACHE_DIR=/app/{}", uv_cache_folder),
+                    String::from("--workdir"),
+                    format!("/app/{}", scratch_folder),
+                    self.options.code_runner_docker_image_name.clone(),
+                    String::from("/bin/bash"),
+                    String::from("-c"),
+                    format!("uv lock {}", self.package_script_args()),
+                ]);
+                command
+            }
+        };
         command
-            .args([
-                "run",
-                "python",
-                "-m",
-                "pyright",
-                "--level=error",
-                execution_storage
-                    .code_entrypoint_file_path
-                    .to_string_lossy()
-                    .to_string()
-                    .as_str(),
-            ])
-            .env(
-                "VIRTUAL_ENV",
-                execution_storage
-                    .python_check_venv_folder_path()
-                    .to_string_lossy()
-                    .to_string()
-                    .as_str(),
-            )
-            .env(
-                "UV_PROJECT_ENVIRONMENT",
-                execution_storage
-                    .python_check_venv_folder_path()
-                    .to_string_lossy()
-                    .to_string()
-                    .as_str(),
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        log::info!("locking python dependencies: {:?}", command);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = explain_offline_error(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+                self.options.offline,
+            );
+            log::error!("uv lock error: {}", error_message);
+            return Err(anyhow::anyhow!(
+                "failed to lock dependencies: {}",
+                error_message
+            ));
+        }
+
+        Ok(std::fs::read_to_string(
+            scratch.path().join(Self::UV_LOCK_FILE_NAME),
+        )?)
+    }
+
+    /// Software bill of materials of the code named after the tool: the packages resolved in its
+    /// `uv.lock`, with the licenses and artifact hashes of the distributions installed in its run
+    /// venv. Uses the supplied or stored `uv.lock`, resolved first without storing it when the
+    /// code has none.
+    ///
+    /// # Returns
+    ///
+    /// A CycloneDX JSON document with the name, version, license and hashes of every dependency
+    pub async fn sbom(&self) -> anyhow::Result<Value> {
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        let mut locked_code =
+            self.extend_with_pyproject_toml_and_lock(&self.inferred_dependencies().await?)?;
+        self.enforce_dependency_policy(&locked_code)?;
+        if !locked_code.files.contains_key(Self::UV_LOCK_FILE_NAME) {
+            let lock = self.resolve_lock(&locked_code, &runner_type).await?;
+            locked_code
+                .files
+                .insert(Self::UV_LOCK_FILE_NAME.to_string(), lock);
+        }
+        let execution_storage =
+            ExecutionStorage::new(locked_code.clone(), self.options.context.clone());
+        execution_storage.init_for_python(None)?;
+        let lease = self
+            .lease_run_venv(
+                &execution_storage,
+                &runner_type,
+                &PrepareProgress::new(None),
             )
-            .current_dir(execution_storage.code_folder_path.clone())
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(explain_offline_error(e.to_string(), self.options.offline))
+            })?;
+        let distributions = python_venv_distributions(lease.venv_folder_path());
+        let uv_lock = locked_code
+            .files
+            .get(Self::UV_LOCK_FILE_NAME)
+            .cloned()
+            .unwrap_or_default();
+        let components = uv_lock_components(&uv_lock, &distributions)?;
+        let tool_name = self
+            .options
+            .tool_definition
+            .as_ref()
+            .map(|tool_definition| tool_definition.name.clone())
+            .unwrap_or_else(|| self.code.entrypoint.clone());
+        Ok(cyclonedx_json(&tool_name, &components))
+    }
+
+    /// Python interpreters tools can run with: in the host the installed ones, the managed ones
+    /// and the ones uv can download; in docker the ones installed in the code runner image
+    pub async fn list_python_interpreters(&self) -> anyhow::Result<Vec<PythonInterpreter>> {
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        let mut command = match runner_type {
+            RunnerType::Host => {
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .args(["python", "list", "--output-format", "json"])
+                    .args(if self.options.offline {
+                        vec!["--only-installed"]
+                    } else {
+                        vec![]
+                    })
+                    .envs(self.python_envs());
+                command
+            }
+            RunnerType::Docker => {
+                let mut command = tokio::process::Command::new("docker");
+                command.args([
+                    "run",
+                    "--rm",
+                    self.options.code_runner_docker_image_name.as_str(),
+                    "uv",
+                    "python",
+                    "list",
+                    "--output-format",
+                    "json",
+                    "--only-installed",
+                ]);
+                command
+            }
+        };
+        command
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .kill_on_drop(true);
-        let output = match command.spawn() {
-            Ok(child) => match child.wait_with_output().await {
-                Ok(output) => output,
-                Err(e) => {
-                    log::error!("failed to get command output: {}", e);
+        let output = command.spawn()?.wait_with_output().await?;
+        if !output.status.success() {
+            let error_message = String::from_utf8_lossy(&output.stderr).to_string();
+            log::error!("uv python list error: {}", error_message);
+            return Err(anyhow::anyhow!(
+                "failed to list python interpreters: {}",
+                error_message
+            ));
+        }
+        let python_interpreters_folder_path =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone())
+                .python_interpreters_folder_path();
+        parse_uv_python_list(
+            &String::from_utf8_lossy(&output.stdout),
+            match runner_type {
+                RunnerType::Host => Some(python_interpreters_folder_path.as_path()),
+                RunnerType::Docker => None,
+            },
+        )
+    }
+
+    /// Installs the Python `versions` with `uv python install` into the managed interpreters folder
+    /// of the storage, so host runs pinned to them don't download an interpreter.
+    /// The code runner image ships every supported version, in docker they are only checked.
+    pub async fn install_python_interpreters(&self, versions: &[String]) -> anyhow::Result<()> {
+        for version in versions {
+            validate_python_version(version, None)?;
+        }
+        match resolve_runner_type(self.options.force_runner_type.clone()) {
+            RunnerType::Host => {
+                std::fs::create_dir_all(
+                    ExecutionStorage::new(self.code.clone(), self.options.context.clone())
+                        .python_interpreters_folder_path(),
+                )?;
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .args(["python", "install"])
+                    .args(versions)
+                    .envs(self.python_envs())
+                    .stdout(std::process::Stdio::piped())
+                    .stderr(std::process::Stdio::piped())
+                    .kill_on_drop(true);
+                log::info!("installing python interpreters: {:?}", command);
+                let output = command.spawn()?.wait_with_output().await?;
+                if !output.status.success() {
+                    let error_message = explain_offline_error(
+                        String::from_utf8_lossy(&output.stderr).to_string(),
+                        self.options.offline,
+                    );
+                    log::error!("uv python install error: {}", error_message);
                     return Err(anyhow::anyhow!(
-                        "failed to get pyright command output: {}",
-                        e
+                        "failed to install python interpreters: {}",
+                        error_message
                     ));
                 }
-            },
-            Err(e) => {
-                let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
-                log::error!("{}", error_msg);
-                return Err(anyhow::anyhow!("{}", error_msg));
             }
+            RunnerType::Docker => {
+                let installed = self.list_python_interpreters().await?;
+                for version in versions {
+                    let version = version.trim();
+                    let is_installed = installed.iter().any(|interpreter| {
+                        interpreter.version == version
+                            || interpreter.version.starts_with(&format!("{}.", version))
+                    });
+                    if !is_installed {
+                        return Err(anyhow::anyhow!(
+                            "python {} is not installed in the image {}",
+                            version,
+                            self.options.code_runner_docker_image_name
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the storage and installs the dependencies of the generated `pyproject.toml` in the
+    /// run venv with `uv sync`, so `run` only executes the code. Progress is reported to `progress`
+    /// when given.
+    pub async fn prepare(
+        &self,
+        max_prepare_timeout: Option<Duration>,
+        progress: Option<UnboundedSender<PrepareEvent>>,
+    ) -> anyhow::Result<()> {
+        let progress = PrepareProgress::new(progress);
+        let runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+        with_prepare_timeout(max_prepare_timeout, async {
+            let execution_storage = progress
+                .step(PrepareStep::CreateStorage, async {
+                    let execution_storage = ExecutionStorage::new(
+                        self.extend_with_pyproject_toml_and_lock(
+                            &self.inferred_dependencies().await?,
+                        )?,
+                        self.options.context.clone(),
+                    );
+                    self.enforce_dependency_policy(&execution_storage.code_files)?;
+                    execution_storage.init_for_python(None)?;
+                    Ok(execution_storage)
+                })
+                .await?;
+            progress
+                .step(
+                    PrepareStep::InstallDependencies,
+                    self.lease_run_venv(&execution_storage, &runner_type, &progress),
+                )
+                .await
+                .map(|_| ())
+                .map_err(|e| {
+                    anyhow::anyhow!(explain_offline_error(e.to_string(), self.options.offline))
+                })
+        })
+        .await
+    }
+
+    /// Key of the shared run venv of the code: hash of the dependencies, Python version and
+    /// package sources of its generated `pyproject.toml`, its `uv.lock` and the package options
+    fn run_venv_key(&self, code_files: &CodeFiles) -> anyhow::Result<String> {
+        let pyproject_toml = code_files
+            .files
+            .get(Self::PYPROJECT_TOML_FILE_NAME)
+            .cloned()
+            .unwrap_or_default()
+            .parse::<DocumentMut>()?;
+        let project = pyproject_toml.get("project");
+        let mut dependencies: Vec<String> = project
+            .and_then(|project| project.get("dependencies"))
+            .and_then(|dependencies| dependencies.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| dependency.as_str().map(|d| d.to_string()))
+            .collect();
+        dependencies.sort();
+        let requires_python = project
+            .and_then(|project| project.get("requires-python"))
+            .map(|requires_python| requires_python.to_string())
+            .unwrap_or_default();
+        let tool = pyproject_toml
+            .get("tool")
+            .map(|tool| tool.to_string())
+            .unwrap_or_default();
+        let lock = code_files
+            .files
+            .get(Self::UV_LOCK_FILE_NAME)
+            .cloned()
+            .unwrap_or_default();
+        let python_version = code_files
+            .files
+            .get(Self::PYTHON_VERSION_FILE_NAME)
+            .cloned()
+            .unwrap_or_default();
+        let package_args = self.package_args(&RunnerType::Host).join(" ");
+        Ok(sha256_hex(
+            [
+                dependencies.join("\n"),
+                requires_python,
+                python_version,
+                tool,
+                lock,
+                package_args,
+            ]
+            .join("\0")
+            .as_bytes(),
+        ))
+    }
+
+    /// Leases the shared run venv of the code, installing its dependencies with `uv sync` when the
+    /// venv doesn't exist yet, and evicts the least recently used venvs over `max_python_venvs`
+    async fn lease_run_venv(
+        &self,
+        execution_storage: &ExecutionStorage,
+        runner_type: &RunnerType,
+        progress: &PrepareProgress,
+    ) -> anyhow::Result<PythonVenvLease> {
+        let key = self.run_venv_key(&execution_storage.code_files)?;
+        // Leases and evictions wait for the venv locks held by other processes and evictions remove
+        // whole venvs, so they run outside of the async runtime threads
+        let lease = {
+            let execution_storage = execution_storage.clone();
+            let runner_type = runner_type.clone();
+            let key = key.clone();
+            tokio::task::spawn_blocking(move || {
+                execution_storage.lease_python_venv(&runner_type, &key)
+            })
+            .await??
         };
-        log::info!("pyright check finished");
-        if !output.status.success() {
-            println!(
-                "pyright check failed error: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-            let lint_message = String::from_utf8(output.stdout)?;
-            let lint_message_lines: Vec<String> =
-                lint_message.lines().map(|s| s.to_string()).collect();
-            log::info!(
-                "python pyright check finished with errors:\n\n{}",
-                lint_message_lines.join("\n")
-            );
-            return Ok(lint_message_lines);
+        let evicted = {
+            let execution_storage = execution_storage.clone();
+            let runner_type = runner_type.clone();
+            let key = key.clone();
+            let max_python_venvs = self.options.max_python_venvs;
+            tokio::task::spawn_blocking(move || {
+                execution_storage.evict_python_venvs(&runner_type, max_python_venvs, &key)
+            })
+            .await?
+        };
+        if let Err(e) = evicted {
+            log::warn!("failed to evict python venvs: {}", e);
         }
+        // uv writes the uv.lock of unlocked code into the project, so it syncs a scratch copy
+        // instead of the code snapshot
+        let scratch = execution_storage.create_scratch_code_folder()?;
+        let command = self.sync_command(
+            execution_storage,
+            runner_type,
+            scratch.path(),
+            lease.venv_folder_path(),
+        )?;
+        execution_storage
+            .ensure_python_venv(&lease, progress.run_command(command))
+            .await?;
+        Ok(lease)
+    }
 
-        log::info!("pyright check finished: passed");
-        Ok(vec![])
+    /// `uv sync` of the dependencies of the project in `project_folder_path` into `venv_folder_path`
+    fn sync_command(
+        &self,
+        execution_storage: &ExecutionStorage,
+        runner_type: &RunnerType,
+        project_folder_path: &Path,
+        venv_folder_path: &Path,
+    ) -> anyhow::Result<tokio::process::Command> {
+        let locked = execution_storage
+            .code_files
+            .files
+            .contains_key(Self::UV_LOCK_FILE_NAME);
+        Ok(match runner_type {
+            RunnerType::Host => {
+                let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())?;
+                let mut command = tokio::process::Command::new(uv_binary_path);
+                command
+                    .arg("sync")
+                    .args(if locked { vec!["--frozen"] } else { vec![] })
+                    .args(self.package_args(&RunnerType::Host))
+                    .envs(self.python_envs())
+                    .env("VIRTUAL_ENV", venv_folder_path)
+                    .env("UV_PROJECT_ENVIRONMENT", venv_folder_path)
+                    .current_dir(project_folder_path);
+                command
+            }
+            RunnerType::Docker => self.sync_command_in_docker(
+                execution_storage,
+                project_folder_path,
+                venv_folder_path,
+                locked,
+            ),
+        })
+    }
+
+    /// Runs `uv sync` in the code runner image with the project, the run venv and the uv cache
+    /// mounted as in `run_in_docker`
+    fn sync_command_in_docker(
+        &self,
+        execution_storage: &ExecutionStorage,
+        project_folder_path: &Path,
+        venv_folder_path: &Path,
+        locked: bool,
+    ) -> tokio::process::Command {
+        let project_folder = execution_storage.relative_to_root(project_folder_path.to_path_buf());
+        let venv_folder =
+            execution_storage.relative_to_global_cache(venv_folder_path.to_path_buf());
+        let uv_cache_folder = execution_storage
+            .relative_to_global_cache(execution_storage.python_run_docker_uv_cache_folder_path());
+        let mount_dirs = [
+            (
+                project_folder_path.to_path_buf().as_normalized_string(),
+                project_folder.clone(),
+            ),
+            (
+                venv_folder_path.to_path_buf().as_normalized_string(),
+                venv_folder.clone(),
+            ),
+            (
+                execution_storage
+                    .python_run_docker_uv_cache_folder_path()
+                    .as_normalized_string(),
+                uv_cache_folder.clone(),
+            ),
+        ];
+        let mut args = vec![String::from("run"), String::from("--rm")];
+        for (dir, relative_path) in mount_dirs {
+            args.extend([
+                String::from("--mount"),
+                format!(r#"type=bind,source={},target=/app/{}"#, dir, relative_path),
+            ]);
+        }
+        args.extend(self.find_links_docker_mount_params());
+        args.extend(self.pyright_docker_mount_params());
+        args.extend([
+            String::from("-e"),
+            format!("VIRTUAL_ENV=/app/{}", venv_folder),
+            String::from("-e"),
+            format!("UV_PROJECT_ENVIRONMENT=/app/{}", venv_folder),
+            String::from("-e"),
+            format!("UV_CACHE_DIR=/app/{}", uv_cache_folder),
+            String::from("--workdir"),
+            format!("/app/{}", project_folder),
+            self.options.code_runner_docker_image_name.clone(),
+            String::from("uv"),
+            String::from("sync"),
+        ]);
+        if locked {
+            args.push(String::from("--frozen"));
+        }
+        args.extend(self.package_args(&RunnerType::Docker));
+        log::info!("syncing dependencies in docker with args: {:?}", args);
+        let mut command = tokio::process::Command::new("docker");
+        command.args(args);
+        command
     }
 
     pub async fn run(
@@ -401,8 +1733,20 @@ requires-python = ">=3.10"
             ));
         }
         let resolved_runner_type = resolve_runner_type(self.options.force_runner_type.clone());
-        let mut code = Self::extend_with_pyproject_toml(self.code.clone()).map_err(|e| {
-            ExecutionError::new(format!("failed to create pyproject.toml: {}", e), None)
+        let inferred_dependencies = self
+            .inferred_dependencies()
+            .await
+            .map_err(|e| ExecutionError::new(e.to_string(), None))?;
+        let mut code = self
+            .extend_with_pyproject_toml_and_lock(&inferred_dependencies)
+            .map_err(|e| {
+                ExecutionError::new(format!("failed to create pyproject.toml: {}", e), None)
+            })?;
+        self.enforce_dependency_policy(&code).map_err(|e| {
+            match e.downcast::<DependencyPolicyError>() {
+                Ok(policy_error) => ExecutionError::from(policy_error),
+                Err(e) => ExecutionError::new(e.to_string(), None),
+            }
         })?;
 
         let entrypoint_code = code.files.get(&self.code.entrypoint.clone()).unwrap();
@@ -418,36 +1762,18 @@ requires-python = ">=3.10"
                 Value::String("__main__.CONFIG".to_string()),
             );
         }
-        // Deep traverse adapted_parameters and normalize mount file paths
-        if !self.options.context.mount_files.is_empty()
-            && matches!(resolved_runner_type, RunnerType::Docker)
-        {
-            let mount_files = self
-                .options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<HashSet<String>>();
-
-            adapted_configurations = adapt_paths_in_value(&adapted_configurations, &mount_files);
-        }
-
         let mut adapted_parameters = parameters.clone();
 
-        // Deep traverse adapted_parameters and normalize mount file paths
-        if !self.options.context.mount_files.is_empty()
-            && matches!(resolved_runner_type, RunnerType::Docker)
-        {
-            let mount_files = self
-                .options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<HashSet<String>>();
-
-            adapted_parameters = adapt_paths_in_value(&adapted_parameters, &mount_files);
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        // Deep traverse configurations and parameters and map mounted paths to their container path
+        if matches!(resolved_runner_type, RunnerType::Docker) {
+            let mounts = execution_storage.resolved_mounts().map_err(|e| {
+                ExecutionError::new(format!("failed to resolve mounts: {}", e), None)
+            })?;
+            let path_mappings = docker_path_mappings(&mounts);
+            adapted_configurations = adapt_paths_in_value(&adapted_configurations, &path_mappings);
+            adapted_parameters = adapt_paths_in_value(&adapted_parameters, &path_mappings);
         }
 
         if let Some(object) = adapted_parameters.as_object_mut() {
@@ -520,30 +1846,74 @@ print("</hanzo-code-result>")
         code.files
             .insert(self.code.entrypoint.clone(), adapted_entrypoint_code);
 
-        let result = match resolved_runner_type {
-            RunnerType::Host => self.run_in_host(code, envs, max_execution_timeout).await,
-            RunnerType::Docker => self.run_in_docker(code, envs, max_execution_timeout).await,
-        }
-        .map_err(|e| ExecutionError::new(e.to_string(), None))?;
+        execution_storage.begin_home_transaction().map_err(|e| {
+            ExecutionError::new(format!("failed to stage home folder: {}", e), None)
+        })?;
 
-        let result_text = result
-            .iter()
-            .skip_while(|line| !line.contains("<hanzo-code-result>"))
-            .skip(1)
-            .take_while(|line| !line.contains("</hanzo-code-result>"))
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>()
-            .join("\n");
+        let change_tracker =
+            ChangeTracker::start(&execution_storage, self.options.track_changes.as_ref())?;
 
-        log::info!("result : {:?}", result);
-        log::info!("result text: {:?}", result_text);
+        let run_result: Result<RunResult, ExecutionError> = async {
+            let result = match resolved_runner_type {
+                RunnerType::Host => self.run_in_host(code, envs, max_execution_timeout).await,
+                RunnerType::Docker => self.run_in_docker(code, envs, max_execution_timeout).await,
+            }
+            .map_err(|e| {
+                ExecutionError::new(
+                    explain_offline_error(e.to_string(), self.options.offline),
+                    None,
+                )
+            })?;
+
+            let result_text = result
+                .iter()
+                .skip_while(|line| !line.contains("<hanzo-code-result>"))
+                .skip(1)
+                .take_while(|line| !line.contains("</hanzo-code-result>"))
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            log::info!("result : {:?}", result);
+            log::info!("result text: {:?}", result_text);
+
+            let result: Value = serde_json::from_str(&result_text).map_err(|e| {
+                log::info!("failed to parse result: {}", e);
+                ExecutionError::new(format!("failed to parse result: {}", e), None)
+            })?;
+            log::info!("successfully parsed run result: {:?}", result);
+
+            let artifacts = RunArtifact::collect(
+                &execution_storage.outputs_folder_path,
+                self.options.inline_artifacts_max_size,
+            )
+            .map_err(|e| {
+                ExecutionError::new(format!("failed to collect artifacts: {}", e), None)
+            })?;
+            Ok(RunResult {
+                data: result,
+                artifacts,
+                changes: Vec::new(),
+                inferred_dependencies,
+            })
+        }
+        .await;
+        let run_result = match change_tracker {
+            Some(change_tracker) => change_tracker.finish(run_result),
+            None => run_result,
+        };
 
-        let result: Value = serde_json::from_str(&result_text).map_err(|e| {
-            log::info!("failed to parse result: {}", e);
-            ExecutionError::new(format!("failed to parse result: {}", e), None)
-        })?;
-        log::info!("successfully parsed run result: {:?}", result);
-        Ok(RunResult { data: result })
+        if self.options.context.home_transaction.mode == HomeTransactionMode::CommitOnSuccess {
+            let home_transaction_result = if run_result.is_ok() {
+                execution_storage.commit_home()
+            } else {
+                execution_storage.discard_home()
+            };
+            home_transaction_result.map_err(|e| {
+                ExecutionError::new(format!("failed to finish home transaction: {}", e), None)
+            })?;
+        }
+        run_result
     }
 
     async fn run_in_docker(
@@ -560,6 +1930,15 @@ print("</hanzo-code-result>")
         log::info!("code files: {:?}", code_files.files.get("main.py"));
         let execution_storage = ExecutionStorage::new(code_files, self.options.context.clone());
         execution_storage.init_for_python(None)?;
+        let venv_lease = self
+            .lease_run_venv(
+                &execution_storage,
+                &RunnerType::Docker,
+                &PrepareProgress::default(),
+            )
+            .await?;
+        let venv_folder =
+            execution_storage.relative_to_global_cache(venv_lease.venv_folder_path().to_path_buf());
 
         let mut mount_params = Vec::<String>::new();
 
@@ -570,14 +1949,19 @@ print("</hanzo-code-result>")
             ),
             (
                 execution_storage.home_folder_path.as_normalized_string(),
-                execution_storage.relative_to_root(execution_storage.home_folder_path.clone()),
+                // Staged homes are mounted at the same path so SHINKAI_HOME doesn't change
+                String::from("home"),
             ),
             (
-                execution_storage
-                    .python_run_docker_venv_folder_path()
+                execution_storage.outputs_folder_path.as_normalized_string(),
+                execution_storage.relative_to_root(execution_storage.outputs_folder_path.clone()),
+            ),
+            (
+                venv_lease
+                    .venv_folder_path()
+                    .to_path_buf()
                     .as_normalized_string(),
-                execution_storage
-                    .relative_to_root(execution_storage.python_run_docker_venv_folder_path()),
+                venv_folder.clone(),
             ),
             (
                 execution_storage
@@ -594,36 +1978,11 @@ print("</hanzo-code-result>")
             mount_params.extend([String::from("--mount"), mount_param]);
         }
 
-        let mut mount_env = String::from("");
-        log::info!("mount files: {:?}", self.options.context.mount_files);
-        // Mount each writable file to /app/mount
-        for file in &self.options.context.mount_files {
-            // Copy the files to the exact same path in the volume.
-            // This will allow to run the same code in the host and in the container.
-            let path = normalize_for_docker_path(file.to_path_buf());
-            let mount_param = format!(r#"type=bind,source={},target={}"#, path, path);
-            log::info!("mount parameter created: {}", mount_param);
-            mount_env += &format!("{},", path);
-            mount_params.extend([String::from("--mount"), mount_param]);
-        }
+        mount_params.extend(self.find_links_docker_mount_params());
 
-        let mut mount_assets_env = String::from("");
-        // Mount each asset file to /app/assets
-        for file in &self.options.context.assets_files {
-            let target_path = format!(
-                "/app/{}/{}",
-                execution_storage.relative_to_root(execution_storage.assets_folder_path.clone()),
-                file.file_name().unwrap().to_str().unwrap()
-            );
-            let mount_param = format!(
-                r#"type=bind,readonly=true,source={},target={}"#,
-                path::absolute(file).unwrap().as_normalized_string(),
-                target_path,
-            );
-            log::debug!("mount parameter created: {}", mount_param);
-            mount_assets_env += &format!("{},", target_path);
-            mount_params.extend([String::from("--mount"), mount_param]);
-        }
+        let mounts = execution_storage.resolved_mounts()?;
+        mount_params.extend(docker_mount_params(&mounts));
+        let (mount_env, mount_assets_env) = mount_envs(&mounts, &RunnerType::Docker);
 
         let mut container_envs = Vec::<String>::new();
 
@@ -636,6 +1995,11 @@ print("</hanzo-code-result>")
         container_envs.push(String::from("-e"));
         container_envs.push(String::from("SHINKAI_HOME=/app/home"));
         container_envs.push(String::from("-e"));
+        container_envs.push(format!(
+            "SHINKAI_OUTPUTS=/app/{}",
+            execution_storage.relative_to_root(execution_storage.outputs_folder_path.clone())
+        ));
+        container_envs.push(String::from("-e"));
         container_envs.push(format!("SHINKAI_ASSETS={}", mount_assets_env));
         container_envs.push(String::from("-e"));
         container_envs.push(format!("SHINKAI_MOUNT={}", mount_env));
@@ -650,17 +2014,9 @@ print("</hanzo-code-result>")
             self.options.context.execution_id
         ));
         container_envs.push(String::from("-e"));
-        container_envs.push(format!(
-            "VIRTUAL_ENV=/app/{}",
-            execution_storage
-                .relative_to_root(execution_storage.python_run_docker_venv_folder_path())
-        ));
+        container_envs.push(format!("VIRTUAL_ENV=/app/{}", venv_folder));
         container_envs.push(String::from("-e"));
-        container_envs.push(format!(
-            "UV_PROJECT_ENVIRONMENT=/app/{}",
-            execution_storage
-                .relative_to_root(execution_storage.python_run_docker_venv_folder_path())
-        ));
+        container_envs.push(format!("UV_PROJECT_ENVIRONMENT=/app/{}", venv_folder));
         container_envs.push(String::from("-e"));
         container_envs.push(format!(
             "UV_CACHE_DIR=/app/{}",
@@ -695,7 +2051,8 @@ print("</hanzo-code-result>")
             .to_string();
 
         let python_start_script = format!(
-            "uv run --project {} {}",
+            "uv run --no-sync {}--project {} {}",
+            self.package_script_args(),
             pyproject_toml_path,
             code_entrypoint.clone().as_str(),
         );
@@ -709,87 +2066,17 @@ print("</hanzo-code-result>")
             python_start_script.as_str(),
         ]);
 
-        let command = command
-            .args(args)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
+        let command = command.args(args);
 
         log::info!("spawning docker command: {:?}", command);
-        let mut child = command.spawn().map_err(|e| {
-            let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
-            log::error!("{}", error_msg);
-            anyhow::anyhow!("{}", error_msg)
-        })?;
-
-        let stdout = child.stdout.take().expect("Failed to get stdout");
-        let mut stdout_stream = BufReader::new(stdout).lines();
-
-        let stderr = child.stderr.take().expect("Failed to get stderr");
-        let mut stderr_stream = BufReader::new(stderr).lines();
-
-        let stdout_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let stderr_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let execution_storage_clone = execution_storage.clone();
-
-        let stdout_lines_clone = stdout_lines.clone();
-        let stderr_lines_clone = stderr_lines.clone();
-        let execution_storage_clone2 = execution_storage_clone.clone();
-
-        let stdout_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stdout_stream.next_line().await {
-                    log::info!("from python: {}", line);
-                    stdout_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone.append_log(line.as_str());
-                }
-            });
-        });
-
-        let stderr_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stderr_stream.next_line().await {
-                    log::info!("from python: {}", line);
-                    stderr_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone2.append_log(line.as_str());
-                }
-            });
-        });
-
-        #[allow(clippy::let_underscore_future)]
-        let std_tasks = tokio::spawn(async move {
-            let _ = futures::future::join_all(vec![stdout_task, stderr_task]).await;
-        });
-
-        let output = if let Some(timeout) = max_execution_timeout {
-            log::info!("executing command with {}[s] timeout", timeout.as_secs());
-            match tokio::time::timeout(timeout, child.wait_with_output()).await {
-                Ok(result) => result?,
-                Err(_) => {
-                    log::error!("command execution timed out after {}[s]", timeout.as_secs());
-                    return Err(anyhow::anyhow!(
-                        "process timed out after {}[s]",
-                        timeout.as_secs()
-                    ));
-                }
-            }
-        } else {
-            log::info!("executing command without timeout");
-            child.wait_with_output().await?
-        };
-        let _ = std_tasks.await;
-        if !output.status.success() {
-            let stderr = stderr_lines.lock().await.to_vec().join("\n");
-            log::error!("command execution failed: {}", stderr);
-            return Err(anyhow::Error::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                stderr.to_string(),
-            )));
-        }
-
-        let stdout: Vec<String> = stdout_lines.lock().await.to_vec();
-        log::info!("command completed successfully with output: {:?}", stdout);
-        Ok(stdout)
+        run_process(
+            &execution_storage,
+            command,
+            None,
+            "python",
+            max_execution_timeout,
+        )
+        .await
     }
 
     async fn run_in_host(
@@ -800,6 +2087,13 @@ print("</hanzo-code-result>")
     ) -> anyhow::Result<Vec<String>> {
         let execution_storage = ExecutionStorage::new(code_files, self.options.context.clone());
         execution_storage.init_for_python(None)?;
+        let venv_lease = self
+            .lease_run_venv(
+                &execution_storage,
+                &RunnerType::Host,
+                &PrepareProgress::default(),
+            )
+            .await?;
 
         let uv_binary_path = path::absolute(self.options.uv_binary_path.clone())
             .unwrap()
@@ -811,8 +2105,10 @@ print("</hanzo-code-result>")
 
         let mut command = tokio::process::Command::new(uv_binary_path);
 
+        // The run venv is already synced, `--no-sync` also keeps uv from locking into the snapshot
+        command.args(["run", "--no-sync"]);
+        command.args(self.package_args(&RunnerType::Host));
         let command = command
-            .arg("run")
             .args([
                 "--project",
                 execution_storage
@@ -822,27 +2118,11 @@ print("</hanzo-code-result>")
                     .unwrap(),
             ])
             .arg(execution_storage.code_entrypoint_file_path.clone())
-            .current_dir(execution_storage.root_folder_path.clone())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
+            .current_dir(execution_storage.root_folder_path.clone());
 
-        command.env(
-            "VIRTUAL_ENV",
-            execution_storage
-                .python_run_host_venv_folder_path()
-                .to_string_lossy()
-                .to_string()
-                .as_str(),
-        );
-        command.env(
-            "UV_PROJECT_ENVIRONMENT",
-            execution_storage
-                .python_run_host_venv_folder_path()
-                .to_string_lossy()
-                .to_string()
-                .as_str(),
-        );
+        command.env("VIRTUAL_ENV", venv_lease.venv_folder_path());
+        command.env("UV_PROJECT_ENVIRONMENT", venv_lease.venv_folder_path());
+        command.envs(self.python_envs());
 
         command.env(
             "SHINKAI_NODE_LOCATION",
@@ -856,25 +2136,13 @@ print("</hanzo-code-result>")
 
         command.env("SHINKAI_HOME", execution_storage.home_folder_path.clone());
         command.env(
-            "SHINKAI_ASSETS",
-            self.options
-                .context
-                .assets_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<Vec<_>>()
-                .join(","),
-        );
-        command.env(
-            "SHINKAI_MOUNT",
-            self.options
-                .context
-                .mount_files
-                .iter()
-                .map(|p| path::absolute(p).unwrap().to_string_lossy().to_string())
-                .collect::<Vec<_>>()
-                .join(","),
+            "SHINKAI_OUTPUTS",
+            execution_storage.outputs_folder_path.clone(),
         );
+        let mounts = execution_storage.resolved_mounts()?;
+        let (mount_env, mount_assets_env) = mount_envs(&mounts, &RunnerType::Host);
+        command.env("SHINKAI_ASSETS", mount_assets_env);
+        command.env("SHINKAI_MOUNT", mount_env);
         command.env(
             "SHINKAI_CONTEXT_ID",
             self.options.context.context_id.clone(),
@@ -888,79 +2156,14 @@ print("</hanzo-code-result>")
             command.envs(envs);
         }
         log::info!("prepared command with arguments: {:?}", command);
-        let mut child = command.spawn().map_err(|e| {
-            let error_msg = format!("failed to spawn command: {:?} error: {}", command, e);
-            log::error!("{}", error_msg);
-            anyhow::anyhow!("{}", error_msg)
-        })?;
-
-        let stdout = child.stdout.take().expect("Failed to get stdout");
-        let mut stdout_stream = BufReader::new(stdout).lines();
-
-        let stderr = child.stderr.take().expect("Failed to get stderr");
-        let mut stderr_stream = BufReader::new(stderr).lines();
-
-        let stdout_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let stderr_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let execution_storage_clone = execution_storage.clone();
-
-        let stdout_lines_clone = stdout_lines.clone();
-        let stderr_lines_clone = stderr_lines.clone();
-        let execution_storage_clone2 = execution_storage_clone.clone();
-
-        let stdout_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stdout_stream.next_line().await {
-                    log::info!("from python: {}", line);
-                    stdout_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone.append_log(line.as_str());
-                }
-            });
-        });
-
-        let stderr_task = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Runtime::new().unwrap().block_on(async {
-                while let Ok(Some(line)) = stderr_stream.next_line().await {
-                    log::info!("from python: {}", line);
-                    stderr_lines_clone.lock().await.push(line.clone());
-                    let _ = execution_storage_clone2.append_log(line.as_str());
-                }
-            });
-        });
-
-        #[allow(clippy::let_underscore_future)]
-        let std_tasks = tokio::spawn(async move {
-            let _ = futures::future::join_all(vec![stdout_task, stderr_task]).await;
-        });
-
-        let output = if let Some(timeout) = max_execution_timeout {
-            log::info!("executing command with {}[s] timeout", timeout.as_secs());
-            match tokio::time::timeout(timeout, child.wait_with_output()).await {
-                Ok(result) => result?,
-                Err(_) => {
-                    log::error!("command execution timed out after {}[s]", timeout.as_secs());
-                    return Err(anyhow::Error::new(std::io::Error::new(
-                        std::io::ErrorKind::TimedOut,
-                        format!("process timed out after {}[s]", timeout.as_secs()),
-                    )));
-                }
-            }
-        } else {
-            log::info!("executing command without timeout");
-            child.wait_with_output().await?
-        };
-        let _ = std_tasks.await;
-        if !output.status.success() {
-            let stderr = stderr_lines.lock().await.to_vec().join("\n");
-            log::error!("command execution failed: {}", stderr);
-            return Err(anyhow::Error::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                stderr.to_string(),
-            )));
-        }
-        let stdout: Vec<String> = stdout_lines.lock().await.to_vec();
-        log::info!("command completed successfully with output: {:?}", stdout);
-        Ok(stdout)
+        run_process(
+            &execution_storage,
+            command,
+            None,
+            "python",
+            max_execution_timeout,
+        )
+        .await
     }
 
     // Helper function for deep merging TOML tables
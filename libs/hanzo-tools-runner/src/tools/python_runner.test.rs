@@ -1,11 +1,23 @@
 use std::collections::HashMap;
 
+use crate::copy_assets::{NODEJS_WHEEL_VERSION, PYRIGHT_VERSION};
 use crate::tools::runner_type::RunnerType;
 use rstest::rstest;
 use serde_json::{json, Value};
 
+use crate::tools::audit::AuditPolicy;
+use crate::tools::dependency_policy::{DependencyPolicy, DependencyViolationReason};
+use crate::tools::diagnostic::DiagnosticSeverity;
 use crate::tools::execution_context::ExecutionContext;
+use crate::tools::execution_storage::ExecutionStorage;
+use crate::tools::file_changes::{ChangeTrackingOptions, FileChangeKind};
+use crate::tools::home_transaction::{HomeTransactionMode, HomeTransactionOptions};
+use crate::tools::lint_rules::LintRules;
+use crate::tools::mounts::Mount;
+use crate::tools::prepare::{PrepareEvent, PrepareStep};
+use crate::tools::python_interpreters::PythonInterpreter;
 use crate::tools::python_runner_options::PythonRunnerOptions;
+use crate::tools::tool_definition::ToolDefinition;
 use crate::tools::hanzo_node_location::HanzoNodeLocation;
 use crate::tools::{code_files::CodeFiles, python_runner::PythonRunner};
 
@@ -255,11 +267,12 @@ def run(configurations, parameters):
     );
 
     let check_result = python_runner.check().await.unwrap();
-    assert_eq!(check_result.len(), 0);
+    assert_eq!(check_result.errors.len(), 0);
 }
 
 #[rstest]
 #[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
 #[tokio::test]
 async fn check_code_with_errors(#[case] runner_type: RunnerType) {
     let _ = env_logger::builder()
@@ -289,12 +302,16 @@ print('test's)
     );
 
     let check_result = python_runner.check().await.unwrap();
-    assert!(!check_result.is_empty());
-    assert!(check_result.iter().any(|err| err.contains("Expected ','")));
+    assert!(check_result.has_errors());
+    assert!(check_result
+        .errors
+        .iter()
+        .any(|err| err.message.contains("Expected ','")));
 }
 
 #[rstest]
 #[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
 #[tokio::test]
 async fn check_code_with_unexisting_fn(#[case] runner_type: RunnerType) {
     let _ = env_logger::builder()
@@ -324,14 +341,16 @@ print('hello', hello())
     );
 
     let check_result = python_runner.check().await.unwrap();
-    assert!(!check_result.is_empty());
+    assert!(check_result.has_errors());
     assert!(check_result
+        .errors
         .iter()
-        .any(|err| err.contains("Undefined name `hello`")));
+        .any(|err| err.message.contains("Undefined name `hello`")));
 }
 
 #[rstest]
 #[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
 #[tokio::test]
 async fn check_code_with_import_with_error(#[case] runner_type: RunnerType) {
     let _ = env_logger::builder()
@@ -373,10 +392,11 @@ print('hello', secondary.hello())
     );
 
     let check_result = python_runner.check().await.unwrap();
-    assert!(!check_result.is_empty());
+    assert!(check_result.has_errors());
     assert!(check_result
+        .errors
         .iter()
-        .any(|err| err.contains("Undefined name `world`")));
+        .any(|err| err.message.contains("Undefined name `world`")));
 }
 
 #[rstest]
@@ -1452,6 +1472,7 @@ async def run(c: CONFIG, p: INPUTS) -> OUTPUT:
 
 #[rstest]
 #[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
 #[tokio::test]
 async fn check_with_wrong_class_instance(#[case] runner_type: RunnerType) {
     let _ = env_logger::builder()
@@ -1485,10 +1506,11 @@ output = OUTPUT(success=False, file_path="None")
     );
 
     let check_result = python_runner.check().await.unwrap();
-    assert!(!check_result.is_empty());
+    assert!(check_result.has_errors());
     assert!(check_result
+        .errors
         .iter()
-        .any(|err| err.contains("No parameter named \"success\"")));
+        .any(|err| err.message.contains("No parameter named \"success\"")));
 }
 
 #[tokio::test]
@@ -1559,7 +1581,7 @@ async def run(config: CONFIG, inputs: INPUTS) -> OUTPUT:
     );
 
     let check_result = python_runner.check().await.unwrap();
-    assert!(check_result.is_empty());
+    assert!(!check_result.has_errors());
 }
 
 #[rstest]
@@ -1731,11 +1753,10 @@ async def run(c: CONFIG, p: INPUTS) -> OUTPUT:
         });
     assert!(result.is_ok());
 
-    let pyproject_toml_path = context
-        .storage
-        .join(context_id)
-        .join("code")
-        .join(code_id)
+    let pyproject_toml_path = ExecutionStorage::new(CodeFiles::default(), context.clone())
+        .code_folder_path_for_id(&code_id)
+        .unwrap()
+        .unwrap()
         .join("pyproject.toml");
     println!("pyproject_toml_path: {:?}", pyproject_toml_path);
     assert!(pyproject_toml_path.exists());
@@ -1905,3 +1926,1423 @@ async def run(config: CONFIG, inputs: INPUTS) -> OUTPUT:
         .unwrap()
         .contains("Hello, world parameters!"));
 }
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn collect_output_artifacts(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import os
+
+async def run(configurations, parameters):
+    with open(os.path.join(os.environ["SHINKAI_OUTPUTS"], "report.txt"), "w") as f:
+        f.write("hello artifact")
+    return { "done": True }
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let python_runner = PythonRunner::new(
+        code_files,
+        json!({}),
+        Some(PythonRunnerOptions {
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let result = python_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(result.data["done"], true);
+    assert_eq!(result.artifacts.len(), 1);
+    assert_eq!(result.artifacts[0].path, "report.txt");
+    assert_eq!(result.artifacts[0].size, 14);
+    assert!(result.artifacts[0].content_base64.is_none());
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn mount_folder_read_write(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_folder = tempfile::tempdir().unwrap();
+    std::fs::write(mount_folder.path().join("input.txt"), "1").unwrap();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import os
+
+async def run(configurations, parameters):
+    folder = os.environ["SHINKAI_MOUNT"].split(",")[0]
+    with open(os.path.join(folder, "input.txt")) as f:
+        content = f.read()
+    with open(os.path.join(folder, "output.txt"), "w") as f:
+        f.write(content + "2")
+    return parameters.folder == folder
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let python_runner = PythonRunner::new(
+        code_files,
+        json!({}),
+        Some(PythonRunnerOptions {
+            context: ExecutionContext {
+                mounts: vec![Mount::read_write(mount_folder.path()).with_target("/data")],
+                ..Default::default()
+            },
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let result = python_runner
+        .run(
+            None,
+            json!({ "folder": mount_folder.path().to_string_lossy() }),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.data, true);
+    let content = std::fs::read_to_string(mount_folder.path().join("output.txt")).unwrap();
+    assert_eq!(content, "12");
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn report_changes_in_home(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import os
+
+async def run(configurations, parameters):
+    with open(os.path.join(os.environ["SHINKAI_HOME"], "state.txt"), "w") as f:
+        f.write("state")
+    return None
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let python_runner = PythonRunner::new(
+        code_files,
+        json!({}),
+        Some(PythonRunnerOptions {
+            track_changes: Some(ChangeTrackingOptions {
+                include_home: true,
+                text_diffs: false,
+            }),
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let result = python_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(result.changes.len(), 1);
+    assert_eq!(result.changes[0].kind, FileChangeKind::Created);
+    assert!(result.changes[0].path.ends_with("home/state.txt"));
+    assert_eq!(result.changes[0].size_after, Some(5));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn manual_home_transaction(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import os
+
+async def run(configurations, parameters):
+    with open(os.path.join(os.environ["SHINKAI_HOME"], "state.txt"), "w") as f:
+        f.write("staged")
+    return None
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let context = ExecutionContext {
+        home_transaction: HomeTransactionOptions {
+            mode: HomeTransactionMode::Manual,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let python_runner = PythonRunner::new(
+        code_files.clone(),
+        json!({}),
+        Some(PythonRunnerOptions {
+            context: context.clone(),
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+    python_runner.run(None, json!({}), None).await.unwrap();
+
+    let execution_storage = ExecutionStorage::new(code_files, context);
+    let committed_state_path = execution_storage
+        .context_home_folder_path()
+        .join("state.txt");
+    assert!(!committed_state_path.exists());
+    assert!(execution_storage
+        .home_folder_path
+        .join("state.txt")
+        .exists());
+
+    execution_storage.commit_home().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(committed_state_path).unwrap(),
+        "staged"
+    );
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn committed_run_keeps_home_content(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let context = ExecutionContext {
+        home_transaction: HomeTransactionOptions {
+            mode: HomeTransactionMode::CommitOnSuccess,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import os
+
+async def run(configurations, parameters):
+    with open(os.path.join(os.environ["SHINKAI_HOME"], "new.txt"), "w") as f:
+        f.write("new")
+    return None
+            "#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let context_home_folder_path =
+        ExecutionStorage::new(code_files.clone(), context.clone()).context_home_folder_path();
+    std::fs::create_dir_all(&context_home_folder_path).unwrap();
+    std::fs::write(context_home_folder_path.join("state.txt"), "seeded").unwrap();
+
+    let python_runner = PythonRunner::new(
+        code_files,
+        json!({}),
+        Some(PythonRunnerOptions {
+            context,
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+    python_runner.prepare(None, None).await.unwrap();
+    python_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("state.txt")).unwrap(),
+        "seeded"
+    );
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("new.txt")).unwrap(),
+        "new"
+    );
+}
+
+#[tokio::test]
+async fn lint_code_with_rules() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import os
+
+async def run(configurations, parameters):
+    value = None
+    if value == None:
+        return "empty"
+    return value
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let diagnostics = PythonRunner::new(code_files.clone(), Value::Null, None)
+        .lint()
+        .await
+        .unwrap();
+    let unused_import = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.code.as_deref() == Some("F401"))
+        .unwrap();
+    assert_eq!(
+        (unused_import.file.as_str(), unused_import.line),
+        ("main.py", 2)
+    );
+    assert!(!diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.code.as_deref() == Some("E711")));
+
+    let diagnostics = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            lint_rules: LintRules {
+                include: vec!["E711".to_string()],
+                exclude: vec!["F401".to_string()],
+            },
+            ..Default::default()
+        }),
+    )
+    .lint()
+    .await
+    .unwrap();
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.code.as_deref() == Some("E711")));
+    assert!(!diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.code.as_deref() == Some("F401")));
+}
+
+#[tokio::test]
+async fn format_code() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            "async def run(configurations,parameters):\n    return {'sum':1+2}\n".to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let formatted = PythonRunner::new(code_files, Value::Null, None)
+        .format()
+        .await
+        .unwrap();
+    assert_eq!(
+        formatted.files["main.py"],
+        "async def run(configurations, parameters):\n    return {\"sum\": 1 + 2}\n"
+    );
+}
+
+#[tokio::test]
+async fn fix_code() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code = "import os\nimport json\n\n\nasync def run(configurations, parameters):\n    return json.dumps(parameters)\n";
+    let code_files = CodeFiles {
+        files: HashMap::from([("main.py".to_string(), code.to_string())]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let python_runner = PythonRunner::new(code_files.clone(), Value::Null, None);
+    let fix_result = python_runner.fix().await.unwrap();
+    assert!(fix_result.has_changes());
+    assert!(!fix_result.code.files["main.py"].contains("import os"));
+    assert!(fix_result.diffs["main.py"].contains("-import os\n"));
+    // The input code is left untouched
+    assert_eq!(code_files.files["main.py"], code);
+}
+
+#[tokio::test]
+async fn check_reports_contract_violations() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+class CONFIG:
+    pass
+
+def run_tool(configurations: CONFIG, parameters: dict) -> str:
+    return "hello"
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let check_result = PythonRunner::new(code_files, Value::Null, None)
+        .check()
+        .await
+        .unwrap();
+    let contract_error = check_result
+        .errors
+        .iter()
+        .find(|error| error.source_tool == "contract")
+        .unwrap();
+    assert_eq!(
+        contract_error.code.as_deref(),
+        Some("misnamed-entry-function")
+    );
+    assert_eq!(contract_error.line, 5);
+    assert!(check_result
+        .warnings
+        .iter()
+        .any(|warning| warning.source_tool == "contract" && warning.message.contains("INPUTS")));
+}
+
+#[tokio::test]
+async fn check_results_are_cached() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            "def run(configurations, parameters):\n    return hello()\n".to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            context: ExecutionContext {
+                storage: storage.path().to_path_buf(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    );
+
+    let first = python_runner.check().await.unwrap();
+    assert!(!first.cache_hit);
+    assert!(first.has_errors());
+
+    let second = python_runner.check().await.unwrap();
+    assert!(second.cache_hit);
+    assert_eq!(second.errors, first.errors);
+}
+
+#[tokio::test]
+async fn lint_uses_pinned_ruff_binary() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            "def run(configurations, parameters):\n    return 1\n".to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            ruff_binary_path: std::path::PathBuf::from("./missing-resources/ruff"),
+            ..Default::default()
+        }),
+    );
+
+    let error = python_runner.lint().await.unwrap_err();
+    assert!(error.to_string().contains("ruff binary not found"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn audit_reports_risky_patterns(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import pickle
+import subprocess
+from os import system as shell
+
+async def run(configurations, parameters):
+    subprocess.run(["ls"])
+    shell("ls")
+    return pickle.loads(parameters.payload)
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            audit_policy: AuditPolicy {
+                severities: HashMap::from([(
+                    "pickle-loads".to_string(),
+                    DiagnosticSeverity::Warning,
+                )]),
+                ..Default::default()
+            },
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+    let diagnostics = python_runner.audit().await.unwrap();
+    let codes: Vec<(String, u32, DiagnosticSeverity)> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.code.clone().unwrap(),
+                diagnostic.line,
+                diagnostic.severity.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        codes,
+        vec![
+            ("subprocess".to_string(), 3, DiagnosticSeverity::Warning),
+            ("subprocess".to_string(), 7, DiagnosticSeverity::Warning),
+            ("os-system".to_string(), 8, DiagnosticSeverity::Warning),
+            ("pickle-loads".to_string(), 9, DiagnosticSeverity::Warning),
+        ]
+    );
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn lock_pins_dependencies(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+# /// script
+# dependencies = [
+#   "requests",
+# ]
+# ///
+import requests
+
+def run(configurations, parameters):
+    return requests.__version__
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let options = PythonRunnerOptions {
+        force_runner_type: Some(runner_type),
+        ..Default::default()
+    };
+
+    let python_runner = PythonRunner::new(code_files.clone(), Value::Null, Some(options.clone()));
+    let locked_code = python_runner.lock().await.unwrap();
+    let lock = locked_code.files[PythonRunner::UV_LOCK_FILE_NAME].clone();
+    assert!(lock.contains("name = \"requests\""));
+    assert!(lock.contains("name = \"jsonpickle\""));
+    assert_eq!(
+        locked_code.files["main.py"], code_files.files["main.py"],
+        "the code itself isn't changed"
+    );
+
+    // The stored lock is used for the same code, a supplied one wins
+    let stored_lock_version = PythonRunner::new(code_files, Value::Null, Some(options.clone()))
+        .run(None, json!({}), None)
+        .await
+        .unwrap();
+    let supplied_lock_version = PythonRunner::new(locked_code, Value::Null, Some(options))
+        .run(None, json!({}), None)
+        .await
+        .unwrap();
+    let locked_version = lock
+        .split("name = \"requests\"\nversion = \"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    assert_eq!(stored_lock_version.data, json!(locked_version));
+    assert_eq!(supplied_lock_version.data, json!(locked_version));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn prepare_installs_dependencies(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+# /// script
+# dependencies = [
+#   "humanize==4.11.0",
+# ]
+# ///
+import humanize
+
+def run(configurations, parameters):
+    return humanize.intcomma(1000000)
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    python_runner
+        .prepare(Some(std::time::Duration::from_secs(120)), Some(sender))
+        .await
+        .unwrap();
+    let mut events = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        events.push(event);
+    }
+    assert_eq!(events[0], PrepareEvent::Started(PrepareStep::CreateStorage));
+    assert!(matches!(
+        events.last().unwrap(),
+        PrepareEvent::Finished {
+            step: PrepareStep::InstallDependencies,
+            ..
+        }
+    ));
+
+    // Dependencies are installed, so running fits in a timeout a cold run wouldn't
+    let result = python_runner
+        .run(None, json!({}), Some(std::time::Duration::from_secs(5)))
+        .await
+        .unwrap();
+    assert_eq!(result.data, json!("1,000,000"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn offline_run_reports_unavailable_dependency(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+# /// script
+# dependencies = [
+#   "pyjokes==0.6.0",
+# ]
+# ///
+import pyjokes
+
+def run(configurations, parameters):
+    return pyjokes.get_joke()
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            force_runner_type: Some(runner_type),
+            // Nothing listens there, any request would fail instead of being skipped
+            index_url: Some(String::from("http://127.0.0.1:9/simple")),
+            offline: true,
+            ..Default::default()
+        }),
+    );
+    let error = python_runner.run(None, json!({}), None).await.unwrap_err();
+    assert!(
+        error
+            .message()
+            .starts_with("dependency not available offline:"),
+        "{}",
+        error.message()
+    );
+}
+
+#[test]
+fn pyright_syncs_dependencies_from_the_configured_sources() {
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            "def run(configurations, parameters):\n    return 1\n".to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            index_url: Some(String::from("http://127.0.0.1:9/simple")),
+            find_links_path: Some(std::path::PathBuf::from("./wheels")),
+            offline: true,
+            ..Default::default()
+        }),
+    );
+
+    let run_args = python_runner.pyright_run_args(&RunnerType::Docker);
+    assert_eq!(
+        run_args[..7],
+        [
+            "run",
+            "--quiet",
+            "--offline",
+            "--index-url",
+            "http://127.0.0.1:9/simple",
+            "--find-links",
+            "/app/python-find-links",
+        ]
+    );
+    assert!(python_runner
+        .pyright_run_args(&RunnerType::Host)
+        .contains(&String::from("--offline")));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_infers_dependencies_from_imports(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import json
+from bs4 import BeautifulSoup
+import yaml
+
+def run(configurations, parameters):
+    soup = BeautifulSoup("<p>hello</p>", "html.parser")
+    return json.dumps(yaml.safe_load(f"text: {soup.p.string}"))
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let options = PythonRunnerOptions {
+        force_runner_type: Some(runner_type),
+        ..Default::default()
+    };
+
+    let python_runner = PythonRunner::new(code_files.clone(), Value::Null, Some(options.clone()));
+    assert_eq!(
+        python_runner.inferred_dependencies().await.unwrap(),
+        vec!["PyYAML", "beautifulsoup4"]
+    );
+    let result = python_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(result.data, json!("{\"text\": \"hello\"}"));
+    assert_eq!(
+        result.inferred_dependencies,
+        vec!["PyYAML", "beautifulsoup4"]
+    );
+
+    // Overridden dependencies are installed instead of the inferred ones
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            dependencies: Some(vec!["beautifulsoup4".to_string()]),
+            ..options
+        }),
+    );
+    assert_eq!(
+        python_runner.inferred_dependencies().await.unwrap(),
+        vec!["beautifulsoup4"]
+    );
+    let error = python_runner.run(None, json!({}), None).await.unwrap_err();
+    assert!(error.message().contains("No module named 'yaml'"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_rejects_dependencies_violating_policy(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+# /// script
+# dependencies = [
+#   "requests==2.30.0",
+#   "pandas",
+# ]
+# ///
+def run(configurations, parameters):
+    return "never runs"
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            force_runner_type: Some(runner_type),
+            dependency_policy: DependencyPolicy {
+                allow: Some(vec!["requests".to_string()]),
+                version_constraints: HashMap::from([(
+                    "requests".to_string(),
+                    ">=2.31".to_string(),
+                )]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    );
+
+    let error = python_runner.run(None, json!({}), None).await.unwrap_err();
+    let violations: Vec<(String, DependencyViolationReason)> = error
+        .dependency_violations()
+        .iter()
+        .map(|violation| (violation.package.clone(), violation.reason.clone()))
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            (
+                "requests".to_string(),
+                DependencyViolationReason::VersionNotAllowed {
+                    constraint: ">=2.31".to_string()
+                }
+            ),
+            ("pandas".to_string(), DependencyViolationReason::NotAllowed),
+        ]
+    );
+    assert!(python_runner.prepare(None, None).await.is_err());
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_rejects_uv_sources_and_indexes_violating_policy(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+# /// script
+# dependencies = [
+#   "requests",
+# ]
+#
+# [tool.uv.sources]
+# requests = { git = "https://evil.example.com/requests" }
+#
+# [[tool.uv.index]]
+# url = "https://evil.example.com/simple"
+# ///
+def run(configurations, parameters):
+    return "never runs"
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = PythonRunner::new(
+        code_files,
+        Value::Null,
+        Some(PythonRunnerOptions {
+            force_runner_type: Some(runner_type),
+            dependency_policy: DependencyPolicy {
+                allowed_registries: Some(vec!["pypi".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    );
+
+    let error = python_runner.run(None, json!({}), None).await.unwrap_err();
+    let violations: Vec<(String, String, DependencyViolationReason)> = error
+        .dependency_violations()
+        .iter()
+        .map(|violation| {
+            (
+                violation.package.clone(),
+                violation.registry.clone(),
+                violation.reason.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            (
+                "https://evil.example.com/simple".to_string(),
+                "https://evil.example.com/simple".to_string(),
+                DependencyViolationReason::RegistryNotAllowed
+            ),
+            (
+                "requests".to_string(),
+                "https://evil.example.com/requests".to_string(),
+                DependencyViolationReason::RegistryNotAllowed
+            ),
+        ]
+    );
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_venvs_are_keyed_by_dependencies(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = |version: &str| CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            format!(
+                r#"
+# /// script
+# dependencies = [
+#   "requests=={}",
+# ]
+# ///
+import requests
+
+def run(configurations, parameters):
+    return requests.__version__
+"#,
+                version
+            ),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let storage = tempfile::tempdir().unwrap();
+    let options = |context_id: &str| PythonRunnerOptions {
+        context: ExecutionContext {
+            storage: storage.path().to_path_buf(),
+            context_id: context_id.to_string(),
+            ..Default::default()
+        },
+        force_runner_type: Some(runner_type.clone()),
+        ..Default::default()
+    };
+
+    // Conflicting dependencies in the same context don't reinstall over each other
+    for version in ["2.31.0", "2.32.3", "2.31.0"] {
+        let result = PythonRunner::new(code_files(version), Value::Null, Some(options("first")))
+            .run(None, json!({}), None)
+            .await
+            .unwrap();
+        assert_eq!(result.data, json!(version));
+    }
+    // The same dependencies in another context reuse the venv
+    let result = PythonRunner::new(code_files("2.32.3"), Value::Null, Some(options("second")))
+        .run(None, json!({}), None)
+        .await
+        .unwrap();
+    assert_eq!(result.data, json!("2.32.3"));
+
+    let venvs_folder_path = ExecutionStorage::new(code_files("2.31.0"), options("first").context)
+        .python_run_venvs_folder_path(&runner_type);
+    assert_eq!(std::fs::read_dir(venvs_folder_path).unwrap().count(), 2);
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn sbom_lists_dependencies(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+# /// script
+# dependencies = [
+#   "requests==2.32.3",
+# ]
+# ///
+import requests
+
+def run(configurations, parameters):
+    return requests.__version__
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let storage = tempfile::tempdir().unwrap();
+    let context = ExecutionContext {
+        storage: storage.path().to_path_buf(),
+        ..Default::default()
+    };
+    let python_runner = PythonRunner::new(
+        code_files.clone(),
+        Value::Null,
+        Some(PythonRunnerOptions {
+            context: context.clone(),
+            force_runner_type: Some(runner_type),
+            tool_definition: Some(ToolDefinition {
+                id: String::from("requests-version"),
+                name: String::from("requests-version"),
+                description: String::new(),
+                author: String::new(),
+                keywords: Vec::new(),
+                configurations: json!({}),
+                parameters: json!({}),
+                result: json!({}),
+                code: None,
+                embedding_metadata: None,
+            }),
+            ..Default::default()
+        }),
+    );
+
+    let sbom = python_runner.sbom().await.unwrap();
+    assert_eq!(sbom["bomFormat"], "CycloneDX");
+    assert_eq!(sbom["metadata"]["component"]["name"], "requests-version");
+    let components = sbom["components"].as_array().unwrap();
+    let requests = components
+        .iter()
+        .find(|component| component["name"] == "requests")
+        .unwrap();
+    assert_eq!(requests["version"], "2.32.3");
+    assert_eq!(requests["purl"], "pkg:pypi/requests@2.32.3");
+    assert_eq!(requests["licenses"][0]["license"]["id"], "Apache-2.0");
+    assert_eq!(requests["hashes"][0]["alg"], "SHA-256");
+    // Transitive dependencies are listed too, with their dependents
+    let requests_dependencies = sbom["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|dependency| dependency["ref"] == "pkg:pypi/requests@2.32.3")
+        .unwrap();
+    assert!(requests_dependencies["dependsOn"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|dependency| dependency
+            .as_str()
+            .unwrap()
+            .starts_with("pkg:pypi/urllib3@")));
+    // The lockfile resolved for the sbom isn't stored
+    assert!(!ExecutionStorage::new(code_files, context)
+        .python_locks_folder_path()
+        .exists());
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_uses_pinned_python_version(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import platform
+
+def run(configurations, parameters):
+    return platform.python_version()
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let options = |python_version: &str| PythonRunnerOptions {
+        force_runner_type: Some(runner_type.clone()),
+        python_version: Some(python_version.to_string()),
+        ..Default::default()
+    };
+
+    let python_runner = PythonRunner::new(code_files.clone(), Value::Null, Some(options("3.11")));
+    python_runner
+        .install_python_interpreters(&["3.11".to_string()])
+        .await
+        .unwrap();
+    let interpreters = python_runner.list_python_interpreters().await.unwrap();
+    assert!(interpreters
+        .iter()
+        .any(|interpreter| interpreter.version.starts_with("3.11.") && interpreter.is_installed()));
+    let result = python_runner.run(None, json!({}), None).await.unwrap();
+    assert!(result.data.as_str().unwrap().starts_with("3.11."));
+
+    // Unsupported versions and versions the code doesn't allow fail before anything is installed
+    let error = PythonRunner::new(code_files.clone(), Value::Null, Some(options("3.7")))
+        .run(None, json!({}), None)
+        .await
+        .unwrap_err();
+    assert!(error.message().contains("unsupported python version 3.7"));
+    let error = PythonRunner::new(code_files, Value::Null, Some(options("3.9")))
+        .prepare(None, None)
+        .await
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("python version 3.9 doesn't satisfy the requires-python >=3.10 of the tool"));
+}
+
+#[tokio::test]
+async fn python_interpreters_are_installed_in_the_storage() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let storage = tempfile::tempdir().unwrap();
+    let python_runner = PythonRunner::new(
+        CodeFiles {
+            files: HashMap::from([("main.py".to_string(), String::new())]),
+            entrypoint: "main.py".to_string(),
+        },
+        Value::Null,
+        Some(PythonRunnerOptions {
+            force_runner_type: Some(RunnerType::Host),
+            context: ExecutionContext {
+                storage: storage.path().to_path_buf(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+    );
+    python_runner
+        .install_python_interpreters(&["3.12".to_string()])
+        .await
+        .unwrap();
+    let interpreters = python_runner.list_python_interpreters().await.unwrap();
+    let managed: Vec<&PythonInterpreter> = interpreters
+        .iter()
+        .filter(|interpreter| interpreter.managed)
+        .collect();
+    assert!(!managed.is_empty());
+    let python_interpreters_folder_path = storage.path().join("global-cache/python-interpreters");
+    assert!(managed.iter().all(|interpreter| interpreter
+        .path
+        .as_ref()
+        .unwrap()
+        .starts_with(&python_interpreters_folder_path)));
+    assert!(managed
+        .iter()
+        .any(|interpreter| interpreter.version.starts_with("3.12.")));
+
+    assert!(python_runner
+        .install_python_interpreters(&["2.7".to_string()])
+        .await
+        .is_err());
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn mount_glob_read_only(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_folder = tempfile::tempdir().unwrap();
+    std::fs::write(mount_folder.path().join("a.csv"), "a").unwrap();
+    std::fs::write(mount_folder.path().join("b.csv"), "b").unwrap();
+    std::fs::write(mount_folder.path().join("c.json"), "c").unwrap();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+import os
+
+def run(configurations, parameters):
+    assets = [asset for asset in os.environ["SHINKAI_ASSETS"].split(",") if asset]
+    content = ""
+    for asset in assets:
+        with open(asset) as f:
+            content += f.read()
+    try:
+        with open(assets[0], "w") as f:
+            f.write("x")
+    except OSError:
+        return content
+    return "write should fail"
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+
+    let python_runner = PythonRunner::new(
+        code_files,
+        json!({}),
+        Some(PythonRunnerOptions {
+            context: ExecutionContext {
+                mounts: vec![Mount::read_only(mount_folder.path().join("*.csv"))],
+                ..Default::default()
+            },
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+
+    let result = python_runner.run(None, json!({}), None).await.unwrap();
+    assert_eq!(result.data, "ab");
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn run_and_check_leave_code_snapshot_unchanged(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            r#"
+# /// script
+# dependencies = [
+#   "requests",
+# ]
+# ///
+import requests
+
+def run(configurations, parameters):
+    return requests.__name__
+"#
+            .to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let storage = tempfile::tempdir().unwrap();
+    let context = ExecutionContext {
+        storage: storage.path().to_path_buf(),
+        ..Default::default()
+    };
+    let python_runner = PythonRunner::new(
+        code_files.clone(),
+        Value::Null,
+        Some(PythonRunnerOptions {
+            context: context.clone(),
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    );
+    python_runner.run(None, json!({}), None).await.unwrap();
+    python_runner.check().await.unwrap();
+
+    // uv locks unlocked code in scratch copies, never in the shared code snapshots
+    let root_code_folder_path = ExecutionStorage::new(code_files, context).root_code_folder_path;
+    for snapshot in std::fs::read_dir(root_code_folder_path).unwrap() {
+        let snapshot = snapshot.unwrap().path();
+        assert!(!snapshot.join(PythonRunner::UV_LOCK_FILE_NAME).exists());
+    }
+}
+
+#[test]
+fn managed_python_interpreters_folder_is_only_used_when_needed() {
+    let storage = tempfile::tempdir().unwrap();
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            "def run(configurations, parameters):\n    return 1\n".to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let context = ExecutionContext {
+        storage: storage.path().to_path_buf(),
+        ..Default::default()
+    };
+    let python_runner = |python_version: Option<&str>| {
+        PythonRunner::new(
+            code_files.clone(),
+            Value::Null,
+            Some(PythonRunnerOptions {
+                context: context.clone(),
+                python_version: python_version.map(String::from),
+                ..Default::default()
+            }),
+        )
+    };
+    let python_interpreters_folder_path =
+        ExecutionStorage::new(code_files.clone(), context.clone())
+            .python_interpreters_folder_path();
+    let install_dir = (
+        String::from("UV_PYTHON_INSTALL_DIR"),
+        python_interpreters_folder_path
+            .to_string_lossy()
+            .to_string(),
+    );
+
+    // uv keeps its default folder, where existing deployments downloaded interpreters to
+    assert_eq!(python_runner(None).python_envs(), vec![]);
+    assert_eq!(
+        python_runner(Some("3.12")).python_envs(),
+        vec![install_dir.clone()]
+    );
+    std::fs::create_dir_all(&python_interpreters_folder_path).unwrap();
+    assert_eq!(python_runner(None).python_envs(), vec![install_dir]);
+}
+
+#[test]
+fn check_runs_the_vendored_pyright_with_the_vendored_node() {
+    let pyright_folder = tempfile::tempdir().unwrap();
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.py".to_string(),
+            "def run(configurations, parameters):\n    return 1\n".to_string(),
+        )]),
+        entrypoint: "main.py".to_string(),
+    };
+    let python_runner = |pyright_folder_path: std::path::PathBuf| {
+        PythonRunner::new(
+            code_files.clone(),
+            Value::Null,
+            Some(PythonRunnerOptions {
+                pyright_folder_path,
+                ..Default::default()
+            }),
+        )
+    };
+
+    let vendored = python_runner(pyright_folder.path().to_path_buf());
+    assert_eq!(
+        vendored.pyright_install_args(&RunnerType::Docker),
+        vec![
+            "--no-index",
+            "--find-links",
+            "/app/pyright",
+            format!("nodejs-wheel-binaries=={}", NODEJS_WHEEL_VERSION).as_str(),
+        ]
+    );
+    assert_eq!(
+        vendored.pyright_command_args(&RunnerType::Host),
+        vec![
+            "python".to_string(),
+            "-m".to_string(),
+            "nodejs_wheel".to_string(),
+            format!(
+                "{}/pyright-{}/package/index.js",
+                pyright_folder.path().to_string_lossy(),
+                PYRIGHT_VERSION
+            ),
+        ]
+    );
+
+    // Without the vendored folder pyright itself is installed from the index at the same version
+    let missing = python_runner(pyright_folder.path().join("missing"));
+    assert_eq!(
+        missing.pyright_install_args(&RunnerType::Host),
+        vec![format!("pyright[nodejs]=={}", PYRIGHT_VERSION)]
+    );
+    assert_eq!(
+        missing.pyright_command_args(&RunnerType::Host),
+        vec!["pyright"]
+    );
+}
@@ -1,17 +1,55 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::copy_assets::PYRIGHT_FOLDER_NAME;
 
 use super::{
-    execution_context::ExecutionContext, runner_type::RunnerType,
-    hanzo_node_location::HanzoNodeLocation,
+    audit::AuditPolicy, dependency_policy::DependencyPolicy, execution_context::ExecutionContext,
+    file_changes::ChangeTrackingOptions, hanzo_node_location::HanzoNodeLocation,
+    lint_rules::LintRules, runner_type::RunnerType, tool_definition::ToolDefinition,
 };
 
 #[derive(Clone)]
 pub struct PythonRunnerOptions {
     pub context: ExecutionContext,
     pub uv_binary_path: PathBuf,
+    /// Pinned ruff used by `check`, `lint`, `fix` and `format`
+    pub ruff_binary_path: PathBuf,
+    /// Folder with the pinned pyright npm package and the nodejs-wheel-binaries wheels it runs
+    /// with, installed in the check venvs without reaching the package index
+    pub pyright_folder_path: PathBuf,
     pub code_runner_docker_image_name: String,
     pub force_runner_type: Option<RunnerType>,
     pub hanzo_node_location: HanzoNodeLocation,
+    /// Output artifacts up to this size (in bytes) are returned inline as base64
+    pub inline_artifacts_max_size: Option<u64>,
+    /// Report files created, modified or deleted by the tool in its read-write mounts
+    pub track_changes: Option<ChangeTrackingOptions>,
+    /// Rules used by `lint`
+    pub lint_rules: LintRules,
+    /// Definition of the tool, its name is the root component of `sbom`
+    pub tool_definition: Option<ToolDefinition>,
+    /// Severities of the risky patterns reported by `audit`
+    pub audit_policy: AuditPolicy,
+    /// Package index used instead of PyPI, e.g. a local mirror (`--index-url`)
+    pub index_url: Option<String>,
+    /// Folder of wheels packages are also resolved from (`--find-links`)
+    pub find_links_path: Option<PathBuf>,
+    /// Only use cached and local packages (`--offline`), packages that aren't available fail
+    /// instead of being downloaded
+    pub offline: bool,
+    /// Dependencies installed when the code has no `# /// script` block, instead of the ones
+    /// inferred from its imports
+    pub dependencies: Option<Vec<String>>,
+    /// Distributions of imports the built-in mapping doesn't know or gets wrong, by import name.
+    /// An empty distribution skips the import.
+    pub import_mapping: HashMap<String, String>,
+    /// Packages the tool may install, checked before any dependency is downloaded
+    pub dependency_policy: DependencyPolicy,
+    /// Run venvs kept in the global cache, the least recently used ones are removed beyond this
+    pub max_python_venvs: usize,
+    /// Python version the tool runs with (`3.12` or `3.12.4`), it must satisfy the tool
+    /// `requires-python`. uv picks one satisfying `requires-python` when `None`.
+    pub python_version: Option<String>,
 }
 
 impl Default for PythonRunnerOptions {
@@ -24,12 +62,34 @@ impl Default for PythonRunnerOptions {
             } else {
                 "./hanzo-tools-runner-resources/uv"
             }),
+            ruff_binary_path: PathBuf::from(if cfg!(windows) {
+                "./hanzo-tools-runner-resources/ruff.exe"
+            } else {
+                "./hanzo-tools-runner-resources/ruff"
+            }),
+            pyright_folder_path: PathBuf::from(format!(
+                "./hanzo-tools-runner-resources/{}",
+                PYRIGHT_FOLDER_NAME
+            )),
             force_runner_type: None,
             hanzo_node_location: HanzoNodeLocation {
                 protocol: String::from("http"),
                 host: String::from("127.0.0.1"),
                 port: 9550,
             },
+            inline_artifacts_max_size: None,
+            track_changes: None,
+            lint_rules: LintRules::default(),
+            tool_definition: None,
+            audit_policy: AuditPolicy::default(),
+            index_url: None,
+            find_links_path: None,
+            offline: false,
+            dependencies: None,
+            import_mapping: HashMap::new(),
+            dependency_policy: DependencyPolicy::default(),
+            max_python_venvs: 20,
+            python_version: None,
         }
     }
 }
@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{hash_utils::sha256_file_hex, path_buf_ext::PathBufExt};
+
+/// A file produced by a tool in its outputs folder
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunArtifact {
+    /// Path relative to the outputs folder, always using `/` as separator
+    pub path: String,
+    pub size: u64,
+    pub mime_type: String,
+    pub sha256: String,
+    /// Base64 encoded content, only present for files under the configured inline size
+    pub content_base64: Option<String>,
+}
+
+impl RunArtifact {
+    /// Collects every file found (recursively) in the outputs folder
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs_folder_path` - Folder the tool wrote its outputs into
+    /// * `inline_max_size` - Files up to this size (in bytes) get their content inlined as base64
+    pub fn collect(
+        outputs_folder_path: &Path,
+        inline_max_size: Option<u64>,
+    ) -> anyhow::Result<Vec<RunArtifact>> {
+        let mut artifacts = Vec::new();
+        if !outputs_folder_path.exists() {
+            return Ok(artifacts);
+        }
+
+        let mut pending: Vec<PathBuf> = vec![outputs_folder_path.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                let path = entry.path();
+                if file_type.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if !file_type.is_file() {
+                    log::warn!("skipping non regular output file: {}", path.display());
+                    continue;
+                }
+
+                let size = entry.metadata()?.len();
+                let content_base64 = match inline_max_size {
+                    Some(max_size) if size <= max_size => {
+                        let content = std::fs::read(&path)?;
+                        Some(base64_simd::STANDARD.encode_to_string(content))
+                    }
+                    _ => None,
+                };
+                let relative_path = path
+                    .strip_prefix(outputs_folder_path)?
+                    .to_path_buf()
+                    .as_normalized_string();
+                artifacts.push(RunArtifact {
+                    mime_type: mime_guess::from_path(&path)
+                        .first_or_octet_stream()
+                        .to_string(),
+                    sha256: sha256_file_hex(&path)?,
+                    path: relative_path,
+                    size,
+                    content_base64,
+                });
+            }
+        }
+        artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+        log::info!("collected {} output artifacts", artifacts.len());
+        Ok(artifacts)
+    }
+}
+
+#[cfg(test)]
+#[path = "run_artifact.test.rs"]
+mod tests;
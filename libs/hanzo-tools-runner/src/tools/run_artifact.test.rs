@@ -0,0 +1,36 @@
+use crate::tools::run_artifact::RunArtifact;
+
+#[tokio::test]
+async fn collect_artifacts_from_outputs_folder() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let outputs = tempfile::tempdir().unwrap();
+    std::fs::write(outputs.path().join("report.json"), "{\"ok\":true}").unwrap();
+    std::fs::create_dir_all(outputs.path().join("charts")).unwrap();
+    std::fs::write(outputs.path().join("charts/big.png"), vec![0u8; 2048]).unwrap();
+
+    let artifacts = RunArtifact::collect(outputs.path(), Some(1024)).unwrap();
+    assert_eq!(artifacts.len(), 2);
+
+    let chart = &artifacts[0];
+    assert_eq!(chart.path, "charts/big.png");
+    assert_eq!(chart.size, 2048);
+    assert_eq!(chart.mime_type, "image/png");
+    assert!(chart.content_base64.is_none());
+
+    let report = &artifacts[1];
+    assert_eq!(report.path, "report.json");
+    assert_eq!(report.mime_type, "application/json");
+    assert_eq!(report.content_base64.as_deref(), Some("eyJvayI6dHJ1ZX0="));
+    assert_eq!(report.sha256.len(), 64);
+}
+
+#[tokio::test]
+async fn collect_artifacts_missing_folder() {
+    let artifacts =
+        RunArtifact::collect(std::path::Path::new("./not-existing-outputs-folder"), None).unwrap();
+    assert!(artifacts.is_empty());
+}
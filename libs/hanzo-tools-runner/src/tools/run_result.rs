@@ -1,7 +1,17 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::{file_changes::FileChange, run_artifact::RunArtifact};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunResult {
     pub data: Value,
+    #[serde(default)]
+    pub artifacts: Vec<RunArtifact>,
+    #[serde(default)]
+    pub changes: Vec<FileChange>,
+    /// Dependencies the Python runner inferred from the imports of code without a `# /// script`
+    /// block and installed
+    #[serde(default)]
+    pub inferred_dependencies: Vec<String>,
 }
@@ -0,0 +1,447 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use regex::Regex;
+use serde_json::{json, Value};
+use toml_edit::DocumentMut;
+
+/// Third-party package or module a tool pulls in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SbomComponent {
+    /// Unique reference of the component in the SBOM, its purl when it has one
+    pub bom_ref: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub purl: Option<String>,
+    /// SPDX expressions or license names
+    pub licenses: Vec<String>,
+    /// CycloneDX hash algorithm (`SHA-256`, `SHA-512`) and hex encoded digest
+    pub hashes: Vec<(String, String)>,
+    /// `bom_ref` of the components it depends on
+    pub dependencies: Vec<String>,
+}
+
+/// PyPI names are case insensitive and treat `-`, `_` and `.` alike
+fn normalize_python_name(name: &str) -> String {
+    Regex::new(r"[-_.]+")
+        .unwrap()
+        .replace_all(&name.to_lowercase(), "-")
+        .to_string()
+}
+
+fn npm_purl(name: &str, version: &str) -> String {
+    format!("pkg:npm/{}@{}", name.replacen('@', "%40", 1), version)
+}
+
+/// `sha256:<hex>` as found in `uv.lock`
+fn uv_lock_hash(hash: &str) -> Option<(String, String)> {
+    let (algorithm, digest) = hash.split_once(':')?;
+    let algorithm = match algorithm {
+        "sha256" => "SHA-256",
+        "sha384" => "SHA-384",
+        "sha512" => "SHA-512",
+        _ => return None,
+    };
+    Some((algorithm.to_string(), digest.to_string()))
+}
+
+/// Subresource integrity (`sha512-<base64>`) as found in `deno.lock` for npm packages
+fn integrity_hash(integrity: &str) -> Option<(String, String)> {
+    let (algorithm, digest) = integrity.split_once('-')?;
+    let algorithm = match algorithm {
+        "sha256" => "SHA-256",
+        "sha384" => "SHA-384",
+        "sha512" => "SHA-512",
+        _ => return None,
+    };
+    let digest = base64_simd::STANDARD.decode_to_vec(digest).ok()?;
+    Some((
+        algorithm.to_string(),
+        hex_simd::encode_to_string(digest, hex_simd::AsciiCase::Lower),
+    ))
+}
+
+/// Distribution installed in a venv
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PythonDistribution {
+    pub licenses: Vec<String>,
+    /// Compatibility tags of the installed wheel, e.g. `cp312-cp312-manylinux_2_17_x86_64`
+    pub wheel_tags: Vec<String>,
+}
+
+/// Compatibility tags of a wheel from its file name, with compressed tag sets expanded:
+/// `pkg-1.0-cp312.cp313-abi3-manylinux_2_17_x86_64.whl` has two tags
+fn wheel_file_tags(url: &str) -> Vec<String> {
+    let file_name = url.rsplit('/').next().unwrap_or_default();
+    let parts: Vec<&str> = file_name.trim_end_matches(".whl").split('-').collect();
+    if parts.len() < 5 {
+        return Vec::new();
+    }
+    let [python_tags, abi_tags, platform_tags] = [
+        parts[parts.len() - 3],
+        parts[parts.len() - 2],
+        parts[parts.len() - 1],
+    ];
+    let mut tags = Vec::new();
+    for python_tag in python_tags.split('.') {
+        for abi_tag in abi_tags.split('.') {
+            for platform_tag in platform_tags.split('.') {
+                tags.push(format!("{}-{}-{}", python_tag, abi_tag, platform_tag));
+            }
+        }
+    }
+    tags
+}
+
+/// Packages resolved in a `uv.lock`, except the tool project itself. `distributions` are the
+/// distributions installed in the venv by normalized name, the hash of each package is the one of
+/// the wheel that was installed, or of the sdist when it was built from it.
+pub fn uv_lock_components(
+    uv_lock: &str,
+    distributions: &HashMap<String, PythonDistribution>,
+) -> anyhow::Result<Vec<SbomComponent>> {
+    let uv_lock = uv_lock.parse::<DocumentMut>()?;
+    let packages = match uv_lock
+        .get("package")
+        .and_then(|packages| packages.as_array_of_tables())
+    {
+        Some(packages) => packages,
+        None => return Ok(Vec::new()),
+    };
+    let purls: HashMap<String, String> = packages
+        .iter()
+        .filter_map(|package| {
+            let name = package.get("name")?.as_str()?;
+            let version = package.get("version")?.as_str()?;
+            Some((
+                name.to_string(),
+                format!("pkg:pypi/{}@{}", normalize_python_name(name), version),
+            ))
+        })
+        .collect();
+
+    let mut components = Vec::new();
+    for package in packages.iter() {
+        let source = package
+            .get("source")
+            .and_then(|source| source.as_inline_table());
+        let is_project = source.is_some_and(|source| {
+            source.contains_key("virtual") || source.contains_key("editable")
+        });
+        let Some(name) = package.get("name").and_then(|name| name.as_str()) else {
+            continue;
+        };
+        if is_project {
+            continue;
+        }
+        let distribution = distributions.get(&normalize_python_name(name));
+        let wheels: Vec<&toml_edit::InlineTable> = package
+            .get("wheels")
+            .and_then(|wheels| wheels.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|wheel| wheel.as_inline_table())
+            .collect();
+        let installed_wheel = distribution.and_then(|distribution| {
+            wheels.iter().find(|wheel| {
+                let url = wheel
+                    .get("url")
+                    .and_then(|url| url.as_str())
+                    .unwrap_or_default();
+                wheel_file_tags(url)
+                    .iter()
+                    .any(|tag| distribution.wheel_tags.contains(tag))
+            })
+        });
+        let sdist_hash = package
+            .get("sdist")
+            .and_then(|sdist| sdist.as_inline_table())
+            .and_then(|sdist| sdist.get("hash"));
+        let first_wheel_hash = wheels.first().and_then(|wheel| wheel.get("hash"));
+        // Without the installed distribution, the wheel is assumed to be the one installed
+        let artifact_hash = match (installed_wheel, distribution) {
+            (Some(wheel), _) => wheel.get("hash"),
+            (None, Some(_)) => sdist_hash.or(first_wheel_hash),
+            (None, None) => first_wheel_hash.or(sdist_hash),
+        }
+        .and_then(|hash| hash.as_str())
+        .and_then(uv_lock_hash);
+        let dependencies = package
+            .get("dependencies")
+            .and_then(|dependencies| dependencies.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| dependency.as_inline_table()?.get("name")?.as_str())
+            .filter_map(|dependency| purls.get(dependency).cloned())
+            .collect();
+        components.push(SbomComponent {
+            bom_ref: purls[name].clone(),
+            name: name.to_string(),
+            version: package
+                .get("version")
+                .and_then(|version| version.as_str())
+                .map(|version| version.to_string()),
+            purl: Some(purls[name].clone()),
+            licenses: distribution
+                .map(|distribution| distribution.licenses.clone())
+                .unwrap_or_default(),
+            hashes: artifact_hash.into_iter().collect(),
+            dependencies,
+        });
+    }
+    Ok(components)
+}
+
+/// Licenses of a distribution from its `METADATA`: `License-Expression`, a short `License` or the
+/// license classifiers
+fn python_metadata_licenses(metadata: &str) -> Vec<String> {
+    let mut license = None;
+    let mut classifiers = Vec::new();
+    for line in metadata.lines() {
+        // The headers end at the first empty line, the description follows
+        if line.is_empty() {
+            break;
+        }
+        if let Some(expression) = line.strip_prefix("License-Expression: ") {
+            return vec![expression.trim().to_string()];
+        } else if let Some(value) = line.strip_prefix("License: ") {
+            let value = value.trim();
+            if !value.is_empty() && value != "UNKNOWN" && value.len() <= 64 {
+                license = Some(value.to_string());
+            }
+        } else if let Some(classifier) = line.strip_prefix("Classifier: License :: ") {
+            if let Some(name) = classifier.rsplit(" :: ").next() {
+                classifiers.push(name.trim().to_string());
+            }
+        }
+    }
+    match license {
+        Some(license) => vec![license],
+        None => classifiers,
+    }
+}
+
+/// Distributions installed in a venv by normalized name, with their licenses and the tags of
+/// their wheel
+pub fn python_venv_distributions(venv_folder_path: &Path) -> HashMap<String, PythonDistribution> {
+    let mut distributions = HashMap::new();
+    for pattern in [
+        "lib/*/site-packages/*.dist-info/METADATA",
+        "Lib/site-packages/*.dist-info/METADATA",
+    ] {
+        let pattern = venv_folder_path.join(pattern).to_string_lossy().to_string();
+        for metadata_path in glob::glob(&pattern).into_iter().flatten().flatten() {
+            let Ok(metadata) = std::fs::read_to_string(&metadata_path) else {
+                continue;
+            };
+            let name = metadata
+                .lines()
+                .find_map(|line| line.strip_prefix("Name: "))
+                .map(normalize_python_name);
+            let Some(name) = name else {
+                continue;
+            };
+            let wheel_tags = std::fs::read_to_string(metadata_path.with_file_name("WHEEL"))
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|line| line.strip_prefix("Tag: "))
+                .map(|tag| tag.trim().to_string())
+                .collect();
+            distributions.insert(
+                name,
+                PythonDistribution {
+                    licenses: python_metadata_licenses(&metadata),
+                    wheel_tags,
+                },
+            );
+        }
+    }
+    distributions
+}
+
+/// npm packages, jsr packages and remote modules of the module graph printed by
+/// `deno info --json`, with the hashes of `deno.lock`. `npm_license` finds the license of an npm
+/// package by name and version.
+pub fn deno_info_components(
+    deno_info: &Value,
+    deno_lock: &Value,
+    npm_license: impl Fn(&str, &str) -> Option<String>,
+) -> Vec<SbomComponent> {
+    let mut components = Vec::new();
+
+    let npm_packages = deno_info["npmPackages"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    let npm_refs: HashMap<&String, String> = npm_packages
+        .iter()
+        .filter_map(|(id, package)| {
+            Some((
+                id,
+                npm_purl(package["name"].as_str()?, package["version"].as_str()?),
+            ))
+        })
+        .collect();
+    let mut npm_ids: Vec<&String> = npm_refs.keys().cloned().collect();
+    npm_ids.sort();
+    for id in npm_ids {
+        let package = &npm_packages[id];
+        let name = package["name"].as_str().unwrap_or_default();
+        let version = package["version"].as_str().unwrap_or_default();
+        // Lockfile keys don't have the peer dependency suffix deno adds to some ids
+        let lock_id = format!("{}@{}", name, version);
+        components.push(SbomComponent {
+            bom_ref: npm_refs[id].clone(),
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            purl: Some(npm_refs[id].clone()),
+            licenses: npm_license(name, version).into_iter().collect(),
+            hashes: deno_lock["npm"][&lock_id]["integrity"]
+                .as_str()
+                .and_then(integrity_hash)
+                .into_iter()
+                .collect(),
+            dependencies: package["dependencies"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|dependency| npm_refs.get(&dependency.as_str()?.to_string()).cloned())
+                .collect(),
+        });
+    }
+
+    let jsr_regex = Regex::new(r"^https://jsr\.io/(@[^/]+/[^/]+)/([^/]+)/").unwrap();
+    let mut seen = HashSet::new();
+    for module in deno_info["modules"].as_array().into_iter().flatten() {
+        let Some(specifier) = module["specifier"].as_str() else {
+            continue;
+        };
+        if let Some(captures) = jsr_regex.captures(specifier) {
+            let id = format!("{}@{}", &captures[1], &captures[2]);
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            components.push(SbomComponent {
+                bom_ref: format!("jsr:{}", id),
+                name: captures[1].to_string(),
+                version: Some(captures[2].to_string()),
+                hashes: deno_lock["jsr"][&id]["integrity"]
+                    .as_str()
+                    .map(|digest| (String::from("SHA-256"), digest.to_string()))
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            });
+        } else if specifier.starts_with("https://") || specifier.starts_with("http://") {
+            if !seen.insert(specifier.to_string()) {
+                continue;
+            }
+            components.push(SbomComponent {
+                bom_ref: specifier.to_string(),
+                name: specifier.to_string(),
+                hashes: deno_lock["remote"][specifier]
+                    .as_str()
+                    .map(|digest| (String::from("SHA-256"), digest.to_string()))
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            });
+        }
+    }
+    components
+}
+
+fn license_json(license: &str) -> Value {
+    if [" OR ", " AND ", " WITH "]
+        .iter()
+        .any(|operator| license.contains(operator))
+    {
+        json!({ "expression": license })
+    } else if Regex::new(r"^[A-Za-z0-9.+-]+$").unwrap().is_match(license) {
+        json!({ "license": { "id": license } })
+    } else {
+        json!({ "license": { "name": license } })
+    }
+}
+
+/// CycloneDX 1.5 JSON document of the tool named `tool_name` and its components. Components no
+/// other component depends on are the direct dependencies of the tool.
+pub fn cyclonedx_json(tool_name: &str, components: &[SbomComponent]) -> Value {
+    let tool_ref = String::from("tool");
+    let dependents: HashSet<&String> = components
+        .iter()
+        .flat_map(|component| component.dependencies.iter())
+        .collect();
+    let direct_dependencies: Vec<&String> = components
+        .iter()
+        .map(|component| &component.bom_ref)
+        .filter(|bom_ref| !dependents.contains(bom_ref))
+        .collect();
+
+    let components_json: Vec<Value> = components
+        .iter()
+        .map(|component| {
+            let mut component_json = json!({
+                "type": "library",
+                "bom-ref": component.bom_ref,
+                "name": component.name,
+            });
+            if let Some(version) = &component.version {
+                component_json["version"] = json!(version);
+            }
+            if let Some(purl) = &component.purl {
+                component_json["purl"] = json!(purl);
+            }
+            if !component.licenses.is_empty() {
+                component_json["licenses"] = component
+                    .licenses
+                    .iter()
+                    .map(|license| license_json(license))
+                    .collect();
+            }
+            if !component.hashes.is_empty() {
+                component_json["hashes"] = component
+                    .hashes
+                    .iter()
+                    .map(|(algorithm, content)| json!({ "alg": algorithm, "content": content }))
+                    .collect();
+            }
+            component_json
+        })
+        .collect();
+    let mut dependencies_json = vec![json!({ "ref": tool_ref, "dependsOn": direct_dependencies })];
+    dependencies_json.extend(
+        components.iter().map(
+            |component| json!({ "ref": component.bom_ref, "dependsOn": component.dependencies }),
+        ),
+    );
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": {
+                "type": "application",
+                "bom-ref": tool_ref,
+                "name": tool_name,
+            },
+        },
+        "components": components_json,
+        "dependencies": dependencies_json,
+    })
+}
+
+#[cfg(test)]
+#[path = "sbom.test.rs"]
+mod tests;
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use crate::tools::sbom::{
+    cyclonedx_json, deno_info_components, python_venv_distributions, uv_lock_components,
+    PythonDistribution, SbomComponent,
+};
+
+const UV_LOCK: &str = r#"
+version = 1
+requires-python = ">=3.10"
+
+[[package]]
+name = "certifi"
+version = "2024.8.30"
+source = { registry = "https://pypi.org/simple" }
+sdist = { url = "https://files.pythonhosted.org/certifi-2024.8.30.tar.gz", hash = "sha256:bec941d2aa8195e248a60b31ff9f0558284cf01a52591ceda73ea9afffd69fd9", size = 168507 }
+wheels = [
+    { url = "https://files.pythonhosted.org/certifi-2024.8.30-py3-none-any.whl", hash = "sha256:922820b53db7a7257ffbda3f597266d435245903d80737e34f8a45ff3e3230d8", size = 167321 },
+]
+
+[[package]]
+name = "hanzo-tool"
+version = "0.0.1"
+source = { virtual = "." }
+dependencies = [
+    { name = "requests" },
+]
+
+[[package]]
+name = "requests"
+version = "2.32.3"
+source = { registry = "https://pypi.org/simple" }
+dependencies = [
+    { name = "certifi" },
+]
+wheels = [
+    { url = "https://files.pythonhosted.org/requests-2.32.3-py3-none-any.whl", hash = "sha256:70761cfe03c773ceb22aa2f671b4757976145175cdfca038c02654d061d6dcc6", size = 64928 },
+]
+"#;
+
+#[test]
+fn test_uv_lock_components() {
+    let distributions = HashMap::from([(
+        "requests".to_string(),
+        PythonDistribution {
+            licenses: vec!["Apache-2.0".to_string()],
+            wheel_tags: vec!["py3-none-any".to_string()],
+        },
+    )]);
+    let components = uv_lock_components(UV_LOCK, &distributions).unwrap();
+    assert_eq!(
+        components,
+        vec![
+            SbomComponent {
+                bom_ref: "pkg:pypi/certifi@2024.8.30".to_string(),
+                name: "certifi".to_string(),
+                version: Some("2024.8.30".to_string()),
+                purl: Some("pkg:pypi/certifi@2024.8.30".to_string()),
+                licenses: vec![],
+                hashes: vec![(
+                    "SHA-256".to_string(),
+                    "922820b53db7a7257ffbda3f597266d435245903d80737e34f8a45ff3e3230d8".to_string()
+                )],
+                dependencies: vec![],
+            },
+            SbomComponent {
+                bom_ref: "pkg:pypi/requests@2.32.3".to_string(),
+                name: "requests".to_string(),
+                version: Some("2.32.3".to_string()),
+                purl: Some("pkg:pypi/requests@2.32.3".to_string()),
+                licenses: vec!["Apache-2.0".to_string()],
+                hashes: vec![(
+                    "SHA-256".to_string(),
+                    "70761cfe03c773ceb22aa2f671b4757976145175cdfca038c02654d061d6dcc6".to_string()
+                )],
+                dependencies: vec!["pkg:pypi/certifi@2024.8.30".to_string()],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_uv_lock_components_hash_the_installed_artifact() {
+    let uv_lock = r#"
+version = 1
+
+[[package]]
+name = "numpy"
+version = "2.1.3"
+source = { registry = "https://pypi.org/simple" }
+sdist = { url = "https://files.pythonhosted.org/numpy-2.1.3.tar.gz", hash = "sha256:aa08" }
+wheels = [
+    { url = "https://files.pythonhosted.org/numpy-2.1.3-cp312-cp312-macosx_14_0_arm64.whl", hash = "sha256:bb01" },
+    { url = "https://files.pythonhosted.org/numpy-2.1.3-cp312-cp312-manylinux_2_17_x86_64.manylinux2014_x86_64.whl", hash = "sha256:bb02" },
+]
+"#;
+    let hashes = |wheel_tags: &[&str]| {
+        let distributions = HashMap::from([(
+            "numpy".to_string(),
+            PythonDistribution {
+                licenses: vec![],
+                wheel_tags: wheel_tags.iter().map(|tag| tag.to_string()).collect(),
+            },
+        )]);
+        uv_lock_components(uv_lock, &distributions).unwrap()[0]
+            .hashes
+            .clone()
+    };
+    let hash = |digest: &str| vec![("SHA-256".to_string(), digest.to_string())];
+    assert_eq!(hashes(&["cp312-cp312-manylinux2014_x86_64"]), hash("bb02"));
+    assert_eq!(hashes(&["cp312-cp312-macosx_14_0_arm64"]), hash("bb01"));
+    // Built from the sdist, e.g. on a platform without wheels
+    assert_eq!(hashes(&["cp312-cp312-linux_riscv64"]), hash("aa08"));
+}
+
+#[test]
+fn test_python_venv_distributions() {
+    let venv = tempfile::tempdir().unwrap();
+    let site_packages = venv.path().join("lib/python3.13/site-packages");
+    for (dist_info, metadata) in [
+        (
+            "requests-2.32.3.dist-info",
+            "Metadata-Version: 2.1\nName: requests\nLicense: Apache-2.0\n\nLicense: not a header",
+        ),
+        (
+            "Typing_Extensions-4.12.2.dist-info",
+            "Metadata-Version: 2.4\nName: Typing_Extensions\nLicense-Expression: PSF-2.0\n",
+        ),
+        (
+            "certifi-2024.8.30.dist-info",
+            "Metadata-Version: 2.1\nName: certifi\nLicense: UNKNOWN\nClassifier: License :: OSI Approved :: Mozilla Public License 2.0 (MPL 2.0)\n",
+        ),
+    ] {
+        std::fs::create_dir_all(site_packages.join(dist_info)).unwrap();
+        std::fs::write(site_packages.join(dist_info).join("METADATA"), metadata).unwrap();
+    }
+    std::fs::write(
+        site_packages.join("requests-2.32.3.dist-info/WHEEL"),
+        "Wheel-Version: 1.0\nRoot-Is-Purelib: true\nTag: py2-none-any\nTag: py3-none-any\n",
+    )
+    .unwrap();
+    let distributions = python_venv_distributions(venv.path());
+    let licenses: HashMap<String, Vec<String>> = distributions
+        .iter()
+        .map(|(name, distribution)| (name.clone(), distribution.licenses.clone()))
+        .collect();
+    assert_eq!(
+        licenses,
+        HashMap::from([
+            ("requests".to_string(), vec!["Apache-2.0".to_string()]),
+            ("typing-extensions".to_string(), vec!["PSF-2.0".to_string()]),
+            (
+                "certifi".to_string(),
+                vec!["Mozilla Public License 2.0 (MPL 2.0)".to_string()]
+            ),
+        ])
+    );
+    assert_eq!(
+        distributions["requests"].wheel_tags,
+        vec!["py2-none-any", "py3-none-any"]
+    );
+    assert!(distributions["certifi"].wheel_tags.is_empty());
+}
+
+#[test]
+fn test_deno_info_components() {
+    let deno_info = json!({
+        "roots": ["file:///app/code/main.ts"],
+        "modules": [
+            { "kind": "esm", "specifier": "file:///app/code/main.ts" },
+            { "kind": "esm", "specifier": "https://jsr.io/@std/path/1.0.8/join.ts" },
+            { "kind": "esm", "specifier": "https://jsr.io/@std/path/1.0.8/_common/assert_path.ts" },
+            { "kind": "esm", "specifier": "https://deno.land/x/case@2.2.0/mod.ts" },
+            { "kind": "npm", "specifier": "npm:/chalk@5.3.0", "npmPackage": "chalk@5.3.0" },
+        ],
+        "npmPackages": {
+            "@scope/colors@1.0.0": { "name": "@scope/colors", "version": "1.0.0", "dependencies": [] },
+            "chalk@5.3.0": { "name": "chalk", "version": "5.3.0", "dependencies": ["@scope/colors@1.0.0"] },
+        },
+    });
+    let deno_lock = json!({
+        "version": "4",
+        "jsr": { "@std/path@1.0.8": { "integrity": "548fa456bb6a04d3c1a1e7477986b6cffbce95102d0bb447c67c4ee70e0364be" } },
+        "npm": { "chalk@5.3.0": { "integrity": "sha512-dLitG79d+GV1Nb/VYcCDFivJeK1hiukt9QjRNVOsUtTy1rR1YJsmpGGTZ3qJos+uw7WmWF4wUwBd9jxjocFC2w==" } },
+        "remote": { "https://deno.land/x/case@2.2.0/mod.ts": "b3b3d1d6cb4ba1bb1b4c2d9ef8b8d1ee5fd2bd3c94b1e5ac8e7bc4ec1dc9d34f" },
+    });
+    let components = deno_info_components(&deno_info, &deno_lock, |name, _| {
+        (name == "chalk").then(|| "MIT".to_string())
+    });
+    let summary: Vec<_> = components
+        .iter()
+        .map(|c| {
+            (
+                c.bom_ref.clone(),
+                c.version.clone(),
+                c.licenses.clone(),
+                c.hashes.len(),
+                c.dependencies.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                "pkg:npm/%40scope/colors@1.0.0".to_string(),
+                Some("1.0.0".to_string()),
+                vec![],
+                0,
+                vec![]
+            ),
+            (
+                "pkg:npm/chalk@5.3.0".to_string(),
+                Some("5.3.0".to_string()),
+                vec!["MIT".to_string()],
+                1,
+                vec!["pkg:npm/%40scope/colors@1.0.0".to_string()]
+            ),
+            (
+                "jsr:@std/path@1.0.8".to_string(),
+                Some("1.0.8".to_string()),
+                vec![],
+                1,
+                vec![]
+            ),
+            (
+                "https://deno.land/x/case@2.2.0/mod.ts".to_string(),
+                None,
+                vec![],
+                1,
+                vec![]
+            ),
+        ]
+    );
+    assert_eq!(
+        components[1].hashes[0],
+        (
+            "SHA-512".to_string(),
+            "74b8ad1bbf5df8657535bfd561c083162bc978ad618ae92df508d13553ac52d4f2d6b475609b26a46193677a89a2cfaec3b5a6585e3053005df63c63a1c142db".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_cyclonedx_json() {
+    let components = vec![
+        SbomComponent {
+            bom_ref: "pkg:npm/chalk@5.3.0".to_string(),
+            name: "chalk".to_string(),
+            version: Some("5.3.0".to_string()),
+            purl: Some("pkg:npm/chalk@5.3.0".to_string()),
+            licenses: vec!["MIT".to_string()],
+            hashes: vec![("SHA-512".to_string(), "74b8".to_string())],
+            dependencies: vec!["pkg:npm/ansi@1.0.0".to_string()],
+        },
+        SbomComponent {
+            bom_ref: "pkg:npm/ansi@1.0.0".to_string(),
+            name: "ansi".to_string(),
+            version: Some("1.0.0".to_string()),
+            purl: Some("pkg:npm/ansi@1.0.0".to_string()),
+            licenses: vec!["MIT OR Apache-2.0".to_string(), "BSD License".to_string()],
+            ..Default::default()
+        },
+    ];
+    let sbom = cyclonedx_json("echo", &components);
+    assert_eq!(sbom["bomFormat"], "CycloneDX");
+    assert_eq!(sbom["specVersion"], "1.5");
+    assert_eq!(sbom["metadata"]["component"]["name"], "echo");
+    assert_eq!(
+        sbom["components"][0],
+        json!({
+            "type": "library",
+            "bom-ref": "pkg:npm/chalk@5.3.0",
+            "name": "chalk",
+            "version": "5.3.0",
+            "purl": "pkg:npm/chalk@5.3.0",
+            "licenses": [{ "license": { "id": "MIT" } }],
+            "hashes": [{ "alg": "SHA-512", "content": "74b8" }],
+        })
+    );
+    assert_eq!(
+        sbom["components"][1]["licenses"],
+        json!([
+            { "expression": "MIT OR Apache-2.0" },
+            { "license": { "name": "BSD License" } },
+        ])
+    );
+    assert_eq!(
+        sbom["dependencies"],
+        json!([
+            { "ref": "tool", "dependsOn": ["pkg:npm/chalk@5.3.0"] },
+            { "ref": "pkg:npm/chalk@5.3.0", "dependsOn": ["pkg:npm/ansi@1.0.0"] },
+            { "ref": "pkg:npm/ansi@1.0.0", "dependsOn": [] },
+        ])
+    );
+}
@@ -0,0 +1,441 @@
+use serde_json::{json, Value};
+
+use crate::tools::{
+    execution_storage::ExecutionStorage,
+    file_changes::ChangeTracker,
+    file_name_utils::{adapt_paths_in_value, sanitize_for_file_name},
+    home_transaction::HomeTransactionMode,
+    mounts::{docker_mount_params, docker_path_mappings, mount_envs},
+    path_buf_ext::PathBufExt,
+    process_utils::run_process,
+    runner_type::{resolve_runner_type, RunnerType},
+};
+
+use super::{
+    code_files::CodeFiles, execution_error::ExecutionError, run_artifact::RunArtifact,
+    run_result::RunResult, shell_runner_options::ShellRunnerOptions,
+};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+/// Flattens `value` into env vars named after `prefix` and the path to each scalar, keys
+/// uppercased and array items by index, e.g. `{"user": {"name": "a"}}` with the prefix
+/// `SHINKAI_PARAM` is `SHINKAI_PARAM_USER_NAME=a`. Strings are set as is, other scalars as JSON and
+/// nulls aren't set. Fails when two paths are set as the same env var, e.g. `first-name` and
+/// `first_name`.
+pub fn flatten_json_envs(prefix: &str, value: &Value) -> anyhow::Result<Vec<(String, String)>> {
+    let mut envs = Vec::new();
+    flatten_json_value(prefix, "", value, &mut envs);
+    let mut env_paths: HashMap<&str, &str> = HashMap::new();
+    for (name, path, _) in &envs {
+        if let Some(other_path) = env_paths.insert(name, path) {
+            return Err(anyhow::anyhow!(
+                "keys `{}` and `{}` are both set as {}",
+                other_path,
+                path,
+                name
+            ));
+        }
+    }
+    Ok(envs
+        .into_iter()
+        .map(|(name, _, value)| (name, value))
+        .collect())
+}
+
+/// Pushes the env var name, the JSON path and the value of every scalar in `value` to `envs`
+fn flatten_json_value(
+    prefix: &str,
+    path: &str,
+    value: &Value,
+    envs: &mut Vec<(String, String, String)>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let name = key
+                    .to_uppercase()
+                    .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+                flatten_json_value(
+                    &format!("{}_{}", prefix, name),
+                    &json_path(path, key),
+                    value,
+                    envs,
+                );
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                flatten_json_value(
+                    &format!("{}_{}", prefix, index),
+                    &json_path(path, &index.to_string()),
+                    item,
+                    envs,
+                );
+            }
+        }
+        Value::Null => {}
+        Value::String(value) => envs.push((prefix.to_string(), path.to_string(), value.clone())),
+        value => envs.push((prefix.to_string(), path.to_string(), value.to_string())),
+    }
+}
+
+fn json_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Runs shell scripts with bash.
+///
+/// The script gets `{"configurations": ..., "parameters": ...}` as JSON in stdin and flattened in
+/// the `SHINKAI_CONFIG_*` and `SHINKAI_PARAM_*` env vars. It writes its result as JSON to the file
+/// in `SHINKAI_RESULT`, the result is `null` when it doesn't.
+#[derive(Default)]
+pub struct ShellRunner {
+    code: CodeFiles,
+    configurations: Value,
+    options: ShellRunnerOptions,
+}
+
+impl ShellRunner {
+    pub const RESULT_FILE_NAME: &'static str = "result.json";
+
+    pub fn new(
+        code_files: CodeFiles,
+        configurations: Value,
+        options: Option<ShellRunnerOptions>,
+    ) -> Self {
+        let options = options.unwrap_or_default();
+        ShellRunner {
+            code: code_files,
+            configurations,
+            options,
+        }
+    }
+
+    /// Folder the script of the execution writes its result file to
+    fn result_folder_path(&self, execution_storage: &ExecutionStorage) -> PathBuf {
+        execution_storage
+            .root_folder_path
+            .join("results")
+            .join(sanitize_for_file_name(
+                self.options.context.execution_id.clone(),
+            ))
+    }
+
+    pub async fn run(
+        &self,
+        envs: Option<HashMap<String, String>>,
+        parameters: Value,
+        max_execution_timeout: Option<Duration>,
+    ) -> Result<RunResult, ExecutionError> {
+        log::info!("preparing to run tool");
+        log::info!("configurations: {}", self.configurations.to_string());
+        log::info!("parameters: {}", parameters.to_string());
+
+        let resolved_runner_type = resolve_runner_type(self.options.force_runner_type.clone());
+
+        let mut adapted_configurations = self.configurations.clone();
+        let mut adapted_parameters = parameters.clone();
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        // Deep traverse configurations and parameters and map mounted paths to their container path
+        if matches!(resolved_runner_type, RunnerType::Docker) {
+            let mounts = execution_storage.resolved_mounts().map_err(|e| {
+                ExecutionError::new(format!("failed to resolve mounts: {}", e), None)
+            })?;
+            let path_mappings = docker_path_mappings(&mounts);
+            adapted_configurations = adapt_paths_in_value(&adapted_configurations, &path_mappings);
+            adapted_parameters = adapt_paths_in_value(&adapted_parameters, &path_mappings);
+        }
+
+        let input = json!({
+            "configurations": adapted_configurations,
+            "parameters": adapted_parameters,
+        })
+        .to_string();
+        let mut script_envs = flatten_json_envs("SHINKAI_CONFIG", &adapted_configurations)
+            .map_err(|e| ExecutionError::new(format!("invalid configurations: {}", e), None))?;
+        script_envs.extend(
+            flatten_json_envs("SHINKAI_PARAM", &adapted_parameters)
+                .map_err(|e| ExecutionError::new(format!("invalid parameters: {}", e), None))?,
+        );
+        if let Some(envs) = envs {
+            script_envs.extend(envs);
+        }
+
+        execution_storage.begin_home_transaction().map_err(|e| {
+            ExecutionError::new(format!("failed to stage home folder: {}", e), None)
+        })?;
+
+        let change_tracker =
+            ChangeTracker::start(&execution_storage, self.options.track_changes.as_ref())?;
+
+        let result_folder_path = self.result_folder_path(&execution_storage);
+        let run_result: Result<RunResult, ExecutionError> = async {
+            execution_storage
+                .init(None)
+                .and_then(|_| {
+                    // A result left by a previous execution with the same id isn't this run result
+                    if result_folder_path.exists() {
+                        std::fs::remove_dir_all(&result_folder_path)?;
+                    }
+                    std::fs::create_dir_all(&result_folder_path)?;
+                    Ok(())
+                })
+                .map_err(|e| {
+                    ExecutionError::new(format!("failed to init execution storage: {}", e), None)
+                })?;
+
+            match resolved_runner_type {
+                RunnerType::Host => {
+                    self.run_in_host(
+                        &execution_storage,
+                        input,
+                        script_envs,
+                        max_execution_timeout,
+                    )
+                    .await
+                }
+                RunnerType::Docker => {
+                    self.run_in_docker(
+                        &execution_storage,
+                        input,
+                        script_envs,
+                        max_execution_timeout,
+                    )
+                    .await
+                }
+            }
+            .map_err(|e| ExecutionError::new(e.to_string(), None))?;
+
+            let result_file_path = result_folder_path.join(Self::RESULT_FILE_NAME);
+            let result = match std::fs::read_to_string(&result_file_path) {
+                Ok(result_text) if !result_text.trim().is_empty() => {
+                    serde_json::from_str(&result_text).map_err(|e| {
+                        log::info!("failed to parse result: {}", e);
+                        ExecutionError::new(format!("failed to parse result: {}", e), None)
+                    })?
+                }
+                _ => Value::Null,
+            };
+            log::info!("successfully parsed run result: {:?}", result);
+
+            let artifacts = RunArtifact::collect(
+                &execution_storage.outputs_folder_path,
+                self.options.inline_artifacts_max_size,
+            )
+            .map_err(|e| {
+                ExecutionError::new(format!("failed to collect artifacts: {}", e), None)
+            })?;
+            Ok(RunResult {
+                data: result,
+                artifacts,
+                changes: Vec::new(),
+                inferred_dependencies: Vec::new(),
+            })
+        }
+        .await;
+        let run_result = match change_tracker {
+            Some(change_tracker) => change_tracker.finish(run_result),
+            None => run_result,
+        };
+        let _ = std::fs::remove_dir_all(&result_folder_path);
+
+        if self.options.context.home_transaction.mode == HomeTransactionMode::CommitOnSuccess {
+            let home_transaction_result = if run_result.is_ok() {
+                execution_storage.commit_home()
+            } else {
+                execution_storage.discard_home()
+            };
+            home_transaction_result.map_err(|e| {
+                ExecutionError::new(format!("failed to finish home transaction: {}", e), None)
+            })?;
+        }
+        run_result
+    }
+
+    async fn run_in_docker(
+        &self,
+        execution_storage: &ExecutionStorage,
+        input: String,
+        envs: Vec<(String, String)>,
+        max_execution_timeout: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        log::info!(
+            "using bash from container image:{:?}",
+            self.options.code_runner_docker_image_name
+        );
+
+        let result_folder_path = self.result_folder_path(execution_storage);
+        let mut mount_params = Vec::<String>::new();
+        let mount_dirs = [
+            (
+                execution_storage.code_folder_path.as_normalized_string(),
+                execution_storage.relative_to_root(execution_storage.code_folder_path.clone()),
+            ),
+            (
+                execution_storage.home_folder_path.as_normalized_string(),
+                // Staged homes are mounted at the same path so SHINKAI_HOME doesn't change
+                String::from("home"),
+            ),
+            (
+                execution_storage.outputs_folder_path.as_normalized_string(),
+                execution_storage.relative_to_root(execution_storage.outputs_folder_path.clone()),
+            ),
+            (
+                result_folder_path.as_normalized_string(),
+                execution_storage.relative_to_root(result_folder_path.clone()),
+            ),
+        ];
+        for (dir, relative_path) in mount_dirs {
+            let mount_param = format!(r#"type=bind,source={},target=/app/{}"#, dir, relative_path);
+            log::info!("mount parameter created: {}", mount_param);
+            mount_params.extend([String::from("--mount"), mount_param]);
+        }
+
+        let mounts = execution_storage.resolved_mounts()?;
+        mount_params.extend(docker_mount_params(&mounts));
+        let (mount_env, mount_assets_env) = mount_envs(&mounts, &RunnerType::Docker);
+
+        let mut script_envs = vec![
+            (
+                String::from("SHINKAI_NODE_LOCATION"),
+                format!(
+                    "{}://host.docker.internal:{}",
+                    self.options.hanzo_node_location.protocol,
+                    self.options.hanzo_node_location.port
+                ),
+            ),
+            (String::from("SHINKAI_HOME"), String::from("/app/home")),
+            (
+                String::from("SHINKAI_OUTPUTS"),
+                format!(
+                    "/app/{}",
+                    execution_storage
+                        .relative_to_root(execution_storage.outputs_folder_path.clone())
+                ),
+            ),
+            (String::from("SHINKAI_ASSETS"), mount_assets_env),
+            (String::from("SHINKAI_MOUNT"), mount_env),
+            (
+                String::from("SHINKAI_CONTEXT_ID"),
+                self.options.context.context_id.clone(),
+            ),
+            (
+                String::from("SHINKAI_EXECUTION_ID"),
+                self.options.context.execution_id.clone(),
+            ),
+            (
+                String::from("SHINKAI_RESULT"),
+                format!(
+                    "/app/{}/{}",
+                    execution_storage.relative_to_root(result_folder_path.clone()),
+                    Self::RESULT_FILE_NAME
+                ),
+            ),
+        ];
+        script_envs.extend(envs);
+        let mut container_envs = Vec::<String>::new();
+        for (key, value) in script_envs {
+            container_envs.push(String::from("-e"));
+            container_envs.push(format!("{}={}", key, value));
+        }
+
+        let code_entrypoint =
+            execution_storage.relative_to_root(execution_storage.code_entrypoint_file_path.clone());
+        let mut command = tokio::process::Command::new("docker");
+        // Keeps stdin open so the script can read its input
+        let mut args = vec!["run", "--rm", "-i"];
+        args.extend(mount_params.iter().map(|s| s.as_str()));
+        args.extend(container_envs.iter().map(|s| s.as_str()));
+        args.extend([
+            "--workdir",
+            "/app",
+            self.options.code_runner_docker_image_name.as_str(),
+            "bash",
+            code_entrypoint.as_str(),
+        ]);
+        command.args(args);
+
+        log::info!("spawning docker command");
+        run_process(
+            execution_storage,
+            &mut command,
+            Some(input),
+            "shell",
+            max_execution_timeout,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run_in_host(
+        &self,
+        execution_storage: &ExecutionStorage,
+        input: String,
+        envs: Vec<(String, String)>,
+        max_execution_timeout: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        log::info!(
+            "using shell from host at path: {:?}",
+            self.options.shell_binary_path
+        );
+
+        let mounts = execution_storage.resolved_mounts()?;
+        let (mount_env, mount_assets_env) = mount_envs(&mounts, &RunnerType::Host);
+
+        let mut command = tokio::process::Command::new(&self.options.shell_binary_path);
+        command
+            .arg(execution_storage.code_entrypoint_file_path.clone())
+            .current_dir(execution_storage.root_folder_path.clone());
+
+        command.env(
+            "SHINKAI_NODE_LOCATION",
+            format!(
+                "{}://{}:{}",
+                self.options.hanzo_node_location.protocol,
+                self.options.hanzo_node_location.host,
+                self.options.hanzo_node_location.port
+            ),
+        );
+        command.env("SHINKAI_HOME", execution_storage.home_folder_path.clone());
+        command.env(
+            "SHINKAI_OUTPUTS",
+            execution_storage.outputs_folder_path.clone(),
+        );
+        command.env("SHINKAI_ASSETS", mount_assets_env);
+        command.env("SHINKAI_MOUNT", mount_env);
+        command.env(
+            "SHINKAI_CONTEXT_ID",
+            self.options.context.context_id.clone(),
+        );
+        command.env(
+            "SHINKAI_EXECUTION_ID",
+            self.options.context.execution_id.clone(),
+        );
+        command.env(
+            "SHINKAI_RESULT",
+            self.result_folder_path(execution_storage)
+                .join(Self::RESULT_FILE_NAME),
+        );
+        command.envs(envs);
+
+        log::info!("prepared command with arguments: {:?}", command);
+        run_process(
+            execution_storage,
+            &mut command,
+            Some(input),
+            "shell",
+            max_execution_timeout,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "shell_runner.test.rs"]
+mod tests;
@@ -0,0 +1,328 @@
+use std::{collections::HashMap, time::Duration};
+
+use rstest::rstest;
+use serde_json::json;
+
+use crate::tools::execution_context::ExecutionContext;
+use crate::tools::execution_storage::ExecutionStorage;
+use crate::tools::file_changes::{ChangeTrackingOptions, FileChangeKind};
+use crate::tools::home_transaction::{HomeTransactionMode, HomeTransactionOptions};
+use crate::tools::runner_type::RunnerType;
+use crate::tools::shell_runner::flatten_json_envs;
+use crate::tools::shell_runner_options::ShellRunnerOptions;
+use crate::tools::{code_files::CodeFiles, shell_runner::ShellRunner};
+
+#[test]
+fn test_flatten_json_envs() {
+    let envs: HashMap<String, String> = flatten_json_envs(
+        "SHINKAI_PARAM",
+        &json!({
+            "name": "world",
+            "user": { "first-name": "Ada", "age": 36, "admin": true },
+            "tags": ["a", "b"],
+            "missing": null,
+        }),
+    )
+    .unwrap()
+    .into_iter()
+    .collect();
+    assert_eq!(
+        envs,
+        HashMap::from([
+            ("SHINKAI_PARAM_NAME".to_string(), "world".to_string()),
+            (
+                "SHINKAI_PARAM_USER_FIRST_NAME".to_string(),
+                "Ada".to_string()
+            ),
+            ("SHINKAI_PARAM_USER_AGE".to_string(), "36".to_string()),
+            ("SHINKAI_PARAM_USER_ADMIN".to_string(), "true".to_string()),
+            ("SHINKAI_PARAM_TAGS_0".to_string(), "a".to_string()),
+            ("SHINKAI_PARAM_TAGS_1".to_string(), "b".to_string()),
+        ])
+    );
+    assert_eq!(
+        flatten_json_envs("SHINKAI_PARAM", &json!("value")).unwrap(),
+        vec![("SHINKAI_PARAM".to_string(), "value".to_string())]
+    );
+}
+
+#[test]
+fn test_flatten_json_envs_rejects_colliding_keys() {
+    let error = flatten_json_envs(
+        "SHINKAI_PARAM",
+        &json!({ "user": { "first-name": "Ada", "first_name": "Grace" } }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "keys `user.first-name` and `user.first_name` are both set as SHINKAI_PARAM_USER_FIRST_NAME"
+    );
+
+    let error =
+        flatten_json_envs("SHINKAI_PARAM", &json!({ "tags": ["a"], "tags_0": "b" })).unwrap_err();
+    assert!(error.to_string().contains("SHINKAI_PARAM_TAGS_0"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn shell_run_reads_input_and_result_file(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let shell_runner = ShellRunner::new(
+        CodeFiles {
+            files: HashMap::from([("main.sh".to_string(), r#"
+input=$(cat)
+echo "running for $SHINKAI_PARAM_NAME"
+cat > "$SHINKAI_RESULT" <<EOF
+{"greeting": "hello $SHINKAI_PARAM_NAME", "key": "$SHINKAI_CONFIG_API_KEY", "custom": "$CUSTOM", "input": $input}
+EOF
+"#.to_string())]),
+            entrypoint: "main.sh".to_string(),
+        },
+        json!({ "api_key": "secret" }),
+        Some(ShellRunnerOptions {
+            force_runner_type: Some(runner_type.clone()),
+            ..Default::default()
+        }),
+    );
+    let result = shell_runner
+        .run(
+            Some(HashMap::from([("CUSTOM".to_string(), "env".to_string())])),
+            json!({ "name": "world" }),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        result.data,
+        json!({
+            "greeting": "hello world",
+            "key": "secret",
+            "custom": "env",
+            "input": {
+                "configurations": { "api_key": "secret" },
+                "parameters": { "name": "world" },
+            },
+        })
+    );
+
+    // Scripts that don't write a result return null
+    let result = ShellRunner::new(
+        CodeFiles {
+            files: HashMap::from([("main.sh".to_string(), "echo done".to_string())]),
+            entrypoint: "main.sh".to_string(),
+        },
+        json!({}),
+        Some(ShellRunnerOptions {
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap();
+    assert_eq!(result.data, json!(null));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn shell_run_errors_and_timeout(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let error = ShellRunner::new(
+        CodeFiles {
+            files: HashMap::from([(
+                "main.sh".to_string(),
+                "echo 'something went wrong' >&2\nexit 3".to_string(),
+            )]),
+            entrypoint: "main.sh".to_string(),
+        },
+        json!({}),
+        Some(ShellRunnerOptions {
+            force_runner_type: Some(runner_type.clone()),
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap_err();
+    assert!(error.message().contains("something went wrong"));
+
+    let error = ShellRunner::new(
+        CodeFiles {
+            files: HashMap::from([("main.sh".to_string(), "sleep 10".to_string())]),
+            entrypoint: "main.sh".to_string(),
+        },
+        json!({}),
+        Some(ShellRunnerOptions {
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), Some(Duration::from_secs(1)))
+    .await
+    .unwrap_err();
+    assert!(error.message().contains("timed out"));
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn shell_run_with_home_mounts_and_outputs(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_file = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    std::fs::write(&mount_file, "mounted").unwrap();
+    let asset_file = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    std::fs::write(&asset_file, "asset").unwrap();
+    let context = ExecutionContext {
+        mount_files: vec![mount_file.to_path_buf()],
+        assets_files: vec![asset_file.to_path_buf()],
+        ..Default::default()
+    };
+
+    let script = r#"
+echo "home" > "$SHINKAI_HOME/home.txt"
+echo "report" > "$SHINKAI_OUTPUTS/report.txt"
+mount=$(cat "${SHINKAI_MOUNT%,}")
+asset=$(cat "${SHINKAI_ASSETS%,}")
+echo "{\"mount\": \"$mount\", \"asset\": \"$asset\", \"path\": \"$SHINKAI_PARAM_PATH\"}" > "$SHINKAI_RESULT"
+"#;
+    let code_files = CodeFiles {
+        files: HashMap::from([("main.sh".to_string(), script.to_string())]),
+        entrypoint: "main.sh".to_string(),
+    };
+    let shell_runner = ShellRunner::new(
+        code_files.clone(),
+        json!({}),
+        Some(ShellRunnerOptions {
+            context: context.clone(),
+            force_runner_type: Some(runner_type.clone()),
+            ..Default::default()
+        }),
+    );
+    let result = shell_runner
+        .run(None, json!({ "path": mount_file.to_string_lossy() }), None)
+        .await
+        .unwrap();
+    assert_eq!(result.data["mount"], "mounted");
+    assert_eq!(result.data["asset"], "asset");
+    // Mounted paths in parameters are mapped to their container path
+    match runner_type {
+        RunnerType::Host => assert_eq!(result.data["path"], *mount_file.to_string_lossy()),
+        RunnerType::Docker => assert!(result.data["path"].as_str().unwrap().starts_with("/app/")),
+    }
+    assert_eq!(result.artifacts.len(), 1);
+    assert_eq!(result.artifacts[0].path, "report.txt");
+
+    let home_folder_path = ExecutionStorage::new(code_files, context).home_folder_path;
+    assert_eq!(
+        std::fs::read_to_string(home_folder_path.join("home.txt")).unwrap(),
+        "home\n"
+    );
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn shell_run_failure_reports_file_changes(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_file = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    std::fs::write(&mount_file, "before").unwrap();
+    let shell_runner = ShellRunner::new(
+        CodeFiles {
+            files: HashMap::from([(
+                "main.sh".to_string(),
+                r#"
+echo "after" > "${SHINKAI_MOUNT%,}"
+exit 1
+"#
+                .to_string(),
+            )]),
+            entrypoint: "main.sh".to_string(),
+        },
+        json!({}),
+        Some(ShellRunnerOptions {
+            context: ExecutionContext {
+                mount_files: vec![mount_file.to_path_buf()],
+                ..Default::default()
+            },
+            force_runner_type: Some(runner_type),
+            track_changes: Some(ChangeTrackingOptions::default()),
+            ..Default::default()
+        }),
+    );
+    let error = shell_runner.run(None, json!({}), None).await.unwrap_err();
+    assert_eq!(error.changes().len(), 1);
+    assert_eq!(error.changes()[0].kind, FileChangeKind::Modified);
+}
+
+#[rstest]
+#[case::host(RunnerType::Host)]
+#[case::docker(RunnerType::Docker)]
+#[tokio::test]
+async fn shell_committed_run_keeps_home_content(#[case] runner_type: RunnerType) {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let context = ExecutionContext {
+        home_transaction: HomeTransactionOptions {
+            mode: HomeTransactionMode::CommitOnSuccess,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let code_files = CodeFiles {
+        files: HashMap::from([(
+            "main.sh".to_string(),
+            r#"echo "new" > "$SHINKAI_HOME/new.txt""#.to_string(),
+        )]),
+        entrypoint: "main.sh".to_string(),
+    };
+    let context_home_folder_path =
+        ExecutionStorage::new(code_files.clone(), context.clone()).context_home_folder_path();
+    std::fs::create_dir_all(&context_home_folder_path).unwrap();
+    std::fs::write(context_home_folder_path.join("state.txt"), "seeded").unwrap();
+
+    ShellRunner::new(
+        code_files,
+        json!({}),
+        Some(ShellRunnerOptions {
+            context,
+            force_runner_type: Some(runner_type),
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("state.txt")).unwrap(),
+        "seeded"
+    );
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("new.txt")).unwrap(),
+        "new\n"
+    );
+}
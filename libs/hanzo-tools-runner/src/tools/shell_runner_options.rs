@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use super::{
+    execution_context::ExecutionContext, file_changes::ChangeTrackingOptions,
+    hanzo_node_location::HanzoNodeLocation, runner_type::RunnerType,
+};
+
+#[derive(Clone)]
+pub struct ShellRunnerOptions {
+    pub context: ExecutionContext,
+    /// Shell used to run the script in the host, looked up in the `PATH` when it isn't a path
+    pub shell_binary_path: PathBuf,
+    pub code_runner_docker_image_name: String,
+    pub force_runner_type: Option<RunnerType>,
+    pub hanzo_node_location: HanzoNodeLocation,
+    /// Output artifacts up to this size (in bytes) are returned inline as base64
+    pub inline_artifacts_max_size: Option<u64>,
+    /// Report files created, modified or deleted by the tool in its read-write mounts
+    pub track_changes: Option<ChangeTrackingOptions>,
+}
+
+impl Default for ShellRunnerOptions {
+    fn default() -> Self {
+        Self {
+            context: ExecutionContext::default(),
+            shell_binary_path: PathBuf::from("bash"),
+            code_runner_docker_image_name: String::from("hanzoai/code-runner:1.0.3"),
+            force_runner_type: None,
+            hanzo_node_location: HanzoNodeLocation {
+                protocol: String::from("http"),
+                host: String::from("127.0.0.1"),
+                port: 9550,
+            },
+            inline_artifacts_max_size: None,
+            track_changes: None,
+        }
+    }
+}
@@ -0,0 +1,534 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use wasmtime::{
+    Config, Engine, Linker, Module, ResourceLimiter, Store, StoreLimits, StoreLimitsBuilder, Trap,
+};
+use wasmtime_wasi::{
+    pipe::{MemoryInputPipe, MemoryOutputPipe},
+    preview1::{self, WasiP1Ctx},
+    DirPerms, FilePerms, I32Exit, WasiCtxBuilder,
+};
+
+use crate::tools::{
+    execution_storage::ExecutionStorage,
+    file_changes::ChangeTracker,
+    file_name_utils::{adapt_paths_in_value, sanitize_for_file_name},
+    home_transaction::HomeTransactionMode,
+    mounts::{docker_path_mappings, mount_envs, MountMode, ResolvedMount},
+    run_artifact::RunArtifact,
+    runner_type::RunnerType,
+};
+
+use super::{
+    code_files::CodeFiles, execution_error::ExecutionError, run_result::RunResult,
+    wasm_runner_options::WasmRunnerOptions,
+};
+
+/// Directory of the host made available to the module at `guest_path`
+#[derive(Clone, Debug)]
+struct Preopen {
+    host_path: PathBuf,
+    guest_path: String,
+    mode: MountMode,
+}
+
+/// Copy of a read-write file mount the module works on, copied back to `host_path` after the run
+struct StagedFile {
+    staged_path: PathBuf,
+    host_path: PathBuf,
+}
+
+/// Memory and table limits of a module execution, growing past them traps. Whether the memory
+/// limit was hit is recorded to report it.
+struct WasmLimits {
+    limits: StoreLimits,
+    memory_limit_exceeded: bool,
+}
+
+impl ResourceLimiter for WasmLimits {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if !self.limits.memory_growing(current, desired, maximum)? {
+            self.memory_limit_exceeded = true;
+            return Err(anyhow::anyhow!(
+                "memory can't grow to {} bytes, over the limit",
+                desired
+            ));
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if !self.limits.table_growing(current, desired, maximum)? {
+            return Err(anyhow::anyhow!(
+                "table can't grow to {} elements, over the limit",
+                desired
+            ));
+        }
+        Ok(true)
+    }
+
+    fn instances(&self) -> usize {
+        self.limits.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.limits.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.limits.memories()
+    }
+}
+
+/// Store data of a module execution
+struct WasmState {
+    wasi: WasiP1Ctx,
+    limits: WasmLimits,
+}
+
+/// Output of a module execution
+struct WasmOutput {
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs WASI (preview 1) modules in-process with wasmtime.
+///
+/// The entrypoint is the module, base64 encoded or in the WebAssembly text format. The module
+/// reads `{"configurations": ..., "parameters": ...}` as JSON from stdin and writes its result as
+/// JSON in the last line of stdout.
+#[derive(Default)]
+pub struct WasmRunner {
+    code: CodeFiles,
+    configurations: Value,
+    options: WasmRunnerOptions,
+}
+
+impl WasmRunner {
+    /// Where the home folder is preopened, the same path docker runs use
+    pub const HOME_GUEST_PATH: &'static str = "/app/home";
+    /// Where the outputs folder is preopened, its files are returned as artifacts
+    pub const OUTPUTS_GUEST_PATH: &'static str = "/app/outputs";
+
+    pub fn new(
+        code_files: CodeFiles,
+        configurations: Value,
+        options: Option<WasmRunnerOptions>,
+    ) -> Self {
+        let options = options.unwrap_or_default();
+        WasmRunner {
+            code: code_files,
+            configurations,
+            options,
+        }
+    }
+
+    /// Bytes of the entrypoint module, decoded from base64 unless it's in the text format
+    fn module_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let module = self
+            .code
+            .files
+            .get(&self.code.entrypoint)
+            .ok_or_else(|| anyhow::anyhow!("no entrypoint found {}", self.code.entrypoint))?;
+        let module = module.trim();
+        if module.starts_with('(') {
+            return Ok(module.as_bytes().to_vec());
+        }
+        base64_simd::STANDARD
+            .decode_to_vec(module)
+            .map_err(|e| anyhow::anyhow!("invalid base64 module {}: {}", self.code.entrypoint, e))
+    }
+
+    /// Compiles the module and checks it's a WASI command, without running it
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let engine = Engine::new(&Self::engine_config(&self.options, false))?;
+        let module = Module::new(&engine, self.module_bytes()?)?;
+        if module.get_export("_start").is_none() {
+            return Err(anyhow::anyhow!(
+                "module {} doesn't export a _start function",
+                self.code.entrypoint
+            ));
+        }
+        Ok(())
+    }
+
+    fn engine_config(options: &WasmRunnerOptions, interruptible: bool) -> Config {
+        let mut config = Config::new();
+        config.consume_fuel(options.max_fuel.is_some());
+        config.epoch_interruption(interruptible);
+        config
+    }
+
+    /// Directories the module can access: the home folder, the directory mounts, for file mounts,
+    /// staging folders with a copy of the mounted files and, last, the outputs folder.
+    ///
+    /// # Returns
+    ///
+    /// The preopens and the staged copies of read-write files with the file they are copied back to
+    fn preopens(
+        &self,
+        execution_storage: &ExecutionStorage,
+        mounts: &[ResolvedMount],
+        staging_folder_path: &Path,
+    ) -> anyhow::Result<(Vec<Preopen>, Vec<StagedFile>)> {
+        let mut preopens = vec![Preopen {
+            host_path: execution_storage.home_folder_path.clone(),
+            guest_path: Self::HOME_GUEST_PATH.to_string(),
+            mode: MountMode::ReadWrite,
+        }];
+        let mut staged_files = Vec::new();
+        // WASI only preopens directories, files are copied to a folder per guest folder and mode
+        let mut staging_folders: BTreeMap<(String, bool), PathBuf> = BTreeMap::new();
+        for mount in mounts {
+            if mount.is_dir {
+                preopens.push(Preopen {
+                    host_path: mount.host_path.clone(),
+                    guest_path: mount.docker_path.clone(),
+                    mode: mount.mode.clone(),
+                });
+                continue;
+            }
+            let guest_path = Path::new(&mount.docker_path);
+            let guest_folder = guest_path
+                .parent()
+                .map(|parent| parent.to_string_lossy().to_string())
+                .unwrap_or_else(|| String::from("/"));
+            let file_name = guest_path
+                .file_name()
+                .ok_or_else(|| anyhow::anyhow!("invalid mount path {}", mount.docker_path))?;
+            let read_write = mount.mode == MountMode::ReadWrite;
+            let key = (guest_folder.clone(), read_write);
+            let staging_folder = match staging_folders.get(&key) {
+                Some(staging_folder) => staging_folder.clone(),
+                None => {
+                    let staging_folder =
+                        staging_folder_path.join(staging_folders.len().to_string());
+                    std::fs::create_dir_all(&staging_folder)?;
+                    preopens.push(Preopen {
+                        host_path: staging_folder.clone(),
+                        guest_path: guest_folder,
+                        mode: mount.mode.clone(),
+                    });
+                    staging_folders.insert(key, staging_folder.clone());
+                    staging_folder
+                }
+            };
+            let staged_file = staging_folder.join(file_name);
+            std::fs::copy(&mount.host_path, &staged_file)?;
+            if read_write {
+                staged_files.push(StagedFile {
+                    staged_path: staged_file,
+                    host_path: mount.host_path.clone(),
+                });
+            }
+        }
+        preopens.push(Preopen {
+            host_path: execution_storage.outputs_folder_path.clone(),
+            guest_path: Self::OUTPUTS_GUEST_PATH.to_string(),
+            mode: MountMode::ReadWrite,
+        });
+        log::info!("wasm preopens: {:?}", preopens);
+        Ok((preopens, staged_files))
+    }
+
+    /// Instantiates the module and runs its `_start` function until it returns, exits or runs out
+    /// of fuel, memory or time
+    fn execute(
+        options: &WasmRunnerOptions,
+        entrypoint: &str,
+        module_bytes: &[u8],
+        input: String,
+        envs: Vec<(String, String)>,
+        preopens: &[Preopen],
+        max_execution_timeout: Option<Duration>,
+    ) -> anyhow::Result<WasmOutput> {
+        let engine = Engine::new(&Self::engine_config(
+            options,
+            max_execution_timeout.is_some(),
+        ))?;
+        let module = Module::new(&engine, module_bytes)
+            .map_err(|e| anyhow::anyhow!("failed to compile module: {}", e))?;
+
+        let stdout = MemoryOutputPipe::new(options.max_output_bytes);
+        let stderr = MemoryOutputPipe::new(options.max_output_bytes);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(input))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .arg(entrypoint)
+            .envs(&envs);
+        for preopen in preopens {
+            let (dir_perms, file_perms) = match preopen.mode {
+                MountMode::ReadWrite => (DirPerms::all(), FilePerms::all()),
+                MountMode::ReadOnly => (DirPerms::READ, FilePerms::READ),
+            };
+            wasi.preopened_dir(
+                &preopen.host_path,
+                &preopen.guest_path,
+                dir_perms,
+                file_perms,
+            )?;
+        }
+
+        let mut linker: Linker<WasmState> = Linker::new(&engine);
+        preview1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)?;
+        let mut store = Store::new(
+            &engine,
+            WasmState {
+                wasi: wasi.build_p1(),
+                limits: WasmLimits {
+                    limits: StoreLimitsBuilder::new()
+                        .memory_size(options.max_memory_bytes)
+                        .table_elements(options.max_memory_bytes / 8)
+                        .build(),
+                    memory_limit_exceeded: false,
+                },
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        if let Some(max_fuel) = options.max_fuel {
+            store.set_fuel(max_fuel)?;
+        }
+
+        // The deadline is reached when the timer increments the epoch, dropping the sender stops it
+        let (_finished, finished_receiver) = std::sync::mpsc::channel::<()>();
+        if let Some(timeout) = max_execution_timeout {
+            store.set_epoch_deadline(1);
+            let engine = engine.clone();
+            std::thread::spawn(move || {
+                if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+                    finished_receiver.recv_timeout(timeout)
+                {
+                    engine.increment_epoch();
+                }
+            });
+        }
+
+        let result = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.get_typed_func::<(), ()>(&mut store, "_start"))
+            .and_then(|start| start.call(&mut store, ()));
+        let exit_code = match result {
+            Ok(()) => 0,
+            Err(e) => {
+                if let Some(exit) = e.downcast_ref::<I32Exit>() {
+                    exit.0
+                } else if let Some(Trap::OutOfFuel) = e.downcast_ref::<Trap>() {
+                    return Err(anyhow::anyhow!(
+                        "module ran out of fuel after {} units",
+                        options.max_fuel.unwrap_or_default()
+                    ));
+                } else if let Some(Trap::Interrupt) = e.downcast_ref::<Trap>() {
+                    let timeout = max_execution_timeout.unwrap_or_default();
+                    log::error!("module execution timed out after {}[s]", timeout.as_secs());
+                    return Err(anyhow::Error::new(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        format!("process timed out after {}[s]", timeout.as_secs()),
+                    )));
+                } else if store.data().limits.memory_limit_exceeded {
+                    return Err(anyhow::anyhow!(
+                        "module exceeded the memory limit of {} bytes",
+                        options.max_memory_bytes
+                    ));
+                } else {
+                    return Err(anyhow::anyhow!("module execution failed: {:#}", e));
+                }
+            }
+        };
+        drop(store);
+        Ok(WasmOutput {
+            exit_code,
+            stdout: String::from_utf8_lossy(&stdout.contents()).to_string(),
+            stderr: String::from_utf8_lossy(&stderr.contents()).to_string(),
+        })
+    }
+
+    pub async fn run(
+        &self,
+        envs: Option<HashMap<String, String>>,
+        parameters: Value,
+        max_execution_timeout: Option<Duration>,
+    ) -> Result<RunResult, ExecutionError> {
+        log::info!("preparing to run wasm tool");
+        log::info!("configurations: {}", self.configurations.to_string());
+        log::info!("parameters: {}", parameters.to_string());
+
+        let module_bytes = self
+            .module_bytes()
+            .map_err(|e| ExecutionError::new(e.to_string(), None))?;
+        let execution_storage =
+            ExecutionStorage::new(self.code.clone(), self.options.context.clone());
+        execution_storage.init(None).map_err(|e| {
+            ExecutionError::new(format!("failed to init execution storage: {}", e), None)
+        })?;
+
+        // Paths in configurations and parameters are mapped to where they are preopened
+        let mounts = execution_storage
+            .resolved_mounts()
+            .map_err(|e| ExecutionError::new(format!("failed to resolve mounts: {}", e), None))?;
+        let path_mappings = docker_path_mappings(&mounts);
+        let input = json!({
+            "configurations": adapt_paths_in_value(&self.configurations, &path_mappings),
+            "parameters": adapt_paths_in_value(&parameters, &path_mappings),
+        })
+        .to_string();
+
+        let (mount_env, mount_assets_env) = mount_envs(&mounts, &RunnerType::Docker);
+        let mut module_envs = vec![
+            (
+                String::from("SHINKAI_HOME"),
+                Self::HOME_GUEST_PATH.to_string(),
+            ),
+            (String::from("SHINKAI_ASSETS"), mount_assets_env),
+            (
+                String::from("SHINKAI_OUTPUTS"),
+                Self::OUTPUTS_GUEST_PATH.to_string(),
+            ),
+            (String::from("SHINKAI_MOUNT"), mount_env),
+            (
+                String::from("SHINKAI_CONTEXT_ID"),
+                self.options.context.context_id.clone(),
+            ),
+            (
+                String::from("SHINKAI_EXECUTION_ID"),
+                self.options.context.execution_id.clone(),
+            ),
+        ];
+        module_envs.extend(envs.unwrap_or_default());
+
+        execution_storage.begin_home_transaction().map_err(|e| {
+            ExecutionError::new(format!("failed to stage home folder: {}", e), None)
+        })?;
+
+        let change_tracker =
+            ChangeTracker::start(&execution_storage, self.options.track_changes.as_ref())?;
+
+        let staging_folder_path = execution_storage
+            .root_folder_path
+            .join("wasm-staging")
+            .join(sanitize_for_file_name(
+                self.options.context.execution_id.clone(),
+            ));
+        let run_result: Result<RunResult, ExecutionError> = async {
+            let (preopens, staged_files) = self
+                .preopens(&execution_storage, &mounts, &staging_folder_path)
+                .map_err(|e| {
+                    ExecutionError::new(format!("failed to preopen mounts: {}", e), None)
+                })?;
+
+            // wasmtime runs the module synchronously, outside of the async runtime threads
+            let options = self.options.clone();
+            let entrypoint = self.code.entrypoint.clone();
+            let output = tokio::task::spawn_blocking(move || {
+                Self::execute(
+                    &options,
+                    &entrypoint,
+                    &module_bytes,
+                    input,
+                    module_envs,
+                    &preopens,
+                    max_execution_timeout,
+                )
+            })
+            .await
+            .map_err(|e| ExecutionError::new(format!("module execution panicked: {}", e), None))?;
+
+            // Files are written back even when the module fails, as with the mounts of the other runners
+            for staged_file in staged_files {
+                std::fs::copy(&staged_file.staged_path, &staged_file.host_path).map_err(|e| {
+                    ExecutionError::new(
+                        format!(
+                            "failed to write back {}: {}",
+                            staged_file.host_path.display(),
+                            e
+                        ),
+                        None,
+                    )
+                })?;
+            }
+            let output = output.map_err(|e| ExecutionError::new(e.to_string(), None))?;
+            for line in output.stdout.lines().chain(output.stderr.lines()) {
+                log::info!("from wasm: {}", line);
+                let _ = execution_storage.append_log(line);
+            }
+            if output.exit_code != 0 {
+                log::error!("module exited with code {}", output.exit_code);
+                return Err(ExecutionError::new(
+                    format!(
+                        "module exited with code {}: {}",
+                        output.exit_code, output.stderr
+                    ),
+                    None,
+                ));
+            }
+
+            let result_text = output
+                .stdout
+                .lines()
+                .rev()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or("null");
+            let result: Value = serde_json::from_str(result_text).map_err(|e| {
+                log::info!("failed to parse result: {}", e);
+                ExecutionError::new(format!("failed to parse result: {}", e), None)
+            })?;
+            log::info!("successfully parsed run result: {:?}", result);
+
+            let artifacts = RunArtifact::collect(
+                &execution_storage.outputs_folder_path,
+                self.options.inline_artifacts_max_size,
+            )
+            .map_err(|e| {
+                ExecutionError::new(format!("failed to collect artifacts: {}", e), None)
+            })?;
+            Ok(RunResult {
+                data: result,
+                artifacts,
+                changes: Vec::new(),
+                inferred_dependencies: Vec::new(),
+            })
+        }
+        .await;
+        let run_result = match change_tracker {
+            Some(change_tracker) => change_tracker.finish(run_result),
+            None => run_result,
+        };
+        if let Err(e) = std::fs::remove_dir_all(&staging_folder_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("failed to remove wasm staging folder: {}", e);
+            }
+        }
+
+        if self.options.context.home_transaction.mode == HomeTransactionMode::CommitOnSuccess {
+            let home_transaction_result = if run_result.is_ok() {
+                execution_storage.commit_home()
+            } else {
+                execution_storage.discard_home()
+            };
+            home_transaction_result.map_err(|e| {
+                ExecutionError::new(format!("failed to finish home transaction: {}", e), None)
+            })?;
+        }
+        run_result
+    }
+}
+
+#[cfg(test)]
+#[path = "wasm_runner.test.rs"]
+mod tests;
@@ -0,0 +1,394 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde_json::json;
+
+use crate::tools::{
+    code_files::CodeFiles,
+    execution_context::ExecutionContext,
+    execution_storage::ExecutionStorage,
+    home_transaction::{HomeTransactionMode, HomeTransactionOptions},
+    wasm_runner::WasmRunner,
+    wasm_runner_options::WasmRunnerOptions,
+};
+
+/// Writes stdin back to stdout, so the result is the input the module received
+const ECHO_MODULE: &str = r#"
+(module
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func (export "_start")
+    (local $total i32)
+    (local $read i32)
+    (block $done
+      (loop $next
+        (i32.store (i32.const 0) (i32.add (i32.const 1024) (local.get $total)))
+        (i32.store (i32.const 4) (i32.sub (i32.const 60000) (local.get $total)))
+        (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+        (local.set $read (i32.load (i32.const 8)))
+        (br_if $done (i32.eqz (local.get $read)))
+        (local.set $total (i32.add (local.get $total) (local.get $read)))
+        (br $next)))
+    (i32.store8 (i32.add (i32.const 1024) (local.get $total)) (i32.const 10))
+    (i32.store (i32.const 0) (i32.const 1024))
+    (i32.store (i32.const 4) (i32.add (local.get $total) (i32.const 1)))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
+"#;
+
+#[tokio::test]
+async fn run_passes_configurations_and_parameters() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let wasm_runner = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([("tool.wasm".to_string(), ECHO_MODULE.to_string())]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({ "api_key": "secret" }),
+        None,
+    );
+    wasm_runner.validate().unwrap();
+    let result = wasm_runner
+        .run(None, json!({ "name": "world" }), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        result.data,
+        json!({
+            "configurations": { "api_key": "secret" },
+            "parameters": { "name": "world" },
+        })
+    );
+
+    // Binary modules are base64 encoded
+    let encoded = base64_simd::STANDARD.encode_to_string(wat::parse_str(ECHO_MODULE).unwrap());
+    let result = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([("tool.wasm".to_string(), encoded)]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({}),
+        None,
+    )
+    .run(None, json!(1), None)
+    .await
+    .unwrap();
+    assert_eq!(result.data["parameters"], json!(1));
+
+    let error = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([("tool.wasm".to_string(), "not a module".to_string())]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({}),
+        None,
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap_err();
+    assert!(error
+        .message()
+        .starts_with("invalid base64 module tool.wasm"));
+}
+
+#[tokio::test]
+async fn run_enforces_resource_limits() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let infinite_loop = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "_start") (loop $forever (br $forever))))
+"#;
+    let error = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([("tool.wasm".to_string(), infinite_loop.to_string())]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({}),
+        Some(WasmRunnerOptions {
+            max_fuel: Some(100_000),
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap_err();
+    assert_eq!(error.message(), "module ran out of fuel after 100000 units");
+
+    let error = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([("tool.wasm".to_string(), infinite_loop.to_string())]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({}),
+        Some(WasmRunnerOptions {
+            max_fuel: None,
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), Some(Duration::from_secs(1)))
+    .await
+    .unwrap_err();
+    assert!(error.message().contains("timed out"));
+
+    let memory_hog = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "_start") (drop (memory.grow (i32.const 100)))))
+"#;
+    let error = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([("tool.wasm".to_string(), memory_hog.to_string())]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({}),
+        Some(WasmRunnerOptions {
+            max_memory_bytes: 1024 * 1024,
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap_err();
+    assert_eq!(
+        error.message(),
+        "module exceeded the memory limit of 1048576 bytes"
+    );
+
+    // Memories larger than the limit fail when the module is instantiated
+    let error = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([(
+                "tool.wasm".to_string(),
+                r#"(module (memory (export "memory") 32) (func (export "_start")))"#.to_string(),
+            )]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({}),
+        Some(WasmRunnerOptions {
+            max_memory_bytes: 1024 * 1024,
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap_err();
+    assert_eq!(
+        error.message(),
+        "module exceeded the memory limit of 1048576 bytes"
+    );
+
+    let exit = r#"
+(module
+  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+  (memory (export "memory") 1)
+  (func (export "_start") (call $proc_exit (i32.const 3))))
+"#;
+    let error = WasmRunner::new(
+        CodeFiles {
+            files: HashMap::from([("tool.wasm".to_string(), exit.to_string())]),
+            entrypoint: "tool.wasm".to_string(),
+        },
+        json!({}),
+        None,
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap_err();
+    assert!(error.message().starts_with("module exited with code 3"));
+}
+
+#[tokio::test]
+async fn run_preopens_home_and_mounts() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let mount_folder = tempfile::tempdir().unwrap();
+    let data_file = mount_folder.path().join("data.txt");
+    std::fs::write(&data_file, "original").unwrap();
+    let assets_folder = tempfile::tempdir().unwrap();
+    let asset_file = assets_folder.path().join("asset.txt");
+    std::fs::write(&asset_file, "asset").unwrap();
+    let context = ExecutionContext {
+        mount_files: vec![data_file.clone()],
+        assets_files: vec![asset_file.clone()],
+        ..Default::default()
+    };
+
+    // Preopened in order: home (fd 3), read-write mounts (fd 4) and assets (fd 5).
+    // Writes home/out.txt and data.txt, then tries to truncate asset.txt.
+    let module = r#"
+(module
+  (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 100) "out.txt")
+  (data (i32.const 120) "data.txt")
+  (data (i32.const 140) "asset.txt")
+  (data (i32.const 200) "changed")
+  (data (i32.const 300) "true\n")
+  (data (i32.const 310) "false\n")
+  (func $write (param $fd i32) (param $ptr i32) (param $len i32)
+    (i32.store (i32.const 0) (local.get $ptr))
+    (i32.store (i32.const 4) (local.get $len))
+    (drop (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))))
+  (func (export "_start")
+    (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 7) (i32.const 1) (i64.const 0x1fffffff) (i64.const 0x1fffffff) (i32.const 0) (i32.const 16))
+      (then unreachable))
+    (call $write (i32.load (i32.const 16)) (i32.const 200) (i32.const 7))
+    (if (call $path_open (i32.const 4) (i32.const 0) (i32.const 120) (i32.const 8) (i32.const 8) (i64.const 0x1fffffff) (i64.const 0x1fffffff) (i32.const 0) (i32.const 16))
+      (then unreachable))
+    (call $write (i32.load (i32.const 16)) (i32.const 200) (i32.const 7))
+    (if (call $path_open (i32.const 5) (i32.const 0) (i32.const 140) (i32.const 9) (i32.const 8) (i64.const 0x1fffffff) (i64.const 0x1fffffff) (i32.const 0) (i32.const 16))
+      (then (call $write (i32.const 1) (i32.const 300) (i32.const 5)))
+      (else (call $write (i32.const 1) (i32.const 310) (i32.const 6))))))
+"#;
+    let code_files = CodeFiles {
+        files: HashMap::from([("tool.wasm".to_string(), module.to_string())]),
+        entrypoint: "tool.wasm".to_string(),
+    };
+    let result = WasmRunner::new(
+        code_files.clone(),
+        json!({}),
+        Some(WasmRunnerOptions {
+            context: context.clone(),
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap();
+    assert_eq!(result.data, json!(true), "assets are read-only");
+    let home_folder_path = ExecutionStorage::new(code_files, context).home_folder_path;
+    assert_eq!(
+        std::fs::read_to_string(home_folder_path.join("out.txt")).unwrap(),
+        "changed"
+    );
+    assert_eq!(std::fs::read_to_string(&data_file).unwrap(), "changed");
+    assert_eq!(std::fs::read_to_string(&asset_file).unwrap(), "asset");
+}
+
+#[tokio::test]
+async fn run_committed_keeps_home_content() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    let context = ExecutionContext {
+        home_transaction: HomeTransactionOptions {
+            mode: HomeTransactionMode::CommitOnSuccess,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    // Writes home/new.txt and prints null as result
+    let module = r#"
+(module
+  (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 100) "new.txt")
+  (data (i32.const 200) "new")
+  (data (i32.const 300) "null")
+  (func $write (param $fd i32) (param $ptr i32) (param $len i32)
+    (i32.store (i32.const 0) (local.get $ptr))
+    (i32.store (i32.const 4) (local.get $len))
+    (drop (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))))
+  (func (export "_start")
+    (if (call $path_open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const 7) (i32.const 1) (i64.const 0x1fffffff) (i64.const 0x1fffffff) (i32.const 0) (i32.const 16))
+      (then unreachable))
+    (call $write (i32.load (i32.const 16)) (i32.const 200) (i32.const 3))
+    (call $write (i32.const 1) (i32.const 300) (i32.const 4))))
+"#;
+    let code_files = CodeFiles {
+        files: HashMap::from([("tool.wasm".to_string(), module.to_string())]),
+        entrypoint: "tool.wasm".to_string(),
+    };
+    let context_home_folder_path =
+        ExecutionStorage::new(code_files.clone(), context.clone()).context_home_folder_path();
+    std::fs::create_dir_all(&context_home_folder_path).unwrap();
+    std::fs::write(context_home_folder_path.join("state.txt"), "seeded").unwrap();
+
+    WasmRunner::new(
+        code_files,
+        json!({}),
+        Some(WasmRunnerOptions {
+            context,
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("state.txt")).unwrap(),
+        "seeded"
+    );
+    assert_eq!(
+        std::fs::read_to_string(context_home_folder_path.join("new.txt")).unwrap(),
+        "new"
+    );
+}
+
+#[tokio::test]
+async fn run_returns_output_artifacts() {
+    let _ = env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .is_test(true)
+        .try_init();
+
+    // Preopened in order: home (fd 3) and outputs (fd 4).
+    // Writes outputs/report.txt and prints null as result.
+    let module = r#"
+(module
+  (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 100) "report.txt")
+  (data (i32.const 200) "hello artifact")
+  (data (i32.const 300) "null")
+  (func $write (param $fd i32) (param $ptr i32) (param $len i32)
+    (i32.store (i32.const 0) (local.get $ptr))
+    (i32.store (i32.const 4) (local.get $len))
+    (drop (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))))
+  (func (export "_start")
+    (if (call $path_open (i32.const 4) (i32.const 0) (i32.const 100) (i32.const 10) (i32.const 1) (i64.const 0x1fffffff) (i64.const 0x1fffffff) (i32.const 0) (i32.const 16))
+      (then unreachable))
+    (call $write (i32.load (i32.const 16)) (i32.const 200) (i32.const 14))
+    (call $write (i32.const 1) (i32.const 300) (i32.const 4))))
+"#;
+    let code_files = CodeFiles {
+        files: HashMap::from([("tool.wasm".to_string(), module.to_string())]),
+        entrypoint: "tool.wasm".to_string(),
+    };
+
+    let result = WasmRunner::new(
+        code_files,
+        json!({}),
+        Some(WasmRunnerOptions {
+            inline_artifacts_max_size: Some(1024),
+            ..Default::default()
+        }),
+    )
+    .run(None, json!({}), None)
+    .await
+    .unwrap();
+    assert_eq!(result.artifacts.len(), 1);
+    assert_eq!(result.artifacts[0].path, "report.txt");
+    assert_eq!(result.artifacts[0].size, 14);
+    assert_eq!(result.artifacts[0].mime_type, "text/plain");
+    assert_eq!(
+        result.artifacts[0].content_base64.as_deref(),
+        Some("aGVsbG8gYXJ0aWZhY3Q=")
+    );
+}
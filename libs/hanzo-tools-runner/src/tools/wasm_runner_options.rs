@@ -0,0 +1,30 @@
+use super::{execution_context::ExecutionContext, file_changes::ChangeTrackingOptions};
+
+#[derive(Clone)]
+pub struct WasmRunnerOptions {
+    pub context: ExecutionContext,
+    /// Fuel the module can consume, roughly one unit per executed instruction. Runs that exhaust it
+    /// fail, there is no limit when `None`.
+    pub max_fuel: Option<u64>,
+    /// Linear memory and tables the module can grow to, in bytes
+    pub max_memory_bytes: usize,
+    /// Output of the module (stdout and stderr each) kept up to this size, in bytes
+    pub max_output_bytes: usize,
+    /// Output artifacts up to this size (in bytes) are returned inline as base64
+    pub inline_artifacts_max_size: Option<u64>,
+    /// Report files created, modified or deleted by the tool in its read-write mounts
+    pub track_changes: Option<ChangeTrackingOptions>,
+}
+
+impl Default for WasmRunnerOptions {
+    fn default() -> Self {
+        Self {
+            context: ExecutionContext::default(),
+            max_fuel: Some(10_000_000_000),
+            max_memory_bytes: 256 * 1024 * 1024,
+            max_output_bytes: 16 * 1024 * 1024,
+            inline_artifacts_max_size: None,
+            track_changes: None,
+        }
+    }
+}